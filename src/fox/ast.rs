@@ -62,6 +62,13 @@ macro_rules! define_ast {
 
 define_ast!(
     Expression accepting ExpressionVisitor {
+        Array(
+            ArrayExpr {
+                bracket: Token,
+                elements: Vec<Expression>,
+            }
+        ) init: array, visit: visit_array,
+
         Assign(
             AssignExpr {
                 name: Token,
@@ -69,6 +76,15 @@ define_ast!(
             }
         ) init: assign, visit: visit_assign,
 
+        AssignPattern(
+            AssignPatternExpr {
+                // opening bracket of the pattern
+                bracket: Token,
+                pattern: Pattern,
+                value: Box<Expression>,
+            }
+        ) init: assign_pattern, visit: visit_assign_pattern,
+
         Binary(
             BinaryExpr {
                 left: Box<Expression>,
@@ -77,11 +93,18 @@ define_ast!(
             }
         ) init: binary, visit: visit_binary,
 
+        Chain(
+            ChainExpr {
+                // postfix chain containing `?.`, nil before `?.` makes the whole chain nil
+                expression: Box<Expression>,
+            }
+        ) init: chain, visit: visit_chain,
+
         Call(
             CallExpr {
                 callee: Box<Expression>,
                 paren: Token,
-                arguments: Vec<Expression>,
+                arguments: Vec<Argument>,
             }
         ) init: call, visit: visit_call,
 
@@ -89,6 +112,8 @@ define_ast!(
             GetExpr {
                 object: Box<Expression>,
                 name: Token,
+                // `?.` access short-circuiting on nil
+                optional: bool,
             }
         ) init: get, visit: visit_get,
 
@@ -98,12 +123,38 @@ define_ast!(
             }
         ) init: grouping, visit: visit_grouping,
 
+        Index(
+            IndexExpr {
+                object: Box<Expression>,
+                // closing bracket
+                bracket: Token,
+                index: Box<Expression>,
+            }
+        ) init: index, visit: visit_index,
+
+        IndexSet(
+            IndexSetExpr {
+                object: Box<Expression>,
+                bracket: Token,
+                index: Box<Expression>,
+                value: Box<Expression>,
+            }
+        ) init: index_set, visit: visit_index_set,
+
         Literal(
             LiteralExpr {
                 value: Object
             }
         ) init: literal, visit: visit_literal,
 
+        Map(
+            MapExpr {
+                brace: Token,
+                // key & value pairs in the source order
+                entries: Vec<(Expression, Expression)>,
+            }
+        ) init: map, visit: visit_map,
+
         Logical(
             LogicalExpr {
                 left: Box<Expression>,
@@ -112,6 +163,15 @@ define_ast!(
             }
         ) init: logical, visit: visit_logical,
 
+        Range(
+            RangeExpr {
+                start: Box<Expression>,
+                // `..` excludes the end, `..=` includes it
+                operator: Token,
+                end: Box<Expression>,
+            }
+        ) init: range, visit: visit_range,
+
         Set(
             SetExpr {
                 object: Box<Expression>,
@@ -149,44 +209,120 @@ define_ast!(
 
 define_ast!(
     Statement accepting StatementVisitor {
+        Assert(
+            AssertStmt {
+                keyword: Token,
+                condition: Box<Expression>,
+                // reported with the failure, evaluated only then
+                message: Option<Box<Expression>>,
+            }
+        ) init: assert_stmt, visit: visit_assert,
+
         Block(
             BlockStmt {
+                // opening brace, the loop keyword for synthetic blocks
+                brace: Token,
                 statements: Vec<Statement>,
+                // produced by desugaring, has no braces in the source
+                synthetic: bool,
             }
         ) init: block, visit: visit_block,
 
+        Break(
+            BreakStmt {
+                keyword: Token,
+                // the loop to leave, the innermost one if omitted
+                label: Option<Token>,
+            }
+        ) init: break_stmt, visit: visit_break,
+
         Class(
             ClassStmt {
                 name: Token,
                 superclass: Option<Expression>,
                 methods: Vec<Statement>,
+                // `const NAME = value;` as constant variable statements
+                constants: Vec<Statement>,
             }
         ) init: class, visit: visit_class,
 
+        Continue(
+            ContinueStmt {
+                keyword: Token,
+                label: Option<Token>,
+            }
+        ) init: continue_stmt, visit: visit_continue,
+
+        Defer(
+            DeferStmt {
+                keyword: Token,
+                // runs when the enclosing block is left
+                body: Box<Statement>,
+            }
+        ) init: defer, visit: visit_defer,
+
         Expression(
             ExpressionStmt {
                 expression: Box<Expression>
             }
         ) init: expression, visit: visit_expression,
 
+        ForIn(
+            ForInStmt {
+                keyword: Token,
+                name: Token,
+                in_keyword: Token,
+                iterable: Box<Expression>,
+                body: Box<Statement>,
+                label: Option<Token>,
+            }
+        ) init: for_in, visit: visit_for_in,
+
         Function(
             FunctionStmt {
                 name: Token,
                 params: Vec<Token>,
+                // `...name` after the params, bound to an array of the extra arguments
+                rest: Option<Token>,
                 body: Vec<Statement>,
+                // method declared without a parameter list, called on property access
+                getter: bool,
             }
         ) init: function, visit: visit_function,
 
         If(
             IfStmt {
+                keyword: Token,
                 condition: Box<Expression>,
                 then_branch: Box<Statement>,
                 else_branch: Option<Box<Statement>>,
             }
         ) init: if_stmt, visit: visit_if,
 
+        Import(
+            ImportStmt {
+                keyword: Token,
+                // string literal with the path relative to the importing file
+                path: Token,
+                // top-level statements of the module, filled by the loader.
+                // Empty for repeated imports of the same file
+                statements: Vec<Statement>,
+            }
+        ) init: import, visit: visit_import,
+
+        Match(
+            MatchStmt {
+                keyword: Token,
+                subject: Box<Expression>,
+                // literal patterns in the source order
+                arms: Vec<(Expression, Statement)>,
+                default: Option<Box<Statement>>,
+            }
+        ) init: match_stmt, visit: visit_match,
+
         Print(
             PrintStmt {
+                keyword: Token,
                 expression: Box<Expression>
             }
         ) init: print, visit: visit_print,
@@ -198,23 +334,108 @@ define_ast!(
             }
         ) init: ret_fn, visit: visit_return,
 
+        Throw(
+            ThrowStmt {
+                keyword: Token,
+                value: Box<Expression>,
+            }
+        ) init: throw_stmt, visit: visit_throw,
+
+        Try(
+            TryStmt {
+                keyword: Token,
+                // blocks of the statements
+                body: Box<Statement>,
+                // gets the thrown value or the message of the runtime error
+                name: Token,
+                handler: Box<Statement>,
+            }
+        ) init: try_stmt, visit: visit_try,
+
         Var(
             VarStmt {
                 name: Token,
                 initializer: Option<Box<Expression>>,
+                // declared with `const`, can't be reassigned
+                constant: bool,
             }
         ) init: var, visit: visit_var,
 
+        VarPattern(
+            VarPatternStmt {
+                // opening bracket of the pattern
+                bracket: Token,
+                pattern: Pattern,
+                initializer: Box<Expression>,
+                constant: bool,
+            }
+        ) init: var_pattern, visit: visit_var_pattern,
+
         While(
             WhileStmt {
+                keyword: Token,
                 condition: Box<Expression>,
                 body: Box<Statement>,
+                // evaluated in order after each iteration, `continue` doesn't skip them
+                increments: Vec<Expression>,
+                // targeted by `break label;` & `continue label;` of the nested loops
+                label: Option<Token>,
             }
         ) init: while_stmt, visit: visit_while,
     }
 );
 
+/// Names bound to the array elements in order, `[a, b, ...rest]`
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct Pattern {
+    pub names: Vec<Token>,
+    // gets the remaining elements as a new array
+    pub rest: Option<Token>,
+}
+
+/// Call argument, a spread one passes the array elements as separate arguments
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct Argument {
+    pub value: Expression,
+    // the `...` token of `f(...items)`
+    pub spread: Option<Token>,
+}
+
+impl Pattern {
+    /// Every bound name, the rest one is the last
+    pub fn bound_names(&self) -> impl Iterator<Item = &Token> {
+        self.names.iter().chain(&self.rest)
+    }
+}
+
 impl Expression {
+    /// Leftmost token of the expression if there is any
+    /// (literals don't keep their tokens)
+    pub fn token(&self) -> Option<&Token> {
+        use Expression::*;
+        match self {
+            Array(data) => Some(&data.bracket),
+            Assign(data) => Some(&data.name),
+            AssignPattern(data) => Some(&data.bracket),
+            Binary(data) => data.left.token().or(Some(&data.operator)),
+            Call(data) => data.callee.token().or(Some(&data.paren)),
+            Chain(data) => data.expression.token(),
+            Get(data) => data.object.token().or(Some(&data.name)),
+            Grouping(data) => data.expression.token(),
+            Index(data) => data.object.token().or(Some(&data.bracket)),
+            IndexSet(data) => data.object.token().or(Some(&data.bracket)),
+            Literal(_) => None,
+            Logical(data) => data.left.token().or(Some(&data.operator)),
+            Map(data) => Some(&data.brace),
+            Range(data) => data.start.token().or(Some(&data.operator)),
+            Set(data) => data.object.token().or(Some(&data.name)),
+            Super(data) => Some(&data.keyword),
+            This(data) => Some(&data.keyword),
+            Unary(data) => Some(&data.operator),
+            Variable(data) => Some(&data.name),
+        }
+    }
+
     pub fn as_variable(&self) -> FoxResult<&VariableExpr> {
         match self {
             Expression::Variable(expr) => Ok(expr),
@@ -226,6 +447,62 @@ impl Expression {
 }
 
 impl Statement {
+    /// Token representing the statement location in the source code.
+    /// Blocks are containers only, so they have no own statement location
+    pub fn token(&self) -> Option<&Token> {
+        use Statement::*;
+        match self {
+            Assert(data) => Some(&data.keyword),
+            Block(_) => None,
+            Break(data) => Some(&data.keyword),
+            Class(data) => Some(&data.name),
+            Continue(data) => Some(&data.keyword),
+            Defer(data) => Some(&data.keyword),
+            Expression(data) => data.expression.token(),
+            ForIn(data) => Some(&data.keyword),
+            Function(data) => Some(&data.name),
+            If(data) => Some(&data.keyword),
+            Import(data) => Some(&data.keyword),
+            Match(data) => Some(&data.keyword),
+            Print(data) => Some(&data.keyword),
+            Return(data) => Some(&data.keyword),
+            Throw(data) => Some(&data.keyword),
+            Try(data) => Some(&data.keyword),
+            Var(data) => Some(&data.name),
+            VarPattern(data) => Some(&data.bracket),
+            While(data) => Some(&data.keyword),
+        }
+    }
+
+    pub fn line_number(&self) -> Option<usize> {
+        self.token().map(|token| token.code_location.line_number())
+    }
+
+    /// Short name of the statement kind used in reports
+    pub fn kind_name(&self) -> &'static str {
+        use Statement::*;
+        match self {
+            Assert(_) => "assert",
+            Block(_) => "block",
+            Break(_) => "break",
+            Class(_) => "class",
+            Continue(_) => "continue",
+            Defer(_) => "defer",
+            Expression(_) => "expression",
+            ForIn(_) => "for-in",
+            Function(_) => "fun",
+            If(_) => "if",
+            Import(_) => "import",
+            Match(_) => "match",
+            Print(_) => "print",
+            Return(_) => "return",
+            Throw(_) => "throw",
+            Try(_) => "try",
+            Var(_) | VarPattern(_) => "var",
+            While(_) => "while",
+        }
+    }
+
     pub fn as_function(&self) -> FoxResult<&FunctionStmt> {
         match self {
             Statement::Function(func) => Ok(func),
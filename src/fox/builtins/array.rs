@@ -0,0 +1,184 @@
+use std::cmp::Ordering;
+
+use crate::fox::{
+    FoxError, FoxResult, Object,
+    interpreter::{Interpreter, whole_index},
+    utils::SharedPtr,
+};
+
+use super::{BuiltinSpec, unexpected_type};
+
+pub const BUILTINS: &[BuiltinSpec] = &[
+    BuiltinSpec {
+        name: "index_of",
+        arities: &[2],
+        handler: index_of,
+    },
+    BuiltinSpec {
+        name: "insert",
+        arities: &[3],
+        handler: insert,
+    },
+    BuiltinSpec {
+        name: "pop",
+        arities: &[1],
+        handler: pop,
+    },
+    BuiltinSpec {
+        name: "push",
+        arities: &[2],
+        handler: push,
+    },
+    BuiltinSpec {
+        name: "remove",
+        arities: &[2],
+        handler: remove,
+    },
+    BuiltinSpec {
+        name: "sort",
+        arities: &[1, 2],
+        handler: sort,
+    },
+];
+
+fn array_argument<'a>(builtin: &str, value: &'a Object) -> FoxResult<&'a SharedPtr<Vec<Object>>> {
+    let Object::Array(items) = value else {
+        return Err(unexpected_type(builtin, "an array", value));
+    };
+    Ok(items)
+}
+
+/// Position of the index in the array of the length or the builtin error,
+/// the length itself is accepted if `past_end` is set
+fn position(builtin: &str, index: &Object, len: usize, past_end: bool) -> FoxResult<usize> {
+    let error = |message: String| FoxError::runtime(None, &format!("{builtin}() {message}"));
+    let whole = whole_index(index).map_err(error)?;
+    // compared as floats since the value may not fit usize
+    let len_value = len as f64;
+    let position = if whole < 0.0 {
+        len_value + whole
+    } else {
+        whole
+    };
+    if position < 0.0 || position > len_value || (position == len_value && !past_end) {
+        return Err(error(format!(
+            "index {index} is out of range for an array of length {len}"
+        )));
+    }
+    Ok(position as usize)
+}
+
+/// Position of the first equal item or nil
+fn index_of(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let items = array_argument("index_of", &args[0])?;
+    let found = items.borrow().iter().position(|item| *item == args[1]);
+    Ok(found.map_or(Object::Nil, |index| Object::Double(index as f64)))
+}
+
+/// Puts the value before the item at the index, the length itself
+/// is accepted to append. Negative indices count from the end as in
+/// indexing, so -1 puts the value before the last item
+fn insert(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let items = array_argument("insert", &args[0])?;
+    let len = items.borrow().len();
+    let index = position("insert", &args[1], len, true)?;
+    items.borrow_mut().insert(index, args[2].clone());
+    Ok(Object::Nil)
+}
+
+/// Removes the last item, nil if the array is empty
+fn pop(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let items = array_argument("pop", &args[0])?;
+    Ok(items.borrow_mut().pop().unwrap_or(Object::Nil))
+}
+
+/// Appends the value, every reference to the array sees it
+fn push(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let items = array_argument("push", &args[0])?;
+    items.borrow_mut().push(args[1].clone());
+    Ok(Object::Nil)
+}
+
+/// Removes the item at the index and returns it
+fn remove(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let items = array_argument("remove", &args[0])?;
+    let len = items.borrow().len();
+    let index = position("remove", &args[1], len, false)?;
+    Ok(items.borrow_mut().remove(index))
+}
+
+/// Sorts numbers or strings in place, or any items with the comparator
+/// returning a negative number, zero or a positive one as `<`, `==` & `>`.
+/// The sort is stable, a failed comparison leaves the array untouched
+fn sort(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let items = array_argument("sort", &args[0])?;
+    // the comparator may change the array, so a copy is sorted
+    let unsorted = items.borrow().clone();
+    let sorted = match args.get(1) {
+        None => natural_sort(unsorted)?,
+        Some(Object::Callee(func)) => merge_sort(unsorted, &mut |a, b| match interpreter
+            .call_callback(func, &[a.clone(), b.clone()])?
+        {
+            Object::Double(value) if value < 0.0 => Ok(Ordering::Less),
+            Object::Double(value) if value > 0.0 => Ok(Ordering::Greater),
+            Object::Double(_) => Ok(Ordering::Equal),
+            other => Err(FoxError::runtime(
+                None,
+                &format!(
+                    "sort() comparator must return a number, got {}",
+                    other.type_name()
+                ),
+            )),
+        })?,
+        Some(other) => return Err(unexpected_type("sort", "a function", other)),
+    };
+    *items.borrow_mut() = sorted;
+    Ok(Object::Nil)
+}
+
+fn natural_sort(mut items: Vec<Object>) -> FoxResult<Vec<Object>> {
+    if items.iter().all(|item| matches!(item, Object::Double(_))) {
+        items.sort_by(|a, b| match (a, b) {
+            (Object::Double(l), Object::Double(r)) => l.total_cmp(r),
+            _ => Ordering::Equal,
+        });
+    } else if items.iter().all(|item| matches!(item, Object::Text(_))) {
+        items.sort_by(|a, b| match (a, b) {
+            (Object::Text(l), Object::Text(r)) => l.cmp(r),
+            _ => Ordering::Equal,
+        });
+    } else {
+        return Err(FoxError::runtime(
+            None,
+            "sort() expects numbers or strings without a comparator",
+        ));
+    }
+    Ok(items)
+}
+
+/// Stable sort stopping at the first failed comparison,
+/// inconsistent comparisons produce some order of the items
+fn merge_sort(
+    mut items: Vec<Object>,
+    compare: &mut impl FnMut(&Object, &Object) -> FoxResult<Ordering>,
+) -> FoxResult<Vec<Object>> {
+    if items.len() < 2 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let mut left = merge_sort(items, compare)?.into_iter().peekable();
+    let mut right = merge_sort(right, compare)?.into_iter().peekable();
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        // the left item goes first unless the right one is less
+        let next = if compare(r, l)? == Ordering::Less {
+            right.next()
+        } else {
+            left.next()
+        };
+        merged.extend(next);
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
@@ -0,0 +1,511 @@
+use std::collections::HashSet;
+
+use crate::fox::{
+    ErrorInfo, ErrorKind, FoxError, FoxResult, Object, ValueType,
+    environment::Environment,
+    interpreter::{Interpreter, TEXT_FIRST_LINE},
+    mutable_cell,
+    range::NumberRange,
+    scanner::parse_number_literal,
+};
+
+use super::{
+    BuiltinConstant, BuiltinSpec, is_standard_constant, text_argument, text_array, unexpected_type,
+};
+
+/// Max length of range materialized as an array
+pub const RANGE_ARRAY_LIMIT: usize = 10_000;
+
+pub const BUILTINS: &[BuiltinSpec] = &[
+    BuiltinSpec {
+        name: "assert_eq",
+        arities: &[2],
+        handler: assert_eq,
+    },
+    BuiltinSpec {
+        name: "assert_ne",
+        arities: &[2],
+        handler: assert_ne,
+    },
+    BuiltinSpec {
+        name: "assert_true",
+        arities: &[1],
+        handler: assert_true,
+    },
+    BuiltinSpec {
+        name: "class_of",
+        arities: &[1],
+        handler: class_of,
+    },
+    BuiltinSpec {
+        name: "clone",
+        arities: &[1, 2],
+        handler: clone,
+    },
+    BuiltinSpec {
+        name: "closure",
+        arities: &[1],
+        handler: closure,
+    },
+    BuiltinSpec {
+        name: "copy",
+        arities: &[1],
+        handler: copy,
+    },
+    BuiltinSpec {
+        name: "error",
+        arities: &[1],
+        handler: error,
+    },
+    BuiltinSpec {
+        name: "eval",
+        arities: &[1],
+        handler: eval,
+    },
+    BuiltinSpec {
+        name: "exit",
+        arities: &[0, 1],
+        handler: exit,
+    },
+    BuiltinSpec {
+        name: "fields",
+        arities: &[1],
+        handler: fields,
+    },
+    BuiltinSpec {
+        name: "freeze",
+        arities: &[1],
+        handler: freeze,
+    },
+    BuiltinSpec {
+        name: "has_field",
+        arities: &[2],
+        handler: has_field,
+    },
+    BuiltinSpec {
+        name: "has_method",
+        arities: &[2],
+        handler: has_method,
+    },
+    BuiltinSpec {
+        name: "is_frozen",
+        arities: &[1],
+        handler: is_frozen,
+    },
+    BuiltinSpec {
+        name: "len",
+        arities: &[1],
+        handler: len,
+    },
+    BuiltinSpec {
+        name: "locals",
+        arities: &[0, 1],
+        handler: locals,
+    },
+    BuiltinSpec {
+        name: "methods",
+        arities: &[1],
+        handler: methods,
+    },
+    BuiltinSpec {
+        name: "num",
+        arities: &[1],
+        handler: num,
+    },
+    BuiltinSpec {
+        name: "range",
+        arities: &[2, 3],
+        handler: range,
+    },
+    BuiltinSpec {
+        name: "str",
+        arities: &[1],
+        handler: str,
+    },
+    BuiltinSpec {
+        name: "type",
+        arities: &[1],
+        handler: type_of,
+    },
+];
+
+/// Type markers compared with `type()` results, one per `ValueType`
+pub const CONSTANTS: &[BuiltinConstant] = &[
+    BuiltinConstant {
+        name: "Nil",
+        value: Object::Type(ValueType::Nil),
+    },
+    BuiltinConstant {
+        name: "Number",
+        value: Object::Type(ValueType::Number),
+    },
+    BuiltinConstant {
+        name: "String",
+        value: Object::Type(ValueType::String),
+    },
+    BuiltinConstant {
+        name: "Bool",
+        value: Object::Type(ValueType::Bool),
+    },
+    BuiltinConstant {
+        name: "Function",
+        value: Object::Type(ValueType::Function),
+    },
+    BuiltinConstant {
+        name: "Class",
+        value: Object::Type(ValueType::Class),
+    },
+    BuiltinConstant {
+        name: "Array",
+        value: Object::Type(ValueType::Array),
+    },
+    BuiltinConstant {
+        name: "Range",
+        value: Object::Type(ValueType::Range),
+    },
+    BuiltinConstant {
+        name: "Map",
+        value: Object::Type(ValueType::Map),
+    },
+    BuiltinConstant {
+        name: "Type",
+        value: Object::Type(ValueType::Type),
+    },
+];
+
+/// Exact class of the instance
+fn class_of(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let Object::Instance(instance) = &args[0] else {
+        return Err(unexpected_type("class_of", "an instance", &args[0]));
+    };
+    Ok(Object::Class(instance.borrow().class()))
+}
+
+/// Sorted names of the instance fields
+fn fields(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let Object::Instance(instance) = &args[0] else {
+        return Err(unexpected_type("fields", "an instance", &args[0]));
+    };
+    Ok(text_array(instance.borrow().field_names()))
+}
+
+/// Sorted names of the methods of the class or the instance class,
+/// inherited ones included
+fn methods(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let names = match &args[0] {
+        Object::Instance(instance) => instance.borrow().class().method_names(),
+        Object::Class(meta) => meta.method_names(),
+        value => return Err(unexpected_type("methods", "a class or an instance", value)),
+    };
+    Ok(text_array(names))
+}
+
+/// Bindings captured by the function as `[name, value, depth]` arrays
+/// innermost first, globals are not included
+fn closure(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let Object::Callee(func) = &args[0] else {
+        return Ok(Object::Array(mutable_cell(Vec::new())));
+    };
+    let bindings = Environment::chain_bindings_until(&func.closure, interpreter.globals());
+    let items = bindings
+        .into_iter()
+        .map(|(name, object, depth)| {
+            let entry = vec![
+                Object::Text(name.into()),
+                object,
+                Object::Double(depth as f64),
+            ];
+            Object::Array(mutable_cell(entry))
+        })
+        .collect();
+    Ok(Object::Array(mutable_cell(items)))
+}
+
+/// Shallow copy of instances & containers, the deep one if the second argument is true
+fn clone(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let deep = args.get(1).is_some_and(|x| x.is_true());
+    if deep {
+        Ok(args[0].deep_copy())
+    } else {
+        Ok(args[0].shallow_copy())
+    }
+}
+
+fn copy(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    Ok(args[0].deep_copy())
+}
+
+/// Makes the instance read-only, returns the instance itself
+fn freeze(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let Object::Instance(instance) = &args[0] else {
+        return Err(FoxError::runtime(None, "Only instances can be frozen"));
+    };
+    instance.borrow_mut().freeze();
+    Ok(args[0].clone())
+}
+
+/// Checks if the instance has the field assigned, false for other values
+fn has_field(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let name = member_name("has_field", &args[1])?;
+    let found = match &args[0] {
+        Object::Instance(instance) => instance.borrow().has_field(name),
+        _ => false,
+    };
+    Ok(Object::Bool(found))
+}
+
+/// Checks if the class of the instance or the class itself declares
+/// or inherits the method, false for other values
+fn has_method(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let name = member_name("has_method", &args[1])?;
+    let found = match &args[0] {
+        Object::Instance(instance) => instance.borrow().class().find_method(name).is_some(),
+        Object::Class(meta) => meta.find_method(name).is_some(),
+        _ => false,
+    };
+    Ok(Object::Bool(found))
+}
+
+fn member_name<'a>(builtin: &str, arg: &'a Object) -> FoxResult<&'a str> {
+    let Object::Text(name) = arg else {
+        let message = format!("{builtin}() expects a string name, got {}", arg.type_name());
+        return Err(FoxError::runtime(None, &message));
+    };
+    Ok(name)
+}
+
+fn is_frozen(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let frozen = match &args[0] {
+        Object::Instance(instance) => instance.borrow().is_frozen(),
+        _ => false,
+    };
+    Ok(Object::Bool(frozen))
+}
+
+/// Count of characters in the string or elements in the collection
+fn len(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let count = match &args[0] {
+        Object::Text(text) => text.chars().count(),
+        Object::Array(items) => items.borrow().len(),
+        Object::Map(map) => map.borrow().len(),
+        Object::Range(range) => range.len(),
+        value => {
+            let message = format!("Can't get length of {}", value.type_name());
+            return Err(FoxError::runtime(None, &message));
+        }
+    };
+    Ok(Object::Double(count as f64))
+}
+
+/// Bindings visible at the call site innermost first
+/// as `[name, value, depth, shadowed]` arrays,
+/// builtins are skipped unless the argument is true,
+/// globals named as builtin constants count as builtins,
+/// globals declared by the host are listed with them as nil until defined
+fn locals(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let include_builtins = args.first().is_some_and(|x| x.is_true());
+    let bindings = interpreter.environment().borrow().all_bindings();
+    let globals_depth = bindings.last().map(|x| x.2).unwrap_or_default();
+    let mut seen = HashSet::new();
+    let mut items = bindings
+        .into_iter()
+        .filter(|(name, object, depth)| {
+            include_builtins
+                || *depth != globals_depth
+                || !(matches!(object, Object::BuiltinCallee(_)) || is_standard_constant(name))
+        })
+        .map(|(name, object, depth)| {
+            let shadowed = !seen.insert(name.clone());
+            let entry = vec![
+                Object::Text(name.into()),
+                object,
+                Object::Double(depth as f64),
+                Object::Bool(shadowed),
+            ];
+            Object::Array(mutable_cell(entry))
+        })
+        .collect::<Vec<_>>();
+    if include_builtins {
+        // declared host globals are listed even before the host defines them
+        let globals = interpreter.globals().borrow();
+        let declared = interpreter
+            .declared_globals()
+            .iter()
+            .filter(|declaration| globals.get_at(0, &declaration.name).is_err())
+            .map(|declaration| {
+                let entry = vec![
+                    Object::Text(declaration.name.as_str().into()),
+                    Object::Nil,
+                    Object::Double(globals_depth as f64),
+                    Object::Bool(false),
+                ];
+                Object::Array(mutable_cell(entry))
+            });
+        items.extend(declared);
+    }
+    Ok(Object::Array(mutable_cell(items)))
+}
+
+/// Number written in the text like a literal or "nan", "inf" & "infinity" in any case,
+/// nil if the text isn't a number
+fn num(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    match &args[0] {
+        Object::Double(value) => Ok(Object::Double(*value)),
+        Object::Text(text) => Ok(parse_number_literal(text)
+            .or_else(|| special_number(text))
+            .map_or(Object::Nil, Object::Double)),
+        value => Err(FoxError::runtime(
+            None,
+            &format!("Can't convert {value} to a number"),
+        )),
+    }
+}
+
+fn special_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (sign, word) = match text.strip_prefix('-') {
+        Some(word) => (-1.0, word),
+        None => (1.0, text),
+    };
+    match word.to_ascii_lowercase().as_str() {
+        "nan" => Some(f64::NAN),
+        "inf" | "infinity" => Some(sign * f64::INFINITY),
+        _ => None,
+    }
+}
+
+/// Value of the expression written in the text, evaluated where it's called.
+/// Failures of the text are catchable runtime errors with the inner message
+fn eval(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let text = text_argument("eval", &args[0])?;
+    interpreter
+        .evaluate_text(text)
+        .map_err(|err| eval_error(text, err))
+}
+
+fn eval_error(text: &str, err: FoxError) -> FoxError {
+    use ErrorKind::*;
+    let kind = err.kind();
+    if matches!(kind, Throw(_) | Exit(_) | Fatal(_) | Bug(_)) || kind.is_control_flow() {
+        return err;
+    }
+    // tokens are located at their last character
+    let start = match err.info() {
+        ErrorInfo::Token(token) => Some((
+            token.code_location,
+            token.lexeme.chars().count().saturating_sub(1),
+        )),
+        ErrorInfo::Code(location) => Some((*location, 0)),
+        ErrorInfo::Empty => None,
+    }
+    // errors of the functions called by the text are located in the code
+    .filter(|(location, _)| location.line_number() >= TEXT_FIRST_LINE)
+    .map(|(location, length)| location.absolute_position().saturating_sub(length));
+    let message = match start {
+        Some(start) => {
+            let before = text.chars().take(start).collect::<Vec<_>>();
+            let line = before.iter().filter(|ch| **ch == '\n').count() + 1;
+            let column = before.iter().rev().take_while(|ch| **ch != '\n').count() + 1;
+            format!("eval() failed at {line}:{column}: {kind}")
+        }
+        None => format!("eval() failed: {kind}"),
+    };
+    FoxError::runtime(None, &message)
+}
+
+fn assertion_failure(message: &str) -> FoxError {
+    FoxError::runtime(None, &format!("Assertion failed: {message}"))
+}
+
+/// Fails unless the actual value equals the expected one as with `==`
+fn assert_eq(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    if args[0] == args[1] {
+        return Ok(Object::Nil);
+    }
+    let message = format!(
+        "expected {}, got {}",
+        interpreter.stringify(&args[1])?,
+        interpreter.stringify(&args[0])?
+    );
+    Err(assertion_failure(&message))
+}
+
+fn assert_ne(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    if args[0] != args[1] {
+        return Ok(Object::Nil);
+    }
+    let message = format!(
+        "expected a value other than {}",
+        interpreter.stringify(&args[1])?
+    );
+    Err(assertion_failure(&message))
+}
+
+/// Fails unless the value is true the way `if` sees it
+fn assert_true(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    if args[0].is_true() {
+        return Ok(Object::Nil);
+    }
+    let message = format!(
+        "expected a true value, got {}",
+        interpreter.stringify(&args[0])?
+    );
+    Err(assertion_failure(&message))
+}
+
+/// Fails at the call site with the value rendered as the message,
+/// the `catch` handler receives the message text
+fn error(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let message = interpreter.stringify(&args[0])?;
+    Err(FoxError::runtime(None, &message))
+}
+
+/// Stops the script, the host exits with the code, 0 if omitted
+fn exit(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let code = match args.first() {
+        None => 0.0,
+        Some(Object::Double(code)) => *code,
+        Some(value) => return Err(unexpected_type("exit", "a number", value)),
+    };
+    if code.fract() != 0.0 || !(i32::MIN as f64..=i32::MAX as f64).contains(&code) {
+        return Err(FoxError::runtime(
+            None,
+            &format!("exit() code must be a whole number, got {code}"),
+        ));
+    }
+    Err(FoxError::error(ErrorKind::Exit(code as i32)))
+}
+
+/// Value rendered the way `print` shows it
+fn str(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let text = interpreter.stringify(&args[0])?;
+    Ok(Object::Text(text.into()))
+}
+
+/// Exact class of the instance, unlike the chain of superclasses,
+/// or the type marker of the value
+fn type_of(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    Ok(args[0].type_of())
+}
+
+/// Array of numbers for small ranges, lazy range value otherwise.
+/// The step is 1 if omitted
+fn range(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let mut values = [0.0, 0.0, 1.0];
+    for (value, arg) in values.iter_mut().zip(args) {
+        let Object::Double(number) = arg else {
+            return Err(FoxError::runtime(None, "Range arguments must be numbers"));
+        };
+        *value = *number;
+    }
+    let [start, end, step] = values;
+    if step == 0.0 {
+        return Err(FoxError::runtime(None, "Range step must not be zero"));
+    }
+    let range = NumberRange::new(start, end, step);
+    if range.len() > RANGE_ARRAY_LIMIT {
+        return Ok(Object::Range(range));
+    }
+    let items = range.iter().map(Object::Double).collect::<Vec<_>>();
+    Ok(Object::Array(mutable_cell(items)))
+}
@@ -0,0 +1,141 @@
+use std::io::{BufRead, Write};
+
+use crate::fox::{FoxError, FoxResult, Object, interpreter::Interpreter};
+
+use super::{BuiltinSpec, text_argument, text_array, unexpected_type};
+
+pub const BUILTINS: &[BuiltinSpec] = &[
+    BuiltinSpec {
+        name: "append_file",
+        arities: &[2],
+        handler: append_file,
+    },
+    BuiltinSpec {
+        name: "args",
+        arities: &[0],
+        handler: args,
+    },
+    BuiltinSpec {
+        name: "env",
+        arities: &[1],
+        handler: env,
+    },
+    BuiltinSpec {
+        name: "file_exists",
+        arities: &[1],
+        handler: file_exists,
+    },
+    BuiltinSpec {
+        name: "read_file",
+        arities: &[1],
+        handler: read_file,
+    },
+    BuiltinSpec {
+        name: "readline",
+        arities: &[0, 1],
+        handler: readline,
+    },
+    BuiltinSpec {
+        name: "write",
+        arities: &[1],
+        handler: write,
+    },
+    BuiltinSpec {
+        name: "write_file",
+        arities: &[2],
+        handler: write_file,
+    },
+    BuiltinSpec {
+        name: "writeln",
+        arities: &[1],
+        handler: writeln,
+    },
+];
+
+/// Command line arguments following the script path as strings
+fn args(interpreter: &mut Interpreter, _: &[Object]) -> FoxResult<Object> {
+    Ok(text_array(interpreter.script_args().to_vec()))
+}
+
+/// Value of the environment variable, nil if it isn't set or isn't valid unicode
+fn env(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let name = text_argument("env", &args[0])?;
+    Ok(std::env::var(name).map_or(Object::Nil, |value| Object::Text(value.into())))
+}
+
+/// Runtime error with the path the operation failed on
+fn file_error(action: &str, path: &str, err: std::io::Error) -> FoxError {
+    FoxError::runtime(None, &format!("Can't {action} '{path}': {err}"))
+}
+
+/// Creates the file if it doesn't exist
+fn append_file(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let path = text_argument("append_file", &args[0])?;
+    let text = text_argument("append_file", &args[1])?;
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|err| file_error("append to", path, err))?;
+    Ok(Object::Nil)
+}
+
+/// Whether the path is of an existing file or directory
+fn file_exists(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let path = text_argument("file_exists", &args[0])?;
+    let exists = std::fs::exists(path).map_err(|err| file_error("check", path, err))?;
+    Ok(Object::Bool(exists))
+}
+
+fn read_file(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let path = text_argument("read_file", &args[0])?;
+    let text = std::fs::read_to_string(path).map_err(|err| file_error("read", path, err))?;
+    Ok(Object::Text(text.into()))
+}
+
+/// Line of stdin without the line break, nil at the end of the input.
+/// The prompt is written before reading without a line break
+fn readline(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    if let Some(prompt) = args.first() {
+        let Object::Text(prompt) = prompt else {
+            return Err(unexpected_type("readline", "a string prompt", prompt));
+        };
+        interpreter.write_output(prompt);
+    }
+    let mut line = String::new();
+    let count = std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|err| FoxError::runtime(None, &format!("Can't read the input: {err}")))?;
+    if count == 0 {
+        return Ok(Object::Nil);
+    }
+    let line = line
+        .strip_suffix('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .unwrap_or(&line);
+    Ok(Object::Text(line.into()))
+}
+
+/// Value rendered the way `print` shows it without the line break
+fn write(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let text = interpreter.stringify(&args[0])?;
+    interpreter.write_output(&text);
+    Ok(Object::Nil)
+}
+
+/// Replaces the content of the file, creates it if it doesn't exist
+fn write_file(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let path = text_argument("write_file", &args[0])?;
+    let text = text_argument("write_file", &args[1])?;
+    std::fs::write(path, text).map_err(|err| file_error("write", path, err))?;
+    Ok(Object::Nil)
+}
+
+/// Same as `print` but usable as an expression
+fn writeln(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let text = interpreter.stringify(&args[0])?;
+    interpreter.write_output(&format!("{text}\n"));
+    Ok(Object::Nil)
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use crate::fox::{
+    FoxResult, Object, interpreter::Interpreter, mutable_cell, object::key_order, utils::SharedPtr,
+};
+
+use super::{BuiltinSpec, unexpected_type};
+
+pub const BUILTINS: &[BuiltinSpec] = &[
+    BuiltinSpec {
+        name: "has_key",
+        arities: &[2],
+        handler: has_key,
+    },
+    BuiltinSpec {
+        name: "keys",
+        arities: &[1],
+        handler: keys,
+    },
+    BuiltinSpec {
+        name: "remove_key",
+        arities: &[2],
+        handler: remove_key,
+    },
+    BuiltinSpec {
+        name: "values",
+        arities: &[1],
+        handler: values,
+    },
+];
+
+fn map_argument<'a>(
+    builtin: &str,
+    value: &'a Object,
+) -> FoxResult<&'a SharedPtr<HashMap<Object, Object>>> {
+    let Object::Map(map) = value else {
+        return Err(unexpected_type(builtin, "a map", value));
+    };
+    Ok(map)
+}
+
+/// Entries in the stable key order, so the listings are deterministic
+fn sorted_entries(map: &SharedPtr<HashMap<Object, Object>>) -> Vec<(Object, Object)> {
+    let mut entries = map
+        .borrow()
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| key_order(&a.0, &b.0));
+    entries
+}
+
+fn has_key(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let map = map_argument("has_key", &args[0])?;
+    Ok(Object::Bool(map.borrow().contains_key(&args[1])))
+}
+
+/// Keys sorted with numbers first, then strings
+fn keys(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let map = map_argument("keys", &args[0])?;
+    let keys = sorted_entries(map)
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect();
+    Ok(Object::Array(mutable_cell(keys)))
+}
+
+/// Deletes the entry and returns its value, nil if there is none
+fn remove_key(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let map = map_argument("remove_key", &args[0])?;
+    Ok(map.borrow_mut().remove(&args[1]).unwrap_or(Object::Nil))
+}
+
+/// Values in the order of their `keys()`
+fn values(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let map = map_argument("values", &args[0])?;
+    let values = sorted_entries(map)
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect();
+    Ok(Object::Array(mutable_cell(values)))
+}
@@ -0,0 +1,186 @@
+use crate::fox::{FoxError, FoxResult, Object, interpreter::Interpreter, random::Random};
+
+use super::{BuiltinConstant, BuiltinSpec};
+
+pub const BUILTINS: &[BuiltinSpec] = &[
+    BuiltinSpec {
+        name: "abs",
+        arities: &[1],
+        handler: abs,
+    },
+    BuiltinSpec {
+        name: "ceil",
+        arities: &[1],
+        handler: ceil,
+    },
+    BuiltinSpec {
+        name: "floor",
+        arities: &[1],
+        handler: floor,
+    },
+    BuiltinSpec {
+        name: "is_finite",
+        arities: &[1],
+        handler: is_finite,
+    },
+    BuiltinSpec {
+        name: "is_nan",
+        arities: &[1],
+        handler: is_nan,
+    },
+    BuiltinSpec {
+        name: "max",
+        arities: &[2],
+        handler: max,
+    },
+    BuiltinSpec {
+        name: "min",
+        arities: &[2],
+        handler: min,
+    },
+    BuiltinSpec {
+        name: "random",
+        arities: &[0],
+        handler: random,
+    },
+    BuiltinSpec {
+        name: "random_int",
+        arities: &[2],
+        handler: random_int,
+    },
+    BuiltinSpec {
+        name: "random_seed",
+        arities: &[1],
+        handler: random_seed,
+    },
+    BuiltinSpec {
+        name: "round",
+        arities: &[1],
+        handler: round,
+    },
+    BuiltinSpec {
+        name: "sqrt",
+        arities: &[1],
+        handler: sqrt,
+    },
+];
+
+pub const CONSTANTS: &[BuiltinConstant] = &[
+    BuiltinConstant {
+        name: "INFINITY",
+        value: Object::Double(f64::INFINITY),
+    },
+    BuiltinConstant {
+        name: "NAN",
+        value: Object::Double(f64::NAN),
+    },
+    BuiltinConstant {
+        name: "PI",
+        value: Object::Double(std::f64::consts::PI),
+    },
+];
+
+fn number(args: &[Object]) -> FoxResult<f64> {
+    let Object::Double(value) = args[0] else {
+        return Err(FoxError::runtime(None, "Argument must be a number"));
+    };
+    Ok(value)
+}
+
+fn numbers(args: &[Object]) -> FoxResult<(f64, f64)> {
+    let (Object::Double(first), Object::Double(second)) = (&args[0], &args[1]) else {
+        return Err(FoxError::runtime(None, "Arguments must be numbers"));
+    };
+    Ok((*first, *second))
+}
+
+fn abs(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    Ok(Object::Double(number(args)?.abs()))
+}
+
+fn ceil(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    Ok(Object::Double(number(args)?.ceil()))
+}
+
+fn floor(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    Ok(Object::Double(number(args)?.floor()))
+}
+
+fn is_finite(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    Ok(Object::Bool(number(args)?.is_finite()))
+}
+
+/// The only way to detect NaN since it isn't equal to itself
+fn is_nan(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    Ok(Object::Bool(number(args)?.is_nan()))
+}
+
+/// NaN if any of the numbers is NaN
+fn max(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let (first, second) = numbers(args)?;
+    if first.is_nan() || second.is_nan() {
+        return Ok(Object::Double(f64::NAN));
+    }
+    Ok(Object::Double(first.max(second)))
+}
+
+/// NaN if any of the numbers is NaN
+fn min(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let (first, second) = numbers(args)?;
+    if first.is_nan() || second.is_nan() {
+        return Ok(Object::Double(f64::NAN));
+    }
+    Ok(Object::Double(first.min(second)))
+}
+
+/// Number in [0, 1)
+fn random(interpreter: &mut Interpreter, _: &[Object]) -> FoxResult<Object> {
+    Ok(Object::Double(interpreter.random().next_unit()))
+}
+
+/// Whole number between the bounds, both included
+fn random_int(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let (lo, hi) = numbers(args)?;
+    if !is_whole(lo) || !is_whole(hi) {
+        return Err(FoxError::runtime(
+            None,
+            &format!("random_int() bounds must be whole numbers, got {lo} and {hi}"),
+        ));
+    }
+    if lo > hi {
+        return Err(FoxError::runtime(
+            None,
+            &format!("random_int() lower bound {lo} is greater than upper bound {hi}"),
+        ));
+    }
+    let span = (hi - lo) as u64 + 1;
+    let offset = interpreter.random().next_u64() % span;
+    Ok(Object::Double(lo + offset as f64))
+}
+
+/// Makes the following random numbers repeat for the same seed
+fn random_seed(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let seed = number(args)?;
+    if !is_whole(seed) {
+        return Err(FoxError::runtime(
+            None,
+            &format!("random_seed() expects a whole number, got {seed}"),
+        ));
+    }
+    *interpreter.random() = Random::with_seed(seed as i64 as u64);
+    Ok(Object::Nil)
+}
+
+fn is_whole(value: f64) -> bool {
+    value.is_finite() && value.fract() == 0.0
+}
+
+/// Halves are rounded away from zero
+fn round(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    Ok(Object::Double(number(args)?.round()))
+}
+
+/// NaN for negative numbers
+fn sqrt(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    Ok(Object::Double(number(args)?.sqrt()))
+}
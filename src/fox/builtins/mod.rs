@@ -0,0 +1,278 @@
+mod array;
+mod core;
+mod io;
+mod map;
+mod math;
+mod string;
+mod time;
+
+use crate::fox::{
+    FoxError, FoxResult, Object, environment::Environment, func::BuiltinFunc,
+    interpreter::Interpreter, mutable_cell,
+};
+
+#[cfg(test)]
+pub use self::core::RANGE_ARRAY_LIMIT;
+
+/// Plain function implementing a builtin declared in a table
+///
+pub type BuiltinHandler = fn(&mut Interpreter, &[Object]) -> FoxResult<Object>;
+
+/// Builtin function declaration
+///
+pub struct BuiltinSpec {
+    pub name: &'static str,
+    /// Accepted argument counts in ascending order
+    pub arities: &'static [usize],
+    pub handler: BuiltinHandler,
+}
+
+/// Builtin value declaration
+///
+pub struct BuiltinConstant {
+    pub name: &'static str,
+    pub value: Object,
+}
+
+/// Whether the name is of a standard constant
+pub fn is_standard_constant(name: &str) -> bool {
+    standard_constant(name).is_some()
+}
+
+/// Value of the standard constant with the name
+pub fn standard_constant(name: &str) -> Option<Object> {
+    [core::CONSTANTS, math::CONSTANTS]
+        .iter()
+        .flat_map(|constants| constants.iter())
+        .find(|constant| constant.name == name)
+        .map(|constant| constant.value.clone())
+}
+
+fn text_array(items: Vec<String>) -> Object {
+    let items = items
+        .into_iter()
+        .map(|item| Object::Text(item.into()))
+        .collect();
+    Object::Array(mutable_cell(items))
+}
+
+/// Error of a builtin called with an argument of a wrong type
+fn unexpected_type(builtin: &str, expected: &str, value: &Object) -> FoxError {
+    let message = format!("{builtin}() expects {expected}, got {}", value.type_name());
+    FoxError::runtime(None, &message)
+}
+
+/// Text of the argument expected to be a string
+fn text_argument<'a>(builtin: &str, value: &'a Object) -> FoxResult<&'a str> {
+    let Object::Text(text) = value else {
+        return Err(unexpected_type(builtin, "a string", value));
+    };
+    Ok(text)
+}
+
+/// Related builtins that are enabled or disabled together
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BuiltinGroup {
+    Core,
+    Math,
+    String,
+    Io,
+    Time,
+    Array,
+    Map,
+}
+
+impl BuiltinGroup {
+    pub const ALL: [BuiltinGroup; 7] = [
+        BuiltinGroup::Core,
+        BuiltinGroup::Math,
+        BuiltinGroup::String,
+        BuiltinGroup::Io,
+        BuiltinGroup::Time,
+        BuiltinGroup::Array,
+        BuiltinGroup::Map,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuiltinGroup::Core => "core",
+            BuiltinGroup::Math => "math",
+            BuiltinGroup::String => "string",
+            BuiltinGroup::Io => "io",
+            BuiltinGroup::Time => "time",
+            BuiltinGroup::Array => "array",
+            BuiltinGroup::Map => "map",
+        }
+    }
+
+    pub fn with_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|group| group.name() == name)
+    }
+}
+
+/// Table of builtin functions & constants by group
+///
+#[derive(Default)]
+pub struct BuiltinRegistry {
+    entries: Vec<(BuiltinGroup, &'static BuiltinSpec)>,
+    constants: Vec<(BuiltinGroup, &'static BuiltinConstant)>,
+}
+
+impl BuiltinRegistry {
+    /// Registry of every builtin shipped with the interpreter
+    pub fn standard() -> FoxResult<Self> {
+        let mut registry = Self::default();
+        registry.register(BuiltinGroup::Core, core::BUILTINS)?;
+        registry.register_constants(BuiltinGroup::Core, core::CONSTANTS)?;
+        registry.register(BuiltinGroup::Math, math::BUILTINS)?;
+        registry.register_constants(BuiltinGroup::Math, math::CONSTANTS)?;
+        registry.register(BuiltinGroup::String, string::BUILTINS)?;
+        registry.register(BuiltinGroup::Io, io::BUILTINS)?;
+        registry.register(BuiltinGroup::Time, time::BUILTINS)?;
+        registry.register(BuiltinGroup::Array, array::BUILTINS)?;
+        registry.register(BuiltinGroup::Map, map::BUILTINS)?;
+        Ok(registry)
+    }
+
+    pub fn register(
+        &mut self,
+        group: BuiltinGroup,
+        specs: &'static [BuiltinSpec],
+    ) -> FoxResult<()> {
+        for spec in specs {
+            self.check_unique(spec.name)?;
+            if spec.arities.is_empty() || !spec.arities.is_sorted() {
+                return Err(FoxError::bug(&format!(
+                    "Builtin '{}' has invalid arities {:?}",
+                    spec.name, spec.arities
+                )));
+            }
+            self.entries.push((group, spec));
+        }
+        Ok(())
+    }
+
+    pub fn register_constants(
+        &mut self,
+        group: BuiltinGroup,
+        constants: &'static [BuiltinConstant],
+    ) -> FoxResult<()> {
+        for constant in constants {
+            self.check_unique(constant.name)?;
+            self.constants.push((group, constant));
+        }
+        Ok(())
+    }
+
+    fn check_unique(&self, name: &str) -> FoxResult<()> {
+        let is_registered = self.entries.iter().any(|(_, x)| x.name == name)
+            || self.constants.iter().any(|(_, x)| x.name == name);
+        if is_registered {
+            return Err(FoxError::bug(&format!(
+                "Builtin '{name}' is registered twice"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Defines the builtins in the environment,
+    /// the functions of disabled groups fail when called.
+    /// Constants can't do any harm, so they are always defined
+    pub fn define(&self, env: &mut Environment, enabled: &[BuiltinGroup]) {
+        for (_, constant) in &self.constants {
+            env.define(constant.name, constant.value.clone());
+        }
+        for (group, spec) in &self.entries {
+            let func = if enabled.contains(group) {
+                BuiltinFunc::new(spec.name, spec.handler, spec.arities)
+            } else {
+                let message = format!("{} builtins are disabled", group.name());
+                let body = move |_: &mut Interpreter, _: &[Object]| -> FoxResult<Object> {
+                    Err(FoxError::runtime(None, &message))
+                };
+                BuiltinFunc::new(spec.name, body, spec.arities)
+            };
+            env.define(spec.name, Object::BuiltinCallee(func));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fox::{ErrorKind, Parser, Scanner, ValueType, resolver::Resolver};
+
+    fn echo(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+        Ok(Object::Double(args.len() as f64))
+    }
+
+    const TEST_IO: &[BuiltinSpec] = &[BuiltinSpec {
+        name: "echo",
+        arities: &[0, 2],
+        handler: echo,
+    }];
+
+    fn run(interpreter: &mut Interpreter, code: &str) -> FoxResult<()> {
+        let source = code.chars().collect::<Vec<_>>();
+        let tokens = Scanner::with_source(&source).scan_tokens()?;
+        let statements = Parser::new(&tokens).parse()?;
+        Resolver::with(interpreter).resolve_statements(&statements)?;
+        interpreter.interpret(&statements)
+    }
+
+    fn interpreter_with_echo(enabled: &[BuiltinGroup]) -> Interpreter {
+        let mut registry = BuiltinRegistry::default();
+        registry.register(BuiltinGroup::Io, TEST_IO).unwrap();
+        let interpreter = Interpreter::with_builtins(enabled);
+        registry.define(&mut interpreter.globals().borrow_mut(), enabled);
+        interpreter
+    }
+
+    #[test]
+    fn test_standard_registry() {
+        assert!(BuiltinRegistry::standard().is_ok());
+    }
+
+    #[test]
+    fn test_type_markers() {
+        let names = core::CONSTANTS.iter().map(|x| x.name).collect::<Vec<_>>();
+        let expected = ValueType::ALL.map(|x| x.name());
+        assert_eq!(names, expected);
+        for constant in core::CONSTANTS {
+            assert_eq!(
+                constant.value.to_string(),
+                format!("type {}", constant.name)
+            );
+        }
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let mut registry = BuiltinRegistry::default();
+        registry.register(BuiltinGroup::Io, TEST_IO).unwrap();
+        let err = registry.register(BuiltinGroup::Core, TEST_IO).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Bug(_)));
+    }
+
+    #[test]
+    fn test_multi_arity_dispatch() {
+        let mut interpreter = interpreter_with_echo(&BuiltinGroup::ALL);
+        run(&mut interpreter, "print echo(); print echo(1, 2);").unwrap();
+        let err = run(&mut interpreter, "echo(1);").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Expected 0 or 2 arguments but got 1"
+        );
+    }
+
+    #[test]
+    fn test_disabled_group() {
+        let mut interpreter = interpreter_with_echo(&[BuiltinGroup::Core]);
+        let err = run(&mut interpreter, "echo();").unwrap_err();
+        assert_eq!(err.kind().to_string(), "io builtins are disabled");
+        let err = run(&mut interpreter, "clock();").unwrap_err();
+        assert_eq!(err.kind().to_string(), "time builtins are disabled");
+        assert!(run(&mut interpreter, "copy(1);").is_ok());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::fox::{FoxError, FoxResult, Object, interpreter::Interpreter};
+
+use super::{BuiltinSpec, text_argument, text_array, unexpected_type};
+
+pub const BUILTINS: &[BuiltinSpec] = &[
+    BuiltinSpec {
+        name: "chr",
+        arities: &[1],
+        handler: chr,
+    },
+    BuiltinSpec {
+        name: "contains",
+        arities: &[2],
+        handler: contains,
+    },
+    BuiltinSpec {
+        name: "ends_with",
+        arities: &[2],
+        handler: ends_with,
+    },
+    BuiltinSpec {
+        name: "join",
+        arities: &[2],
+        handler: join,
+    },
+    BuiltinSpec {
+        name: "lower",
+        arities: &[1],
+        handler: lower,
+    },
+    BuiltinSpec {
+        name: "ord",
+        arities: &[1],
+        handler: ord,
+    },
+    BuiltinSpec {
+        name: "split",
+        arities: &[2],
+        handler: split,
+    },
+    BuiltinSpec {
+        name: "starts_with",
+        arities: &[2],
+        handler: starts_with,
+    },
+    BuiltinSpec {
+        name: "str_len",
+        arities: &[1],
+        handler: str_len,
+    },
+    BuiltinSpec {
+        name: "trim",
+        arities: &[1],
+        handler: trim,
+    },
+    BuiltinSpec {
+        name: "upper",
+        arities: &[1],
+        handler: upper,
+    },
+];
+
+/// One-character string of the code point,
+/// surrogates are not characters and fail like other invalid codes
+fn chr(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let Object::Double(code) = args[0] else {
+        return Err(unexpected_type("chr", "a number", &args[0]));
+    };
+    let ch = Some(code)
+        .filter(|code| code.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(code))
+        .and_then(|code| char::from_u32(code as u32));
+    let Some(ch) = ch else {
+        return Err(FoxError::runtime(
+            None,
+            &format!("chr() expects a valid code point, got {code}"),
+        ));
+    };
+    Ok(Object::Text(ch.to_string().into()))
+}
+
+fn contains(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let text = text_argument("contains", &args[0])?;
+    let part = text_argument("contains", &args[1])?;
+    Ok(Object::Bool(text.contains(part)))
+}
+
+fn ends_with(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let text = text_argument("ends_with", &args[0])?;
+    let suffix = text_argument("ends_with", &args[1])?;
+    Ok(Object::Bool(text.ends_with(suffix)))
+}
+
+/// Items are rendered the way `print` shows them
+fn join(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let Object::Array(items) = &args[0] else {
+        return Err(unexpected_type("join", "an array", &args[0]));
+    };
+    let separator = text_argument("join", &args[1])?;
+    // `toString` methods may change the array
+    let items = items.borrow().clone();
+    let parts = items
+        .iter()
+        .map(|item| interpreter.stringify(item))
+        .collect::<FoxResult<Vec<_>>>()?;
+    Ok(Object::Text(parts.join(separator).into()))
+}
+
+fn lower(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let text = text_argument("lower", &args[0])?;
+    Ok(Object::Text(text.to_lowercase().into()))
+}
+
+/// Code point of the one-character string
+fn ord(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let text = text_argument("ord", &args[0])?;
+    let mut chars = text.chars();
+    let (Some(ch), None) = (chars.next(), chars.next()) else {
+        return Err(FoxError::runtime(
+            None,
+            &format!(
+                "ord() expects a single character, got {} characters",
+                text.chars().count()
+            ),
+        ));
+    };
+    Ok(Object::Double(ch as u32 as f64))
+}
+
+/// The empty separator splits the text into characters
+fn split(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let text = text_argument("split", &args[0])?;
+    let separator = text_argument("split", &args[1])?;
+    let parts = if separator.is_empty() {
+        text.chars().map(String::from).collect()
+    } else {
+        text.split(separator).map(String::from).collect()
+    };
+    Ok(text_array(parts))
+}
+
+fn starts_with(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let text = text_argument("starts_with", &args[0])?;
+    let prefix = text_argument("starts_with", &args[1])?;
+    Ok(Object::Bool(text.starts_with(prefix)))
+}
+
+/// Count of characters, not bytes
+fn str_len(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let text = text_argument("str_len", &args[0])?;
+    Ok(Object::Double(text.chars().count() as f64))
+}
+
+/// Whitespace is removed from both ends
+fn trim(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let text = text_argument("trim", &args[0])?;
+    Ok(Object::Text(text.trim().into()))
+}
+
+fn upper(_: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let text = text_argument("upper", &args[0])?;
+    Ok(Object::Text(text.to_uppercase().into()))
+}
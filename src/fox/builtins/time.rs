@@ -0,0 +1,78 @@
+use std::time::{Duration, Instant};
+
+use crate::fox::{FoxError, FoxResult, Object, interpreter::Interpreter};
+
+use super::{BuiltinSpec, unexpected_type};
+
+pub const BUILTINS: &[BuiltinSpec] = &[
+    BuiltinSpec {
+        name: "clock",
+        arities: &[0],
+        handler: clock,
+    },
+    BuiltinSpec {
+        name: "clock_ms",
+        arities: &[0],
+        handler: clock_ms,
+    },
+    BuiltinSpec {
+        name: "elapsed",
+        arities: &[1],
+        handler: elapsed,
+    },
+    BuiltinSpec {
+        name: "sleep",
+        arities: &[1],
+        handler: sleep,
+    },
+];
+
+/// Longest uninterrupted sleep, the cancellation is checked between the slices
+const SLEEP_SLICE: Duration = Duration::from_millis(10);
+
+/// Seconds since the interpreter start
+fn clock(interpreter: &mut Interpreter, _: &[Object]) -> FoxResult<Object> {
+    Ok(Object::Double(interpreter.uptime().as_secs_f64()))
+}
+
+/// Milliseconds since the interpreter start
+fn clock_ms(interpreter: &mut Interpreter, _: &[Object]) -> FoxResult<Object> {
+    Ok(Object::Double(interpreter.uptime().as_secs_f64() * 1000.0))
+}
+
+/// Seconds passed since the `clock()` value
+fn elapsed(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let Object::Double(start) = args[0] else {
+        return Err(unexpected_type("elapsed", "a number", &args[0]));
+    };
+    let now = interpreter.uptime().as_secs_f64();
+    Ok(Object::Double(now - start))
+}
+
+/// Blocks for the fractional number of seconds
+fn sleep(interpreter: &mut Interpreter, args: &[Object]) -> FoxResult<Object> {
+    let duration = match args[0] {
+        Object::Double(seconds) => Duration::try_from_secs_f64(seconds).ok(),
+        _ => None,
+    };
+    let Some(duration) = duration else {
+        return Err(FoxError::runtime(
+            None,
+            &format!(
+                "sleep() expects a non-negative number of seconds, got {}",
+                args[0]
+            ),
+        ));
+    };
+    // counted down, a deadline may not fit into an instant
+    let mut left = duration;
+    loop {
+        interpreter.check_cancelled()?;
+        if left.is_zero() {
+            return Ok(Object::Nil);
+        }
+        let slice_start = Instant::now();
+        std::thread::sleep(left.min(SLEEP_SLICE));
+        left = left.saturating_sub(slice_start.elapsed());
+    }
+}
@@ -2,12 +2,21 @@ use std::{collections::HashMap, fmt::Display, rc::Rc};
 
 pub const INITIALIZER_NAME: &str = "init";
 
+/// Method converting instances to text for `print` & string concatenation
+pub const TO_STRING_NAME: &str = "toString";
+
+/// Method called with the name of a property the instance doesn't have
+pub const ON_MISSING_NAME: &str = "onMissing";
+
+/// Method comparing an instance with the other operand of `==` & `!=`
+pub const EQUALS_NAME: &str = "equals";
+
 use crate::fox::{
     FoxError, FoxResult,
     func::Func,
     object::*,
     token::Token,
-    utils::{SharedPtr, fill_hash, mutable_cell},
+    utils::{SharedPtr, mutable_cell},
 };
 
 /// MetaClass (functions)
@@ -17,6 +26,7 @@ pub struct MetaClass {
     name: String,
     superclass: Option<Rc<MetaClass>>,
     methods: HashMap<String, Func>,
+    constants: HashMap<String, Object>,
 }
 
 pub struct Constructor {
@@ -25,6 +35,10 @@ pub struct Constructor {
 }
 
 impl MetaClass {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn constructor(meta: Rc<Self>) -> Constructor {
         let instance = ClassInstance::new(meta.clone());
         let instance = mutable_cell(instance);
@@ -41,11 +55,13 @@ impl MetaClass {
         name: &str,
         superclass: Option<Rc<MetaClass>>,
         methods: HashMap<String, Func>,
+        constants: HashMap<String, Object>,
     ) -> Self {
         Self {
             name: name.to_string(),
             superclass,
             methods,
+            constants,
         }
     }
 
@@ -56,6 +72,67 @@ impl MetaClass {
         method.arity()
     }
 
+    pub fn is_variadic(&self) -> bool {
+        self.find_method(INITIALIZER_NAME)
+            .is_some_and(|method| method.is_variadic())
+    }
+
+    /// Checks if the class is the other one or inherits from it
+    pub fn is_subclass_of(&self, other: &MetaClass) -> bool {
+        std::ptr::eq(self, other)
+            || self
+                .superclass
+                .as_ref()
+                .is_some_and(|superclass| superclass.is_subclass_of(other))
+    }
+
+    /// The `toString()` method if the class or an ancestor declares one without parameters
+    pub fn to_string_method(&self) -> Option<Func> {
+        self.find_method(TO_STRING_NAME)
+            .filter(|method| method.arity() == 0 && !method.is_variadic() && !method.is_getter())
+    }
+
+    /// The `onMissing(name)` method if the class or an ancestor declares one
+    pub fn missing_handler(&self) -> Option<Func> {
+        self.find_method(ON_MISSING_NAME)
+            .filter(|method| method.arity() == 1 && !method.is_variadic() && !method.is_getter())
+    }
+
+    /// The `equals(other)` method if the class or an ancestor declares one
+    pub fn equals_method(&self) -> Option<Func> {
+        self.find_method(EQUALS_NAME)
+            .filter(|method| method.arity() == 1 && !method.is_variadic() && !method.is_getter())
+    }
+
+    /// Names of the declared & inherited methods, sorted
+    pub fn method_names(&self) -> Vec<String> {
+        let mut names = self.methods.keys().cloned().collect::<Vec<_>>();
+        if let Some(superclass) = &self.superclass {
+            names.extend(superclass.method_names());
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    pub fn constant_assignment_error(&self, name: &Token) -> FoxError {
+        let message = format!(
+            "Can't assign to constant '{}' of class '{}'",
+            name.lexeme, self.name
+        );
+        FoxError::runtime(Some(name.clone()), &message)
+    }
+
+    /// Value of the constant declared by the class or the nearest ancestor
+    pub fn find_constant(&self, name: &str) -> Option<Object> {
+        if let Some(value) = self.constants.get(name) {
+            return Some(value.clone());
+        }
+        self.superclass
+            .as_ref()
+            .and_then(|superclass| superclass.find_constant(name))
+    }
+
     pub fn find_method(&self, name: &str) -> Option<Func> {
         let func = self.methods.get(name);
         if func.is_some() {
@@ -75,11 +152,14 @@ impl Display for MetaClass {
     }
 }
 
-impl std::hash::Hash for MetaClass {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.name.hash(state);
-        fill_hash(&self.methods, state);
-    }
+/// Instance property found by name
+///
+pub enum Property {
+    Value(Object),
+    // bound getter the caller has to execute to get the value
+    Getter(Func),
+    // bound `onMissing(name)` the caller has to execute instead of failing
+    Missing(Func),
 }
 
 /// Class instance (data only)
@@ -88,6 +168,8 @@ impl std::hash::Hash for MetaClass {
 pub struct ClassInstance {
     meta_class_ref: Rc<MetaClass>,
     fields: HashMap<String, Object>,
+    // the hash is field based, so it stays stable only once frozen
+    frozen: bool,
 }
 
 impl ClassInstance {
@@ -95,21 +177,82 @@ impl ClassInstance {
         Self {
             meta_class_ref,
             fields: HashMap::new(),
+            frozen: false,
         }
     }
 
-    pub fn get(instance_ref: SharedPtr<Self>, name: &Token) -> FoxResult<Object> {
+    /// The exact class the instance was created by
+    pub fn class(&self) -> Rc<MetaClass> {
+        self.meta_class_ref.clone()
+    }
+
+    /// Forbids any further field assignment, can't be undone
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Names of the assigned fields, sorted
+    pub fn field_names(&self) -> Vec<String> {
+        let mut names = self.fields.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    pub fn field_values(&self) -> impl Iterator<Item = &Object> {
+        self.fields.values()
+    }
+
+    pub fn has_field(&self, name: &str) -> bool {
+        self.fields.contains_key(name)
+    }
+
+    /// Frozen instances of the same class with equal fields are equal,
+    /// the field values are compared with `eq`
+    pub fn frozen_eq<F>(&self, other: &Self, mut eq: F) -> bool
+    where
+        F: FnMut(&Object, &Object) -> bool,
+    {
+        self.frozen
+            && other.frozen
+            && Rc::ptr_eq(&self.meta_class_ref, &other.meta_class_ref)
+            && self.fields.len() == other.fields.len()
+            && self.fields.iter().all(|(name, value)| {
+                other
+                    .fields
+                    .get(name)
+                    .is_some_and(|other_value| eq(value, other_value))
+            })
+    }
+
+    pub fn get(instance_ref: SharedPtr<Self>, name: &Token) -> FoxResult<Property> {
         let lexeme = &name.lexeme;
         if let Some(obj) = instance_ref.borrow().fields.get(lexeme).cloned() {
-            return Ok(obj);
+            return Ok(Property::Value(obj));
         };
 
-        if let Some(method) = instance_ref
+        let method = instance_ref
             .borrow()
             .meta_class_ref
-            .find_method(&name.lexeme)
-        {
-            return Ok(Object::Callee(method.bind(instance_ref.clone())));
+            .find_method(&name.lexeme);
+        if let Some(method) = method {
+            let func = method.bind(instance_ref.clone());
+            if func.is_getter() {
+                return Ok(Property::Getter(func));
+            }
+            return Ok(Property::Value(Object::Callee(func)));
+        }
+
+        if let Some(value) = instance_ref.borrow().meta_class_ref.find_constant(lexeme) {
+            return Ok(Property::Value(value));
+        }
+
+        let handler = instance_ref.borrow().meta_class_ref.missing_handler();
+        if let Some(handler) = handler {
+            return Ok(Property::Missing(handler.bind(instance_ref.clone())));
         }
 
         let err = FoxError::runtime(
@@ -119,15 +262,42 @@ impl ClassInstance {
         Err(err)
     }
 
-    pub fn set(&mut self, name: &Token, value: Object) {
+    pub fn set(&mut self, name: &Token, value: Object) -> FoxResult<()> {
+        if self.meta_class_ref.find_constant(&name.lexeme).is_some() {
+            return Err(self.meta_class_ref.constant_assignment_error(name));
+        }
+        if self.frozen {
+            let message = format!(
+                "cannot assign to field '{}' of frozen {} instance",
+                name.lexeme, self.meta_class_ref.name
+            );
+            return Err(FoxError::runtime(Some(name.clone()), &message));
+        }
         self.fields.insert(name.lexeme.clone(), value);
+        Ok(())
+    }
+
+    /// Replaces every field value with the transformed one
+    pub fn map_fields<F>(&mut self, mut transform: F)
+    where
+        F: FnMut(&Object) -> Object,
+    {
+        for value in self.fields.values_mut() {
+            *value = transform(value);
+        }
     }
 }
 
 impl std::hash::Hash for ClassInstance {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.meta_class_ref.hash(state);
-        fill_hash(&self.fields, state);
+        Rc::as_ptr(&self.meta_class_ref).hash(state);
+        // fields may point back at the instance, so their values are hashed shallow
+        let mut names = self.fields.keys().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            name.hash(state);
+            self.fields[name].hash_shallow(state);
+        }
     }
 }
 
@@ -0,0 +1,559 @@
+use std::fmt::Display;
+
+use crate::fox::{
+    Object, Scanner, Source, Token, TokenType, class::INITIALIZER_NAME, interpreter::Interpreter,
+};
+
+/// Reserved words offered outside of member access
+pub const KEYWORDS: [&str; 28] = [
+    "and", "assert", "break", "catch", "class", "const", "continue", "defer", "else", "false",
+    "for", "fun", "if", "import", "in", "is", "match", "nil", "or", "print", "return", "super",
+    "this", "throw", "true", "try", "var", "while",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompletionKind {
+    Variable,
+    Function,
+    Method,
+    Class,
+    Keyword,
+}
+
+impl CompletionKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompletionKind::Variable => "variable",
+            CompletionKind::Function => "function",
+            CompletionKind::Method => "method",
+            CompletionKind::Class => "class",
+            CompletionKind::Keyword => "keyword",
+        }
+    }
+}
+
+/// Identifier that may be typed at the cursor
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Completion {
+    pub name: String,
+    pub kind: CompletionKind,
+    /// Argument count of callables,
+    /// the smallest accepted one for builtins taking several
+    pub arity: Option<usize>,
+}
+
+impl Completion {
+    pub fn new(name: &str, kind: CompletionKind, arity: Option<usize>) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            arity,
+        }
+    }
+
+    /// Candidate describing the global value
+    pub fn with_value(name: &str, value: &Object) -> Self {
+        use CompletionKind::*;
+        let (kind, arity) = match value {
+            Object::BuiltinCallee(func) => (Function, func.arities().first().copied()),
+            Object::Callee(func) => (Function, Some(func.arity())),
+            Object::Class(meta) => (Class, Some(meta.arity())),
+            _ => (Variable, None),
+        };
+        Self::new(name, kind, arity)
+    }
+}
+
+impl Display for Completion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.arity {
+            Some(arity) => write!(f, "{}/{arity} ({})", self.name, self.kind.name()),
+            None => write!(f, "{} ({})", self.name, self.kind.name()),
+        }
+    }
+}
+
+/// Values defined by the interpreter & the globals declared by the host
+pub fn global_completions(interpreter: &Interpreter) -> Vec<Completion> {
+    let mut globals = interpreter
+        .global_bindings()
+        .iter()
+        .map(|(name, value)| Completion::with_value(name, value))
+        .collect::<Vec<_>>();
+    for declaration in interpreter.declared_globals() {
+        let kind = match declaration.arity {
+            Some(_) => CompletionKind::Function,
+            None => CompletionKind::Variable,
+        };
+        globals.push(Completion::new(&declaration.name, kind, declaration.arity));
+    }
+    globals
+}
+
+/// Position of the 1-based line & column,
+/// columns past the line end refer to the end
+pub fn position_at(code: &Source, line: usize, column: usize) -> usize {
+    let mut start = 0;
+    for _ in 1..line {
+        match code[start..].iter().position(|ch| *ch == '\n') {
+            Some(index) => start += index + 1,
+            None => return code.len(),
+        }
+    }
+    let end = code[start..]
+        .iter()
+        .position(|ch| *ch == '\n')
+        .map_or(code.len(), |index| start + index);
+    (start + column.saturating_sub(1)).min(end)
+}
+
+/// Methods & fields found in a class declaration
+struct ClassMembers {
+    name: String,
+    superclass: Option<String>,
+    members: Vec<Completion>,
+}
+
+/// Local scope opened by a `{`
+struct Scope {
+    names: Vec<Completion>,
+    // set for class bodies
+    class: Option<String>,
+}
+
+/// Candidates for the identifier ending at the position, sorted by name.
+/// `globals` are the values the interpreter defines before the script runs.
+///
+/// The code is analyzed by tokens since it's usually incomplete while typed:
+/// braces open scopes, parameters & loop variables belong to the following block.
+/// After `.` only the receivers known statically are completed:
+/// `this.` with the methods & assigned fields of the enclosing class,
+/// `ClassName.` with the methods declared for its instances.
+/// Members of other expressions are unknown, no candidates are returned for them
+pub fn complete(code: &Source, position: usize, globals: &[Completion]) -> Vec<Completion> {
+    let position = position.min(code.len());
+    let start = code[..position]
+        .iter()
+        .rposition(|ch| !(ch.is_alphanumeric() || *ch == '_'))
+        .map_or(0, |index| index + 1);
+    let word = code[start..position].iter().collect::<String>();
+    // the cursor inside a string or comment has nothing to complete
+    let Ok(mut prefix) = Scanner::with_source(&code[..start]).scan_tokens() else {
+        return Vec::new();
+    };
+    _ = prefix.pop();
+    let tokens = Scanner::with_source(code)
+        .scan_tokens()
+        .unwrap_or(prefix.clone());
+    let classes = collect_classes(&tokens);
+
+    let (scopes, pending) = track_scopes(&prefix, &classes);
+    let candidates = match prefix.as_slice() {
+        [.., receiver, access]
+            if matches!(access.token_type, TokenType::Dot | TokenType::QuestionDot) =>
+        {
+            member_candidates(receiver, &scopes, &classes)
+        }
+        _ => {
+            let mut candidates = pending;
+            for scope in scopes.into_iter().rev() {
+                candidates.extend(scope.names);
+            }
+            candidates.extend(top_level_declarations(&tokens, &classes));
+            candidates.extend_from_slice(globals);
+            let keywords = KEYWORDS
+                .iter()
+                .map(|name| Completion::new(name, CompletionKind::Keyword, None));
+            candidates.extend(keywords);
+            candidates
+        }
+    };
+
+    let mut result = Vec::<Completion>::new();
+    for candidate in candidates {
+        // inner declarations shadow the outer ones
+        if candidate.name.starts_with(&word) && !result.iter().any(|x| x.name == candidate.name) {
+            result.push(candidate);
+        }
+    }
+    result.sort_by(|a, b| a.name.cmp(&b.name));
+    result
+}
+
+fn identifier(token: Option<&Token>) -> Option<&str> {
+    token
+        .filter(|token| token.token_type == TokenType::Identifier)
+        .map(|token| token.lexeme.as_str())
+}
+
+fn is_type(tokens: &[Token], index: usize, token_type: TokenType) -> bool {
+    tokens
+        .get(index)
+        .is_some_and(|x| x.token_type == token_type)
+}
+
+/// Parameter names listed in parentheses starting at the index
+fn parameters(tokens: &[Token], open: usize) -> Vec<Completion> {
+    if !is_type(tokens, open, TokenType::LeftParenthesis) {
+        return Vec::new();
+    }
+    tokens[open + 1..]
+        .iter()
+        .take_while(|token| token.token_type != TokenType::RightParenthesis)
+        .filter(|token| token.token_type == TokenType::Identifier)
+        .map(|token| Completion::new(&token.lexeme, CompletionKind::Variable, None))
+        .collect()
+}
+
+/// Count of the parameters before the rest one if any
+fn least_arity(tokens: &[Token], open: usize) -> usize {
+    let params = parameters(tokens, open).len();
+    let is_variadic = tokens
+        .get(open + 1..)
+        .unwrap_or_default()
+        .iter()
+        .take_while(|token| token.token_type != TokenType::RightParenthesis)
+        .any(|token| token.token_type == TokenType::DotDotDot);
+    params - usize::from(is_variadic && params > 0)
+}
+
+/// Name of the class if the brace at the index opens its body
+fn class_body_name(tokens: &[Token], brace: usize) -> Option<String> {
+    let before = |offset: usize| brace.checked_sub(offset).and_then(|i| tokens.get(i));
+    let name = identifier(before(1))?;
+    if before(2).is_some_and(|x| x.token_type == TokenType::Class) {
+        return Some(name.to_string());
+    }
+    let is_subclass = before(2).is_some_and(|x| x.token_type == TokenType::Less)
+        && before(4).is_some_and(|x| x.token_type == TokenType::Class);
+    is_subclass
+        .then(|| identifier(before(3)))
+        .flatten()
+        .map(str::to_string)
+}
+
+fn collect_classes(tokens: &[Token]) -> Vec<ClassMembers> {
+    let mut classes = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if token.token_type != TokenType::LeftBrace {
+            continue;
+        }
+        let Some(name) = class_body_name(tokens, index) else {
+            continue;
+        };
+        let superclass =
+            is_type(tokens, index - 2, TokenType::Less).then(|| tokens[index - 1].lexeme.clone());
+        let mut members = Vec::new();
+        let mut depth = 0;
+        for (i, token) in tokens.iter().enumerate().skip(index) {
+            match token.token_type {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace => depth -= 1,
+                TokenType::Identifier
+                    if depth == 1 && is_type(tokens, i + 1, TokenType::LeftParenthesis) =>
+                {
+                    let arity = least_arity(tokens, i + 1);
+                    let method =
+                        Completion::new(&token.lexeme, CompletionKind::Method, Some(arity));
+                    members.push(method);
+                }
+                // getters & constants are read like fields
+                TokenType::Identifier
+                    if depth == 1
+                        && (is_type(tokens, i + 1, TokenType::LeftBrace)
+                            || is_type(tokens, i - 1, TokenType::Const)) =>
+                {
+                    members.push(Completion::new(
+                        &token.lexeme,
+                        CompletionKind::Variable,
+                        None,
+                    ));
+                }
+                TokenType::This
+                    if is_type(tokens, i + 1, TokenType::Dot)
+                        && is_type(tokens, i + 3, TokenType::Equal) =>
+                {
+                    if let Some(field) = identifier(tokens.get(i + 2)) {
+                        members.push(Completion::new(field, CompletionKind::Variable, None));
+                    }
+                }
+                _ => {}
+            }
+            if depth == 0 {
+                break;
+            }
+        }
+        classes.push(ClassMembers {
+            name,
+            superclass,
+            members,
+        });
+    }
+    classes
+}
+
+fn class_arity(classes: &[ClassMembers], name: &str) -> Option<usize> {
+    class_members(classes, name)
+        .into_iter()
+        .find(|member| member.name == INITIALIZER_NAME)
+        .map_or(Some(0), |init| init.arity)
+}
+
+/// Members of the class & its superclasses, the subclass ones go first
+fn class_members(classes: &[ClassMembers], name: &str) -> Vec<Completion> {
+    let mut members = Vec::new();
+    let mut visited = Vec::new();
+    let mut current = Some(name.to_string());
+    while let Some(name) = current.take() {
+        if visited.contains(&name) {
+            break;
+        }
+        let Some(class) = classes.iter().find(|class| class.name == name) else {
+            break;
+        };
+        members.extend(class.members.iter().cloned());
+        current = class.superclass.clone();
+        visited.push(name);
+    }
+    members
+}
+
+/// Global variables, functions & classes of the script wherever they are declared
+fn top_level_declarations(tokens: &[Token], classes: &[ClassMembers]) -> Vec<Completion> {
+    let mut declarations = Vec::new();
+    let mut depth = 0usize;
+    let mut parens = 0usize;
+    for (index, token) in tokens.iter().enumerate() {
+        match token.token_type {
+            TokenType::LeftBrace => depth += 1,
+            TokenType::RightBrace => depth = depth.saturating_sub(1),
+            TokenType::LeftParenthesis => parens += 1,
+            TokenType::RightParenthesis => parens = parens.saturating_sub(1),
+            _ => {}
+        }
+        if depth > 0 || parens > 0 {
+            continue;
+        }
+        declarations.extend(declared_by(tokens, index, classes));
+    }
+    declarations
+}
+
+/// Variables, function or class declared by the keyword at the index
+fn declared_by(tokens: &[Token], index: usize, classes: &[ClassMembers]) -> Vec<Completion> {
+    if matches!(tokens[index].token_type, TokenType::Var | TokenType::Const) {
+        return variables(tokens, index);
+    }
+    let Some(name) = identifier(tokens.get(index + 1)) else {
+        return Vec::new();
+    };
+    let completion = match tokens[index].token_type {
+        TokenType::Fun => {
+            let arity = least_arity(tokens, index + 2);
+            Completion::new(name, CompletionKind::Function, Some(arity))
+        }
+        TokenType::Class => {
+            Completion::new(name, CompletionKind::Class, class_arity(classes, name))
+        }
+        _ => return Vec::new(),
+    };
+    vec![completion]
+}
+
+/// Variables of the list declared by the keyword at the index,
+/// e.g. `var a = 1, b;` or `var [a, ...rest] = list;`
+fn variables(tokens: &[Token], keyword: usize) -> Vec<Completion> {
+    let mut names = Vec::new();
+    let mut nesting = 0usize;
+    let mut expects_name = true;
+    let mut in_pattern = false;
+    for token in &tokens[keyword + 1..] {
+        match token.token_type {
+            TokenType::LeftBracket if nesting == 0 && expects_name => {
+                nesting += 1;
+                in_pattern = true;
+                continue;
+            }
+            TokenType::LeftParenthesis | TokenType::LeftBracket | TokenType::LeftBrace => {
+                nesting += 1
+            }
+            TokenType::RightParenthesis | TokenType::RightBracket | TokenType::RightBrace => {
+                let Some(outer) = nesting.checked_sub(1) else {
+                    break;
+                };
+                nesting = outer;
+                in_pattern = false;
+            }
+            TokenType::Semicolon if nesting == 0 => break,
+            TokenType::Comma if nesting == 0 || in_pattern => {
+                expects_name = true;
+                continue;
+            }
+            TokenType::DotDotDot if in_pattern => continue,
+            TokenType::Identifier if expects_name => {
+                names.push(Completion::new(
+                    &token.lexeme,
+                    CompletionKind::Variable,
+                    None,
+                ));
+            }
+            _ => {}
+        }
+        expects_name = false;
+    }
+    names
+}
+
+/// Local scopes open at the end of the tokens
+/// and the names waiting for the next block, e.g. parameters
+fn track_scopes(tokens: &[Token], classes: &[ClassMembers]) -> (Vec<Scope>, Vec<Completion>) {
+    let mut scopes = Vec::<Scope>::new();
+    let mut pending = Vec::new();
+    let mut parens = 0usize;
+    for (index, token) in tokens.iter().enumerate() {
+        match token.token_type {
+            TokenType::LeftParenthesis => parens += 1,
+            TokenType::RightParenthesis => parens = parens.saturating_sub(1),
+            TokenType::LeftBrace => scopes.push(Scope {
+                names: std::mem::take(&mut pending),
+                class: class_body_name(tokens, index),
+            }),
+            TokenType::RightBrace => _ = scopes.pop(),
+            // a loop body without braces is over
+            TokenType::Semicolon if parens == 0 => pending.clear(),
+            TokenType::Var if parens > 0 => pending.extend(variables(tokens, index)),
+            TokenType::Var | TokenType::Const | TokenType::Class => {
+                // the globals are collected from the whole script
+                if let Some(scope) = scopes.last_mut() {
+                    scope.names.extend(declared_by(tokens, index, classes));
+                }
+            }
+            TokenType::Fun => {
+                let open = if identifier(tokens.get(index + 1)).is_some() {
+                    index + 2
+                } else {
+                    index + 1
+                };
+                if let Some(scope) = scopes.last_mut() {
+                    scope.names.extend(declared_by(tokens, index, classes));
+                }
+                pending = parameters(tokens, open);
+            }
+            TokenType::Identifier
+                if scopes.last().is_some_and(|scope| scope.class.is_some())
+                    && is_type(tokens, index + 1, TokenType::LeftParenthesis) =>
+            {
+                // method declaration
+                pending = parameters(tokens, index + 1);
+            }
+            _ => {}
+        }
+    }
+    (scopes, pending)
+}
+
+fn member_candidates(
+    receiver: &Token,
+    scopes: &[Scope],
+    classes: &[ClassMembers],
+) -> Vec<Completion> {
+    match receiver.token_type {
+        TokenType::This => scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.class.as_deref())
+            .map(|class| class_members(classes, class))
+            .unwrap_or_default(),
+        TokenType::Identifier if classes.iter().any(|x| x.name == receiver.lexeme) => {
+            class_members(classes, &receiver.lexeme)
+                .into_iter()
+                .filter(|member| member.kind == CompletionKind::Method)
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names(code: &str) -> Vec<String> {
+        let code = code.chars().collect::<Vec<_>>();
+        complete(&code, code.len(), &[])
+            .into_iter()
+            .map(|x| x.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_position_at() {
+        let code = "ab\ncd\n".chars().collect::<Vec<_>>();
+        assert_eq!(position_at(&code, 1, 1), 0);
+        assert_eq!(position_at(&code, 1, 3), 2);
+        assert_eq!(position_at(&code, 1, 10), 2);
+        assert_eq!(position_at(&code, 2, 2), 4);
+        assert_eq!(position_at(&code, 3, 1), 6);
+        assert_eq!(position_at(&code, 9, 1), 6);
+    }
+
+    #[test]
+    fn test_rest_parameter() {
+        assert_eq!(names("fun f(first, ...rest) { re"), ["rest", "return"]);
+        let code = "fun sum(first, ...rest) {}\nsu".chars().collect::<Vec<_>>();
+        assert_eq!(
+            complete(&code, code.len(), &[])[0],
+            Completion::new("sum", CompletionKind::Function, Some(1))
+        );
+    }
+
+    #[test]
+    fn test_getter_member() {
+        let code = "class C { const MAX = 2; area { return 1; } add(x) {} m() { this."
+            .chars()
+            .collect::<Vec<_>>();
+        let candidates = complete(&code, code.len(), &[]);
+        assert!(candidates.contains(&Completion::new("area", CompletionKind::Variable, None)));
+        assert!(candidates.contains(&Completion::new("MAX", CompletionKind::Variable, None)));
+        assert!(candidates.contains(&Completion::new("add", CompletionKind::Method, Some(1))));
+    }
+
+    #[test]
+    fn test_scopes() {
+        assert_eq!(names("{ var inner; }\nvar outer; in"), ["in"]);
+        assert_eq!(names("fun f(arg) { { var a; } a"), ["and", "arg", "assert"]);
+        assert_eq!(names("var f = fun (arg) { ar"), ["arg"]);
+        assert_eq!(
+            names("for (var i = 0; i < 3; i = i + 1) { var ix; i"),
+            ["i", "if", "import", "in", "is", "ix"]
+        );
+    }
+
+    #[test]
+    fn test_var_list() {
+        let code = "var alpha = f(1, a1), beta = [2, a2], abc;\na";
+        assert_eq!(names(code), ["abc", "alpha", "and", "assert"]);
+        assert_eq!(
+            names("for (var i = 0, ix = 3; i < ix; i = i + 1) { i"),
+            ["i", "if", "import", "in", "is", "ix"]
+        );
+        assert_eq!(names("{ var bx, by = {\"k\": 1}; b"), ["break", "bx", "by"]);
+    }
+
+    #[test]
+    fn test_var_pattern() {
+        let code = "var [head, ...hidden] = [h1, h2], [hx] = h3;
+h";
+        assert_eq!(names(code), ["head", "hidden", "hx"]);
+    }
+
+    #[test]
+    fn test_nothing_to_complete() {
+        assert!(names("print \"Sha").is_empty());
+        assert!(names("/* cl").is_empty());
+        // members of values are unknown statically
+        assert!(names("var a = 1;\na.").is_empty());
+        assert!(names("fun f() {}\nf().").is_empty());
+    }
+}
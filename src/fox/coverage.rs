@@ -0,0 +1,293 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::fox::{
+    CodeLocation,
+    ast::{FunctionStmt, Statement},
+};
+
+/// Function coverage entry
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionCoverage {
+    pub name: String,
+    pub line: usize,
+    pub calls: usize,
+}
+
+/// Runtime coverage collector
+/// keeps execution counters for every executable line & declared function
+///
+#[derive(Debug, Default)]
+pub struct Coverage {
+    lines: BTreeMap<usize, usize>,
+    functions: BTreeMap<(usize, String), usize>,
+    // position of the last registered statement, the imported modules
+    // follow the script in the source so anything after it isn't covered
+    last_position: usize,
+}
+
+impl Coverage {
+    pub fn with(statements: &[Statement]) -> Self {
+        let mut coverage = Self::default();
+        coverage.register_statements(statements);
+        coverage
+    }
+
+    fn register_statements(&mut self, statements: &[Statement]) {
+        for stmt in statements {
+            self.register_statement(stmt);
+        }
+    }
+
+    fn register_statement(&mut self, stmt: &Statement) {
+        if let Some(token) = stmt.token() {
+            let location = token.code_location;
+            self.lines.entry(location.line_number()).or_insert(0);
+            self.last_position = self.last_position.max(location.absolute_position());
+        }
+        match stmt {
+            Statement::Block(data) => self.register_statements(&data.statements),
+            Statement::Class(data) => self.register_statements(&data.methods),
+            Statement::Function(data) => {
+                self.functions.entry(Self::function_key(data)).or_insert(0);
+                self.register_statements(&data.body);
+            }
+            Statement::If(data) => {
+                self.register_statement(&data.then_branch);
+                if let Some(else_branch) = &data.else_branch {
+                    self.register_statement(else_branch);
+                }
+            }
+            Statement::Match(data) => {
+                for (_, stmt) in &data.arms {
+                    self.register_statement(stmt);
+                }
+                if let Some(default) = &data.default {
+                    self.register_statement(default);
+                }
+            }
+            Statement::While(data) => self.register_statement(&data.body),
+            Statement::ForIn(data) => self.register_statement(&data.body),
+            Statement::Defer(data) => self.register_statement(&data.body),
+            Statement::Try(data) => {
+                self.register_statement(&data.body);
+                self.register_statement(&data.handler);
+            }
+            Statement::Assert(_)
+            | Statement::Break(_)
+            | Statement::Continue(_)
+            | Statement::Expression(_)
+            | Statement::Import(_)
+            | Statement::Print(_)
+            | Statement::Return(_)
+            | Statement::Throw(_)
+            | Statement::Var(_)
+            | Statement::VarPattern(_) => {
+                // no nested statements of the script
+            }
+        }
+    }
+
+    fn function_key(func: &FunctionStmt) -> (usize, String) {
+        (
+            func.name.code_location.line_number(),
+            func.name.lexeme.clone(),
+        )
+    }
+
+    fn is_covered(&self, location: &CodeLocation) -> bool {
+        location.absolute_position() <= self.last_position
+    }
+
+    pub fn hit_statement(&mut self, stmt: &Statement) {
+        if let Some(token) = stmt.token()
+            && self.is_covered(&token.code_location)
+        {
+            *self
+                .lines
+                .entry(token.code_location.line_number())
+                .or_insert(0) += 1;
+        }
+    }
+
+    pub fn hit_function(&mut self, func: &FunctionStmt) {
+        if self.is_covered(&func.name.code_location) {
+            *self.functions.entry(Self::function_key(func)).or_insert(0) += 1;
+        }
+    }
+
+    pub fn report(self, source_name: &str) -> CoverageReport {
+        let functions = self
+            .functions
+            .into_iter()
+            .map(|((line, name), calls)| FunctionCoverage { name, line, calls })
+            .collect();
+        CoverageReport {
+            source_name: source_name.to_string(),
+            lines: self.lines,
+            functions,
+        }
+    }
+}
+
+/// Coverage data collected during single script run
+///
+#[derive(Debug, Clone)]
+pub struct CoverageReport {
+    source_name: String,
+    lines: BTreeMap<usize, usize>,
+    functions: Vec<FunctionCoverage>,
+}
+
+impl CoverageReport {
+    pub fn source_name(&self) -> &str {
+        &self.source_name
+    }
+
+    /// Execution count per line for every line containing a statement
+    pub fn lines(&self) -> &BTreeMap<usize, usize> {
+        &self.lines
+    }
+
+    /// Declared functions & methods ordered by declaration line
+    pub fn functions(&self) -> &[FunctionCoverage] {
+        &self.functions
+    }
+
+    pub fn uncovered_lines(&self) -> Vec<usize> {
+        self.lines
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(line, _)| *line)
+            .collect()
+    }
+
+    pub fn percentage(&self) -> f32 {
+        if self.lines.is_empty() {
+            return 100.0;
+        }
+        let covered = self.lines.values().filter(|count| **count > 0).count();
+        100.0 * covered as f32 / self.lines.len() as f32
+    }
+}
+
+impl Display for CoverageReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let uncovered = self.uncovered_lines();
+        writeln!(
+            f,
+            "Coverage of {}: {}/{} lines ({:.1}%)",
+            self.source_name,
+            self.lines.len() - uncovered.len(),
+            self.lines.len(),
+            self.percentage()
+        )?;
+        if !uncovered.is_empty() {
+            let lines = uncovered
+                .iter()
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(f, "Uncovered lines: {lines}")?;
+        }
+        if !self.functions.is_empty() {
+            writeln!(f, "Functions:")?;
+        }
+        for func in &self.functions {
+            write!(f, "  {} (line {}): ", func.name, func.line)?;
+            if func.calls == 0 {
+                writeln!(f, "never called")?;
+            } else {
+                writeln!(f, "{} call(s)", func.calls)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fox::{Fox, FoxConfig};
+
+    fn run_with_coverage(code: &str) -> Fox {
+        let config = FoxConfig {
+            coverage: true,
+            ..Default::default()
+        };
+        let mut fox = Fox::with_config(code.chars().collect(), config);
+        fox.run().unwrap();
+        fox
+    }
+
+    #[test]
+    fn test_uncovered_branch() {
+        let fox = run_with_coverage(
+            r"var a = 1;
+if (a > 1) {
+    a = 2;
+} else {
+    a = 3;
+}
+fun never() {
+    return 1;
+}
+fun once() {
+    return 2;
+}
+once();",
+        );
+        let report = fox.coverage_report().unwrap();
+        assert_eq!(report.uncovered_lines(), vec![3, 8]);
+        assert_eq!(report.lines()[&5], 1);
+        assert_eq!(report.lines()[&13], 1);
+
+        let calls = report
+            .functions()
+            .iter()
+            .map(|func| (func.name.as_str(), func.calls))
+            .collect::<Vec<_>>();
+        assert_eq!(calls, vec![("never", 0), ("once", 1)]);
+    }
+
+    #[test]
+    fn test_loop_counts() {
+        let fox = run_with_coverage(
+            r"var i = 0;
+while (i < 3) {
+    i = i + 1;
+}",
+        );
+        let report = fox.coverage_report().unwrap();
+        assert_eq!(report.lines()[&2], 1);
+        assert_eq!(report.lines()[&3], 3);
+        assert!(report.uncovered_lines().is_empty());
+        assert_eq!(report.percentage(), 100.0);
+    }
+
+    #[test]
+    fn test_imported_code_not_covered() {
+        let config = FoxConfig {
+            source_name: "tests/fixtures/imports/main.fox".to_string(),
+            coverage: true,
+            ..Default::default()
+        };
+        let code = std::fs::read_to_string(&config.source_name).unwrap();
+        let mut fox = Fox::with_config(code.chars().collect(), config);
+        fox.run().unwrap();
+        let report = fox.coverage_report().unwrap();
+        // the helpers declare `greet` at line 2, which is an import here
+        assert_eq!(
+            report.lines().keys().copied().collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5]
+        );
+        assert!(report.lines().values().all(|count| *count == 1));
+        assert!(report.functions().is_empty());
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let mut fox = Fox::with("print 1;".chars().collect());
+        fox.run().unwrap();
+        assert!(fox.coverage_report().is_none());
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt::Write;
+
+use crate::fox::escape_json;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// Error or warning prepared for machine-readable output
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable identifier of the error or warning kind
+    pub code: &'static str,
+    pub message: String,
+    pub file: String,
+    /// 1-based, absent if unknown
+    pub line: Option<usize>,
+    /// 1-based, absent if unknown or the code isn't available
+    pub column: Option<usize>,
+    /// Count of the highlighted chars
+    pub length: usize,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &'static str, message: &str, file: &str) -> Self {
+        Self {
+            severity,
+            code,
+            message: message.to_string(),
+            file: file.to_string(),
+            line: None,
+            column: None,
+            length: 0,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Single line JSON object, the notes are omitted if empty
+    pub fn to_json(&self) -> String {
+        let optional = |value: Option<usize>| value.map_or("null".to_string(), |x| x.to_string());
+        let mut json = format!(
+            "{{\"severity\":{},\"code\":{},\"message\":{},\"file\":{},\"line\":{},\"column\":{},\"length\":{}",
+            json_string(self.severity.name()),
+            json_string(self.code),
+            json_string(&self.message),
+            json_string(&self.file),
+            optional(self.line),
+            optional(self.column),
+            self.length
+        );
+        if !self.notes.is_empty() {
+            let notes = self
+                .notes
+                .iter()
+                .map(|note| json_string(note))
+                .collect::<Vec<_>>();
+            _ = write!(json, ",\"notes\":[{}]", notes.join(","));
+        }
+        json.push('}');
+        json
+    }
+}
+
+/// Quoted & escaped JSON string
+pub fn json_string(text: &str) -> String {
+    format!("\"{}\"", escape_json(text))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(
+            json_string("\"a\\b\"\n\t\u{1}▲"),
+            "\"\\\"a\\\\b\\\"\\n\\t\\u0001▲\""
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut diagnostic = Diagnostic::new(Severity::Error, "parse", "Expect ';'", "a.fox");
+        assert_eq!(
+            diagnostic.to_json(),
+            r#"{"severity":"error","code":"parse","message":"Expect ';'","file":"a.fox","line":null,"column":null,"length":0}"#
+        );
+        diagnostic.line = Some(2);
+        diagnostic.column = Some(5);
+        diagnostic.length = 3;
+        diagnostic.notes = vec!["line 1: var".to_string()];
+        assert_eq!(
+            diagnostic.to_json(),
+            r#"{"severity":"error","code":"parse","message":"Expect ';'","file":"a.fox","line":2,"column":5,"length":3,"notes":["line 1: var"]}"#
+        );
+    }
+}
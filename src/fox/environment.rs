@@ -1,6 +1,12 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 
-use crate::fox::{FoxError, FoxResult, mutable_cell, token::Token, utils::SharedPtr};
+use crate::fox::{
+    FoxError, FoxResult, KEYWORD_SUPER, class::MetaClass, mutable_cell, token::Token,
+    utils::SharedPtr,
+};
 
 use super::Object;
 
@@ -9,7 +15,11 @@ pub type SharedEnvironmentPtr = SharedPtr<Environment>;
 #[derive(Debug)]
 pub struct Environment {
     values: HashMap<String, Object>,
+    // declared without a value & not assigned yet, they hold nil meanwhile
+    unassigned: HashSet<String>,
     enclosing: Option<SharedEnvironmentPtr>,
+    // number of enclosing environments, zero for globals
+    depth: usize,
 }
 
 impl Environment {
@@ -18,12 +28,21 @@ impl Environment {
     }
 
     pub fn with(enclosing: Option<SharedEnvironmentPtr>) -> Self {
+        let depth = enclosing
+            .as_ref()
+            .map_or(0, |enclosing| enclosing.borrow().depth + 1);
         Self {
             values: Default::default(),
+            unassigned: Default::default(),
             enclosing,
+            depth,
         }
     }
 
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
     pub fn shared_ptr(self) -> SharedEnvironmentPtr {
         mutable_cell(self)
     }
@@ -46,11 +65,126 @@ impl Environment {
     }
 
     pub fn define(&mut self, name: &str, object: Object) {
+        self.unassigned.remove(name);
         self.values.insert(name.to_string(), object);
     }
 
+    /// Defines the variable that fails to be read until assigned
+    pub fn declare(&mut self, name: &str) {
+        self.values.insert(name.to_string(), Object::Nil);
+        self.unassigned.insert(name.to_string());
+    }
+
+    /// Whether the own variable has got a value
+    pub fn is_assigned(&self, name: &str) -> bool {
+        !self.unassigned.contains(name)
+    }
+
+    /// The error has no location, the caller knows the variable token
+    fn check_assigned(&self, name: &str) -> FoxResult<()> {
+        // most environments have no unassigned variables
+        if self.unassigned.is_empty() || !self.unassigned.contains(name) {
+            return Ok(());
+        }
+        Err(FoxError::runtime(
+            None,
+            &format!("Variable '{name}' used before being assigned"),
+        ))
+    }
+
+    /// Own bindings of the environment sorted by name
+    pub fn bindings(&self) -> Vec<(String, Object)> {
+        let mut bindings = self
+            .values
+            .iter()
+            .map(|(name, object)| (name.clone(), object.clone()))
+            .collect::<Vec<_>>();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
+
+    /// Own binding names sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names = self.values.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Bindings of the whole chain as (name, value, depth),
+    /// innermost environment first
+    pub fn all_bindings(&self) -> Vec<(String, Object, usize)> {
+        let mut result = self
+            .bindings()
+            .into_iter()
+            .map(|(name, object)| (name, object, 0))
+            .collect::<Vec<_>>();
+        let mut ptr = self.enclosing.clone();
+        let mut depth = 1;
+        while let Some(env) = ptr {
+            let env = env.borrow();
+            result.extend(
+                env.bindings()
+                    .into_iter()
+                    .map(|(name, object)| (name, object, depth)),
+            );
+            ptr = env.enclosing.clone();
+            depth += 1;
+        }
+        result
+    }
+
+    /// Bindings as (name, value, depth) from the environment up to,
+    /// but excluding, the `stop` one, innermost first
+    pub fn chain_bindings_until(
+        env: &SharedEnvironmentPtr,
+        stop: &SharedEnvironmentPtr,
+    ) -> Vec<(String, Object, usize)> {
+        let mut result = Vec::new();
+        let mut ptr = Some(env.clone());
+        let mut depth = 0;
+        while let Some(current) = ptr {
+            if Rc::ptr_eq(&current, stop) {
+                break;
+            }
+            let current = current.borrow();
+            result.extend(
+                current
+                    .bindings()
+                    .into_iter()
+                    .map(|(name, object)| (name, object, depth)),
+            );
+            ptr = current.enclosing.clone();
+            depth += 1;
+        }
+        result
+    }
+
+    /// Own binding names of each environment from the one up to,
+    /// but excluding, the `stop` one, outermost first
+    pub fn chain_names_until(
+        env: &SharedEnvironmentPtr,
+        stop: &SharedEnvironmentPtr,
+    ) -> Vec<Vec<String>> {
+        let mut result = Vec::new();
+        let mut ptr = Some(env.clone());
+        while let Some(current) = ptr {
+            if Rc::ptr_eq(&current, stop) {
+                break;
+            }
+            let current = current.borrow();
+            result.push(current.names());
+            ptr = current.enclosing.clone();
+        }
+        result.reverse();
+        result
+    }
+
     pub fn get(&self, token: &Token) -> FoxResult<Object> {
         let mut obj = self.values.get(&token.lexeme).cloned();
+        if obj.is_some() {
+            self.check_assigned(&token.lexeme)
+                .map_err(|err| err.with_fallback_token(token))?;
+        }
 
         if let Some(enclosing) = &self.enclosing
             && obj.is_none()
@@ -69,30 +203,38 @@ impl Environment {
         Ok(obj)
     }
 
+    /// Hot path of every local variable access:
+    /// the chain is walked by borrowing, without touching reference counters
     pub fn get_at(&self, distance: usize, name: &str) -> FoxResult<Object> {
-        let value = if distance == 0 {
-            self.values.get(name).cloned()
-        } else {
-            let enclosing = self.traverse_enclosing(distance)?;
-            enclosing.borrow().values.get(name).cloned()
-        };
-        let Some(obj) = value else {
+        if distance > 0 {
+            let Some(enclosing) = self.enclosing.as_ref() else {
+                let err = FoxError::bug("Invalid depth: Ancestor environment not found");
+                return Err(err);
+            };
+            return enclosing.borrow().get_at(distance - 1, name);
+        }
+        let Some(obj) = self.values.get(name).cloned() else {
             let err = FoxError::bug("Object not found");
             return Err(err);
         };
+        self.check_assigned(name)?;
         Ok(obj)
     }
 
-    pub fn assign_at(&mut self, distance: usize, name: &Token, value: Object) -> FoxResult<()> {
-        let insert_data =
-            |map: &mut HashMap<String, Object>| map.insert(name.lexeme.clone(), value);
+    /// Superclass of the innermost subclass body enclosing the environment
+    pub fn enclosing_superclass(&self) -> Option<Rc<MetaClass>> {
+        if let Some(Object::Class(superclass)) = self.values.get(KEYWORD_SUPER) {
+            return Some(superclass.clone());
+        }
+        self.enclosing.as_ref()?.borrow().enclosing_superclass()
+    }
 
+    pub fn assign_at(&mut self, distance: usize, name: &Token, value: Object) -> FoxResult<()> {
         if distance == 0 {
-            insert_data(&mut self.values);
+            self.define(&name.lexeme, value);
         } else {
             let enclosing = self.traverse_enclosing(distance)?;
-            let map = &mut enclosing.borrow_mut().values;
-            insert_data(map);
+            enclosing.borrow_mut().define(&name.lexeme, value);
         }
         Ok(())
     }
@@ -2,11 +2,11 @@ use std::fmt::Display;
 
 use crate::fox::{Object, Source, Token};
 
-use super::CodeLocation;
+use super::{BOM, CodeLocation};
 
 pub type FoxResult<T> = Result<T, FoxError>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FoxError {
     kind: ErrorKind,
     info: ErrorInfo,
@@ -45,6 +45,16 @@ impl FoxError {
         Self::token(kind, None)
     }
 
+    /// Recoverable failure of a builtin, the script may handle it
+    pub fn catchable(message: &str) -> Self {
+        Self::error(ErrorKind::Catchable(message.to_string()))
+    }
+
+    /// Host failure aborting the run whatever handlers the script has
+    pub fn fatal(message: &str) -> Self {
+        Self::error(ErrorKind::Fatal(message.to_string()))
+    }
+
     pub fn error(kind: ErrorKind) -> Self {
         Self {
             kind,
@@ -52,6 +62,44 @@ impl FoxError {
         }
     }
 
+    /// Control flow signals must never cross the given boundary,
+    /// so the leaked one is reported as a bug instead of being observed
+    pub fn sanitized(self, boundary: &str) -> Self {
+        if !self.kind.is_control_flow() {
+            return self;
+        }
+        let message = format!(
+            "Control flow signal '{}' escaped {boundary}",
+            self.kind.control_flow_name()
+        );
+        Self {
+            kind: ErrorKind::Bug(message),
+            info: self.info,
+        }
+    }
+
+    /// Thrown value nobody caught is reported as a runtime error
+    pub fn uncaught(self) -> Self {
+        if !matches!(self.kind, ErrorKind::Throw(_)) {
+            return self;
+        }
+        Self {
+            kind: ErrorKind::Runtime(self.kind.to_string()),
+            info: self.info,
+        }
+    }
+
+    /// Attaches the token to the error if it has no location yet
+    pub fn with_fallback_token(self, token: &Token) -> Self {
+        match self.info {
+            ErrorInfo::Empty => Self {
+                kind: self.kind,
+                info: ErrorInfo::Token(Box::new(token.clone())),
+            },
+            _ => self,
+        }
+    }
+
     pub fn kind(&self) -> &ErrorKind {
         &self.kind
     }
@@ -72,17 +120,117 @@ pub enum ErrorInfo {
 pub enum ErrorKind {
     UnexpectedCharacter,
     UnterminatedString,
+    UnterminatedComment,
+    InvalidDigitSeparator,
     ExpressionExpected,
     ExpectedOperator,
     TooManyFunctionArguments,
     UndefinedVariable(String),
     InvalidAssignmentTarget,
-    OperandMustBeNumber,
+    // type name of the operand received
+    OperandMustBeNumber(String),
     Runtime(String),
     Parse(String),
     Resolver(String),
+    Cache(String),
     Bug(String),
+    Catchable(String),
+    Fatal(String),
+    // `throw` unwinding to the nearest `catch`
+    Throw(Object),
     Return(Object),
+    // label of the targeted loop, the innermost one if none
+    Break(Option<String>),
+    Continue(Option<String>),
+    // `?.` met nil, caught by the enclosing chain
+    ShortCircuit,
+    // `exit()` stopping the script, nothing in the script can intercept it
+    Exit(i32),
+}
+
+impl ErrorKind {
+    pub fn is_control_flow(&self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Return(_)
+                | ErrorKind::Break(_)
+                | ErrorKind::Continue(_)
+                | ErrorKind::ShortCircuit
+        )
+    }
+
+    /// Whether a script-level handler may intercept the error,
+    /// fatal errors and bugs always propagate
+    pub fn is_catchable(&self) -> bool {
+        use ErrorKind::*;
+        matches!(
+            self,
+            Catchable(_) | Throw(_) | Runtime(_) | UndefinedVariable(_) | OperandMustBeNumber(_)
+        )
+    }
+
+    /// Whether the error was raised by the executed code,
+    /// failures the execution history & globals are kept for
+    pub fn is_runtime(&self) -> bool {
+        use ErrorKind::*;
+        matches!(
+            self,
+            Runtime(_) | UndefinedVariable(_) | OperandMustBeNumber(_)
+        )
+    }
+
+    /// Whether the error must abort the run even where failures are collected
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, ErrorKind::Fatal(_))
+    }
+
+    /// Process exit code requested by the script
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            ErrorKind::Exit(code) => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// Stable identifier used in machine-readable output
+    pub fn code(&self) -> &'static str {
+        use ErrorKind::*;
+        match self {
+            UnexpectedCharacter => "unexpected-character",
+            UnterminatedString => "unterminated-string",
+            UnterminatedComment => "unterminated-comment",
+            InvalidDigitSeparator => "invalid-digit-separator",
+            ExpressionExpected => "expression-expected",
+            ExpectedOperator => "expected-operator",
+            TooManyFunctionArguments => "too-many-arguments",
+            UndefinedVariable(_) => "undefined-variable",
+            InvalidAssignmentTarget => "invalid-assignment-target",
+            OperandMustBeNumber(_) => "operand-must-be-number",
+            Runtime(_) => "runtime",
+            Parse(_) => "parse",
+            Resolver(_) => "resolver",
+            Cache(_) => "cache",
+            Bug(_) => "bug",
+            Catchable(_) => "catchable",
+            Fatal(_) => "fatal",
+            Throw(_) => "uncaught-exception",
+            Return(_) => "return-signal",
+            Break(_) => "break-signal",
+            Continue(_) => "continue-signal",
+            ShortCircuit => "short-circuit-signal",
+            Exit(_) => "exit",
+        }
+    }
+
+    fn control_flow_name(&self) -> &str {
+        match self {
+            ErrorKind::Return(_) => "return",
+            ErrorKind::Break(_) => "break",
+            ErrorKind::Continue(_) => "continue",
+            ErrorKind::ShortCircuit => "short-circuit",
+            _ => "none",
+        }
+    }
 }
 
 impl Display for ErrorKind {
@@ -91,20 +239,110 @@ impl Display for ErrorKind {
         let text = match self {
             UnexpectedCharacter => "Unexpected character",
             UnterminatedString => "Unterminated string",
+            UnterminatedComment => "Unterminated block comment",
+            InvalidDigitSeparator => "Digit separator '_' must be between digits",
             ExpressionExpected => "Expect expression",
             ExpectedOperator => "Expect operator",
             TooManyFunctionArguments => "Can't have more than 255 arguments",
             UndefinedVariable(name) => &format!("Undefined variable {name}"),
             InvalidAssignmentTarget => "Invalid assignment target",
-            OperandMustBeNumber => "Operand must be a number",
-            Runtime(message) | Parse(message) | Resolver(message) => message,
+            OperandMustBeNumber(name) => &format!("Operand must be a number, got {name}"),
+            Runtime(message) | Parse(message) | Resolver(message) | Cache(message)
+            | Catchable(message) => message,
             Bug(message) => &format!("[BUG] {message}"),
-            Return(_) => unreachable!("Return shouldn't be an error"),
+            Fatal(message) => &format!("[FATAL] {message}"),
+            Throw(value) => &format!("Uncaught exception: {value}"),
+            Return(_) => "[BUG] Unhandled 'return' signal",
+            Break(_) => "[BUG] Unhandled 'break' signal",
+            Continue(_) => "[BUG] Unhandled 'continue' signal",
+            ShortCircuit => "[BUG] Unhandled optional chain signal",
+            Exit(code) => &format!("Exited with code {code}"),
         };
         write!(f, "{text}")
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    Deprecated,
+    MissingReturn,
+    DeepNesting,
+}
+
+impl WarningKind {
+    pub const ALL: [WarningKind; 3] = [
+        WarningKind::Deprecated,
+        WarningKind::MissingReturn,
+        WarningKind::DeepNesting,
+    ];
+
+    /// Name used to refer the warning kind from the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            WarningKind::Deprecated => "deprecated",
+            WarningKind::MissingReturn => "missing-return",
+            WarningKind::DeepNesting => "deep-nesting",
+        }
+    }
+
+    /// Stable identifier used in machine-readable output
+    pub fn code(&self) -> &'static str {
+        self.name()
+    }
+
+    /// Opt-in warnings are reported only when explicitly enabled
+    pub fn is_opt_in(&self) -> bool {
+        matches!(self, WarningKind::MissingReturn | WarningKind::DeepNesting)
+    }
+
+    pub fn with_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Warning {
+    kind: WarningKind,
+    message: String,
+    info: ErrorInfo,
+}
+
+impl Warning {
+    pub fn new(kind: WarningKind, message: &str) -> Self {
+        Self {
+            kind,
+            message: message.to_string(),
+            info: ErrorInfo::Empty,
+        }
+    }
+
+    pub fn with_token(kind: WarningKind, message: &str, token: &Token) -> Self {
+        Self {
+            kind,
+            message: message.to_string(),
+            info: ErrorInfo::Token(Box::new(token.clone())),
+        }
+    }
+
+    pub fn set_fallback_token(&mut self, token: &Token) {
+        if matches!(self.info, ErrorInfo::Empty) {
+            self.info = ErrorInfo::Token(Box::new(token.clone()));
+        }
+    }
+
+    pub fn kind(&self) -> WarningKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn info(&self) -> &ErrorInfo {
+        &self.info
+    }
+}
+
 pub struct ErrorLine {
     line_number: usize,
     text: String,
@@ -159,12 +397,21 @@ impl ErrorLine {
                 right += 1;
             }
         }
+        // scanner skips the mark, so it isn't part of the first line
+        if left == 0 && location.absolute_position() > 0 && code.first() == Some(&BOM) {
+            left = 1;
+        }
 
         (
             location.absolute_position() - left,
             code[left..=right].iter().collect::<String>(),
         )
     }
+
+    /// 1-based position of the location in the line
+    pub fn column(&self) -> usize {
+        self.position + 1
+    }
 }
 
 #[cfg(test)]
@@ -185,6 +432,80 @@ mod test {
         assert_eq!(chars[el.position], marker);
     }
 
+    #[test]
+    fn unique_codes() {
+        use ErrorKind::*;
+        let kinds = [
+            UnexpectedCharacter,
+            UnterminatedString,
+            UnterminatedComment,
+            InvalidDigitSeparator,
+            ExpressionExpected,
+            ExpectedOperator,
+            TooManyFunctionArguments,
+            UndefinedVariable(String::new()),
+            InvalidAssignmentTarget,
+            OperandMustBeNumber(String::new()),
+            Runtime(String::new()),
+            Parse(String::new()),
+            Resolver(String::new()),
+            Cache(String::new()),
+            Bug(String::new()),
+            Catchable(String::new()),
+            Fatal(String::new()),
+            Throw(Object::Nil),
+            Return(Object::Nil),
+            Break(None),
+            Continue(None),
+            ShortCircuit,
+            Exit(0),
+        ];
+        // fails to compile until a new variant is added to the list above
+        for kind in &kinds {
+            match kind {
+                UnexpectedCharacter
+                | UnterminatedString
+                | UnterminatedComment
+                | InvalidDigitSeparator
+                | ExpressionExpected
+                | ExpectedOperator
+                | TooManyFunctionArguments
+                | UndefinedVariable(_)
+                | InvalidAssignmentTarget
+                | OperandMustBeNumber(_)
+                | Runtime(_)
+                | Parse(_)
+                | Resolver(_)
+                | Cache(_)
+                | Bug(_)
+                | Catchable(_)
+                | Fatal(_)
+                | Throw(_)
+                | Return(_)
+                | Break(_)
+                | Continue(_)
+                | ShortCircuit
+                | Exit(_) => {}
+            }
+        }
+        let mut codes = kinds.iter().map(|kind| kind.code()).collect::<Vec<_>>();
+        codes.extend(WarningKind::ALL.iter().map(|kind| kind.code()));
+        let count = codes.len();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), count);
+    }
+
+    #[test]
+    fn fetch_first_line_after_bom() {
+        let source = "\u{feff}var X = 1;\r\nprint X;".chars().collect::<Vec<_>>();
+        let location = CodeLocation::new(1, 5);
+
+        let el = ErrorLine::with(&source, &location);
+        assert_eq!("var X = 1;", el.text);
+        assert_eq!(el.position, 4);
+    }
+
     fn make_source() -> Vec<char> {
         r"
             if (match(LEFT_PAREN)) {
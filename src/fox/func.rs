@@ -1,29 +1,30 @@
 use std::cell::RefCell;
+use std::fmt::{Debug, Display};
 use std::rc::Rc;
-use std::{
-    fmt::{Debug, Display},
-    time::{SystemTime, UNIX_EPOCH},
-};
 
 use crate::fox::ast::FunctionStmt;
 use crate::fox::class::ClassInstance;
 use crate::fox::environment::{Environment, SharedEnvironmentPtr};
-use crate::fox::{KEYWORD_THIS, Object};
+use crate::fox::interpreter::Interpreter;
+use crate::fox::{FoxResult, KEYWORD_THIS, Object};
 
 /// Builtin function definition
+/// errors without location are reported at the call site
 ///
-pub type BuiltinFnBody = dyn Fn(&[Object]) -> Object;
+pub type BuiltinFnBody = dyn Fn(&mut Interpreter, &[Object]) -> FoxResult<Object>;
 
 #[derive(Clone)]
 pub struct BuiltinFunc {
     pub body: Rc<BuiltinFnBody>,
-    arity: usize,
+    name: Rc<str>,
+    arities: &'static [usize],
 }
 
 impl Debug for BuiltinFunc {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Builtin func")
-            .field("arity", &self.arity)
+            .field("name", &self.name)
+            .field("arities", &self.arities)
             .finish()
     }
 }
@@ -31,7 +32,7 @@ impl Debug for BuiltinFunc {
 impl std::hash::Hash for BuiltinFunc {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         Rc::as_ptr(&self.body).hash(state);
-        self.arity.hash(state);
+        self.arities.hash(state);
     }
 }
 
@@ -39,34 +40,56 @@ impl Eq for BuiltinFunc {}
 
 impl PartialEq for BuiltinFunc {
     fn eq(&self, other: &Self) -> bool {
-        Rc::ptr_eq(&self.body, &other.body) && self.arity == other.arity
+        Rc::ptr_eq(&self.body, &other.body) && self.arities == other.arities
     }
 }
 
 impl Display for BuiltinFunc {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "<builtin fun ({} args)>", self.arity())
+        write!(f, "<builtin fun ({} args)>", self.arity_description())
     }
 }
 
 impl BuiltinFunc {
-    pub fn arity(&self) -> usize {
-        self.arity
+    pub fn new<F>(name: &str, body: F, arities: &'static [usize]) -> Self
+    where
+        F: Fn(&mut Interpreter, &[Object]) -> FoxResult<Object> + 'static,
+    {
+        Self {
+            body: Rc::new(body),
+            name: name.into(),
+            arities,
+        }
+    }
+
+    /// Name the builtin is defined with
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Accepted argument counts
+    pub fn arities(&self) -> &'static [usize] {
+        self.arities
     }
 
-    pub fn clock() -> Self {
-        let body = |_: &[Object]| -> Object {
-            let time = SystemTime::now();
-            let Ok(duration) = time.duration_since(UNIX_EPOCH) else {
-                println!("[ERROR] failed to calculate system time duration");
-                return Object::Nil;
-            };
-            Object::Double(duration.as_secs() as f32)
+    pub fn accepts(&self, count: usize) -> bool {
+        self.arities.contains(&count)
+    }
+
+    /// Accepted argument counts as "0", "0 or 1", "1, 2 or 3"
+    pub fn arity_description(&self) -> String {
+        let Some((last, rest)) = self.arities.split_last() else {
+            return "no".to_string();
         };
-        Self {
-            body: Rc::new(body),
-            arity: 0,
+        if rest.is_empty() {
+            return last.to_string();
         }
+        let rest = rest
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{rest} or {last}")
     }
 }
 
@@ -110,7 +133,8 @@ impl PartialEq for Func {
 impl Display for Func {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let id = if self.is_initializer { "init" } else { "fun" };
-        write!(f, "<{id} ({} args)>", self.arity())
+        let more = if self.is_variadic() { "+" } else { "" };
+        write!(f, "<{id} ({}{more} args)>", self.arity())
     }
 }
 
@@ -127,10 +151,21 @@ impl Func {
         }
     }
 
+    /// Least count of arguments, the exact one unless the function is variadic
     pub fn arity(&self) -> usize {
         self.decl.params.len()
     }
 
+    /// Takes any count of arguments over the arity
+    pub fn is_variadic(&self) -> bool {
+        self.decl.rest.is_some()
+    }
+
+    /// Method called on property access, `instance.name`
+    pub fn is_getter(&self) -> bool {
+        self.decl.getter
+    }
+
     pub fn bind(&self, instance: Rc<RefCell<ClassInstance>>) -> Func {
         let mut env = Environment::with(Some(self.closure.clone()));
         env.define(KEYWORD_THIS, Object::Instance(instance));
@@ -0,0 +1,102 @@
+use std::{collections::VecDeque, fmt::Display};
+
+use crate::fox::{CodeLocation, Object, ast::Statement};
+
+/// What was executed. The callee is kept as a shared value,
+/// so recording doesn't format anything
+///
+#[derive(Debug, Clone)]
+pub enum ExecutionEvent {
+    Statement(&'static str),
+    Call(Object),
+}
+
+impl Display for ExecutionEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Statement(kind) => write!(f, "{kind}"),
+            Self::Call(Object::BuiltinCallee(func)) => write!(f, "call {}", func.name()),
+            Self::Call(Object::Callee(func)) => write!(f, "call {}", func.decl.name.lexeme),
+            Self::Call(Object::Class(meta)) => write!(f, "call {}", meta.name()),
+            Self::Call(callee) => write!(f, "call {callee}"),
+        }
+    }
+}
+
+/// Ring buffer of the last executed statements & calls
+///
+#[derive(Debug)]
+pub struct ExecutionHistory {
+    events: VecDeque<(CodeLocation, ExecutionEvent)>,
+    capacity: usize,
+}
+
+impl ExecutionHistory {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, location: CodeLocation, event: ExecutionEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back((location, event));
+    }
+
+    pub fn record_statement(&mut self, stmt: &Statement) {
+        if let Some(token) = stmt.token() {
+            self.record(
+                token.code_location,
+                ExecutionEvent::Statement(stmt.kind_name()),
+            );
+        }
+    }
+
+    /// Events from the oldest to the latest one
+    pub fn events(&self) -> impl Iterator<Item = &(CodeLocation, ExecutionEvent)> {
+        self.events.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl Display for ExecutionHistory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "recent execution history:")?;
+        for (location, event) in self.events() {
+            write!(f, "\n  line {}: {event}", location.line_number())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ring_buffer() {
+        let mut history = ExecutionHistory::with_capacity(2);
+        for line in 1..=3 {
+            history.record(
+                CodeLocation::new(line, 0),
+                ExecutionEvent::Statement("print"),
+            );
+        }
+        assert_eq!(
+            history.to_string(),
+            "recent execution history:\n  line 2: print\n  line 3: print"
+        );
+        let mut disabled = ExecutionHistory::with_capacity(0);
+        disabled.record(CodeLocation::new(1, 0), ExecutionEvent::Statement("if"));
+        assert!(disabled.is_empty());
+    }
+}
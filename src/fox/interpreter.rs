@@ -1,42 +1,390 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
 use crate::fox::{
-    ErrorKind, FoxError, FoxResult, KEYWORD_SUPER, KEYWORD_THIS, Object, TokenType,
+    BinaryError, BinaryOp, CodeLocation, ErrorKind, FoxError, FoxResult, KEYWORD_SUPER,
+    KEYWORD_THIS, Object, TokenType, Warning, WarningKind,
     ast::*,
-    class::{ClassInstance, INITIALIZER_NAME, MetaClass},
+    builtins::{BuiltinGroup, BuiltinRegistry, standard_constant},
+    class::{
+        ClassInstance, INITIALIZER_NAME, MetaClass, ON_MISSING_NAME, Property, TO_STRING_NAME,
+    },
+    coverage::Coverage,
     environment::{Environment, SharedEnvironmentPtr},
     func::*,
+    history::{ExecutionEvent, ExecutionHistory},
+    mutable_cell,
+    object::key_order,
+    parser::{MAX_FUNCTION_ARGUMENT_COUNT, Parser},
+    random::Random,
+    range::NumberRange,
+    resolver::Resolver,
+    scanner::Scanner,
     token::Token,
 };
 
+/// Renamed builtins as (old name, new name) pairs,
+/// old names keep working but produce deprecation warning
+const DEPRECATED_BUILTINS: &[(&str, &str)] = &[];
+
+/// Line number of the first line of the `eval()` text, far beyond the code lines
+/// so the resolution keys of the text expressions never match the code ones
+pub const TEXT_FIRST_LINE: usize = usize::MAX / 2;
+
+/// Environment depth reported by the deep nesting warning
+pub const DEEP_NESTING_THRESHOLD: usize = 1000;
+
+/// Leading part of the property names private to the class & subclasses
+const PRIVATE_PREFIX: char = '_';
+
+/// Max count of nested `onMissing` calls, a handler reading
+/// a missing property of its own class would recurse forever
+const MISSING_HANDLER_DEPTH_LIMIT: usize = 64;
+
+thread_local! {
+    // updated on every statement so panics can be reported with the script location
+    static EXECUTING_LOCATION: Cell<Option<CodeLocation>> = const { Cell::new(None) };
+}
+
+/// Location of the statement executed last on the current thread
+pub fn executing_location() -> Option<CodeLocation> {
+    EXECUTING_LOCATION.get()
+}
+
+pub fn reset_executing_location() {
+    EXECUTING_LOCATION.set(None);
+}
+
+/// Global the host defines at runtime, known to the resolver in advance
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlobalDeclaration {
+    pub name: String,
+    /// Checked at call sites when set
+    pub arity: Option<usize>,
+}
+
+/// Counters collected during the execution
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Statistics {
+    /// Length of the longest environment chain a block was executed in
+    pub max_environment_depth: usize,
+}
+
+impl std::fmt::Display for Statistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Statistics:")?;
+        writeln!(f, "  max environment depth: {}", self.max_environment_depth)
+    }
+}
+
 pub struct Interpreter {
     environment: SharedEnvironmentPtr,
     globals: SharedEnvironmentPtr,
     locals: HashMap<Expression, usize>,
+    coverage: Option<Coverage>,
+    warnings: Vec<Warning>,
+    denied_warnings: HashSet<WarningKind>,
+    // once-flags for warnings that must be reported once per name
+    reported_warnings: HashSet<(WarningKind, String)>,
+    // printed values are collected here instead of stdout when set
+    output: Option<String>,
+    statistics: Statistics,
+    // depth warned about, none if the warning is disabled or already reported
+    nesting_threshold: Option<usize>,
+    history: Option<ExecutionHistory>,
+    cancel_flag: Option<Arc<AtomicBool>>,
+    declared_globals: Vec<GlobalDeclaration>,
+    // statements deferred by each block being executed, innermost last
+    deferred: Vec<Vec<Statement>>,
+    // count of `onMissing` calls being executed
+    missing_handler_depth: usize,
+    random: Random,
+    script_args: Vec<String>,
+    started: Instant,
+    // division by zero gives infinity or NaN instead of failing
+    ieee_division: bool,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        Self::with_builtins(&BuiltinGroup::ALL)
+    }
+
+    /// Builtins of other groups fail when called
+    pub fn with_builtins(enabled: &[BuiltinGroup]) -> Self {
         let mut env = Environment::new();
-        // register builtin functions
-        env.define("clock", Object::BuiltinCallee(BuiltinFunc::clock()));
+        BuiltinRegistry::standard()
+            .expect("builtin tables must be consistent")
+            .define(&mut env, enabled);
+        #[cfg(test)]
+        env.define(
+            "inject_panic",
+            Object::BuiltinCallee(BuiltinFunc::new(
+                "inject_panic",
+                |_, _| panic!("injected panic"),
+                &[0],
+            )),
+        );
+        #[cfg(test)]
+        env.define(
+            "inject_catchable",
+            Object::BuiltinCallee(BuiltinFunc::new(
+                "inject_catchable",
+                |_, _| Err(FoxError::catchable("injected failure")),
+                &[0],
+            )),
+        );
+        #[cfg(test)]
+        env.define(
+            "inject_fatal",
+            Object::BuiltinCallee(BuiltinFunc::new(
+                "inject_fatal",
+                |_, _| Err(FoxError::fatal("injected host failure")),
+                &[0],
+            )),
+        );
         let ptr = env.shared_ptr();
 
-        Self {
+        let mut interpreter = Self {
             environment: ptr.clone(),
             globals: ptr,
             locals: HashMap::new(),
+            coverage: None,
+            warnings: Vec::new(),
+            denied_warnings: HashSet::new(),
+            reported_warnings: HashSet::new(),
+            output: None,
+            statistics: Statistics::default(),
+            nesting_threshold: None,
+            history: None,
+            cancel_flag: None,
+            declared_globals: Vec::new(),
+            deferred: Vec::new(),
+            missing_handler_depth: 0,
+            random: Random::from_time(),
+            script_args: Vec::new(),
+            started: Instant::now(),
+            ieee_division: false,
+        };
+        for (old, new) in DEPRECATED_BUILTINS {
+            // the table is verified by tests
+            _ = interpreter.deprecated_alias(old, new);
+        }
+        interpreter
+    }
+
+    /// Defines `old` as a builtin forwarding to the builtin `new`
+    /// that warns about deprecation on the first call
+    pub fn deprecated_alias(&mut self, old: &str, new: &str) -> FoxResult<()> {
+        let target = match self.globals.borrow().get_at(0, new) {
+            Ok(Object::BuiltinCallee(func)) => func,
+            _ => {
+                return Err(FoxError::bug(&format!(
+                    "Deprecated alias target '{new}' isn't a builtin function"
+                )));
+            }
+        };
+        let old_name = old.to_string();
+        let message = format!("'{old}' is deprecated, use '{new}' instead");
+        let arities = target.arities();
+        let body = move |interpreter: &mut Interpreter, args: &[Object]| {
+            interpreter.warn_once(WarningKind::Deprecated, &old_name, &message)?;
+            (target.body)(interpreter, args)
+        };
+        let alias = BuiltinFunc::new(old, body, arities);
+        self.globals
+            .borrow_mut()
+            .define(old, Object::BuiltinCallee(alias));
+        Ok(())
+    }
+
+    /// Makes the warnings of the kind runtime errors
+    pub fn deny(&mut self, kind: WarningKind) {
+        self.denied_warnings.insert(kind);
+    }
+
+    pub fn is_denied(&self, kind: WarningKind) -> bool {
+        self.denied_warnings.contains(&kind)
+    }
+
+    pub fn add_warning(&mut self, warning: Warning) {
+        self.warnings.push(warning);
+    }
+
+    /// Emits the warning once per key or fails if the warning kind is denied
+    pub fn warn_once(&mut self, kind: WarningKind, key: &str, message: &str) -> FoxResult<()> {
+        if self.is_denied(kind) {
+            return Err(FoxError::runtime(None, message));
+        }
+        if self.reported_warnings.insert((kind, key.to_string())) {
+            self.warnings.push(Warning::new(kind, message));
+        }
+        Ok(())
+    }
+
+    /// Warns once about the first block executed deeper than the threshold
+    pub fn warn_deep_nesting(&mut self, threshold: usize) {
+        self.nesting_threshold = Some(threshold);
+    }
+
+    pub fn statistics(&self) -> Statistics {
+        self.statistics
+    }
+
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    pub fn enable_coverage(&mut self, statements: &[Statement]) {
+        self.coverage = Some(Coverage::with(statements));
+    }
+
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    pub fn declare_globals(&mut self, declarations: &[GlobalDeclaration]) {
+        self.declared_globals.extend_from_slice(declarations);
+    }
+
+    pub fn declared_globals(&self) -> &[GlobalDeclaration] {
+        &self.declared_globals
+    }
+
+    /// Execution fails at the next statement once the flag is set
+    pub fn set_cancel_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.cancel_flag = Some(flag);
+    }
+
+    /// Division & remainder by zero give infinity or NaN instead of failing
+    pub fn set_ieee_division(&mut self, enabled: bool) {
+        self.ieee_division = enabled;
+    }
+
+    /// Keeps the last `capacity` executed statements & calls
+    pub fn record_history(&mut self, capacity: usize) {
+        self.history = Some(ExecutionHistory::with_capacity(capacity));
+    }
+
+    pub fn take_history(&mut self) -> Option<ExecutionHistory> {
+        self.history.take()
+    }
+
+    /// Generator of the `random` builtins
+    pub fn random(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
+    /// Value of the text written as an expression that is resolved
+    /// against the running code scopes and evaluated in the current environment
+    // the resolution keys are syntax trees, their literals are never mutated
+    #[allow(clippy::mutable_key_type)]
+    pub fn evaluate_text(&mut self, text: &str) -> FoxResult<Object> {
+        let source = text.chars().collect::<Vec<_>>();
+        let tokens = Scanner::with_source_at(&source, 0, TEXT_FIRST_LINE).scan_tokens()?;
+        let expr = Parser::new(&tokens).parse_expression()?;
+        let scopes = Environment::chain_names_until(&self.environment, &self.globals);
+        // the text is resolved into its own table first, so a failure leaves no entries
+        let code_locals = std::mem::take(&mut self.locals);
+        let resolved = Resolver::with_scopes(self, scopes).resolve_expression(&expr);
+        let text_locals = std::mem::replace(&mut self.locals, code_locals);
+        resolved?;
+        // the entries of an enclosing `eval()` of the same text are put back afterwards
+        let replaced = text_locals
+            .into_iter()
+            .map(|(expr, depth)| {
+                let previous = self.locals.insert(expr.clone(), depth);
+                (expr, previous)
+            })
+            .collect::<Vec<_>>();
+        let result = self.evaluate(&expr);
+        for (expr, previous) in replaced {
+            match previous {
+                Some(depth) => _ = self.locals.insert(expr, depth),
+                None => _ = self.locals.remove(&expr),
+            }
+        }
+        result
+    }
+
+    /// Fails once the cancel flag is set
+    pub fn check_cancelled(&self) -> FoxResult<()> {
+        if let Some(flag) = &self.cancel_flag
+            && flag.load(Ordering::Relaxed)
+        {
+            // blocks have no location, so the last known one is reported
+            let kind = ErrorKind::Runtime("Execution cancelled".to_string());
+            return Err(match executing_location() {
+                Some(location) => FoxError::code_location(kind, location),
+                None => FoxError::error(kind),
+            });
+        }
+        Ok(())
+    }
+
+    /// Time passed since the interpreter was created
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Command line arguments of the script returned by `args()`
+    pub fn set_script_args(&mut self, args: Vec<String>) {
+        self.script_args = args;
+    }
+
+    pub fn script_args(&self) -> &[String] {
+        &self.script_args
+    }
+
+    /// Collects printed values instead of writing them to stdout
+    pub fn capture_output(&mut self) {
+        self.output = Some(String::new());
+    }
+
+    pub fn take_output(&mut self) -> Option<String> {
+        self.output.take()
+    }
+
+    /// Writes the text to stdout or the captured output as is,
+    /// everything the script prints goes through it
+    pub fn write_output(&mut self, text: &str) {
+        match &mut self.output {
+            Some(output) => output.push_str(text),
+            None => {
+                print!("{text}");
+                _ = std::io::Write::flush(&mut std::io::stdout());
+            }
         }
     }
 
     pub fn interpret(&mut self, statements: &[Statement]) -> FoxResult<()> {
         for statement in statements {
-            self.execute(statement)?;
+            self.execute(statement)
+                .map_err(|err| err.sanitized("top-level code").uncaught())?;
         }
         Ok(())
     }
 
     fn execute(&mut self, stmt: &Statement) -> FoxResult<()> {
+        if let Some(token) = stmt.token() {
+            EXECUTING_LOCATION.set(Some(token.code_location));
+        }
+        self.check_cancelled()?;
+        if let Some(coverage) = &mut self.coverage {
+            coverage.hit_statement(stmt);
+        }
+        if let Some(history) = &mut self.history {
+            history.record_statement(stmt);
+        }
         stmt.accept(self)
     }
 
@@ -44,10 +392,18 @@ impl Interpreter {
         expr.accept(self)
     }
 
-    fn execute_block(&mut self, statements: &[Statement], env: Environment) -> FoxResult<()> {
+    /// The token points to the code that has created the environment
+    fn execute_block(
+        &mut self,
+        statements: &[Statement],
+        env: Environment,
+        token: &Token,
+    ) -> FoxResult<()> {
+        self.track_depth(env.depth(), token)?;
         let prev = self.environment.clone();
 
         self.environment = env.shared_ptr();
+        self.deferred.push(Vec::new());
 
         // emulate the throw behavior
         let mut result: FoxResult<()> = FoxResult::Ok(());
@@ -58,12 +414,142 @@ impl Interpreter {
             }
         }
 
+        let result = self.run_deferred(result);
         self.environment = prev;
         result
     }
 
-    fn func_arity_check(&self, token: &Token, arity: usize, args: &[Object]) -> FoxResult<()> {
-        if args.len() != arity {
+    /// Runs the statements deferred by the block being left in reverse order, all of them.
+    /// An error of a deferred statement replaces the normal exit or a control flow signal,
+    /// so `return` keeps its value only if every deferred statement succeeds.
+    /// The block failed on its own keeps the error, the deferred ones are dropped
+    /// unless they can't be caught, so a `try` around can't swallow `exit()`
+    fn run_deferred(&mut self, mut result: FoxResult<()>) -> FoxResult<()> {
+        let deferred = self.deferred.pop().unwrap_or_default();
+        for stmt in deferred.iter().rev() {
+            let Err(err) = self.execute(stmt) else {
+                continue;
+            };
+            let replaces = match &result {
+                Ok(()) => true,
+                Err(current) => {
+                    current.kind().is_control_flow()
+                        || (current.kind().is_catchable() && !err.kind().is_catchable())
+                }
+            };
+            if replaces {
+                result = Err(err);
+            }
+        }
+        result
+    }
+
+    fn track_depth(&mut self, depth: usize, token: &Token) -> FoxResult<()> {
+        let max_depth = &mut self.statistics.max_environment_depth;
+        *max_depth = depth.max(*max_depth);
+
+        let Some(threshold) = self.nesting_threshold.filter(|limit| depth > *limit) else {
+            return Ok(());
+        };
+        let message = format!(
+            "environment depth {depth} exceeds {threshold}, deep nesting slows down variable access"
+        );
+        if self.is_denied(WarningKind::DeepNesting) {
+            return Err(FoxError::runtime(Some(token.clone()), &message));
+        }
+        self.nesting_threshold = None;
+        self.add_warning(Warning::with_token(
+            WarningKind::DeepNesting,
+            &message,
+            token,
+        ));
+        Ok(())
+    }
+
+    pub fn globals(&self) -> &SharedEnvironmentPtr {
+        &self.globals
+    }
+
+    /// Environment of the code being executed
+    pub fn environment(&self) -> &SharedEnvironmentPtr {
+        &self.environment
+    }
+
+    pub fn global_bindings(&self) -> Vec<(String, Object)> {
+        self.globals.borrow().bindings()
+    }
+
+    /// Globals as `var` declarations restoring the state in a fresh interpreter,
+    /// sorted by name. Builtins are skipped, values without a source form
+    /// are listed in comments
+    pub fn dump_globals_as_source(&self) -> String {
+        let mut bindings = self.global_bindings();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut source = String::new();
+        for (name, value) in bindings {
+            let is_builtin = match (&value, standard_constant(&name)) {
+                (Object::BuiltinCallee(_), _) => true,
+                // NaN isn't equal to itself
+                (Object::Double(value), Some(Object::Double(constant))) => {
+                    value.to_bits() == constant.to_bits()
+                }
+                (value, Some(constant)) => *value == constant,
+                _ => false,
+            };
+            if is_builtin {
+                continue;
+            }
+            if !self.globals.borrow().is_assigned(&name) {
+                _ = writeln!(source, "var {name};");
+                continue;
+            }
+            if let Some(literal) = value.to_source() {
+                _ = writeln!(source, "var {name} = {literal};");
+                continue;
+            }
+            let kind = match value {
+                Object::Callee(_) => "function",
+                Object::Class(_) => "class",
+                Object::Instance(_) => "instance",
+                Object::Range(_) => "range",
+                _ => "value without source form",
+            };
+            _ = writeln!(source, "// {name}: {kind} omitted");
+        }
+        source
+    }
+
+    /// Calls the function from the host code
+    pub fn call_function(&mut self, func: &Func, args: &[Object]) -> FoxResult<Object> {
+        self.func_arity_check(&func.decl.name, func.arity(), func.is_variadic(), args)?;
+        self.func_execute(func, args)
+            .map_err(|err| err.sanitized("the function call").uncaught())
+    }
+
+    /// Calls the function on behalf of a builtin,
+    /// thrown values keep unwinding to the script handlers
+    pub fn call_callback(&mut self, func: &Func, args: &[Object]) -> FoxResult<Object> {
+        self.func_arity_check(&func.decl.name, func.arity(), func.is_variadic(), args)?;
+        self.func_execute(func, args)
+    }
+
+    /// Variadic functions accept the arity as the least count of arguments
+    fn func_arity_check(
+        &self,
+        token: &Token,
+        arity: usize,
+        variadic: bool,
+        args: &[Object],
+    ) -> FoxResult<()> {
+        if variadic && args.len() < arity {
+            let msg = format!(
+                "Expected at least {} arguments but got {}",
+                arity,
+                args.len()
+            );
+            return Err(FoxError::runtime(Some(token.clone()), &msg));
+        }
+        if !variadic && args.len() != arity {
             let msg = format!("Expected {}  arguments but got {}", arity, args.len());
             return Err(FoxError::runtime(Some(token.clone()), &msg));
         }
@@ -71,6 +557,9 @@ impl Interpreter {
     }
 
     fn func_execute(&mut self, func: &Func, args: &[Object]) -> FoxResult<Object> {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.hit_function(&func.decl);
+        }
         let mut env = Environment::with(Some(func.closure.clone()));
 
         func.decl
@@ -80,8 +569,12 @@ impl Interpreter {
             .for_each(|(token, object)| {
                 env.define(&token.lexeme, object.clone());
             });
+        if let Some(rest) = &func.decl.rest {
+            let extra = args.get(func.arity()..).unwrap_or_default().to_vec();
+            env.define(&rest.lexeme, Object::Array(mutable_cell(extra)));
+        }
 
-        let result = self.execute_block(&func.decl.body, env);
+        let result = self.execute_block(&func.decl.body, env, &func.decl.name);
         if let Err(err) = result {
             return match err.kind() {
                 ErrorKind::Return(_) if func.is_initializer => {
@@ -98,39 +591,214 @@ impl Interpreter {
         Ok(Object::Nil)
     }
 
+    /// Text of the value as printed,
+    /// an instance with a `toString()` method is converted by calling it,
+    /// including the elements & keys of arrays and maps
+    pub fn stringify(&mut self, value: &Object) -> FoxResult<String> {
+        self.stringify_visiting(value, &mut Vec::new())
+    }
+
+    fn stringify_visiting(
+        &mut self,
+        value: &Object,
+        visiting: &mut Vec<*const ()>,
+    ) -> FoxResult<String> {
+        match value {
+            Object::Array(array) => {
+                let ptr = Rc::as_ptr(array) as *const ();
+                if visiting.contains(&ptr) {
+                    return Ok("[...]".to_string());
+                }
+                // `toString()` may change the array while it's printed
+                let items = array.borrow().clone();
+                visiting.push(ptr);
+                let parts = items
+                    .iter()
+                    .map(|item| self.stringify_visiting(item, visiting))
+                    .collect::<FoxResult<Vec<_>>>();
+                visiting.pop();
+                Ok(format!("[{}]", parts?.join(", ")))
+            }
+            Object::Map(map) => {
+                let ptr = Rc::as_ptr(map) as *const ();
+                if visiting.contains(&ptr) {
+                    return Ok("{...}".to_string());
+                }
+                let mut entries = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect::<Vec<_>>();
+                entries.sort_by(|a, b| key_order(&a.0, &b.0));
+                visiting.push(ptr);
+                let parts = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        let key = self.stringify_visiting(key, visiting)?;
+                        let value = self.stringify_visiting(value, visiting)?;
+                        Ok(format!("{key}: {value}"))
+                    })
+                    .collect::<FoxResult<Vec<_>>>();
+                visiting.pop();
+                Ok(format!("{{{}}}", parts?.join(", ")))
+            }
+            _ => Ok(self
+                .custom_text(value)?
+                .unwrap_or_else(|| value.to_string())),
+        }
+    }
+
+    /// Result of `toString()` if the value is an instance declaring it
+    fn custom_text(&mut self, value: &Object) -> FoxResult<Option<String>> {
+        let Object::Instance(instance) = value else {
+            return Ok(None);
+        };
+        let Some(method) = instance.borrow().class().to_string_method() else {
+            return Ok(None);
+        };
+        let func = method.bind(instance.clone());
+        match self.func_execute(&func, &[])? {
+            Object::Text(text) => Ok(Some(text.to_string())),
+            other => {
+                let message = format!(
+                    "'{TO_STRING_NAME}' must return a string, got {}",
+                    other.type_name()
+                );
+                Err(FoxError::runtime(Some(func.decl.name.clone()), &message))
+            }
+        }
+    }
+
     pub fn resolve(&mut self, expr: Expression, depth: usize) -> FoxResult<()> {
         self.locals.insert(expr, depth);
         Ok(())
     }
 
+    /// Resolution table as (expression, scope depth) pairs
+    pub fn resolved_locals(&self) -> Vec<(Expression, usize)> {
+        self.locals
+            .iter()
+            .map(|(expr, depth)| (expr.clone(), *depth))
+            .collect()
+    }
+
     fn look_up_variable(&self, name: &Token, expr: Expression) -> FoxResult<Object> {
         if let Some(distance) = self.locals.get(&expr) {
-            self.environment.borrow().get_at(*distance, &name.lexeme)
+            self.environment
+                .borrow()
+                .get_at(*distance, &name.lexeme)
+                .map_err(|err| err.with_fallback_token(name))
+        } else if let Some(value) = self.inherited_constant(&name.lexeme) {
+            Ok(value)
         } else {
             self.globals.borrow().get(name)
         }
     }
+
+    /// Constant declared by an ancestor of the class whose body encloses the code,
+    /// the resolver leaves such names to the globals since the superclass is known
+    /// at runtime only. Like the own constants of the class they shadow the globals
+    fn inherited_constant(&self, name: &str) -> Option<Object> {
+        self.environment
+            .borrow()
+            .enclosing_superclass()?
+            .find_constant(name)
+    }
+
+    fn assign_variable(&self, name: &Token, expr: Expression, value: Object) -> FoxResult<()> {
+        if let Some(distance) = self.locals.get(&expr) {
+            self.environment
+                .borrow_mut()
+                .assign_at(*distance, name, value)
+        } else {
+            let superclass = self.environment.borrow().enclosing_superclass();
+            if let Some(superclass) = superclass
+                && superclass.find_constant(&name.lexeme).is_some()
+            {
+                return Err(superclass.constant_assignment_error(name));
+            }
+            self.globals.borrow_mut().assign(name, value)
+        }
+    }
 }
 
 impl ExpressionVisitor<Object> for Interpreter {
     fn visit_binary(&mut self, data: &BinaryExpr) -> FoxResult<Object> {
         let left = self.evaluate(&data.left)?;
         let right = self.evaluate(&data.right)?;
-        use TokenType::*;
-        let result = match (&data.operator.token_type, &left, &right) {
-            (Minus, l, r) => l.minus(r),
-            (Slash, l, r) => l.divide(r),
-            (Star, l, r) => l.multiply(r),
-            (Plus, l, r) => l.plus(r),
-            (Greater, l, r) => l.greater(r),
-            (GreaterEqual, l, r) => l.greater_equal(r),
-            (Less, l, r) => l.less(r),
-            (LessEqual, l, r) => l.less_equal(r),
-            (BangEqual, l, r) => Ok(Object::Bool(l != r)),
-            (EqualEqual, l, r) => Ok(Object::Bool(l == r)),
-            _ => Err("Type mismatch".to_string()),
-        };
-        result.map_err(|err| FoxError::runtime(Some(data.operator.clone()), &err))
+        let operator = &data.operator;
+        if operator.token_type == TokenType::Is {
+            return is_instance_of(&left, &right, operator).map(Object::Bool);
+        }
+        let Some(op) = operator.token_type.binary_op() else {
+            return Err(FoxError::bug(&format!(
+                "Unexpected binary operator '{}'",
+                operator.lexeme
+            )));
+        };
+        // the left instance declaring `equals(other)` decides on equality
+        if let (Object::Instance(instance), BinaryOp::Equal | BinaryOp::NotEqual) = (&left, op) {
+            // the method may assign fields, so the instance isn't kept borrowed
+            let method = instance.borrow().class().equals_method();
+            if let Some(method) = method {
+                let func = method.bind(instance.clone());
+                let equal = self.func_execute(&func, &[right])?.is_true();
+                return Ok(Object::Bool(equal == (op == BinaryOp::Equal)));
+            }
+        }
+        // values joined with strings are converted as printed, calling `toString()`
+        let (left, right) = match (&left, &right) {
+            (Object::Text(_), Object::Text(_)) => (left, right),
+            (Object::Text(_), _) if op == BinaryOp::Add => {
+                let right = Object::Text(self.stringify(&right)?.into());
+                (left, right)
+            }
+            (_, Object::Text(_)) if op == BinaryOp::Add => {
+                let left = Object::Text(self.stringify(&left)?.into());
+                (left, right)
+            }
+            _ => (left, right),
+        };
+        // desugared prefix operators
+        if matches!(
+            operator.token_type,
+            TokenType::PlusPlus | TokenType::MinusMinus
+        ) && !matches!(left, Object::Double(_))
+        {
+            return Err(FoxError::token(
+                ErrorKind::OperandMustBeNumber(left.type_name().to_string()),
+                Some(operator.clone()),
+            ));
+        }
+        if self.ieee_division
+            && let (Object::Double(l), Object::Double(r)) = (&left, &right)
+        {
+            match op {
+                BinaryOp::Divide => return Ok(Object::Double(l / r)),
+                BinaryOp::Modulo => return Ok(Object::Double(l % r)),
+                _ => {}
+            }
+        }
+        left.apply_binary(op, &right).map_err(|err| {
+            let message = match err {
+                BinaryError::TypeMismatch { left, right } => {
+                    let expected = if op == BinaryOp::Add {
+                        "two numbers or include a string"
+                    } else {
+                        "numbers"
+                    };
+                    format!(
+                        "Operands of '{}' must be {expected}, got {left} and {right}",
+                        operator.lexeme
+                    )
+                }
+                BinaryError::DivisionByZero => "Division by zero".to_string(),
+                BinaryError::NanComparison => {
+                    format!("NaN can't be compared with '{}'", operator.lexeme)
+                }
+            };
+            FoxError::runtime(Some(operator.clone()), &message)
+        })
     }
 
     fn visit_grouping(&mut self, data: &GroupingExpr) -> FoxResult<Object> {
@@ -147,8 +815,8 @@ impl ExpressionVisitor<Object> for Interpreter {
         use TokenType::*;
         match (&data.operator.token_type, &right) {
             (Minus, Object::Double(value)) => Ok(Object::Double(-value)),
-            (Minus, _) => Err(FoxError::token(
-                ErrorKind::OperandMustBeNumber,
+            (Minus, r) => Err(FoxError::token(
+                ErrorKind::OperandMustBeNumber(r.type_name().to_string()),
                 Some(data.operator.clone()),
             )),
             (Bang, r) => Ok(Object::Bool(!r.is_true())),
@@ -163,19 +831,38 @@ impl ExpressionVisitor<Object> for Interpreter {
 
     fn visit_assign(&mut self, data: &AssignExpr) -> FoxResult<Object> {
         let value = self.evaluate(&data.value)?;
-        let expr = Expression::Assign(data.clone());
-        if let Some(distance) = self.locals.get(&expr) {
-            self.environment
-                .borrow_mut()
-                .assign_at(*distance, &data.name, value.clone())?;
-        } else {
-            self.globals
-                .borrow_mut()
-                .assign(&data.name, value.clone())?;
+        self.assign_variable(&data.name, Expression::Assign(data.clone()), value.clone())?;
+        Ok(value)
+    }
+
+    fn visit_assign_pattern(&mut self, data: &AssignPatternExpr) -> FoxResult<Object> {
+        let value = self.evaluate(&data.value)?;
+        let values = unpack(&data.bracket, &data.pattern, &value)?;
+        for (name, item) in data.pattern.bound_names().zip(values) {
+            // the resolver keys every bound name by its own variable expression
+            self.assign_variable(name, Expression::variable(name.clone()), item)?;
         }
         Ok(value)
     }
 
+    /// Lazy range value stepping by 1, empty if the end is before the start
+    fn visit_range(&mut self, data: &RangeExpr) -> FoxResult<Object> {
+        let (Object::Double(start), Object::Double(end)) =
+            (self.evaluate(&data.start)?, self.evaluate(&data.end)?)
+        else {
+            return Err(FoxError::runtime(
+                Some(data.operator.clone()),
+                "Range bounds must be numbers",
+            ));
+        };
+        let range = if data.operator.token_type == TokenType::DotDotEqual {
+            NumberRange::inclusive(start, end, 1.0)
+        } else {
+            NumberRange::new(start, end, 1.0)
+        };
+        Ok(Object::Range(range))
+    }
+
     fn visit_logical(&mut self, data: &LogicalExpr) -> FoxResult<Object> {
         let left = self.evaluate(&data.left)?;
 
@@ -190,21 +877,57 @@ impl ExpressionVisitor<Object> for Interpreter {
         let eval = self.evaluate(&data.callee)?;
         let mut args = Vec::new();
         for arg in &data.arguments {
-            let expr = self.evaluate(arg)?;
-            args.push(expr);
+            let value = self.evaluate(&arg.value)?;
+            match (&arg.spread, value) {
+                (None, value) => args.push(value),
+                (Some(_), Object::Array(items)) => args.extend(items.borrow().iter().cloned()),
+                (Some(token), value) => {
+                    let message = format!("Only arrays can be spread, got {}", value.type_name());
+                    return Err(FoxError::runtime(Some(token.clone()), &message));
+                }
+            }
+        }
+        if args.len() > MAX_FUNCTION_ARGUMENT_COUNT {
+            return Err(FoxError::token(
+                ErrorKind::TooManyFunctionArguments,
+                Some(data.paren.clone()),
+            ));
+        }
+        if let Some(history) = &mut self.history
+            && matches!(
+                eval,
+                Object::BuiltinCallee(_) | Object::Callee(_) | Object::Class(_)
+            )
+        {
+            let event = ExecutionEvent::Call(eval.clone());
+            history.record(data.paren.code_location, event);
         }
         match eval {
             Object::BuiltinCallee(func) => {
-                self.func_arity_check(&data.paren, func.arity(), &args)?;
-                let value = (func.body)(&args);
-                Ok(value)
+                if !func.accepts(args.len()) {
+                    let msg = format!(
+                        "Expected {} arguments but got {}",
+                        func.arity_description(),
+                        args.len()
+                    );
+                    return Err(FoxError::runtime(Some(data.paren.clone()), &msg));
+                }
+                let warnings_count = self.warnings.len();
+                let result = (func.body)(self, &args);
+                for warning in self.warnings.iter_mut().skip(warnings_count) {
+                    warning.set_fallback_token(&data.paren);
+                }
+                result.map_err(|err| {
+                    err.sanitized("the builtin function")
+                        .with_fallback_token(&data.paren)
+                })
             }
             Object::Callee(func) => {
-                self.func_arity_check(&data.paren, func.arity(), &args)?;
+                self.func_arity_check(&data.paren, func.arity(), func.is_variadic(), &args)?;
                 self.func_execute(&func, &args)
             }
             Object::Class(meta) => {
-                self.func_arity_check(&data.paren, meta.arity(), &args)?;
+                self.func_arity_check(&data.paren, meta.arity(), meta.is_variadic(), &args)?;
                 let constructor = MetaClass::constructor(meta);
                 if let Some(func) = constructor.initializer {
                     self.func_execute(&func, &args)?;
@@ -218,13 +941,55 @@ impl ExpressionVisitor<Object> for Interpreter {
         }
     }
 
+    fn visit_chain(&mut self, data: &ChainExpr) -> FoxResult<Object> {
+        match self.evaluate(&data.expression) {
+            Err(err) if matches!(err.kind(), ErrorKind::ShortCircuit) => Ok(Object::Nil),
+            result => result,
+        }
+    }
+
     fn visit_get(&mut self, data: &GetExpr) -> FoxResult<Object> {
         let object = self.evaluate(&data.object)?;
-        let Object::Instance(instance) = object else {
-            let err = FoxError::runtime(Some(data.name.clone()), "Only instances have properties");
-            return Err(err);
+        if data.optional && matches!(object, Object::Nil) {
+            return Err(FoxError::error(ErrorKind::ShortCircuit));
+        }
+        let instance = match object {
+            Object::Instance(instance) => instance,
+            Object::Class(meta) => {
+                return meta.find_constant(&data.name.lexeme).ok_or_else(|| {
+                    let message = format!(
+                        "Undefined constant '{}' of class '{}'",
+                        data.name.lexeme,
+                        meta.name()
+                    );
+                    FoxError::runtime(Some(data.name.clone()), &message)
+                });
+            }
+            _ => {
+                let err =
+                    FoxError::runtime(Some(data.name.clone()), "Only instances have properties");
+                return Err(err);
+            }
         };
-        ClassInstance::get(instance, &data.name)
+        private_access_check(&data.object, &data.name)?;
+        match ClassInstance::get(instance, &data.name)? {
+            Property::Value(value) => Ok(value),
+            Property::Getter(func) => self.func_execute(&func, &[]),
+            Property::Missing(func) => {
+                if self.missing_handler_depth >= MISSING_HANDLER_DEPTH_LIMIT {
+                    let message = format!(
+                        "Undefined property '{}', '{ON_MISSING_NAME}' calls are nested too deep",
+                        data.name.lexeme
+                    );
+                    return Err(FoxError::runtime(Some(data.name.clone()), &message));
+                }
+                self.missing_handler_depth += 1;
+                let name = Object::Text(data.name.lexeme.as_str().into());
+                let result = self.func_execute(&func, &[name]);
+                self.missing_handler_depth -= 1;
+                result
+            }
+        }
     }
 
     fn visit_set(&mut self, data: &SetExpr) -> FoxResult<Object> {
@@ -232,10 +997,14 @@ impl ExpressionVisitor<Object> for Interpreter {
 
         match object {
             Object::Instance(instance) => {
+                private_access_check(&data.object, &data.name)?;
                 let value = self.evaluate(&data.value)?;
-                instance.borrow_mut().set(&data.name, value.clone());
+                instance.borrow_mut().set(&data.name, value.clone())?;
                 Ok(value)
             }
+            Object::Class(meta) if meta.find_constant(&data.name.lexeme).is_some() => {
+                Err(meta.constant_assignment_error(&data.name))
+            }
             _ => {
                 let err = FoxError::runtime(Some(data.name.clone()), "Only instances have fields");
                 Err(err)
@@ -243,6 +1012,85 @@ impl ExpressionVisitor<Object> for Interpreter {
         }
     }
 
+    fn visit_array(&mut self, data: &ArrayExpr) -> FoxResult<Object> {
+        let elements = data
+            .elements
+            .iter()
+            .map(|element| self.evaluate(element))
+            .collect::<FoxResult<Vec<_>>>()?;
+        Ok(Object::Array(mutable_cell(elements)))
+    }
+
+    // keys with interior mutability are rejected by `map_key_check`
+    #[allow(clippy::mutable_key_type)]
+    fn visit_map(&mut self, data: &MapExpr) -> FoxResult<Object> {
+        let mut map = HashMap::with_capacity(data.entries.len());
+        for (key, value) in &data.entries {
+            let key = self.evaluate(key)?;
+            map_key_check(&key, &data.brace)?;
+            let value = self.evaluate(value)?;
+            map.insert(key, value);
+        }
+        Ok(Object::Map(mutable_cell(map)))
+    }
+
+    fn visit_index(&mut self, data: &IndexExpr) -> FoxResult<Object> {
+        let object = self.evaluate(&data.object)?;
+        let index = self.evaluate(&data.index)?;
+        let value = match &object {
+            Object::Array(array) => {
+                let array = array.borrow();
+                let index = sequence_index("Array", &index, array.len(), &data.bracket)?;
+                array[index].clone()
+            }
+            Object::Range(range) => {
+                let index = sequence_index("Range", &index, range.len(), &data.bracket)?;
+                Object::Double(range.get(index).unwrap_or_default())
+            }
+            // characters, not bytes, like the scanner sees the code
+            Object::Text(text) => {
+                let index = sequence_index("String", &index, text.chars().count(), &data.bracket)?;
+                let ch = text.chars().nth(index).unwrap_or_default();
+                Object::Text(ch.to_string().into())
+            }
+            // missing keys read as nil
+            Object::Map(map) => map.borrow().get(&index).cloned().unwrap_or(Object::Nil),
+            _ => {
+                return Err(FoxError::runtime(
+                    Some(data.bracket.clone()),
+                    "Only arrays, strings and maps can be indexed",
+                ));
+            }
+        };
+        Ok(value)
+    }
+
+    fn visit_index_set(&mut self, data: &IndexSetExpr) -> FoxResult<Object> {
+        let object = self.evaluate(&data.object)?;
+        if !matches!(object, Object::Array(_) | Object::Map(_)) {
+            let err = FoxError::runtime(
+                Some(data.bracket.clone()),
+                "Only array elements & map entries can be assigned",
+            );
+            return Err(err);
+        }
+        let index = self.evaluate(&data.index)?;
+        let value = self.evaluate(&data.value)?;
+        match object {
+            Object::Array(array) => {
+                let mut array = array.borrow_mut();
+                let index = sequence_index("Array", &index, array.len(), &data.bracket)?;
+                array[index] = value.clone();
+            }
+            Object::Map(map) => {
+                map_key_check(&index, &data.bracket)?;
+                map.borrow_mut().insert(index, value.clone());
+            }
+            _ => unreachable!(),
+        }
+        Ok(value)
+    }
+
     fn visit_this(&mut self, data: &ThisExpr) -> FoxResult<Object> {
         let expr = Expression::This(data.clone());
         self.look_up_variable(&data.keyword, expr)
@@ -270,6 +1118,9 @@ impl ExpressionVisitor<Object> for Interpreter {
             ));
         };
         let func = method.bind(object);
+        if func.is_getter() {
+            return self.func_execute(&func, &[]);
+        }
         Ok(Object::Callee(func))
     }
 }
@@ -282,197 +1133,3636 @@ impl StatementVisitor<()> for Interpreter {
 
     fn visit_print(&mut self, data: &PrintStmt) -> FoxResult<()> {
         let value = self.evaluate(&data.expression)?;
-        println!("{value}");
+        let text = self.stringify(&value)?;
+        self.write_output(&format!("{text}\n"));
         Ok(())
     }
 
-    fn visit_var(&mut self, data: &VarStmt) -> FoxResult<()> {
-        let value = if let Some(init) = &data.initializer {
-            self.evaluate(init)?
-        } else {
-            Object::Nil
-        };
+    fn visit_assert(&mut self, data: &AssertStmt) -> FoxResult<()> {
+        if self.evaluate(&data.condition)?.is_true() {
+            return Ok(());
+        }
+        let message = match &data.message {
+            Some(expr) => {
+                let value = self.evaluate(expr)?;
+                format!("Assertion failed: {}", self.stringify(&value)?)
+            }
+            None => "Assertion failed".to_string(),
+        };
+        Err(FoxError::runtime(Some(data.keyword.clone()), &message))
+    }
+
+    fn visit_var(&mut self, data: &VarStmt) -> FoxResult<()> {
+        let Some(init) = &data.initializer else {
+            self.environment.borrow_mut().declare(&data.name.lexeme);
+            return Ok(());
+        };
+        let value = self.evaluate(init)?;
+        self.environment
+            .borrow_mut()
+            .define(&data.name.lexeme, value);
+        Ok(())
+    }
+
+    fn visit_var_pattern(&mut self, data: &VarPatternStmt) -> FoxResult<()> {
+        let value = self.evaluate(&data.initializer)?;
+        let values = unpack(&data.bracket, &data.pattern, &value)?;
+        let mut environment = self.environment.borrow_mut();
+        for (name, item) in data.pattern.bound_names().zip(values) {
+            environment.define(&name.lexeme, item);
+        }
+        Ok(())
+    }
+
+    fn visit_block(&mut self, data: &BlockStmt) -> FoxResult<()> {
+        let env = Environment::with(Some(self.environment.clone()));
+        self.execute_block(&data.statements, env, &data.brace)
+    }
+
+    fn visit_if(&mut self, data: &IfStmt) -> FoxResult<()> {
+        if self.evaluate(&data.condition)?.is_true() {
+            self.execute(&data.then_branch)
+        } else if let Some(else_branch) = &data.else_branch {
+            self.execute(else_branch)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn visit_match(&mut self, data: &MatchStmt) -> FoxResult<()> {
+        let subject = self.evaluate(&data.subject)?;
+        for (pattern, stmt) in &data.arms {
+            if self.evaluate(pattern)? == subject {
+                return self.execute(stmt);
+            }
+        }
+        match &data.default {
+            Some(default) => self.execute(default),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_while(&mut self, data: &WhileStmt) -> FoxResult<()> {
+        let label = data.label.as_ref();
+        while self.evaluate(&data.condition)?.is_true() {
+            match self.execute(&data.body) {
+                Err(err) if matches!(err.kind(), ErrorKind::Break(target) if targets(target, label)) =>
+                {
+                    break;
+                }
+                Err(err) if matches!(err.kind(), ErrorKind::Continue(target) if targets(target, label)) =>
+                {
+                    // proceed to the increments
+                }
+                result => result?,
+            }
+            for increment in &data.increments {
+                self.evaluate(increment)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Elements are taken before the first iteration,
+    /// every iteration binds the loop variable in a fresh environment
+    fn visit_for_in(&mut self, data: &ForInStmt) -> FoxResult<()> {
+        let items: Box<dyn Iterator<Item = Object>> = match self.evaluate(&data.iterable)? {
+            Object::Array(array) => Box::new(array.borrow().clone().into_iter()),
+            Object::Text(text) => Box::new(
+                text.chars()
+                    .map(|ch| Object::Text(ch.to_string().into()))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            ),
+            // ranges stay lazy, they may be too large for an array
+            Object::Range(range) => Box::new(
+                (0..range.len()).filter_map(move |index| range.get(index).map(Object::Double)),
+            ),
+            _ => {
+                return Err(FoxError::runtime(
+                    Some(data.in_keyword.clone()),
+                    "Only arrays, strings and ranges can be iterated",
+                ));
+            }
+        };
+        let body = std::slice::from_ref(data.body.as_ref());
+        let label = data.label.as_ref();
+        for item in items {
+            let mut env = Environment::with(Some(self.environment.clone()));
+            env.define(&data.name.lexeme, item);
+            match self.execute_block(body, env, &data.keyword) {
+                Err(err) if matches!(err.kind(), ErrorKind::Break(target) if targets(target, label)) =>
+                {
+                    break;
+                }
+                Err(err) if matches!(err.kind(), ErrorKind::Continue(target) if targets(target, label)) =>
+                {
+                    continue;
+                }
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_continue(&mut self, data: &ContinueStmt) -> FoxResult<()> {
+        let label = data.label.as_ref().map(|label| label.lexeme.clone());
+        Err(FoxError::token(
+            ErrorKind::Continue(label),
+            Some(data.keyword.clone()),
+        ))
+    }
+
+    fn visit_break(&mut self, data: &BreakStmt) -> FoxResult<()> {
+        let label = data.label.as_ref().map(|label| label.lexeme.clone());
+        Err(FoxError::token(
+            ErrorKind::Break(label),
+            Some(data.keyword.clone()),
+        ))
+    }
+
+    fn visit_defer(&mut self, data: &DeferStmt) -> FoxResult<()> {
+        let Some(frame) = self.deferred.last_mut() else {
+            return Err(FoxError::bug("Deferred statement outside of a block"));
+        };
+        frame.push(data.body.as_ref().clone());
+        Ok(())
+    }
+
+    fn visit_import(&mut self, data: &ImportStmt) -> FoxResult<()> {
+        // imports are top-level only, so the module defines into the globals
+        for stmt in &data.statements {
+            self.execute(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn visit_function(&mut self, data: &FunctionStmt) -> FoxResult<()> {
+        let object = Func::new(Rc::new(data.clone()), self.environment.clone(), false);
+        self.environment
+            .borrow_mut()
+            .define(&data.name.lexeme, Object::Callee(object));
+        Ok(())
+    }
+
+    fn visit_return(&mut self, data: &ReturnStmt) -> FoxResult<()> {
+        let value = if let Some(val) = &data.value {
+            self.evaluate(val)?
+        } else {
+            Object::Nil
+        };
+        Err(FoxError::token(
+            ErrorKind::Return(value),
+            Some(data.keyword.clone()),
+        ))
+    }
+
+    fn visit_throw(&mut self, data: &ThrowStmt) -> FoxResult<()> {
+        let value = self.evaluate(&data.value)?;
+        Err(FoxError::token(
+            ErrorKind::Throw(value),
+            Some(data.keyword.clone()),
+        ))
+    }
+
+    fn visit_try(&mut self, data: &TryStmt) -> FoxResult<()> {
+        let err = match self.execute(&data.body) {
+            Err(err) if err.kind().is_catchable() => err,
+            result => return result,
+        };
+        // native errors are seen by the script as their messages
+        let value = match err.kind() {
+            ErrorKind::Throw(value) => value.clone(),
+            kind => Object::Text(kind.to_string().into()),
+        };
+        let mut env = Environment::with(Some(self.environment.clone()));
+        env.define(&data.name.lexeme, value);
+        let handler = std::slice::from_ref(data.handler.as_ref());
+        self.execute_block(handler, env, &data.keyword)
+    }
+
+    fn visit_class(&mut self, data: &ClassStmt) -> FoxResult<()> {
+        let mut superclass: Option<Rc<MetaClass>> = None;
+        if let Some(expr) = &data.superclass {
+            let eval = self.evaluate(expr)?;
+            match eval {
+                Object::Class(val) => {
+                    superclass = Some(val.clone());
+                }
+                _ => {
+                    let token = expr.as_variable()?.name.clone();
+                    return Err(FoxError::runtime(Some(token), "Superclass must be a class"));
+                }
+            }
+        }
+
+        self.environment
+            .borrow_mut()
+            .define(&data.name.lexeme, Object::Nil);
+
+        let enclosing = self.environment.clone();
+        // methods see the constants as the variables of the enclosing scope,
+        // it's outside of the `super` one since `this` is bound right inside that
+        let mut constants = HashMap::new();
+        if !data.constants.is_empty() {
+            let scope = Environment::with(Some(enclosing.clone())).shared_ptr();
+            self.environment = scope.clone();
+            let result = data
+                .constants
+                .iter()
+                .try_for_each(|stmt| self.execute(stmt));
+            if let Err(err) = result {
+                self.environment = enclosing;
+                return Err(err);
+            }
+            for (name, value) in scope.borrow().bindings() {
+                constants.insert(name, value);
+            }
+        }
+
+        if let Some(obj) = &superclass {
+            self.environment = Environment::with(Some(self.environment.clone())).shared_ptr();
+            let value = Object::Class(obj.clone());
+            self.environment.borrow_mut().define(KEYWORD_SUPER, value);
+        }
+
+        let mut methods = HashMap::new();
+        for stmt in &data.methods {
+            let func = stmt.as_function()?;
+            let method = Func::new(
+                Rc::new(func.clone()),
+                self.environment.clone(),
+                func.name.lexeme == INITIALIZER_NAME,
+            );
+            methods.insert(func.name.lexeme.clone(), method);
+        }
+        let class_data = MetaClass::new(&data.name.lexeme, superclass, methods, constants);
+        let class = Object::Class(std::rc::Rc::new(class_data));
+
+        self.environment = enclosing;
+
+        self.environment.borrow_mut().assign(&data.name, class)
+    }
+}
+
+/// Properties named with a leading underscore are accessed via `this` only,
+/// so just the methods of the class and its subclasses reach them
+fn private_access_check(object: &Expression, name: &Token) -> FoxResult<()> {
+    if !name.lexeme.starts_with(PRIVATE_PREFIX) || matches!(object, Expression::This(_)) {
+        return Ok(());
+    }
+    let message = format!("Property '{}' is private", name.lexeme);
+    Err(FoxError::runtime(Some(name.clone()), &message))
+}
+
+fn map_key_check(key: &Object, token: &Token) -> FoxResult<()> {
+    if key.is_hashable() {
+        return Ok(());
+    }
+    let message = "Map key can't be an array, a map or an instance that isn't deeply frozen";
+    Err(FoxError::runtime(Some(token.clone()), message))
+}
+
+/// Max distance to the nearest whole number for a float to be accepted as an index
+pub const INDEX_EPSILON: f64 = 1e-6;
+
+/// Whole number held by the index.
+/// Numbers are floats, so only whole ones (within `INDEX_EPSILON`) are accepted
+pub fn whole_index(index: &Object) -> Result<f64, String> {
+    let Object::Double(value) = index else {
+        return Err(format!("index must be a number, got {index}"));
+    };
+    let whole = value.round();
+    if !whole.is_finite() || (value - whole).abs() > INDEX_EPSILON {
+        return Err(format!("index must be a whole number, got {index}"));
+    }
+    Ok(whole)
+}
+
+/// Position in a sequence of the length addressed by the index,
+/// negative indices count from the end: -1 is the last element.
+/// The receiver names the indexed value in the errors, e.g. "String"
+pub fn index_position(receiver: &str, index: &Object, len: usize) -> Result<usize, String> {
+    let whole = whole_index(index).map_err(|message| format!("{receiver} {message}"))?;
+    // compared as floats since the value may not fit usize
+    let len_value = len as f64;
+    let position = if whole < 0.0 {
+        len_value + whole
+    } else {
+        whole
+    };
+    if position < 0.0 || position >= len_value {
+        return Err(format!(
+            "{receiver} index {index} is out of range for length {len}"
+        ));
+    }
+    Ok(position as usize)
+}
+
+fn sequence_index(receiver: &str, index: &Object, len: usize, bracket: &Token) -> FoxResult<usize> {
+    index_position(receiver, index, len)
+        .map_err(|message| FoxError::runtime(Some(bracket.clone()), &message))
+}
+
+/// `value is Class` checks the class of the instance & its superclasses,
+/// `value is Number` and the like compare the type marker of the value
+fn is_instance_of(value: &Object, target: &Object, keyword: &Token) -> FoxResult<bool> {
+    match (value, target) {
+        (Object::Instance(instance), Object::Class(class)) => {
+            Ok(instance.borrow().class().is_subclass_of(class))
+        }
+        (_, Object::Class(_)) => Ok(false),
+        (value, Object::Type(_)) => Ok(value.type_of() == *target),
+        _ => Err(FoxError::runtime(
+            Some(keyword.clone()),
+            &format!(
+                "Right operand of 'is' must be a class or a type, got {}",
+                target.type_name()
+            ),
+        )),
+    }
+}
+
+/// Checks if the `break` or `continue` stops at the loop with the label,
+/// the unlabeled ones stop at the innermost loop
+fn targets(target: &Option<String>, label: Option<&Token>) -> bool {
+    match target {
+        None => true,
+        Some(name) => label.is_some_and(|label| label.lexeme == *name),
+    }
+}
+
+/// Values for the pattern names in order, the rest ones are collected into a new array
+fn unpack(bracket: &Token, pattern: &Pattern, value: &Object) -> FoxResult<Vec<Object>> {
+    let Object::Array(array) = value else {
+        let message = format!("Can't destructure {}, array expected", value.type_name());
+        return Err(FoxError::runtime(Some(bracket.clone()), &message));
+    };
+    let items = array.borrow();
+    let count = pattern.names.len();
+    if items.len() < count {
+        let message = format!(
+            "Expected at least {count} elements to destructure but got {}",
+            items.len()
+        );
+        return Err(FoxError::runtime(Some(bracket.clone()), &message));
+    }
+    let mut values = items[..count].to_vec();
+    if pattern.rest.is_some() {
+        values.push(Object::Array(mutable_cell(items[count..].to_vec())));
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fox::ErrorInfo;
+    use crate::fox::builtins::RANGE_ARRAY_LIMIT;
+
+    fn make_token(t_type: TokenType, lexeme: &str) -> Token {
+        Token {
+            token_type: t_type,
+            lexeme: lexeme.to_string(),
+            literal: Object::Nil,
+            code_location: Default::default(),
+        }
+    }
+
+    fn binary_expr(l: Object, t_type: TokenType, r: Object) -> BinaryExpr {
+        let left = Box::new(Expression::literal(l));
+        let right = Box::new(Expression::literal(r));
+        let operator = make_token(t_type, "Debug");
+        BinaryExpr {
+            left,
+            operator,
+            right,
+        }
+    }
+
+    fn run_code(interpreter: &mut Interpreter, code: &str) -> FoxResult<()> {
+        let source = code.chars().collect::<Vec<_>>();
+        let tokens = crate::fox::Scanner::with_source(&source).scan_tokens()?;
+        let statements = crate::fox::Parser::new(&tokens).parse()?;
+        crate::fox::resolver::Resolver::with(interpreter).resolve_statements(&statements)?;
+        interpreter.interpret(&statements)
+    }
+
+    fn global(interpreter: &Interpreter, name: &str) -> Object {
+        interpreter.globals.borrow().get_at(0, name).unwrap()
+    }
+
+    #[test]
+    fn test_value_semantics_of_primitives() {
+        let mut interpreter = Interpreter::new();
+        let code = r#"
+            var n = 1;
+            var n_copy = n;
+            n_copy = 2;
+            var s = "x";
+            var s_copy = s;
+            s_copy = s_copy + "y";
+            fun change(value) {
+                value = value + value;
+                return value;
+            }
+            var changed = change(s);
+        "#;
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "n"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "s"), Object::Text("x".into()));
+        assert_eq!(global(&interpreter, "s_copy"), Object::Text("xy".into()));
+        assert_eq!(global(&interpreter, "changed"), Object::Text("xx".into()));
+    }
+
+    #[test]
+    fn test_instances_are_aliased() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            class Point {}
+            var a = Point();
+            a.x = 1;
+            var b = a;
+            b.x = 2;
+            fun change(p) {
+                p.y = 3;
+            }
+            change(a);
+            var ax = a.x;
+            var by = b.y;
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "ax"), Object::Double(2.0));
+        assert_eq!(global(&interpreter, "by"), Object::Double(3.0));
+    }
+
+    #[test]
+    fn test_copy_instance() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            class Point {}
+            var a = Point();
+            a.x = 1;
+            a.inner = Point();
+            a.inner.z = 1;
+            a.me = a;
+            var b = copy(a);
+            b.x = 2;
+            b.inner.z = 2;
+            var ax = a.x;
+            var az = a.inner.z;
+            var bx = b.me.x;
+            var n = copy(10);
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "ax"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "az"), Object::Double(1.0));
+        // the cycle points to the copy itself
+        assert_eq!(global(&interpreter, "bx"), Object::Double(2.0));
+        assert_eq!(global(&interpreter, "n"), Object::Double(10.0));
+    }
+
+    #[test]
+    fn test_range_builtin() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            var up = range(0, 3, 1);
+            var down = range(1, -1, -0.5);
+            var empty = range(0, 3, -1);
+            var lazy = range(0, 10000000, 1);
+            var limit = range(0, 10000, 1);
+            var default_step = range(0, 3);
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "up").to_string(), "[0, 1, 2]");
+        assert_eq!(
+            global(&interpreter, "down").to_string(),
+            "[1, 0.5, 0, -0.5]"
+        );
+        assert_eq!(global(&interpreter, "empty").to_string(), "[]");
+        assert_eq!(
+            global(&interpreter, "default_step").to_string(),
+            "[0, 1, 2]"
+        );
+        let Object::Range(lazy) = global(&interpreter, "lazy") else {
+            panic!("Lazy range expected");
+        };
+        assert_eq!(lazy.len(), 10_000_000);
+        let Object::Array(limit) = global(&interpreter, "limit") else {
+            panic!("Array expected");
+        };
+        assert_eq!(limit.borrow().len(), RANGE_ARRAY_LIMIT);
+    }
+
+    #[test]
+    fn test_range_builtin_errors() {
+        for code in [
+            "range(0, 1, 0);",
+            "range(0, \"a\", 1);",
+            "range(nil, 1, 1);",
+            "range(1);",
+        ] {
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::Runtime(_)), "{code}");
+        }
+    }
+
+    #[test]
+    fn test_special_numbers() {
+        let mut interpreter = Interpreter::new();
+        let code = r#"
+            var inf = INFINITY;
+            var neg_inf = -INFINITY;
+            var overflow = 1;
+            for (var i = 0; i < 400; i = i + 1) overflow = overflow * 10;
+            var nan = INFINITY - INFINITY;
+            var zero_inf = 0 * INFINITY;
+            var neg_zero = -0;
+            var nan_eq = NAN == NAN;
+            var nan_ne = nan != nan;
+            var zeros_eq = neg_zero == 0;
+            var checks = [
+                is_nan(nan), is_nan(NAN), is_nan(inf), is_nan(1),
+                is_finite(1), is_finite(inf), is_finite(neg_inf), is_finite(nan)
+            ];
+            var parsed = [num("nan"), num(" inf "), num("-Infinity"), num("NaN"), num("1.5"), num(2)];
+        "#;
+        run_code(&mut interpreter, code).unwrap();
+        let text = |name| global(&interpreter, name).to_string();
+        assert_eq!(text("inf"), "inf");
+        assert_eq!(text("neg_inf"), "-inf");
+        assert_eq!(text("overflow"), "inf");
+        assert_eq!(text("nan"), "nan");
+        assert_eq!(text("zero_inf"), "nan");
+        assert_eq!(text("neg_zero"), "-0");
+        assert_eq!(global(&interpreter, "nan_eq"), Object::Bool(false));
+        assert_eq!(global(&interpreter, "nan_ne"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "zeros_eq"), Object::Bool(true));
+        assert_eq!(
+            text("checks"),
+            "[true, true, false, false, true, false, false, false]"
+        );
+        assert_eq!(text("parsed"), "[nan, inf, -inf, nan, 1.5, 2]");
+    }
+
+    #[test]
+    fn test_math_builtins() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            var values = [
+                sqrt(16), abs(-2.5), floor(-1.5), ceil(1.2), round(2.5), round(-2.5),
+                min(3, -1), max(3, -1), max(1, NAN), sqrt(-1), floor(PI)
+            ];
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            global(&interpreter, "values").to_string(),
+            "[4, 2.5, -2, 2, 3, -3, -1, 3, nan, nan, 3]"
+        );
+        for (code, message) in [
+            ("sqrt(\"4\");", "Argument must be a number"),
+            ("round(nil);", "Argument must be a number"),
+            ("min(1, \"2\");", "Arguments must be numbers"),
+            ("max(true, 2);", "Arguments must be numbers"),
+        ] {
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Call site expected for {code}");
+            };
+            assert_eq!(token.token_type, TokenType::RightParenthesis);
+        }
+    }
+
+    #[test]
+    fn test_string_builtins() {
+        let mut interpreter = Interpreter::new();
+        let code = "
+            var values = [
+                str_len(\"héllo\"), str_len(\"\"), upper(\"Straße\"), lower(\"ÀB c\"),
+                trim(\"  a b \t\n\"), trim(\"\")
+            ];
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            global(&interpreter, "values").to_string(),
+            "[5, 0, STRASSE, àb c, a b, ]"
+        );
+        for (code, message) in [
+            ("str_len(1);", "str_len() expects a string, got Number"),
+            ("upper(nil);", "upper() expects a string, got Nil"),
+            ("lower([]);", "lower() expects a string, got Array"),
+            ("trim(true);", "trim() expects a string, got Bool"),
+            ("split(\"a\", 1);", "split() expects a string, got Number"),
+            ("join(\"ab\", \"\");", "join() expects an array, got String"),
+            ("join([1], nil);", "join() expects a string, got Nil"),
+            (
+                "contains(nil, \"a\");",
+                "contains() expects a string, got Nil",
+            ),
+            (
+                "ends_with(\"a\", [\"a\"]);",
+                "ends_with() expects a string, got Array",
+            ),
+        ] {
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_random_builtins() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            fun draw() {
+                var values = range(0, 100, 1);
+                for (var i = 0; i < 100; i = i + 2) {
+                    values[i] = random_int(-2, 2);
+                    values[i + 1] = random();
+                }
+                return values;
+            }
+            random_seed(42);
+            var first = draw();
+            random_seed(42);
+            var second = draw();
+            var single = random_int(7, 7);
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        let first = global(&interpreter, "first");
+        assert_eq!(first, global(&interpreter, "second"));
+        let Object::Array(values) = first else {
+            panic!("Array expected");
+        };
+        for (index, value) in values.borrow().iter().enumerate() {
+            let Object::Double(value) = *value else {
+                panic!("Number expected");
+            };
+            if index % 2 == 0 {
+                assert!((-2.0..=2.0).contains(&value) && value.fract() == 0.0);
+            } else {
+                assert!((0.0..1.0).contains(&value));
+            }
+        }
+        assert_eq!(global(&interpreter, "single"), Object::Double(7.0));
+
+        for (code, message) in [
+            (
+                "random_int(3, 1);",
+                "random_int() lower bound 3 is greater than upper bound 1",
+            ),
+            (
+                "random_int(0, 1.5);",
+                "random_int() bounds must be whole numbers, got 0 and 1.5",
+            ),
+            ("random_int(\"0\", 1);", "Arguments must be numbers"),
+            (
+                "random_seed(0.5);",
+                "random_seed() expects a whole number, got 0.5",
+            ),
+        ] {
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_conversion_builtins() {
+        let mut interpreter = Interpreter::new();
+        let code = r#"
+            class Point {
+                init(x) { this.x = x; }
+                toString() { return "Point " + str(this.x); }
+            }
+            var texts = [str(nil), str(1.5), str(-0), str("text"), str([1, "a"]), str(Point(2))];
+            var numbers = [
+                num(" -12.5 "), num("1_000"), num(""), num("12abc"), num("1e5"), num("- 1"),
+                num(str(42)) == 42
+            ];
+        "#;
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            global(&interpreter, "texts").to_string(),
+            "[nil, 1.5, -0, text, [1, a], Point 2]"
+        );
+        assert_eq!(
+            global(&interpreter, "numbers").to_string(),
+            "[-12.5, 1000, nil, nil, nil, nil, true]"
+        );
+    }
+
+    #[test]
+    fn test_io_arguments() {
+        let mut interpreter = Interpreter::new();
+        let err = run_code(&mut interpreter, "readline(1);").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "readline() expects a string prompt, got Number"
+        );
+        let err = run_code(&mut interpreter, "env(nil);").unwrap_err();
+        assert_eq!(err.kind().to_string(), "env() expects a string, got Nil");
+        let mut interpreter = Interpreter::with_builtins(&[BuiltinGroup::Core]);
+        let err = run_code(&mut interpreter, "readline();").unwrap_err();
+        assert_eq!(err.kind().to_string(), "io builtins are disabled");
+    }
+
+    #[test]
+    fn test_file_builtins() {
+        let dir = std::env::temp_dir().join(format!("fox-files-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.txt").to_string_lossy().to_string();
+        let missing = dir.join("missing.txt").to_string_lossy().to_string();
+        let code = format!(
+            "
+            var before = file_exists(\"{path}\");
+            write_file(\"{path}\", \"first\");
+            write_file(\"{path}\", \"line 1\n\");
+            append_file(\"{path}\", \"line 2\n\");
+            var after = file_exists(\"{path}\");
+            var text = read_file(\"{path}\");
+        "
+        );
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, &code).unwrap();
+        assert_eq!(global(&interpreter, "before"), Object::Bool(false));
+        assert_eq!(global(&interpreter, "after"), Object::Bool(true));
+        assert_eq!(
+            global(&interpreter, "text"),
+            Object::Text("line 1\nline 2\n".into())
+        );
+
+        let err = run_code(&mut interpreter, &format!("read_file(\"{missing}\");")).unwrap_err();
+        let message = err.kind().to_string();
+        assert!(
+            message.starts_with(&format!("Can't read '{missing}': ")),
+            "{message}"
+        );
+        let err = run_code(&mut interpreter, &format!("write_file(\"{path}\", 1);")).unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "write_file() expects a string, got Number"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_time_builtins() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            var start = clock();
+            var start_ms = clock_ms();
+            var spins = 0;
+            while (elapsed(start) <= 0) {
+                spins = spins + 1;
+            }
+            var duration = elapsed(start);
+            var duration_ms = clock_ms() - start_ms;
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        let number = |name| match global(&interpreter, name) {
+            Object::Double(value) => value,
+            value => panic!("Number expected for {name}, got {value}"),
+        };
+        assert!(number("start") < 1.0);
+        assert!((0.0..1.0).contains(&number("duration")) && number("duration") > 0.0);
+        assert!(number("duration_ms") >= 0.0);
+        let err = run_code(&mut interpreter, "elapsed(\"0\");").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "elapsed() expects a number, got String"
+        );
+    }
+
+    #[test]
+    fn test_eval() {
+        let code = r#"
+            var g = 3;
+            fun scaled(a) {
+                var b = 2;
+                return eval("a * b + g");
+            }
+            fun bumped() {
+                var c = 1;
+                eval("c = c + 1");
+                return c;
+            }
+            class Point {
+                init(x) { this.x = x; }
+                get() { return eval("this.x"); }
+            }
+            fun thrower() { throw 5; }
+            fun failed(text) {
+                try { eval(text); } catch (e) { return e; }
+            }
+            var inner = "2 + 2";
+            var values = [
+                eval("1 + 2 * 3"), scaled(5), bumped(), Point(4).get(), eval("eval(inner)")
+            ];
+            var thrown;
+            try { eval("thrower()"); } catch (e) { thrown = e; }
+            var errors = [
+                failed("1 +"), failed("1 + nil"), failed("missing"), failed("1 2"),
+                failed("[1,
+                    nope]"), failed(5)
+            ];
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        let locals_count = interpreter.locals.len();
+        assert_eq!(
+            global(&interpreter, "values").to_string(),
+            "[7, 13, 2, 4, 4]"
+        );
+        assert_eq!(global(&interpreter, "thrown"), Object::Double(5.0));
+        let Object::Array(errors) = global(&interpreter, "errors") else {
+            panic!("Array expected");
+        };
+        let errors = errors
+            .borrow()
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "eval() failed at 1:3: Expect expression",
+                "eval() failed at 1:3: Operands of '+' must be two numbers or include a string, got Number and Nil",
+                "eval() failed at 1:1: Undefined variable missing",
+                "eval() failed at 1:3: Expect end of expression",
+                "eval() failed at 2:21: Undefined variable nope",
+                "eval() expects a string, got Number",
+            ]
+        );
+        run_code(&mut interpreter, "scaled(1);").unwrap();
+        assert_eq!(interpreter.locals.len(), locals_count);
+    }
+
+    #[test]
+    fn test_write_builtins() {
+        let code = r#"
+            class Point {
+                toString() { return "point"; }
+            }
+            write("progress: ");
+            write(50);
+            writeln("%");
+            print [1, nil];
+            write(Point());
+            writeln(true);
+        "#;
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            interpreter.take_output().unwrap(),
+            "progress: 50%\n[1, nil]\npointtrue\n"
+        );
+    }
+
+    #[test]
+    fn test_number_precision() {
+        let code = r#"
+            var big = 16777217;
+            print big;
+            print big + 1 - 1;
+            print 16777216 + 1;
+            print num(str(big)) == big;
+            print 0.1 + 0.2;
+            var count = 0;
+            for (var i = 0; i < 3; i = i + 1) count = count + 4294967296;
+            print count;
+        "#;
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            interpreter.take_output().unwrap(),
+            "16777217\n16777217\n16777217\ntrue\n0.30000000000000004\n12884901888\n"
+        );
+    }
+
+    #[test]
+    fn test_sleep() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            var start = clock();
+            sleep(0.05);
+            sleep(0);
+            var slept = elapsed(start);
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        let Object::Double(slept) = global(&interpreter, "slept") else {
+            panic!("Number expected");
+        };
+        assert!(slept >= 0.05, "{slept}");
+        for code in [
+            "sleep(-1);",
+            "sleep(NAN);",
+            "sleep(INFINITY);",
+            "sleep(\"1\");",
+        ] {
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            let message = err.kind().to_string();
+            assert!(
+                message.starts_with("sleep() expects a non-negative number of seconds, got "),
+                "{code}: {message}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_exit() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            var cleaned = false;
+            var caught = false;
+            fun stop() {
+                defer cleaned = true;
+                defer exit(5);
+                try { exit(3); } catch (e) { caught = true; }
+            }
+            stop();
+            var after = true;
+        ";
+        let err = run_code(&mut interpreter, code).unwrap_err();
+        assert_eq!(err.kind().exit_code(), Some(3));
+        assert_eq!(global(&interpreter, "cleaned"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "caught"), Object::Bool(false));
+        assert!(interpreter.globals.borrow().get_at(0, "after").is_err());
+
+        // a deferred exit isn't swallowed by the handler of the error in flight
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        let code = r#"
+            fun f() { defer exit(7); throw "boom"; }
+            try { f(); } catch (e) { print "caught " + e; }
+        "#;
+        let err = run_code(&mut interpreter, code).unwrap_err();
+        assert_eq!(err.kind().exit_code(), Some(7));
+        assert_eq!(interpreter.take_output().unwrap_or_default(), "");
+        let code = "fun g() { defer exit(8); var x = -nil; }\ntry { g(); } catch (e) {}";
+        let err = run_code(&mut interpreter, code).unwrap_err();
+        assert_eq!(err.kind().exit_code(), Some(8));
+
+        let err = run_code(&mut interpreter, "exit();").unwrap_err();
+        assert_eq!(err.kind().exit_code(), Some(0));
+        for (code, message) in [
+            ("exit(1.5);", "exit() code must be a whole number, got 1.5"),
+            ("exit(\"1\");", "exit() expects a number, got String"),
+        ] {
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_special_numbers_errors() {
+        for (code, message) in [
+            ("is_nan(\"a\");", "Argument must be a number"),
+            ("is_finite(nil);", "Argument must be a number"),
+            ("num(true);", "Can't convert true to a number"),
+        ] {
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_copy_array() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            var a = range(0, 2, 1);
+            var alias = a;
+            var b = copy(a);
+            var same = a == alias;
+            var equal = a == b;
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        let (Object::Array(a), Object::Array(b)) =
+            (global(&interpreter, "a"), global(&interpreter, "b"))
+        else {
+            panic!("Arrays expected");
+        };
+        assert!(!Rc::ptr_eq(&a, &b));
+        assert_eq!(global(&interpreter, "same"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "equal"), Object::Bool(true));
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut interpreter = Interpreter::new();
+        let code = r#"
+            class Node {
+                init(value) {
+                    this.value = value;
+                    this.next = nil;
+                }
+            }
+            var node = Node([1]);
+            node.next = node;
+            var shallow = clone(node);
+            shallow.value = [2];
+            var shares_next = shallow.next == node;
+            var deep = clone(node, true);
+            var cycle_kept = deep.next == deep and deep.next != node;
+            var items = [[1], 2];
+            var items_copy = clone(items);
+            items_copy[1] = 3;
+            var inner_shared = items_copy[0] == items[0] and items[1] == 2;
+            var map_copy = clone({"a": 1});
+            var primitive = clone("text");
+            var original_value = node.value;
+        "#;
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "shares_next"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "cycle_kept"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "inner_shared"), Object::Bool(true));
+        assert_eq!(
+            global(&interpreter, "primitive"),
+            Object::Text("text".into())
+        );
+        assert_eq!(global(&interpreter, "original_value").to_string(), "[1]");
+        assert_eq!(global(&interpreter, "map_copy").to_string(), "{a: 1}");
+    }
+
+    #[test]
+    fn test_break() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            var a = 0;
+            while (true) {
+                a = a + 1;
+                if (a == 3) break;
+            }
+            var b = 0;
+            for (var i = 0; i < 10; i = i + 1) {
+                b = i;
+                {
+                    if (i >= 4) {
+                        break;
+                    }
+                }
+            }
+            var c = 0;
+            for (;;) {
+                c = c + 1;
+                if (c > 1) break;
+            }
+            var d = 0;
+            for (var i = 0; i < 3; i = i + 1) {
+                while (true) {
+                    break;
+                }
+                d = d + 1;
+            }
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "a"), Object::Double(3.0));
+        assert_eq!(global(&interpreter, "b"), Object::Double(4.0));
+        assert_eq!(global(&interpreter, "c"), Object::Double(2.0));
+        // only the innermost loop is interrupted
+        assert_eq!(global(&interpreter, "d"), Object::Double(3.0));
+    }
+
+    #[test]
+    fn test_for_clause_error_locations() {
+        let cases = [
+            ("for (var i = nil + 1; i < 1; i = i + 1) {}", "+", 17),
+            ("for (var i = 0; i < nil; i = i + 1) {}", "<", 18),
+            ("for (var i = 0; i < 1; i = i - nil) {}", "-", 29),
+            ("for (var i = 0; i < 1; i = i + 1) { print -nil; }", "-", 42),
+            ("for (;;) { nil(); }", ")", 15),
+        ];
+        for (code, lexeme, position) in cases {
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(
+                (
+                    token.lexeme.as_str(),
+                    token.code_location.absolute_position()
+                ),
+                (lexeme, position),
+                "{code}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_continue() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            var odd = 0;
+            var iterations = 0;
+            for (var i = 0; i < 6; i = i + 1) {
+                iterations = iterations + 1;
+                if (i == 0 or i == 2 or i == 4) {
+                    continue;
+                }
+                odd = odd + i;
+            }
+            var w = 0;
+            var skipped = 0;
+            while (w < 4) {
+                w = w + 1;
+                {
+                    if (w < 3) continue;
+                }
+                skipped = skipped + 1;
+            }
+            var inner = 0;
+            for (var i = 0; i < 2; i = i + 1) {
+                for (var j = 0; j < 3; j = j + 1) {
+                    if (j == 1) continue;
+                    inner = inner + 1;
+                }
+            }
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        // the increment runs after `continue`
+        assert_eq!(global(&interpreter, "iterations"), Object::Double(6.0));
+        assert_eq!(global(&interpreter, "odd"), Object::Double(9.0));
+        assert_eq!(global(&interpreter, "skipped"), Object::Double(2.0));
+        assert_eq!(global(&interpreter, "inner"), Object::Double(4.0));
+    }
+
+    #[test]
+    fn test_rest_parameter() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            fun sum(...values) {
+                var total = 0;
+                for (var value in values) total = total + value;
+                return total;
+            }
+            fun tail(first, ...others) {
+                return others;
+            }
+            class Bag {
+                init(name, ...items) {
+                    this.count = len(items);
+                }
+            }
+            var none = sum();
+            var three = sum(1, 2, 3);
+            var empty = tail(1);
+            var rest = tail(1, 2, 3);
+            var bag = Bag(1, 2, 3).count;
+            var described = tail;
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "none"), Object::Double(0.0));
+        assert_eq!(global(&interpreter, "three"), Object::Double(6.0));
+        assert_eq!(global(&interpreter, "empty").to_string(), "[]");
+        assert_eq!(global(&interpreter, "rest").to_string(), "[2, 3]");
+        assert_eq!(global(&interpreter, "bag"), Object::Double(2.0));
+        assert_eq!(
+            global(&interpreter, "described").to_string(),
+            "<fun (1+ args)>"
+        );
+
+        for code in [
+            "fun f(a, ...b) {}\nf();",
+            "class C { init(a, b, ...c) {} }\nC(1);",
+        ] {
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert!(
+                err.kind().to_string().starts_with("Expected at least"),
+                "{code}: {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_spread_arguments() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            fun sum(...values) {
+                var total = 0;
+                for (var value in values) total = total + value;
+                return total;
+            }
+            fun pair(a, b) {
+                return a * 10 + b;
+            }
+            var items = [1, 2];
+            var exact = pair(...items);
+            var mixed = sum(0, ...items, 3, ...[], ...items);
+            var emptied = sum(...[]);
+            var limit = range(0, 255, 1);
+            var over = range(0, 256, 1);
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "exact"), Object::Double(12.0));
+        assert_eq!(global(&interpreter, "mixed"), Object::Double(9.0));
+        assert_eq!(global(&interpreter, "emptied"), Object::Double(0.0));
+
+        let err = run_code(&mut interpreter, "pair(...items, 3);").unwrap_err();
+        assert_eq!(err.kind().to_string(), "Expected 2  arguments but got 3");
+        assert!(run_code(&mut interpreter, "sum(...limit);").is_ok());
+        let err = run_code(&mut interpreter, "sum(...over);").unwrap_err();
+        assert_eq!(err.kind().to_string(), "Can't have more than 255 arguments");
+        let err = run_code(&mut interpreter, "sum(1, ...\"ab\");").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Only arrays can be spread, got String"
+        );
+        let ErrorInfo::Token(token) = err.info() else {
+            panic!("Error location expected");
+        };
+        assert_eq!(token.lexeme, "...");
+    }
+
+    #[test]
+    fn test_labeled_loops() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            var pairs = 0;
+            outer: for (var i = 0; i < 3; i = i + 1) {
+                for (var j = 0; j < 3; j = j + 1) {
+                    if (j > i) continue outer;
+                    if (i == 2) break outer;
+                    pairs = pairs + 1;
+                }
+            }
+            var found;
+            rows: for (var row in [[1, 2], [3, 4], [5, 6]]) {
+                var k = 0;
+                while (k < 2) {
+                    if (row[k] == 4) {
+                        found = row;
+                        break rows;
+                    }
+                    k = k + 1;
+                }
+            }
+            var deferred = 0;
+            fun scan() {
+                items: for (var x in [1, 2]) {
+                    defer deferred = deferred + x;
+                    while (true) {
+                        break items;
+                    }
+                }
+                return true;
+            }
+            var scanned = scan();
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        // (0, 0), (1, 0), (1, 1)
+        assert_eq!(global(&interpreter, "pairs"), Object::Double(3.0));
+        assert_eq!(global(&interpreter, "found").to_string(), "[3, 4]");
+        assert_eq!(global(&interpreter, "deferred"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "scanned"), Object::Bool(true));
+    }
+
+    #[test]
+    fn test_for_increment_list() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            var j = 10;
+            var pairs = 0;
+            for (var i = 0; i < j; i = i + 1, j = j - 1) {
+                pairs = pairs + 1;
+            }
+            var order = 0;
+            var skipped = 0;
+            for (var k = 0; k < 4; k = k + 1, order = order * 10 + k) {
+                if (k < 2) continue;
+                skipped = skipped + 1;
+            }
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "pairs"), Object::Double(5.0));
+        assert_eq!(global(&interpreter, "j"), Object::Double(5.0));
+        // evaluated left to right & after `continue` too
+        assert_eq!(global(&interpreter, "order"), Object::Double(1234.0));
+        assert_eq!(global(&interpreter, "skipped"), Object::Double(2.0));
+    }
+
+    fn locals_entries(object: Object) -> Vec<(String, usize, bool)> {
+        let Object::Array(items) = object else {
+            panic!("Array expected, found {object:?}");
+        };
+        items
+            .borrow()
+            .iter()
+            .map(|item| {
+                let Object::Array(entry) = item else {
+                    panic!("Array entry expected");
+                };
+                let entry = entry.borrow();
+                let (Object::Text(name), Object::Double(depth), Object::Bool(shadowed)) =
+                    (&entry[0], &entry[2], &entry[3])
+                else {
+                    panic!("Invalid entry {entry:?}");
+                };
+                (name.to_string(), *depth as usize, *shadowed)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_locals_in_closure() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            var g = 1;
+            fun outer(a) {
+                fun inner() {
+                    var g = 2;
+                    {
+                        var b = 3;
+                        return locals();
+                    }
+                }
+                return inner;
+            }
+            var result = outer(1)();
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        let expected = [
+            ("b", 0, false),
+            ("g", 1, false),
+            ("a", 2, false),
+            ("inner", 2, false),
+            ("g", 3, true),
+            ("outer", 3, false),
+        ]
+        .map(|(name, depth, shadowed)| (name.to_string(), depth, shadowed));
+        assert_eq!(locals_entries(global(&interpreter, "result")), expected);
+    }
+
+    #[test]
+    fn test_locals_with_declared_globals() {
+        let mut interpreter = Interpreter::new();
+        interpreter.declare_globals(&[GlobalDeclaration {
+            name: "emit".to_string(),
+            arity: Some(2),
+        }]);
+        run_code(
+            &mut interpreter,
+            "var all = locals(true);\nvar own = locals();",
+        )
+        .unwrap();
+        let names = |name: &str| {
+            locals_entries(global(&interpreter, name))
+                .into_iter()
+                .map(|(name, _, _)| name)
+                .collect::<Vec<_>>()
+        };
+        assert!(names("all").contains(&"emit".to_string()));
+        assert!(!names("own").contains(&"emit".to_string()));
+    }
+
+    #[test]
+    fn test_locals_at_top_level() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            var a = 1;
+            var result;
+            {
+                var a = 2;
+                result = locals();
+            }
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        let expected = [("a", 0, false), ("a", 1, true), ("result", 1, false)]
+            .map(|(name, depth, shadowed)| (name.to_string(), depth, shadowed));
+        assert_eq!(locals_entries(global(&interpreter, "result")), expected);
+
+        run_code(&mut interpreter, "result = locals(true);").unwrap();
+        let names = locals_entries(global(&interpreter, "result"))
+            .into_iter()
+            .map(|(name, _, _)| name)
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"clock".to_string()), "{names:?}");
+    }
+
+    #[test]
+    fn test_closure_builtin() {
+        let mut interpreter = Interpreter::new();
+        let code = r"
+            fun make_counter() {
+                var count = 0;
+                fun counter() {
+                    count = count + 1;
+                    return count;
+                }
+                return counter;
+            }
+            var counter = make_counter();
+            var before = closure(counter);
+            counter();
+            counter();
+            var after = closure(counter);
+            var builtin = closure(clock);
+            class A {}
+            var class_closure = closure(A);
+            var global_fun = closure(make_counter);
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            global(&interpreter, "before").to_string(),
+            "[[count, 0, 0], [counter, <fun (0 args)>, 0]]"
+        );
+        // the capture is shared with the function
+        assert_eq!(
+            global(&interpreter, "after").to_string(),
+            "[[count, 2, 0], [counter, <fun (0 args)>, 0]]"
+        );
+        for name in ["builtin", "class_closure", "global_fun"] {
+            assert_eq!(global(&interpreter, name).to_string(), "[]", "{name}");
+        }
+    }
+
+    const FROZEN_POINT: &str = r"
+        class Point {
+            init(x) {
+                this.x = x;
+            }
+            getX() {
+                return this.x;
+            }
+            move(x) {
+                this.x = x;
+            }
+        }
+        var p = freeze(Point(1));
+    ";
+
+    fn frozen_assignment_error(code: &str) -> FoxError {
+        let mut interpreter = Interpreter::new();
+        let code = format!("{FROZEN_POINT}{code}");
+        let err = run_code(&mut interpreter, &code).unwrap_err();
+        let ErrorKind::Runtime(message) = err.kind() else {
+            panic!("Runtime error expected, found {err:?}");
+        };
+        assert_eq!(
+            message,
+            "cannot assign to field 'x' of frozen Point instance"
+        );
+        err
+    }
+
+    #[test]
+    fn test_frozen_external_assignment() {
+        let err = frozen_assignment_error("p.x = 2;");
+        let ErrorInfo::Token(token) = err.info() else {
+            panic!("Error location expected");
+        };
+        assert_eq!(token.lexeme, "x");
+    }
+
+    #[test]
+    fn test_frozen_method_assignment() {
+        frozen_assignment_error("p.move(2);");
+    }
+
+    #[test]
+    fn test_frozen_reads() {
+        let mut interpreter = Interpreter::new();
+        let code = format!(
+            "{FROZEN_POINT}
+            var x = p.x;
+            var method_x = p.getX();
+            var frozen = is_frozen(p);
+            var other = is_frozen(Point(1));
+            var number = is_frozen(1);"
+        );
+        run_code(&mut interpreter, &code).unwrap();
+        assert_eq!(global(&interpreter, "x"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "method_x"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "frozen"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "other"), Object::Bool(false));
+        assert_eq!(global(&interpreter, "number"), Object::Bool(false));
+    }
+
+    #[test]
+    fn test_double_freeze() {
+        let mut interpreter = Interpreter::new();
+        let code = format!(
+            "{FROZEN_POINT}
+            var same = freeze(p);
+            var frozen = is_frozen(same);
+            var equal = freeze(Point(1)) == p;
+            var different = freeze(Point(2)) == p;
+            var mutable = Point(1) == Point(1);"
+        );
+        run_code(&mut interpreter, &code).unwrap();
+        assert_eq!(global(&interpreter, "frozen"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "equal"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "different"), Object::Bool(false));
+        assert_eq!(global(&interpreter, "mutable"), Object::Bool(false));
+        let err = run_code(&mut interpreter, "freeze(1);").unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Runtime(_)));
+    }
+
+    #[test]
+    fn test_self_referencing_frozen_instances() {
+        let mut interpreter = Interpreter::new();
+        let code = r#"
+            class P { init(x) { this.x = x; this.me = this; } }
+            var p = freeze(P(1));
+            var q = freeze(P(1));
+            var r = freeze(P(2));
+            var equal = p == q;
+            var different = p == r;
+            var m = {};
+            m[p] = 1;
+            var found = m[q];
+            var missing = has_key(m, r);
+        "#;
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "equal"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "different"), Object::Bool(false));
+        assert_eq!(global(&interpreter, "found"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "missing"), Object::Bool(false));
+    }
+
+    /// cargo test --release bench_large_string_call_chain -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_large_string_call_chain() {
+        let code = r#"
+            var s = "x";
+            for (var i = 0; i < 20; i = i + 1) {
+                s = s + s;
+            }
+            fun f5(x) { return x; }
+            fun f4(x) { return f5(x); }
+            fun f3(x) { return f4(x); }
+            fun f2(x) { return f3(x); }
+            fun f1(x) { return f2(x); }
+            for (var i = 0; i < 100; i = i + 1) {
+                f1(s);
+            }
+        "#;
+        let mut interpreter = Interpreter::new();
+        let start = std::time::Instant::now();
+        run_code(&mut interpreter, code).unwrap();
+        println!(
+            "1MB string through 5-deep call chain x100: {:?}",
+            start.elapsed()
+        );
+    }
+
+    #[test]
+    fn test_deprecated_builtins_table() {
+        for (old, new) in DEPRECATED_BUILTINS {
+            let mut interpreter = Interpreter::new();
+            assert!(interpreter.deprecated_alias(old, new).is_ok(), "{old}");
+        }
+    }
+
+    #[test]
+    fn test_deprecated_alias_warns_once() {
+        let mut interpreter = Interpreter::new();
+        interpreter.deprecated_alias("clock_old", "clock").unwrap();
+        run_code(&mut interpreter, "clock();\nclock_old();\nclock_old();").unwrap();
+
+        let warnings = interpreter.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind(), WarningKind::Deprecated);
+        assert_eq!(
+            warnings[0].message(),
+            "'clock_old' is deprecated, use 'clock' instead"
+        );
+        let crate::fox::ErrorInfo::Token(token) = warnings[0].info() else {
+            panic!("Call site location expected");
+        };
+        assert_eq!(token.code_location.line_number(), 2);
+    }
+
+    #[test]
+    fn test_new_name_never_warns() {
+        let mut interpreter = Interpreter::new();
+        interpreter.deprecated_alias("clock_old", "clock").unwrap();
+        run_code(&mut interpreter, "clock(); clock();").unwrap();
+        assert!(interpreter.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_denied_deprecation() {
+        let mut interpreter = Interpreter::new();
+        interpreter.deprecated_alias("clock_old", "clock").unwrap();
+        interpreter.deny(WarningKind::Deprecated);
+        let err = run_code(&mut interpreter, "var a = 1;\nclock_old();").unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Runtime(_)));
+        let crate::fox::ErrorInfo::Token(token) = err.info() else {
+            panic!("Call site location expected");
+        };
+        assert_eq!(token.code_location.line_number(), 2);
+        assert!(interpreter.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_alias_of_unknown_builtin() {
+        let mut interpreter = Interpreter::new();
+        assert!(interpreter.deprecated_alias("old", "missing").is_err());
+    }
+
+    #[test]
+    fn test_return_escaping_top_level() {
+        // the resolver rejects such code, so statements are injected directly
+        let statements = vec![Statement::block(
+            make_token(TokenType::LeftBrace, "{"),
+            vec![Statement::ret_fn(
+                make_token(TokenType::Return, "return"),
+                Some(Box::new(Expression::literal(Object::Double(1.0)))),
+            )],
+            false,
+        )];
+        let mut interpreter = Interpreter::new();
+        let err = interpreter.interpret(&statements).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Bug(_)));
+        assert!(matches!(err.info(), crate::fox::ErrorInfo::Token(_)));
+    }
+
+    #[test]
+    fn test_control_flow_description_is_bug() {
+        let err = FoxError::error(ErrorKind::Return(Object::Nil));
+        let fox = crate::fox::Fox::with(Vec::new());
+        let text = fox.error_description(&err);
+        assert!(text.starts_with("[BUG]"), "{text}");
+    }
+
+    #[test]
+    fn test_binary_double_plus() {
+        let mut interpreter = Interpreter::new();
+        let expr = binary_expr(Object::Double(2.0), TokenType::Plus, Object::Double(2.0));
+        let obj = interpreter.visit_binary(&expr).unwrap();
+        assert_eq!(obj, Object::Double(4.0));
+        // err
+        let expr = binary_expr(Object::Double(3.0), TokenType::Plus, Object::Nil);
+        let res = interpreter.visit_binary(&expr);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_binary_string_plus() {
+        let mut interpreter = Interpreter::new();
+        let expr = binary_expr(
+            Object::Text("hello,".into()),
+            TokenType::Plus,
+            Object::Text("fox lang".into()),
+        );
+        let obj = interpreter.visit_binary(&expr).unwrap();
+        assert_eq!(obj, Object::Text("hello,fox lang".into()));
+    }
+
+    #[test]
+    fn test_binary_double_minus() {
+        let mut interpreter = Interpreter::new();
+        let expr = binary_expr(Object::Double(3.0), TokenType::Minus, Object::Double(2.0));
+        let obj = interpreter.visit_binary(&expr).unwrap();
+        assert_eq!(obj, Object::Double(1.0));
+        // err
+        let expr = binary_expr(Object::Double(3.0), TokenType::Minus, Object::Nil);
+        let res = interpreter.visit_binary(&expr);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_binary_double_multiply() {
+        let mut interpreter = Interpreter::new();
+        // ok
+        let expr = binary_expr(Object::Double(3.0), TokenType::Star, Object::Double(2.0));
+        let result = interpreter.visit_binary(&expr);
+        assert!(result.is_ok());
+        let obj = result.unwrap();
+        assert_eq!(obj, Object::Double(6.0));
+        // err
+        let expr = binary_expr(Object::Double(3.0), TokenType::Star, Object::Nil);
+        let result = interpreter.visit_binary(&expr);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_binary_double_divide() {
+        let mut interpreter = Interpreter::new();
+        // ok
+        let expr = binary_expr(Object::Double(6.0), TokenType::Slash, Object::Double(2.0));
+        let result = interpreter.visit_binary(&expr);
+        assert!(result.is_ok());
+        let obj = result.unwrap();
+        assert_eq!(obj, Object::Double(3.0));
+        // div by zero
+        let expr = binary_expr(Object::Double(6.0), TokenType::Slash, Object::Double(0.0));
+        let result = interpreter.visit_binary(&expr);
+        assert!(result.is_err());
+        // type mismatch
+        let expr = binary_expr(Object::Nil, TokenType::Slash, Object::Double(0.0));
+        let result = interpreter.visit_binary(&expr);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_binary_double_modulo() {
+        let mut interpreter = Interpreter::new();
+        let expr = binary_expr(Object::Double(5.0), TokenType::Percent, Object::Double(2.0));
+        let obj = interpreter.visit_binary(&expr).unwrap();
+        assert_eq!(obj, Object::Double(1.0));
+        // sign follows the dividend
+        let expr = binary_expr(
+            Object::Double(-5.5),
+            TokenType::Percent,
+            Object::Double(2.0),
+        );
+        let obj = interpreter.visit_binary(&expr).unwrap();
+        assert_eq!(obj, Object::Double(-1.5));
+        // by zero
+        let expr = binary_expr(Object::Double(5.0), TokenType::Percent, Object::Double(0.0));
+        assert!(interpreter.visit_binary(&expr).is_err());
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        let mut interpreter = Interpreter::new();
+        for code in [
+            "var a = 1;\nprint 10 / 0;",
+            "var a = 1;\nprint 0 / 0;",
+            "var a = 1;\nprint 7 % 0;",
+            "var a = 1;\nvar b = a / (a - 1);",
+            "var a = 1;\nfun half(x) { return x / (2 * a - 2); } half(4);",
+        ] {
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), "Division by zero", "{code}");
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(token.lexeme, if code.contains('%') { "%" } else { "/" });
+            assert_eq!(token.code_location.line_number(), 2, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_ieee_division() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_ieee_division(true);
+        let code = "
+            var zero = 0;
+            var inf = 10 / zero;
+            var neg_inf = -1 / 0;
+            var nan = 0 / 0;
+            var rem = 7 % zero;
+            var half = 1 / 2;
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "inf"), Object::Double(f64::INFINITY));
+        assert_eq!(
+            global(&interpreter, "neg_inf"),
+            Object::Double(f64::NEG_INFINITY)
+        );
+        assert_eq!(global(&interpreter, "nan").to_string(), "nan");
+        assert_eq!(global(&interpreter, "rem").to_string(), "nan");
+        assert_eq!(global(&interpreter, "half"), Object::Double(0.5));
+        let err = run_code(&mut interpreter, "nil / 0;").unwrap_err();
+        assert_ne!(err.kind().to_string(), "Division by zero");
+    }
+
+    #[test]
+    fn test_unassigned_variable() {
+        let mut interpreter = Interpreter::new();
+        let code = r#"
+            var a;
+            var b = nil;
+            var read_b = b;
+            var c;
+            c = 1;
+            var read_c = c;
+            {
+                var d;
+                d = "set";
+                var read_d = d;
+                a = read_d;
+            }
+            fun later() {
+                var e;
+                fun set() { e = 2; }
+                set();
+                return e;
+            }
+            var read_e = later();
+        "#;
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "read_b"), Object::Nil);
+        assert_eq!(global(&interpreter, "read_c"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "a"), Object::Text("set".into()));
+        assert_eq!(global(&interpreter, "read_e"), Object::Double(2.0));
+        for (code, line) in [
+            ("var x;\nprint x;", 2),
+            ("var x;\nx = x + 1;", 2),
+            ("{\n  var x;\n  print x;\n}", 3),
+            (
+                "fun f() {\n  var x;\n  fun g() { return x; }\n  return g();\n}\nf();",
+                3,
+            ),
+            ("var x;\nfun f() { return x; }\nf();", 2),
+        ] {
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(
+                err.kind().to_string(),
+                "Variable 'x' used before being assigned",
+                "{code}"
+            );
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(token.lexeme, "x", "{code}");
+            assert_eq!(token.code_location.line_number(), line, "{code}");
+        }
+        // the failed read is caught like other runtime errors
+        let mut interpreter = Interpreter::new();
+        let code = "var x; var caught; try { print x; } catch (e) { caught = e; }";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            global(&interpreter, "caught"),
+            Object::Text("Variable 'x' used before being assigned".into())
+        );
+        // the exported state keeps the variable unassigned
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, "var x; var y = nil;").unwrap();
+        assert_eq!(
+            interpreter.dump_globals_as_source(),
+            "var x;\nvar y = nil;\n"
+        );
+    }
+
+    #[test]
+    fn test_var_list() {
+        let mut interpreter = Interpreter::new();
+        let code = "
+            var a = 1, b = a + 1, c;
+            var steps = 0;
+            for (var i = 0, j = 6; i < j; i = i + 1, j = j - 1) steps = steps + 1;
+            fun f() { var x = 2, y = x * 3; return y; }
+            var d = f();
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "b"), Object::Double(2.0));
+        assert!(!interpreter.globals.borrow().is_assigned("c"));
+        assert_eq!(global(&interpreter, "steps"), Object::Double(3.0));
+        assert_eq!(global(&interpreter, "d"), Object::Double(6.0));
+    }
+
+    #[test]
+    fn test_try_catch() {
+        let code = r#"
+            class Failure { init(reason) { this.reason = reason; } }
+            fun fail(reason) { throw Failure(reason); }
+            fun guarded(reason) {
+                try {
+                    fail(reason);
+                    return "unreachable";
+                } catch (e) {
+                    return e.reason;
+                }
+            }
+            var reason = guarded("broken");
+            var thrown;
+            try { throw 42; } catch (e) { thrown = e; }
+            var rethrown;
+            try {
+                try { throw "inner"; } catch (e) { throw e + "!"; }
+            } catch (e) { rethrown = e; }
+            var native;
+            try { nil + 1; } catch (e) { native = e; }
+            var undefined;
+            try { print missing; } catch (e) { undefined = e; }
+            var parsed;
+            try { parsed = num(true); } catch (e) { parsed = e; }
+            var builtin;
+            try { inject_catchable(); } catch (e) { builtin = e; }
+            var steps = 0;
+            for (var i = 0; i < 5; i = i + 1) {
+                try {
+                    if (i == 3) break;
+                    steps = steps + 1;
+                } catch (e) {}
+            }
+            var untouched = "yes";
+            try {} catch (e) { untouched = "no"; }
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        let text = |value: &str| Object::Text(value.into());
+        assert_eq!(global(&interpreter, "reason"), text("broken"));
+        assert_eq!(global(&interpreter, "thrown"), Object::Double(42.0));
+        assert_eq!(global(&interpreter, "rethrown"), text("inner!"));
+        assert_eq!(
+            global(&interpreter, "native"),
+            text("Operands of '+' must be two numbers or include a string, got Nil and Number")
+        );
+        assert_eq!(
+            global(&interpreter, "undefined"),
+            text("Undefined variable missing")
+        );
+        assert_eq!(
+            global(&interpreter, "parsed"),
+            text("Can't convert true to a number")
+        );
+        assert_eq!(global(&interpreter, "builtin"), text("injected failure"));
+        assert_eq!(global(&interpreter, "steps"), Object::Double(3.0));
+        assert_eq!(global(&interpreter, "untouched"), text("yes"));
+        // the environment of the failed block is left
+        assert!(Rc::ptr_eq(&interpreter.environment, &interpreter.globals));
+    }
+
+    #[test]
+    fn test_assert() {
+        let mut interpreter = Interpreter::new();
+        let code = r#"
+            var evaluated = false;
+            fun mark() { evaluated = true; return "message"; }
+            assert true;
+            assert 1 + 1 == 2, mark();
+            assert [];
+            var caught;
+            try { assert nil, [1, "two"]; } catch (e) { caught = e; }
+        "#;
+        run_code(&mut interpreter, code).unwrap();
+        // the message is evaluated on failure only
+        assert_eq!(global(&interpreter, "evaluated"), Object::Bool(false));
+        assert_eq!(
+            global(&interpreter, "caught"),
+            Object::Text("Assertion failed: [1, two]".into())
+        );
+
+        for (code, message, position) in [
+            ("assert false;", "Assertion failed", 5),
+            ("var a = 2;\nassert a < 1, a;", "Assertion failed: 2", 16),
+            ("assert 0 == 1, \"zero\";", "Assertion failed: zero", 5),
+        ] {
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::Runtime(_)), "{code}");
+            assert_eq!(err.kind().to_string(), message, "{code}");
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(token.token_type, TokenType::Assert, "{code}");
+            assert_eq!(token.code_location.absolute_position(), position, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_defer() {
+        let code = r#"
+            var log = "";
+            fun note(x) { log = log + x; }
+            fun f() {
+                defer note("a");
+                var local = "b";
+                defer note(local);
+                note("1");
+                if (true) return "result";
+                note("unreachable");
+            }
+            var result = f();
+            note("|");
+            {
+                defer note("c");
+                note("2");
+            }
+            note("|");
+            for (var i = 0; i < 3; i = i + 1) {
+                defer note("x");
+                if (i == 1) continue;
+                if (i == 2) break;
+                note("y");
+            }
+            note("|");
+            fun late() { defer throw "late"; return 1; }
+            fun early() { defer note("d"); defer throw "late"; throw "early"; }
+            var replaced;
+            try { late(); } catch (e) { replaced = e; }
+            var kept;
+            try { early(); } catch (e) { kept = e; }
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        let text = |value: &str| Object::Text(value.into());
+        assert_eq!(global(&interpreter, "result"), text("result"));
+        assert_eq!(global(&interpreter, "log"), text("1ba|2c|yxxx|d"));
+        assert_eq!(global(&interpreter, "replaced"), text("late"));
+        assert_eq!(global(&interpreter, "kept"), text("early"));
+        assert!(interpreter.deferred.is_empty());
+    }
+
+    #[test]
+    fn test_uncaught_throw() {
+        for (code, message, position) in [
+            ("throw \"boom\";", "Uncaught exception: boom", 4),
+            (
+                "fun f() { throw [1, 2]; }\nf();",
+                "Uncaught exception: [1, 2]",
+                14,
+            ),
+            (
+                "try { throw 1; } catch (e) { throw e + 1; }",
+                "Uncaught exception: 2",
+                33,
+            ),
+        ] {
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::Runtime(_)), "{code}");
+            assert_eq!(err.kind().to_string(), message, "{code}");
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(token.lexeme, "throw");
+            assert_eq!(token.code_location.absolute_position(), position, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_error_builtin() {
+        let mut interpreter = Interpreter::new();
+        let code = r#"
+            fun parse(x) {
+                if (x < 0) error("negative " + str(x));
+                return x;
+            }
+            var caught;
+            try { parse(-2); } catch (e) { caught = e; }
+            var number;
+            try { error(42); } catch (e) { number = e; }
+        "#;
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            global(&interpreter, "caught"),
+            Object::Text("negative -2".into())
+        );
+        assert_eq!(global(&interpreter, "number"), Object::Text("42".into()));
+
+        let err = run_code(
+            &mut interpreter,
+            "var a = 1;
+error(\"bad\");",
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Runtime(_)));
+        assert_eq!(err.kind().to_string(), "bad");
+        let ErrorInfo::Token(token) = err.info() else {
+            panic!("Error location expected");
+        };
+        assert_eq!(token.lexeme, ")");
+        assert_eq!(token.code_location.line_number(), 2);
+    }
+
+    #[test]
+    fn test_assert_builtins() {
+        let mut interpreter = Interpreter::new();
+        let code = r#"
+            class Named {
+                toString() { return "named"; }
+            }
+            assert_eq(2 + 2, 4);
+            assert_eq([1, "a"], [1, "a"]);
+            assert_ne(1, "1");
+            assert_true(0);
+            assert_true("");
+        "#;
+        run_code(&mut interpreter, code).unwrap();
+        for (code, message) in [
+            ("assert_eq(5, 4);", "Assertion failed: expected 4, got 5"),
+            (
+                "assert_eq(\"a\", [nil]);",
+                "Assertion failed: expected [nil], got a",
+            ),
+            (
+                "assert_eq(Named(), 1);",
+                "Assertion failed: expected 1, got named",
+            ),
+            (
+                "assert_ne(nil, nil);",
+                "Assertion failed: expected a value other than nil",
+            ),
+            (
+                "assert_true(false);",
+                "Assertion failed: expected a true value, got false",
+            ),
+        ] {
+            let code = format!("var a = 1;\n{code}");
+            let err = run_code(&mut interpreter, &code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(token.code_location.line_number(), 2, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_fatal_error_is_not_caught() {
+        let mut interpreter = Interpreter::new();
+        let code = "var caught = false;\ntry { inject_fatal(); } catch (e) { caught = true; }";
+        let err = run_code(&mut interpreter, code).unwrap_err();
+        assert!(err.kind().is_fatal());
+        assert_eq!(global(&interpreter, "caught"), Object::Bool(false));
+    }
+
+    #[test]
+    fn test_destructuring() {
+        let mut interpreter = Interpreter::new();
+        let code = "
+            var list = [1, 2, 3, 4];
+            var [a, b] = list;
+            var [first, ...rest] = list;
+            var [w, x, y, z, ...none] = list;
+            [a, b] = [b, a];
+            var swapped = [a, b];
+            fun tail(items) { var [head, ...other] = items; return other; }
+            fun local() { var p = 0, q = 0; var r = [p, q] = [7, 8]; return [p + q, r]; }
+            var t = tail([5, 6]);
+            var [sum, result] = local();
+        ";
+        run_code(&mut interpreter, code).unwrap();
+        let numbers = |values: &[f64]| {
+            let items = values
+                .iter()
+                .map(|x| Object::Double(*x))
+                .collect::<Vec<_>>();
+            Object::Array(mutable_cell(items))
+        };
+        assert_eq!(global(&interpreter, "swapped"), numbers(&[2.0, 1.0]));
+        assert_eq!(global(&interpreter, "first"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "rest"), numbers(&[2.0, 3.0, 4.0]));
+        assert_eq!(global(&interpreter, "z"), Object::Double(4.0));
+        assert_eq!(global(&interpreter, "none"), numbers(&[]));
+        assert_eq!(global(&interpreter, "t"), numbers(&[6.0]));
+        assert_eq!(global(&interpreter, "sum"), Object::Double(15.0));
+        assert_eq!(global(&interpreter, "result"), numbers(&[7.0, 8.0]));
+        // the rest is a copy
+        run_code(&mut interpreter, "rest[0] = 0;").unwrap();
+        assert_eq!(global(&interpreter, "list"), numbers(&[1.0, 2.0, 3.0, 4.0]));
+    }
+
+    #[test]
+    fn test_destructuring_errors() {
+        for (code, message) in [
+            (
+                "var [a, b] = [1];",
+                "Expected at least 2 elements to destructure but got 1",
+            ),
+            (
+                "var a; var b; [a, b, ...c] = [1];",
+                "Expected at least 2 elements to destructure but got 1",
+            ),
+            (
+                "var [a] = \"a\";",
+                "Can't destructure String, array expected",
+            ),
+            ("var [...a] = nil;", "Can't destructure Nil, array expected"),
+        ] {
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected");
+            };
+            assert_eq!(token.lexeme, "[", "{code}");
+            let position = code.find('[').unwrap();
+            assert_eq!(token.code_location.absolute_position(), position, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_nan_comparison() {
+        let mut interpreter = Interpreter::new();
+        let code = r#"
+            var nan = 0 * INFINITY;
+            var equal = [nan == nan, nan != nan, NAN == 1, nan == "nan"];
+            var finite = [is_finite(1 / 3), is_finite(INFINITY), is_finite(nan)];
+            var ordered = [INFINITY > 1, -INFINITY < -1, INFINITY >= INFINITY];
+        "#;
+        run_code(&mut interpreter, code).unwrap();
+        let text = |name| global(&interpreter, name).to_string();
+        assert_eq!(text("equal"), "[false, true, false, false]");
+        assert_eq!(text("finite"), "[true, false, false]");
+        assert_eq!(text("ordered"), "[true, true, true]");
+        for (code, op) in [
+            ("NAN < 1;", "<"),
+            ("1 <= NAN;", "<="),
+            ("nan > nan;", ">"),
+            ("INFINITY >= NAN;", ">="),
+        ] {
+            let code = format!("var a = 1;\n{code}");
+            let err = run_code(&mut interpreter, &code).unwrap_err();
+            assert_eq!(
+                err.kind().to_string(),
+                format!("NaN can't be compared with '{op}'"),
+                "{code}"
+            );
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(token.code_location.line_number(), 2, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_nan_hash() {
+        use std::hash::{DefaultHasher, Hash, Hasher};
+        let hash = |value: &Object| {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        };
+        let nan = Object::Double(f64::NAN);
+        // consistent, but never found since it isn't equal to itself
+        assert_eq!(hash(&nan), hash(&nan.clone()));
+        assert_ne!(nan, nan.clone());
+        let mut interpreter = Interpreter::new();
+        let code = "var map = {NAN: 1}; var found = has_key(map, NAN); var size = len(map);";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "found"), Object::Bool(false));
+        assert_eq!(global(&interpreter, "size"), Object::Double(1.0));
+    }
+
+    #[test]
+    fn test_string_concatenation() {
+        let mut interpreter = Interpreter::new();
+        let code = r#"
+            var score = "score: " + 42;
+            var order = 1 + "x";
+            var fraction = "x" + 2.5 + 1;
+            var sum = 2 + 2 + "!";
+            var flags = "" + true + false + nil;
+            var items = "items: " + [1, "a"];
+        "#;
+        run_code(&mut interpreter, code).unwrap();
+        for (name, expected) in [
+            ("score", "score: 42"),
+            ("order", "1x"),
+            ("fraction", "x2.51"),
+            ("sum", "4!"),
+            ("flags", "truefalsenil"),
+            ("items", "items: [1, a]"),
+        ] {
+            assert_eq!(global(&interpreter, name), Object::Text(expected.into()));
+        }
+        for (code, message) in [
+            (
+                "nil + nil;",
+                "Operands of '+' must be two numbers or include a string, got Nil and Nil",
+            ),
+            (
+                "1 + nil;",
+                "Operands of '+' must be two numbers or include a string, got Number and Nil",
+            ),
+            (
+                "true + 1;",
+                "Operands of '+' must be two numbers or include a string, got Bool and Number",
+            ),
+            (
+                "\"x\" - 1;",
+                "Operands of '-' must be numbers, got String and Number",
+            ),
+        ] {
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_binary_operator_matrix() {
+        // same typed samples differ, so only nil equals its counterpart
+        let left = [
+            ("nil", Object::Nil),
+            ("6", Object::Double(6.0)),
+            ("\"ab\"", Object::Text("ab".into())),
+            ("true", Object::Bool(true)),
+        ];
+        let right = [
+            ("nil", Object::Nil),
+            ("4", Object::Double(4.0)),
+            ("\"cd\"", Object::Text("cd".into())),
+            ("false", Object::Bool(false)),
+        ];
+        // results for number & string operand pairs, the rest pairs mismatch
+        // unless a string is added
+        let operators = [
+            (
+                "+",
+                Some(Object::Double(10.0)),
+                Some(Object::Text("abcd".into())),
+            ),
+            ("-", Some(Object::Double(2.0)), None),
+            ("*", Some(Object::Double(24.0)), None),
+            ("/", Some(Object::Double(1.5)), None),
+            ("%", Some(Object::Double(2.0)), None),
+            (">", Some(Object::Bool(true)), None),
+            (">=", Some(Object::Bool(true)), None),
+            ("<", Some(Object::Bool(false)), None),
+            ("<=", Some(Object::Bool(false)), None),
+        ];
+        let mut interpreter = Interpreter::new();
+        let mut evaluate = |code: String| {
+            run_code(&mut interpreter, &format!("var result = {code};"))
+                .map(|_| global(&interpreter, "result"))
+        };
+        for (l_code, l) in &left {
+            for (r_code, r) in &right {
+                for (op, numbers, strings) in &operators {
+                    let code = format!("{l_code} {op} {r_code}");
+                    let joined = Some(Object::Text(format!("{l}{r}").into()));
+                    let expected = match (l, r) {
+                        (Object::Double(_), Object::Double(_)) => numbers,
+                        (Object::Text(_), Object::Text(_)) => strings,
+                        (Object::Text(_), _) | (_, Object::Text(_)) if *op == "+" => &joined,
+                        _ => &None,
+                    };
+                    let result = evaluate(code.clone());
+                    match expected {
+                        Some(value) => assert_eq!(result.unwrap(), *value, "{code}"),
+                        None => assert_eq!(
+                            result.unwrap_err().kind().to_string(),
+                            format!(
+                                "Operands of '{op}' must be {}, got {} and {}",
+                                if *op == "+" {
+                                    "two numbers or include a string"
+                                } else {
+                                    "numbers"
+                                },
+                                l.type_name(),
+                                r.type_name()
+                            ),
+                            "{code}"
+                        ),
+                    }
+                }
+                let both_nil = matches!((l, r), (Object::Nil, Object::Nil));
+                for (op, value) in [("==", both_nil), ("!=", !both_nil)] {
+                    let code = format!("{l_code} {op} {r_code}");
+                    assert_eq!(
+                        evaluate(code.clone()).unwrap(),
+                        Object::Bool(value),
+                        "{code}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_modulo_type_mismatch() {
+        let mut interpreter = Interpreter::new();
+        let err = run_code(&mut interpreter, "print \"a\" % 2;").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Operands of '%' must be numbers, got String and Number"
+        );
+        let ErrorInfo::Token(token) = err.info() else {
+            panic!("Error location expected");
+        };
+        assert_eq!(token.lexeme, "%");
+        assert_eq!(token.code_location.absolute_position(), 10);
+    }
+
+    #[test]
+    fn test_prefix_increment_decrement() {
+        let code = r#"
+            var i = 1;
+            var a = ++i;
+            var b = --i + 10;
+            var c;
+            {
+                var local = 5;
+                ++local;
+                c = ++local;
+            }
+            fun counter() {
+                var count = 0;
+                fun next() { return ++count; }
+                return next;
+            }
+            var next = counter();
+            next();
+            var d = next();
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "i"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "a"), Object::Double(2.0));
+        assert_eq!(global(&interpreter, "b"), Object::Double(11.0));
+        assert_eq!(global(&interpreter, "c"), Object::Double(7.0));
+        assert_eq!(global(&interpreter, "d"), Object::Double(2.0));
+    }
+
+    #[test]
+    fn test_negation_of_non_number() {
+        let mut interpreter = Interpreter::new();
+        for (code, message) in [
+            ("-nil;", "Operand must be a number, got Nil"),
+            ("-\"1\";", "Operand must be a number, got String"),
+            ("-[1];", "Operand must be a number, got Array"),
+            ("class A {} -A();", "Operand must be a number, got A"),
+        ] {
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(token.lexeme, "-");
+        }
+    }
+
+    #[test]
+    fn test_prefix_update_of_non_number() {
+        for (code, lexeme, name) in [
+            ("var s = \"a\"; ++s;", "++", "String"),
+            ("var n = nil; --n;", "--", "Nil"),
+        ] {
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert!(
+                matches!(err.kind(), ErrorKind::OperandMustBeNumber(x) if x == name),
+                "{code}"
+            );
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected");
+            };
+            assert_eq!(token.lexeme, lexeme);
+            let position = code.find(lexeme).unwrap() + 1;
+            assert_eq!(token.code_location.absolute_position(), position);
+        }
+    }
+
+    #[test]
+    fn test_self_containing_arrays() {
+        let code = r#"
+            var a = [1];
+            push(a, a);
+            var b = [1];
+            push(b, b);
+            var c = [1, [1]];
+            var shared = [2];
+            var twice = [shared, shared];
+            var size = len(a);
+            var same = a == b;
+            var other = a == c;
+            var text = str(a);
+        "#;
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        run_code(&mut interpreter, &format!("{code}print a;")).unwrap();
+        assert_eq!(interpreter.take_output().unwrap(), "[1, [...]]\n");
+        assert_eq!(global(&interpreter, "size"), Object::Double(2.0));
+        assert_eq!(global(&interpreter, "same"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "other"), Object::Bool(false));
+        assert_eq!(global(&interpreter, "text").to_string(), "[1, [...]]");
+        assert_eq!(global(&interpreter, "twice").to_string(), "[[2], [2]]");
+    }
+
+    #[test]
+    fn test_self_containing_maps() {
+        let code = r#"
+            var m = {"a": 1};
+            m["self"] = m;
+            var n = {"a": 1};
+            n["self"] = n;
+            var mixed = [m];
+            m["list"] = mixed;
+            n["list"] = [n];
+            var same = m == n;
+            var other = m == {"a": 1, "self": {}, "list": []};
+        "#;
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        run_code(&mut interpreter, &format!("{code}print m;")).unwrap();
+        assert_eq!(
+            interpreter.take_output().unwrap(),
+            "{a: 1, list: [{...}], self: {...}}\n"
+        );
+        assert_eq!(
+            global(&interpreter, "mixed").to_string(),
+            "[{a: 1, list: [...], self: {...}}]"
+        );
+        assert_eq!(global(&interpreter, "same"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "other"), Object::Bool(false));
+    }
+
+    #[test]
+    fn test_array_literal_and_indexing() {
+        let code = r#"
+            var xs = [1, 2, 3];
+            var empty = [];
+            var first = xs[0];
+            xs[1] = 5;
+            var alias = xs;
+            alias[2] = xs[1] + 1;
+            var nested = [[1, 2], ["a"]];
+            nested[0][1] = nested[1][0];
+            var result = (xs[2] = 10) + 1;
+            var step = range(0, 10, 2)[3];
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "first"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "xs").to_string(), "[1, 5, 10]");
+        assert_eq!(global(&interpreter, "empty").to_string(), "[]");
+        assert_eq!(global(&interpreter, "nested").to_string(), "[[1, a], [a]]");
+        assert_eq!(global(&interpreter, "result"), Object::Double(11.0));
+        assert_eq!(global(&interpreter, "step"), Object::Double(6.0));
+    }
+
+    #[test]
+    fn test_map_literal_and_access() {
+        let code = r#"
+            var m = {"b": 2, "a": 1, 3: "three"};
+            var a = m["a"];
+            var missing = m["c"];
+            m["c"] = 3;
+            m[3] = m[3] + "!";
+            var alias = m;
+            alias[true] = nil;
+            var empty = {};
+            var nested = {"inner": {1: [1, 2]}};
+            nested["inner"][1][0] = 5;
+            var same = {1: 2} == {1: 2};
+            var zeros = {0: "zero"}[-0];
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "a"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "missing"), Object::Nil);
+        assert_eq!(
+            global(&interpreter, "m").to_string(),
+            "{3: three!, a: 1, b: 2, c: 3, true: nil}"
+        );
+        assert_eq!(global(&interpreter, "empty").to_string(), "{}");
+        assert_eq!(
+            global(&interpreter, "nested").to_string(),
+            "{inner: {1: [5, 2]}}"
+        );
+        assert_eq!(global(&interpreter, "same"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "zeros").to_string(), "zero");
+    }
+
+    #[test]
+    fn test_map_key_errors() {
+        for code in [
+            "var m = {[1]: 1};",
+            "var m = {};\nm[{}] = 1;",
+            "class A {}\nvar m = {A(): 1};",
+            // freezing is shallow, the array may still change
+            "class P { init() { this.xs = [1]; } }\nvar p = freeze(P());\nvar m = {p: 1};",
+            "class P { init(x) { this.x = x; } }\nvar m = {};\nm[freeze(P(P(1)))] = 1;",
+        ] {
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(
+                err.kind().to_string(),
+                "Map key can't be an array, a map or an instance that isn't deeply frozen",
+                "{code}"
+            );
+        }
+        // lookups of any value are fine
+        let mut interpreter = Interpreter::new();
+        let code = "var a = [1];\npush(a, a);\nvar m = {1: a};\nvar found = has_key(m, a) or has_key({1: m}, m);";
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "found"), Object::Bool(false));
+        let mut interpreter = Interpreter::new();
+        let code = r#"
+            class P {}
+            var m = {freeze(P()): 1};
+            var n = m[freeze(P())];
+            class Pair { init(a, b) { this.a = a; this.b = b; } }
+            var key = freeze(Pair(freeze(P()), "b"));
+            m[key] = 2;
+            var found = has_key(m, freeze(Pair(freeze(P()), "b")));
+        "#;
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "n"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "found"), Object::Bool(true));
+    }
+
+    #[test]
+    fn test_string_indexing_and_len() {
+        let code = r#"
+            var s = "fÖx🦊";
+            var first = s[0];
+            var wide = s[-1];
+            var chars = "";
+            for (var i = 0; i < len(s); i = i + 1) chars = chars + s[i] + ".";
+            var sizes = [len(""), len(s), len([1, [2, 3]]), len({1: 2}), len(range(0, 5))];
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "first"), Object::Text("f".into()));
+        assert_eq!(global(&interpreter, "wide"), Object::Text("🦊".into()));
+        assert_eq!(
+            global(&interpreter, "chars"),
+            Object::Text("f.Ö.x.🦊.".into())
+        );
+        assert_eq!(global(&interpreter, "sizes").to_string(), "[0, 4, 2, 1, 5]");
+
+        for (code, message) in [
+            ("len(1);", "Can't get length of Number"),
+            ("len(nil);", "Can't get length of Nil"),
+            ("class A {}\nlen(A());", "Can't get length of A"),
+        ] {
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_array_index_errors() {
+        let cases = [
+            (
+                "var xs = [1, 2];\nprint xs[2];",
+                "Array index 2 is out of range for length 2",
+            ),
+            (
+                "var xs = [1, 2];\nxs[-3] = 0;",
+                "Array index -3 is out of range for length 2",
+            ),
+            (
+                "var xs = [1, 2];\nprint xs[0.5];",
+                "Array index must be a whole number, got 0.5",
+            ),
+            (
+                "var xs = [1, 2];\nprint xs[\"0\"];",
+                "Array index must be a number, got 0",
+            ),
+            (
+                "var xs = nil;\nprint xs[0];",
+                "Only arrays, strings and maps can be indexed",
+            ),
+            (
+                "var s = \"fox\";\nprint s[3];",
+                "String index 3 is out of range for length 3",
+            ),
+            (
+                "var s = \"\";\nprint s[0];",
+                "String index 0 is out of range for length 0",
+            ),
+            (
+                "var s = \"ab\";\nprint s[0.5];",
+                "String index must be a whole number, got 0.5",
+            ),
+            (
+                "var r = range(0, 20000);\nprint r[-20001];",
+                "Range index -20001 is out of range for length 20000",
+            ),
+            (
+                "var xs = \"ab\";\nxs[0] = 1;",
+                "Only array elements & map entries can be assigned",
+            ),
+        ];
+        for (code, message) in cases {
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(token.token_type, TokenType::RightBracket, "{code}");
+            assert_eq!(token.code_location.line_number(), 2, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_for_in_loop() {
+        let code = r#"
+            var sum = 0;
+            for (var x in [1, 2, 3, 4, 5]) {
+                if (x == 2) continue;
+                if (x == 5) break;
+                sum = sum + x;
+            }
+            var letters = "";
+            for (var ch in "fox") letters = ch + letters;
+            var fns = [];
+            var xs = [1, 2];
+            for (var x in xs) {
+                xs[0] = 10;
+                fun get() { return x; }
+                fns = [get, fns];
+            }
+            var last = fns[0]();
+            var first = fns[1][0]();
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "sum"), Object::Double(8.0));
+        assert_eq!(global(&interpreter, "letters"), Object::Text("xof".into()));
+        // every iteration has its own variable
+        assert_eq!(global(&interpreter, "first"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "last"), Object::Double(2.0));
+    }
+
+    #[test]
+    fn test_inheritance() {
+        let code = r#"
+            class Animal {
+                init(name) {
+                    this.name = name;
+                }
+                describe() {
+                    return this.name + " " + this.sound();
+                }
+                sound() {
+                    return "...";
+                }
+            }
+            class Dog < Animal {
+                sound() {
+                    return "woof";
+                }
+            }
+            class Puppy < Dog {
+                sound() {
+                    return super.sound() + "!";
+                }
+            }
+            var animal = Animal("cat").describe();
+            var dog = Dog("rex").describe();
+            var puppy = Puppy("bit").describe();
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            global(&interpreter, "animal"),
+            Object::Text("cat ...".into())
+        );
+        assert_eq!(global(&interpreter, "dog"), Object::Text("rex woof".into()));
+        assert_eq!(
+            global(&interpreter, "puppy"),
+            Object::Text("bit woof!".into())
+        );
+    }
+
+    #[test]
+    fn test_getters() {
+        let code = r"
+            class Circle {
+                init(radius) {
+                    this.radius = radius;
+                }
+                area {
+                    return 3 * this.radius * this.radius;
+                }
+                scaled {
+                    this.radius = this.radius * 2;
+                    return this;
+                }
+                method() {
+                    return this.radius;
+                }
+            }
+            class Ring < Circle {
+                area {
+                    return super.area - 3;
+                }
+            }
+            var circle = Circle(2);
+            var area = circle.area;
+            var chained = circle.scaled.area;
+            var method = circle.method;
+            var inherited = Ring(1).area;
+        ";
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "area"), Object::Double(12.0));
+        assert_eq!(global(&interpreter, "chained"), Object::Double(48.0));
+        assert!(matches!(global(&interpreter, "method"), Object::Callee(_)));
+        assert_eq!(global(&interpreter, "inherited"), Object::Double(0.0));
+
+        let err = run_code(&mut interpreter, "circle.area();").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Can only call functions and classes"
+        );
+    }
+
+    #[test]
+    fn test_to_string_hook() {
+        let code = r#"
+            class Point {
+                init(x, y) {
+                    this.x = x;
+                    this.y = y;
+                }
+                toString() {
+                    return "(" + num_text(this.x) + ", " + num_text(this.y) + ")";
+                }
+            }
+            fun num_text(n) {
+                if (n == 1) return "1";
+                return "2";
+            }
+            class Named < Point {}
+            class Plain {}
+            print Point(1, 2);
+            print "at " + Named(2, 1) + "!";
+            print Plain();
+            var text = Point(2, 2) + "";
+        "#;
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            interpreter.take_output().unwrap(),
+            "(1, 2)\nat (2, 1)!\ninstance of class 'Plain'\n"
+        );
+        assert_eq!(global(&interpreter, "text"), Object::Text("(2, 2)".into()));
+
+        run_code(&mut interpreter, "var plain = Plain() + \"\";").unwrap();
+        assert_eq!(
+            global(&interpreter, "plain"),
+            Object::Text("instance of class 'Plain'".into())
+        );
+        let code = "class Bad {\n  toString() { return 1; }\n}\nprint Bad();";
+        let err = run_code(&mut interpreter, code).unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "'toString' must return a string, got Number"
+        );
+        let ErrorInfo::Token(token) = err.info() else {
+            panic!("Error location expected");
+        };
+        assert_eq!(token.lexeme, "toString");
+    }
+
+    #[test]
+    fn test_to_string_hook_in_containers() {
+        let code = r#"
+            class C {
+                toString() { return "C!"; }
+            }
+            var key = C();
+            freeze(key);
+            var items = [C(), 1, [C()]];
+            push(items, items);
+            print items;
+            print {key: C()};
+            var joined = "" + [C()];
+            var converted = str([C()]);
+            var listed = join([[C()]], "");
+        "#;
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            interpreter.take_output().unwrap(),
+            "[C!, 1, [C!], [...]]\n{C!: C!}\n"
+        );
+        for name in ["joined", "converted", "listed"] {
+            assert_eq!(global(&interpreter, name), Object::Text("[C!]".into()));
+        }
+    }
+
+    #[test]
+    #[allow(clippy::mutable_key_type)]
+    fn test_identity_equality() {
+        use std::hash::{BuildHasher, RandomState};
+
+        let code = r"
+            class Point {
+                init(x) {
+                    this.x = x;
+                }
+            }
+            var p = Point(1);
+            var results = [p == p, p == Point(1), p != Point(1), Point == Point];
+        ";
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            global(&interpreter, "results").to_string(),
+            "[true, false, true, true]"
+        );
 
-        self.environment
-            .borrow_mut()
-            .define(&data.name.lexeme, value);
-        Ok(())
+        let hasher = RandomState::new();
+        let point = global(&interpreter, "p");
+        let before = hasher.hash_one(&point);
+        run_code(&mut interpreter, "p.x = 2;\np.y = 3;").unwrap();
+        assert_eq!(hasher.hash_one(&point), before);
+        let class = global(&interpreter, "Point");
+        let mut keys = HashSet::new();
+        keys.insert(class.clone());
+        keys.insert(point.clone());
+        run_code(&mut interpreter, "p.x = 4;").unwrap();
+        assert!(keys.contains(&point) && keys.contains(&class));
     }
 
-    fn visit_block(&mut self, data: &BlockStmt) -> FoxResult<()> {
-        let env = Environment::with(Some(self.environment.clone()));
-        self.execute_block(&data.statements, env)
+    #[test]
+    fn test_has_member() {
+        let code = r#"
+            class Shape {
+                init() {
+                    this.name = "shape";
+                }
+                render() {}
+            }
+            class Circle < Shape {
+                area {
+                    return 0;
+                }
+            }
+            var circle = Circle();
+            var results = [
+                has_method(circle, "render"), has_method(circle, "area"),
+                has_method(circle, "name"), has_method(Circle, "render"),
+                has_method(Shape, "area"), has_field(circle, "name"),
+                has_field(circle, "render"), has_field(Circle, "name"),
+                has_method(1, "render"), has_field(nil, "name")
+            ];
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            global(&interpreter, "results").to_string(),
+            "[true, true, false, true, false, true, false, false, false, false]"
+        );
+        for (code, builtin) in [
+            ("has_method(circle, 1);", "has_method"),
+            ("has_field(circle, nil);", "has_field"),
+        ] {
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert!(
+                err.kind()
+                    .to_string()
+                    .starts_with(&format!("{builtin}() expects a string name")),
+                "{code}: {err:?}"
+            );
+        }
     }
 
-    fn visit_if(&mut self, data: &IfStmt) -> FoxResult<()> {
-        if self.evaluate(&data.condition)?.is_true() {
-            self.execute(&data.then_branch)
-        } else if let Some(else_branch) = &data.else_branch {
-            self.execute(else_branch)
-        } else {
-            Ok(())
-        }
+    #[test]
+    fn test_missing_property_handler() {
+        let code = r#"
+            class Target {
+                greet() {
+                    return "hi";
+                }
+            }
+            class Proxy {
+                init(target) {
+                    this.target = target;
+                    this.misses = 0;
+                }
+                onMissing(name) {
+                    this.misses = this.misses + 1;
+                    if (name == "greet") return this.target.greet;
+                    return name + "?";
+                }
+            }
+            class Loop {
+                onMissing(name) {
+                    return this.other;
+                }
+            }
+            var proxy = Proxy(Target());
+            var greeting = proxy.greet();
+            var unknown = proxy.color;
+            var misses = proxy.misses;
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "greeting"), Object::Text("hi".into()));
+        assert_eq!(
+            global(&interpreter, "unknown"),
+            Object::Text("color?".into())
+        );
+        assert_eq!(global(&interpreter, "misses"), Object::Double(2.0));
+
+        let err = run_code(&mut interpreter, "Target().color;").unwrap_err();
+        assert_eq!(err.kind().to_string(), "Undefined property 'color'");
+        let err = run_code(&mut interpreter, "Loop().start;").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Undefined property 'other', 'onMissing' calls are nested too deep"
+        );
+        // the depth is restored after the failure
+        assert!(run_code(&mut interpreter, "proxy.size;").is_ok());
     }
 
-    fn visit_while(&mut self, data: &WhileStmt) -> FoxResult<()> {
-        while self.evaluate(&data.condition)?.is_true() {
-            self.execute(&data.body)?;
+    #[test]
+    fn test_introspection() {
+        let code = r"
+            class Shape {
+                init() {
+                    this.name = 1;
+                }
+                area() {}
+                render() {}
+            }
+            class Circle < Shape {
+                init() {
+                    super.init();
+                    this.radius = 2;
+                }
+                area() {}
+            }
+            var circle = Circle();
+            var field_names = fields(circle);
+            var instance_methods = methods(circle);
+            var class_methods = methods(Shape);
+            var exact = class_of(circle);
+            var empty = fields(Shape());
+        ";
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        let names = |name: &str| global(&interpreter, name).to_string();
+        assert_eq!(names("field_names"), "[name, radius]");
+        assert_eq!(names("instance_methods"), "[area, init, render]");
+        assert_eq!(names("class_methods"), "[area, init, render]");
+        assert_eq!(names("exact"), "class meta class Circle");
+        assert_eq!(names("empty"), "[name]");
+
+        for (code, message) in [
+            ("fields(Shape);", "fields() expects an instance, got Class"),
+            ("class_of(1);", "class_of() expects an instance, got Number"),
+            (
+                "methods(nil);",
+                "methods() expects a class or an instance, got Nil",
+            ),
+        ] {
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
         }
-        Ok(())
     }
 
-    fn visit_function(&mut self, data: &FunctionStmt) -> FoxResult<()> {
-        let object = Func::new(Rc::new(data.clone()), self.environment.clone(), false);
-        self.environment
-            .borrow_mut()
-            .define(&data.name.lexeme, Object::Callee(object));
-        Ok(())
+    #[test]
+    fn test_private_properties() {
+        let code = r"
+            class Counter {
+                init() {
+                    this._count = 0;
+                }
+                increment() {
+                    this._count = this._count + 1;
+                    return this._check();
+                }
+                _check() {
+                    return this._count;
+                }
+            }
+            class Twice < Counter {
+                increment() {
+                    super.increment();
+                    this._count = this._count + 1;
+                    return super._check();
+                }
+            }
+            var counter = Counter();
+            counter.increment();
+            var counted = counter.increment();
+            var twice = Twice().increment();
+        ";
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "counted"), Object::Double(2.0));
+        assert_eq!(global(&interpreter, "twice"), Object::Double(2.0));
+
+        for (code, name) in [
+            ("counter._count;", "_count"),
+            ("counter._count = 5;", "_count"),
+            ("counter._check();", "_check"),
+            (
+                "class Other { peek(c) { return c._count; } }\nOther().peek(counter);",
+                "_count",
+            ),
+        ] {
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(
+                err.kind().to_string(),
+                format!("Property '{name}' is private"),
+                "{code}"
+            );
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(token.lexeme, name);
+        }
+        assert_eq!(global(&interpreter, "counted"), Object::Double(2.0));
     }
 
-    fn visit_return(&mut self, data: &ReturnStmt) -> FoxResult<()> {
-        let value = if let Some(val) = &data.value {
-            self.evaluate(val)?
-        } else {
-            Object::Nil
+    #[test]
+    fn test_inherited_class_constants() {
+        let code = r"
+            class C {
+                const PI = 3;
+                const NAME = 1;
+            }
+            class D < C {
+                const NAME = 2;
+                f() { return PI; }
+                name() { return NAME; }
+                later() { fun inner() { return PI * 2; } return inner; }
+            }
+            class E < D {}
+            var local = D().f();
+            var own = D().name();
+            var captured = D().later()();
+            var nested = E().f();
+            fun top() { return PI; }
+            var global_pi = top();
+        ";
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        for (name, value) in [
+            // not the math constant
+            ("local", 3.0),
+            ("own", 2.0),
+            ("captured", 6.0),
+            ("nested", 3.0),
+            ("global_pi", std::f64::consts::PI),
+        ] {
+            assert_eq!(global(&interpreter, name), Object::Double(value), "{name}");
+        }
+
+        let code = "class F < C { m() { PI = 4; } }
+F().m();";
+        let err = run_code(&mut interpreter, code).unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Can't assign to constant 'PI' of class 'C'"
+        );
+        let ErrorInfo::Token(token) = err.info() else {
+            panic!("Error location expected");
         };
-        Err(FoxError::error(ErrorKind::Return(value)))
+        assert_eq!(token.lexeme, "PI");
     }
 
-    fn visit_class(&mut self, data: &ClassStmt) -> FoxResult<()> {
-        let mut superclass: Option<Rc<MetaClass>> = None;
-        if let Some(expr) = &data.superclass {
-            let eval = self.evaluate(expr)?;
-            match eval {
-                Object::Class(val) => {
-                    superclass = Some(val.clone());
+    #[test]
+    fn test_class_constants() {
+        let code = r"
+            var evaluated = 0;
+            fun next() {
+                evaluated = evaluated + 1;
+                return evaluated;
+            }
+            class Circle {
+                const PI = 3;
+                const TAU = PI * 2;
+                const ORDER = next();
+                init(radius) {
+                    this.radius = radius;
                 }
-                _ => {
-                    let token = expr.as_variable()?.name.clone();
-                    return Err(FoxError::runtime(Some(token), "Superclass must be a class"));
+                area() {
+                    return PI * this.radius * this.radius;
+                }
+                tau() {
+                    return this.TAU;
                 }
             }
+            class Precise < Circle {
+                const PI = 3.5;
+            }
+            var pi = Circle.PI;
+            var area = Circle(2).area();
+            var tau = Circle(1).tau();
+            var order = Circle(1).ORDER + Circle.ORDER;
+            var inherited = Precise.TAU;
+            var shadowed = Precise.PI;
+            var precise_area = Precise(2).area();
+        ";
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        for (name, value) in [
+            ("pi", 3.0),
+            ("area", 12.0),
+            ("tau", 6.0),
+            ("order", 2.0),
+            ("evaluated", 1.0),
+            ("inherited", 6.0),
+            ("shadowed", 3.5),
+            // methods see the constants of their own class
+            ("precise_area", 12.0),
+        ] {
+            assert_eq!(global(&interpreter, name), Object::Double(value), "{name}");
         }
 
-        self.environment
-            .borrow_mut()
-            .define(&data.name.lexeme, Object::Nil);
-
-        let enclosing = self.environment.clone();
-        if let Some(obj) = &superclass {
-            self.environment = Environment::with(Some(enclosing.clone())).shared_ptr();
-            let value = Object::Class(obj.clone());
-            self.environment.borrow_mut().define(KEYWORD_SUPER, value);
+        for (code, message) in [
+            (
+                "Circle.PI = 4;",
+                "Can't assign to constant 'PI' of class 'Circle'",
+            ),
+            (
+                "Circle(1).PI = 4;",
+                "Can't assign to constant 'PI' of class 'Circle'",
+            ),
+            (
+                "Precise.TAU = 4;",
+                "Can't assign to constant 'TAU' of class 'Precise'",
+            ),
+            ("Circle.E;", "Undefined constant 'E' of class 'Circle'"),
+        ] {
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(token.token_type, TokenType::Identifier, "{code}");
         }
+        let err =
+            run_code(&mut interpreter, "class D { const X = 1; m() { X = 2; } }").unwrap_err();
+        assert_eq!(err.kind().to_string(), "Can't assign to constant 'X'");
+        let err = run_code(&mut interpreter, "class D { const X = this; }").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Can't use 'this' outside of a class"
+        );
+    }
 
-        let mut methods = HashMap::new();
-        for stmt in &data.methods {
-            let func = stmt.as_function()?;
-            let method = Func::new(
-                Rc::new(func.clone()),
-                self.environment.clone(),
-                func.name.lexeme == INITIALIZER_NAME,
-            );
-            methods.insert(func.name.lexeme.clone(), method);
-        }
-        let class_data = MetaClass::new(&data.name.lexeme, superclass, methods);
-        let class = Object::Class(std::rc::Rc::new(class_data));
+    #[test]
+    fn test_equals_method() {
+        let code = r"
+            class Point {
+                init(x, y) {
+                    this.x = x;
+                    this.y = y;
+                    this.compared = 0;
+                }
+                equals(other) {
+                    this.compared = this.compared + 1;
+                    return other is Point and this.x == other.x and this.y == other.y;
+                }
+            }
+            class Point3 < Point {}
+            class Plain {}
+            var p = Point(1, 2);
+            var plain = Plain();
+            var results = [
+                p == Point(1, 2), p != Point(1, 2), p == Point(2, 1), p == 1,
+                Point3(1, 2) == p, plain == plain, plain == Plain(), plain != Plain()
+            ];
+            var compared = p.compared;
+        ";
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            global(&interpreter, "results").to_string(),
+            "[true, false, false, false, true, true, false, true]"
+        );
+        assert_eq!(global(&interpreter, "compared"), Object::Double(4.0));
+    }
 
-        if data.superclass.is_some() {
-            self.environment = enclosing;
+    #[test]
+    fn test_is_operator() {
+        let code = r#"
+            class Shape {}
+            class Circle < Shape {}
+            class Other {}
+            var circle = Circle();
+            var results = [
+                circle is Circle, circle is Shape, circle is Other, Shape() is Circle,
+                1 is Number, "a" is String, nil is Nil, [] is Array, circle is Class,
+                Shape is Class, 1 is Shape, 1 + 1 is Number == true
+            ];
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(
+            global(&interpreter, "results").to_string(),
+            "[true, true, false, false, true, true, true, true, false, true, false, true]"
+        );
+        for (code, target) in [
+            ("1 is 2;", "Number"),
+            ("var s = Circle;\nnil is \"Shape\";", "String"),
+        ] {
+            let code = format!("class Circle {{}}\n{code}");
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, &code).unwrap_err();
+            assert_eq!(
+                err.kind().to_string(),
+                format!("Right operand of 'is' must be a class or a type, got {target}")
+            );
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(token.token_type, TokenType::Is);
         }
-
-        self.environment.borrow_mut().assign(&data.name, class)
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    fn test_type_dispatch() {
+        let code = r#"
+            class Shape {}
+            class Circle < Shape {}
+            fun kind(x) {
+                var t = type(x);
+                if (t == Nil) return "nil";
+                if (t == Number) return "number";
+                if (t == String) return "string";
+                if (t == Bool) return "bool";
+                if (t == Function) return "function";
+                if (t == Class) return "class";
+                if (t == Array) return "array";
+                if (t == Range) return "range";
+                if (t == Map) return "map";
+                if (t == Type) return "type";
+                if (t == Shape) return "shape";
+                if (t == Circle) return "circle";
+                return "unknown";
+            }
+            var values = [nil, 1, "a", false, kind, clock, Shape, [], range(0, 20000), {}, Number, Shape(), Circle()];
+            for (var value in values) print kind(value);
+            var exact = type(Circle()) == Shape;
+            var marker = type(2) == type(3);
+        "#;
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        run_code(&mut interpreter, code).unwrap();
+        let output = interpreter.take_output().unwrap();
+        let expected = [
+            "nil", "number", "string", "bool", "function", "function", "class", "array", "range",
+            "map", "type", "shape", "circle",
+        ];
+        assert_eq!(output.lines().collect::<Vec<_>>(), expected);
+        // the exact class is returned, superclasses don't match
+        assert_eq!(global(&interpreter, "exact"), Object::Bool(false));
+        assert_eq!(global(&interpreter, "marker"), Object::Bool(true));
+    }
 
-    fn binary_expr(l: Object, t_type: TokenType, r: Object) -> BinaryExpr {
-        let left = Box::new(Expression::literal(l));
-        let right = Box::new(Expression::literal(r));
-        let operator = Token {
-            token_type: t_type,
-            lexeme: "Debug".to_string(),
-            literal: Object::Nil,
-            code_location: Default::default(),
-        };
-        BinaryExpr {
-            left,
-            operator,
-            right,
+    #[test]
+    fn test_optional_chaining() {
+        let code = r#"
+            class Point {
+                init(x) { this.x = x; this.next = nil; }
+                double() { return this.x * 2; }
+            }
+            var none = nil;
+            var calls = 0;
+            fun count() { calls = calls + 1; return 1; }
+            var a = none?.x;
+            var b = none?.next.x;
+            var c = none?.double(count());
+            var p = Point(3);
+            var d = p?.x;
+            var e = p?.double();
+            var f = p?.next?.x;
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        for name in ["a", "b", "c", "f"] {
+            assert_eq!(global(&interpreter, name), Object::Nil, "{name}");
         }
+        assert_eq!(global(&interpreter, "d"), Object::Double(3.0));
+        assert_eq!(global(&interpreter, "e"), Object::Double(6.0));
+        // arguments of a short-circuited call are skipped
+        assert_eq!(global(&interpreter, "calls"), Object::Double(0.0));
     }
 
     #[test]
-    fn test_binary_double_plus() {
+    fn test_optional_chaining_plain_get_of_nil() {
+        let code = r#"
+            class Point { init() { this.next = nil; } }
+            var p = Point();
+            print p?.next.x;
+        "#;
         let mut interpreter = Interpreter::new();
-        let expr = binary_expr(Object::Double(2.0), TokenType::Plus, Object::Double(2.0));
-        let obj = interpreter.visit_binary(&expr).unwrap();
-        assert_eq!(obj, Object::Double(4.0));
-        // err
-        let expr = binary_expr(Object::Double(3.0), TokenType::Plus, Object::Nil);
-        let res = interpreter.visit_binary(&expr);
-        assert!(res.is_err());
+        let err = run_code(&mut interpreter, code).unwrap_err();
+        assert_eq!(err.kind().to_string(), "Only instances have properties");
     }
 
     #[test]
-    fn test_binary_string_plus() {
+    fn test_match_statement() {
+        let code = r#"
+            var calls = 0;
+            fun subject(x) { calls = calls + 1; return x; }
+            fun describe(x) {
+                var result = "none";
+                match (subject(x)) {
+                    0 => result = "zero";
+                    -1.5 => result = "negative";
+                    "a" => result = "letter";
+                    true => result = "yes";
+                    nil => result = "nothing";
+                    else => result = "other";
+                }
+                return result;
+            }
+            var results = [describe(0), describe(-1.5), describe("a"), describe(true), describe(nil), describe(7)];
+            var skipped = "unchanged";
+            match (5) {
+                1 => skipped = "changed";
+            }
+            var sum = 0;
+            for (var x in [1, 2, 3, 4]) {
+                match (x) {
+                    2 => continue;
+                    4 => break;
+                }
+                sum = sum + x;
+            }
+        "#;
         let mut interpreter = Interpreter::new();
-        let expr = binary_expr(
-            Object::Text("hello,".to_string()),
-            TokenType::Plus,
-            Object::Text("fox lang".to_string()),
+        run_code(&mut interpreter, code).unwrap();
+        let Object::Array(results) = global(&interpreter, "results") else {
+            panic!("Array expected");
+        };
+        let results = results
+            .borrow()
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            results,
+            ["zero", "negative", "letter", "yes", "nothing", "other"]
         );
-        let obj = interpreter.visit_binary(&expr).unwrap();
-        assert_eq!(obj, Object::Text("hello,fox lang".to_string()));
+        // the subject is evaluated once per match
+        assert_eq!(global(&interpreter, "calls"), Object::Double(6.0));
+        assert_eq!(
+            global(&interpreter, "skipped"),
+            Object::Text("unchanged".into())
+        );
+        assert_eq!(global(&interpreter, "sum"), Object::Double(4.0));
     }
 
     #[test]
-    fn test_binary_double_minus() {
+    fn test_match_first_arm_only() {
+        let code = r#"
+            match (1) {
+                1 => print "first";
+                2 => print "second";
+                else => print "default";
+            }
+        "#;
         let mut interpreter = Interpreter::new();
-        let expr = binary_expr(Object::Double(3.0), TokenType::Minus, Object::Double(2.0));
-        let obj = interpreter.visit_binary(&expr).unwrap();
-        assert_eq!(obj, Object::Double(1.0));
-        // err
-        let expr = binary_expr(Object::Double(3.0), TokenType::Minus, Object::Nil);
-        let res = interpreter.visit_binary(&expr);
-        assert!(res.is_err());
+        interpreter.capture_output();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(interpreter.take_output().unwrap(), "first\n");
     }
 
     #[test]
-    fn test_binary_double_multiply() {
+    fn test_dump_globals_as_source() {
+        let code = r#"
+            var b = [1, "two", nil, true, {"k": [2.5, -1], 3: {}}, Number];
+            var a = 0.1;
+            fun f() {}
+            class C {}
+            var c = C();
+            var r = range(0, 20000);
+            var cyclic = [0];
+            cyclic[0] = cyclic;
+            var limits = [NAN, -INFINITY];
+            Map = 1;
+        "#;
         let mut interpreter = Interpreter::new();
-        // ok
-        let expr = binary_expr(Object::Double(3.0), TokenType::Star, Object::Double(2.0));
-        let result = interpreter.visit_binary(&expr);
-        assert!(result.is_ok());
-        let obj = result.unwrap();
-        assert_eq!(obj, Object::Double(6.0));
-        // err
-        let expr = binary_expr(Object::Double(3.0), TokenType::Star, Object::Nil);
-        let result = interpreter.visit_binary(&expr);
-        assert!(result.is_err());
+        run_code(&mut interpreter, code).unwrap();
+        let expected = r#"// C: class omitted
+var Map = 1;
+var a = 0.1;
+var b = [1, "two", nil, true, {3: {}, "k": [2.5, -1]}, Number];
+// c: instance omitted
+// cyclic: value without source form omitted
+// f: function omitted
+var limits = [NAN, -INFINITY];
+// r: range omitted
+"#;
+        assert_eq!(interpreter.dump_globals_as_source(), expected);
+        assert_eq!(Interpreter::new().dump_globals_as_source(), "");
     }
 
     #[test]
-    fn test_binary_double_divide() {
+    fn test_range_expressions() {
+        let code = r"
+            var sum = 0;
+            for (var i in 0..10) sum = sum + i;
+            var inclusive = 0;
+            for (var i in 1..=4) inclusive = inclusive + i;
+            var n = 3;
+            var last;
+            for (var i in 0..n - 1) last = i;
+            var reversed = 0;
+            for (var i in 10..0) reversed = reversed + 1;
+            var big = 0;
+            for (var i in 0..10000000) {
+                big = i;
+                if (i == 5) break;
+            }
+            var r = 2..=5;
+            var picked = r[1];
+            var size = len(r);
+            var less = 1 < 0..2;
+        ";
         let mut interpreter = Interpreter::new();
-        // ok
-        let expr = binary_expr(Object::Double(6.0), TokenType::Slash, Object::Double(2.0));
-        let result = interpreter.visit_binary(&expr);
-        assert!(result.is_ok());
-        let obj = result.unwrap();
-        assert_eq!(obj, Object::Double(3.0));
-        // div by zero
-        let expr = binary_expr(Object::Double(6.0), TokenType::Slash, Object::Double(0.0));
-        let result = interpreter.visit_binary(&expr);
-        assert!(result.is_err());
-        // type mismatch
-        let expr = binary_expr(Object::Nil, TokenType::Slash, Object::Double(0.0));
-        let result = interpreter.visit_binary(&expr);
-        assert!(result.is_err());
+        let err = run_code(&mut interpreter, code).unwrap_err();
+        // the range is a term, it can't be compared
+        assert_eq!(
+            err.kind().to_string(),
+            "Operands of '<' must be numbers, got Number and Range"
+        );
+        let code = code.replace("var less = 1 < 0..2;", "");
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, &code).unwrap();
+        assert_eq!(global(&interpreter, "sum"), Object::Double(45.0));
+        assert_eq!(global(&interpreter, "inclusive"), Object::Double(10.0));
+        assert_eq!(global(&interpreter, "last"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "reversed"), Object::Double(0.0));
+        assert_eq!(global(&interpreter, "big"), Object::Double(5.0));
+        assert_eq!(global(&interpreter, "picked"), Object::Double(3.0));
+        assert_eq!(global(&interpreter, "size"), Object::Double(4.0));
+    }
+
+    #[test]
+    fn test_range_bounds_not_numbers() {
+        for code in ["var r = 0\n..\"a\";", "var r = nil..=1;"] {
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), "Range bounds must be numbers");
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert!(token.lexeme.starts_with(".."), "{code}");
+        }
+    }
+
+    #[test]
+    fn test_for_in_not_iterable() {
+        for code in ["for (var x\n in 1) {}", "for (var x\n in {}) print x;"] {
+            let mut interpreter = Interpreter::new();
+            let err = run_code(&mut interpreter, code).unwrap_err();
+            assert_eq!(
+                err.kind().to_string(),
+                "Only arrays, strings and ranges can be iterated",
+                "{code}"
+            );
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(token.token_type, TokenType::In, "{code}");
+            assert_eq!(token.code_location.line_number(), 2, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_index_position() {
+        let len = 4;
+        let cases = [
+            (0.0, Some(0)),
+            (-0.0, Some(0)),
+            (3.0, Some(3)),
+            (-1.0, Some(3)),
+            (-4.0, Some(0)),
+            (2.0000001, Some(2)),
+            (1.9999999, Some(2)),
+            (len as f64, None),
+            (-5.0, None),
+            (len as f64 - 0.5, None),
+            (1.5, None),
+            (-0.5, None),
+            (1e30, None),
+            (-1e30, None),
+            (f64::MAX, None),
+            (f64::INFINITY, None),
+            (f64::NEG_INFINITY, None),
+            (f64::NAN, None),
+        ];
+        for (value, expected) in cases {
+            let result = index_position("Array", &Object::Double(value), len);
+            assert_eq!(result.as_ref().ok(), expected.as_ref(), "{value}");
+        }
+        assert_eq!(
+            index_position("Array", &Object::Double(0.0), 0).unwrap_err(),
+            "Array index 0 is out of range for length 0"
+        );
+        assert_eq!(
+            index_position("Array", &Object::Double(1e30), 4).unwrap_err(),
+            "Array index 1e30 is out of range for length 4"
+        );
+        assert_eq!(
+            index_position("Array", &Object::Double(2.5), 4).unwrap_err(),
+            "Array index must be a whole number, got 2.5"
+        );
+        assert_eq!(
+            index_position("Array", &Object::Double(f64::NAN), 4).unwrap_err(),
+            "Array index must be a whole number, got nan"
+        );
+        assert_eq!(
+            index_position("Array", &Object::Nil, 4).unwrap_err(),
+            "Array index must be a number, got nil"
+        );
+    }
+
+    #[test]
+    fn test_negative_index() {
+        let code = r#"
+            var xs = [1, 2, 3];
+            xs[-1] = 30;
+            var last = xs[-1];
+            var first = xs[-3];
+            var step = range(0, 30000)[-1];
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        assert_eq!(global(&interpreter, "last"), Object::Double(30.0));
+        assert_eq!(global(&interpreter, "first"), Object::Double(1.0));
+        assert_eq!(global(&interpreter, "step"), Object::Double(29999.0));
     }
 
     #[test]
@@ -582,4 +4872,103 @@ mod test {
         let result = interpreter.visit_binary(&expr);
         assert!(result.is_err());
     }
+
+    /// Blocks nested `depth` levels, one opening brace per line,
+    /// the innermost block reads the global variable
+    fn nested_blocks(depth: usize) -> String {
+        let mut code = "var x = 1;\n".to_string();
+        code.push_str(&"{\n".repeat(depth));
+        code.push_str("x = x + 1;\n");
+        code.push_str(&"}".repeat(depth));
+        code
+    }
+
+    #[test]
+    fn test_environment_depth_statistics() {
+        let code = r#"
+            fun f() { { var a = 1; } }
+            fun r(n) { if (n > 0) r(n - 1); }
+            f();
+            r(50);
+        "#;
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, code).unwrap();
+        // recursion doesn't make the chain longer, closures are lexical
+        assert_eq!(interpreter.statistics().max_environment_depth, 2);
+
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, &nested_blocks(30)).unwrap();
+        assert_eq!(interpreter.statistics().max_environment_depth, 30);
+        assert_eq!(global(&interpreter, "x"), Object::Double(2.0));
+    }
+
+    #[test]
+    fn test_deep_nesting_warning() {
+        let mut interpreter = Interpreter::new();
+        run_code(&mut interpreter, &nested_blocks(30)).unwrap();
+        assert!(interpreter.take_warnings().is_empty());
+
+        let mut interpreter = Interpreter::new();
+        interpreter.warn_deep_nesting(10);
+        run_code(&mut interpreter, &nested_blocks(30)).unwrap();
+        let warnings = interpreter.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind(), WarningKind::DeepNesting);
+        let crate::fox::ErrorInfo::Token(token) = warnings[0].info() else {
+            panic!("Block location expected");
+        };
+        // the 11th brace crosses the threshold
+        assert_eq!(token.token_type, TokenType::LeftBrace);
+        assert_eq!(token.code_location.line_number(), 12);
+
+        let mut interpreter = Interpreter::new();
+        interpreter.warn_deep_nesting(10);
+        interpreter.deny(WarningKind::DeepNesting);
+        let err = run_code(&mut interpreter, &nested_blocks(30)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Runtime(_)));
+        assert_eq!(global(&interpreter, "x"), Object::Double(1.0));
+    }
+
+    #[test]
+    fn test_deep_nesting_warning_at_function() {
+        let code = "fun f() {\n  { print 1; }\n}\nf();";
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        interpreter.warn_deep_nesting(0);
+        run_code(&mut interpreter, code).unwrap();
+        let warnings = interpreter.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        let crate::fox::ErrorInfo::Token(token) = warnings[0].info() else {
+            panic!("Function location expected");
+        };
+        assert_eq!(token.lexeme, "f");
+    }
+
+    /// cargo test --release bench_deep_nesting -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_deep_nesting() {
+        const DEPTH: usize = 5000;
+        // the front end recurses per nesting level
+        let handle = std::thread::Builder::new()
+            .stack_size(512 * 1024 * 1024)
+            .spawn(|| {
+                // the local declared at the top is DEPTH environments away
+                let code = format!(
+                    "{{ var y = 0; {}for (var i = 0; i < 1000; i = i + 1) y = y + 1; {} }}",
+                    "{".repeat(DEPTH),
+                    "}".repeat(DEPTH)
+                );
+                let mut interpreter = Interpreter::new();
+                let start = std::time::Instant::now();
+                run_code(&mut interpreter, &code).unwrap();
+                println!(
+                    "1000 updates of a variable {DEPTH} environments away: {:?}",
+                    start.elapsed()
+                );
+                assert_eq!(interpreter.statistics().max_environment_depth, DEPTH + 2);
+            })
+            .unwrap();
+        handle.join().unwrap();
+    }
 }
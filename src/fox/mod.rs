@@ -1,68 +1,811 @@
 mod ast;
+mod builtins;
 mod class;
+mod completion;
+mod coverage;
+mod diagnostic;
 mod environment;
 mod error;
 mod func;
+mod history;
 mod interpreter;
+mod module;
 mod object;
 mod parser;
+mod program;
+mod random;
+mod range;
+mod repl;
 mod resolver;
 mod scanner;
+mod testing;
 mod token;
 mod utils;
 
+pub use builtins::BuiltinGroup;
+pub use completion::{Completion, CompletionKind};
+pub use coverage::{CoverageReport, FunctionCoverage};
+pub use diagnostic::{Diagnostic, Severity, json_string};
 pub use error::*;
+pub use history::{ExecutionEvent, ExecutionHistory};
+pub use interpreter::{DEEP_NESTING_THRESHOLD, GlobalDeclaration, Statistics, executing_location};
 use object::*;
 use parser::*;
+pub use program::Program;
+pub use repl::Repl;
 use scanner::*;
+pub use testing::{TestOutcome, TestReport};
 use token::*;
 use utils::*;
+pub use utils::{CodeLocation, edit_distance, escape_dot, escape_json};
 
-use crate::fox::{interpreter::Interpreter, resolver::Resolver};
+use std::{
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::Path,
+    sync::{Arc, atomic::AtomicBool},
+};
+
+use crate::fox::{
+    ast::Statement,
+    interpreter::Interpreter,
+    module::{ImportingFile, Modules},
+    resolver::Resolver,
+};
 
 const KEYWORD_THIS: &str = "this";
 const KEYWORD_SUPER: &str = "super";
 
 pub type Source = [char];
 
+#[derive(Clone)]
+pub struct FoxConfig {
+    /// Script name used in reports
+    pub source_name: String,
+    /// Collect per line & per function execution counters
+    pub coverage: bool,
+    /// Opt-in warning kinds to report
+    pub warn: Vec<WarningKind>,
+    /// Warning kinds reported as errors
+    pub deny: Vec<WarningKind>,
+    /// Builtin groups failing when called
+    pub disabled_builtins: Vec<BuiltinGroup>,
+    /// Count of the last executed statements & calls shown with runtime errors,
+    /// zero disables the recording. Once enabled, the globals are also kept
+    /// as source when the run fails, see `Fox::failure_state`
+    pub history: usize,
+    /// Once set, execution stops with an error at the next statement,
+    /// the flag may be set from another thread
+    pub cancel_flag: Option<Arc<AtomicBool>>,
+    /// Globals the host defines at runtime
+    pub globals: Vec<GlobalDeclaration>,
+    /// Reject references to globals that are neither builtins,
+    /// declared nor defined at the top level of the script
+    pub strict_globals: bool,
+    /// Command line arguments following the script path, see `args()`
+    pub script_args: Vec<String>,
+    /// Division & remainder by zero give infinity or NaN instead of failing
+    pub ieee_division: bool,
+}
+
+impl Default for FoxConfig {
+    fn default() -> Self {
+        Self {
+            source_name: "<script>".to_string(),
+            coverage: false,
+            warn: Vec::new(),
+            deny: Vec::new(),
+            disabled_builtins: Vec::new(),
+            history: 0,
+            cancel_flag: None,
+            globals: Vec::new(),
+            strict_globals: false,
+            script_args: Vec::new(),
+            ieee_division: false,
+        }
+    }
+}
+
+impl FoxConfig {
+    /// Makes the resolver treat the name as defined,
+    /// calls of it are checked against the arity if one is given
+    pub fn declare_global(&mut self, name: &str, arity: Option<usize>) {
+        self.globals.push(GlobalDeclaration {
+            name: name.to_string(),
+            arity,
+        });
+    }
+}
+
 pub struct Fox {
+    // the script followed by the imported files
     code: Vec<char>,
+    script_len: usize,
+    // resolves the imports of the script against its directory
+    script: ImportingFile,
+    modules: Modules,
+    config: FoxConfig,
+    // executed instead of the code if present
+    program: Option<Program>,
+    coverage: Option<CoverageReport>,
+    warnings: Vec<Warning>,
+    statistics: Statistics,
+    history: Option<ExecutionHistory>,
+    failure_state: Option<String>,
 }
 
 impl Fox {
     pub fn with(code: Vec<char>) -> Self {
-        Self { code }
+        Self::with_config(code, FoxConfig::default())
+    }
+
+    pub fn with_config(code: Vec<char>, config: FoxConfig) -> Self {
+        let path = Path::new(&config.source_name);
+        let script = ImportingFile {
+            name: config.source_name.clone(),
+            path: path.canonicalize().ok(),
+            dir: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        };
+        Self {
+            script_len: code.len(),
+            code,
+            script,
+            modules: Modules::default(),
+            config,
+            program: None,
+            coverage: None,
+            warnings: Vec::new(),
+            statistics: Statistics::default(),
+            history: None,
+            failure_state: None,
+        }
+    }
+
+    /// Runs the compiled program skipping the front end.
+    /// The original code, if available, is used in error messages
+    /// and must match the one the program was compiled from
+    pub fn with_program(
+        program: Program,
+        code: Option<Vec<char>>,
+        mut config: FoxConfig,
+    ) -> FoxResult<Self> {
+        if let Some(code) = &code {
+            program.verify_source(code)?;
+        }
+        config.source_name = program.source_name().to_string();
+        let mut fox = Self::with_config(code.unwrap_or_default(), config);
+        fox.program = Some(program);
+        Ok(fox)
+    }
+
+    /// Scans, parses & resolves the code without running it
+    pub fn compile(&mut self) -> FoxResult<Program> {
+        let (mut interpreter, statements) = self.front_end()?;
+        self.finish(&mut interpreter);
+        let locals = interpreter.resolved_locals();
+        Ok(Program::new(
+            &self.config.source_name,
+            self.script_code(),
+            statements,
+            locals,
+        ))
+    }
+
+    /// Warnings emitted during the last run
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Coverage collected during the last run if it was enabled in the config
+    pub fn coverage_report(&self) -> Option<&CoverageReport> {
+        self.coverage.as_ref()
+    }
+
+    /// Statistics of the last run
+    pub fn statistics(&self) -> Statistics {
+        self.statistics
+    }
+
+    /// Statements & calls executed last in the last run if it was enabled in the config
+    pub fn history(&self) -> Option<&ExecutionHistory> {
+        self.history.as_ref()
+    }
+
+    /// Identifiers that may be typed at the 1-based line & column of the code
+    pub fn completions(&self, line: usize, column: usize) -> Vec<Completion> {
+        let code = self.script_code();
+        let position = completion::position_at(code, line, column);
+        let globals = completion::global_completions(&self.make_interpreter());
+        completion::complete(code, position, &globals)
+    }
+
+    /// Globals at the runtime error of the last run as `var` declarations,
+    /// kept if the history is enabled in the config.
+    /// Running the state followed by the failing code reproduces the error
+    pub fn failure_state(&self) -> Option<&str> {
+        self.failure_state.as_deref()
+    }
+
+    pub fn run(&mut self) -> FoxResult<()> {
+        let (mut interpreter, statements) = self.prepare()?;
+        let result = catch_panic(|| interpreter.interpret(&statements));
+        self.keep_failure_state(&interpreter, &result);
+        self.finish(&mut interpreter);
+        result
+    }
+
+    /// Runs the script and then calls every global function named `test_*`
+    /// in declaration order. Errors of the top-level code abort the run
+    pub fn run_tests(&mut self) -> FoxResult<TestReport> {
+        let (mut interpreter, statements) = self.prepare()?;
+        let result = catch_panic(|| interpreter.interpret(&statements));
+        self.keep_failure_state(&interpreter, &result);
+        if let Err(err) = result {
+            self.finish(&mut interpreter);
+            return Err(err);
+        }
+
+        let mut report = TestReport::default();
+        // a failing test doesn't stop the others unless the failure is fatal or an exit
+        for func in testing::discover_tests(interpreter.global_bindings()) {
+            let name = func.decl.name.lexeme.clone();
+            let result = if func.arity() > 0 {
+                Err(FoxError::runtime(
+                    Some(func.decl.name.clone()),
+                    "Test function must take no arguments",
+                ))
+            } else {
+                catch_panic(|| interpreter.call_function(&func, &[]))
+            };
+            if let Err(err) = &result
+                && (err.kind().is_fatal() || err.kind().exit_code().is_some())
+            {
+                self.finish(&mut interpreter);
+                return Err(err.clone());
+            }
+            let failure = result.err().map(|err| self.error_description(&err));
+            report.push(TestOutcome { name, failure });
+        }
+        self.finish(&mut interpreter);
+        Ok(report)
+    }
+
+    fn prepare(&mut self) -> FoxResult<(Interpreter, Vec<Statement>)> {
+        let (mut interpreter, statements) = match &self.program {
+            Some(program) => {
+                let mut interpreter = self.make_interpreter();
+                for (expr, depth) in program.locals() {
+                    interpreter.resolve(expr.clone(), *depth)?;
+                }
+                (interpreter, program.statements().to_vec())
+            }
+            None => self.front_end()?,
+        };
+        if self.config.coverage {
+            interpreter.enable_coverage(&statements);
+        }
+        if self.config.history > 0 {
+            interpreter.record_history(self.config.history);
+        }
+        Ok((interpreter, statements))
     }
 
-    pub fn run(&self) -> FoxResult<()> {
+    fn make_interpreter(&self) -> Interpreter {
+        let enabled = BuiltinGroup::ALL
+            .into_iter()
+            .filter(|group| !self.config.disabled_builtins.contains(group))
+            .collect::<Vec<_>>();
+        let mut interpreter = Interpreter::with_builtins(&enabled);
+        for kind in &self.config.deny {
+            interpreter.deny(*kind);
+        }
+        interpreter.declare_globals(&self.config.globals);
+        interpreter.set_script_args(self.config.script_args.clone());
+        interpreter.set_ieee_division(self.config.ieee_division);
+        if let Some(flag) = &self.config.cancel_flag {
+            interpreter.set_cancel_flag(flag.clone());
+        }
+        let deep_nesting = WarningKind::DeepNesting;
+        if self.config.warn.contains(&deep_nesting) || self.config.deny.contains(&deep_nesting) {
+            interpreter.warn_deep_nesting(DEEP_NESTING_THRESHOLD);
+        }
+        interpreter
+    }
+
+    fn script_code(&self) -> &Source {
+        &self.code[..self.script_len]
+    }
+
+    fn front_end(&mut self) -> FoxResult<(Interpreter, Vec<Statement>)> {
+        // the files are imported anew if the code runs again
+        self.code.truncate(self.script_len);
+        self.modules = Modules::default();
         let mut scanner = Scanner::with_source(&self.code);
         let tokens = scanner.scan_tokens()?;
 
         let mut parser = Parser::new(&tokens);
-        let statements = parser.parse()?;
-
-        let mut interpreter = Interpreter::new();
+        let mut statements = parser.parse()?;
+        self.load_imports(&mut statements)?;
 
+        let mut interpreter = self.make_interpreter();
         let mut resolver = Resolver::with(&mut interpreter);
+        // denied warnings are enabled implicitly
+        for kind in self.config.warn.iter().chain(&self.config.deny) {
+            resolver.enable_warning(*kind);
+        }
+        if self.config.strict_globals {
+            resolver.enable_strict_globals();
+        }
         resolver.resolve_statements(&statements)?;
+        Ok((interpreter, statements))
+    }
+
+    /// Puts the imported files after the code, the files imported earlier are skipped
+    fn load_imports(&mut self, statements: &mut [Statement]) -> FoxResult<()> {
+        self.modules.load(&mut self.code, statements, &self.script)
+    }
 
-        interpreter.interpret(&statements)
+    fn keep_failure_state(&mut self, interpreter: &Interpreter, result: &FoxResult<()>) {
+        let is_runtime_error = matches!(result, Err(err) if err.kind().is_runtime());
+        self.failure_state = (self.config.history > 0 && is_runtime_error)
+            .then(|| interpreter.dump_globals_as_source());
+    }
+
+    fn finish(&mut self, interpreter: &mut Interpreter) {
+        self.coverage = interpreter
+            .take_coverage()
+            .map(|coverage| coverage.report(&self.config.source_name));
+        self.warnings = interpreter.take_warnings();
+        self.statistics = interpreter.statistics();
+        self.history = interpreter.take_history();
     }
 
     pub fn error_description(&self, error: &FoxError) -> String {
-        let mut text = format!("{}", error.kind());
+        let error = error.clone().sanitized("error reporting");
+        let text = format!("{}", error.kind());
+        let mut description = self.located_description(text, error.info());
+        if let Some(history) = &self.history
+            && error.kind().is_runtime()
+            && !history.is_empty()
+        {
+            description = format!("{description}\n{history}");
+        }
+        description
+    }
+
+    pub fn warning_description(&self, warning: &Warning) -> String {
+        let text = format!("warning: {}", warning.message());
+        self.located_description(text, warning.info())
+    }
+
+    /// Machine-readable error, runtime errors have the recent execution history as notes
+    pub fn error_diagnostic(&self, error: &FoxError) -> Diagnostic {
+        let error = error.clone().sanitized("error reporting");
+        let kind = error.kind();
+        let mut diagnostic = self.diagnostic(
+            Severity::Error,
+            kind.code(),
+            &kind.to_string(),
+            error.info(),
+        );
+        if let Some(history) = &self.history
+            && kind.is_runtime()
+        {
+            diagnostic.notes = history
+                .events()
+                .map(|(location, event)| format!("line {}: {event}", location.line_number()))
+                .collect();
+        }
+        diagnostic
+    }
+
+    pub fn warning_diagnostic(&self, warning: &Warning) -> Diagnostic {
+        let code = warning.kind().code();
+        self.diagnostic(Severity::Warning, code, warning.message(), warning.info())
+    }
+
+    fn diagnostic(
+        &self,
+        severity: Severity,
+        code: &'static str,
+        message: &str,
+        info: &ErrorInfo,
+    ) -> Diagnostic {
+        let (location, length) = match info {
+            ErrorInfo::Empty => {
+                return Diagnostic::new(severity, code, message, &self.config.source_name);
+            }
+            ErrorInfo::Code(location) => (location, 1),
+            ErrorInfo::Token(token) => (&token.code_location, token.lexeme.chars().count().max(1)),
+        };
+        let file = self.file_name(location).unwrap_or(&self.config.source_name);
+        let mut diagnostic = Diagnostic::new(severity, code, message, file);
+        diagnostic.line = Some(location.line_number());
+        diagnostic.length = length;
+        if let Some(el) = self.error_line(location) {
+            diagnostic.column = Some(el.column());
+        }
+        diagnostic
+    }
 
-        let location = match error.info() {
+    fn located_description(&self, mut text: String, info: &ErrorInfo) -> String {
+        let location = match info {
             ErrorInfo::Empty => None,
             ErrorInfo::Code(location) => Some(location),
             ErrorInfo::Token(token) => Some(&token.code_location),
         };
 
-        if let Some(location) = location {
-            let el = ErrorLine::with(&self.code, location);
+        if let Some(el) = location.and_then(|x| self.error_line(x)) {
             text = el.formatted(&text);
         }
+        // the script name is known to the reader, unlike the imported one
+        if let Some(name) = location.and_then(|x| self.file_name(x)) {
+            text = format!("{name}:\n{text}");
+        }
 
         text
     }
+
+    /// Name of the imported file the location belongs to
+    fn file_name(&self, location: &CodeLocation) -> Option<&String> {
+        self.modules
+            .file_at(location.absolute_position())
+            .map(|file| &file.name)
+    }
+
+    fn error_line(&self, location: &CodeLocation) -> Option<ErrorLine> {
+        // compiled programs may run without the code
+        (location.absolute_position() < self.code.len())
+            .then(|| ErrorLine::with(&self.code, location))
+    }
+}
+
+/// Converts a panic escaped from the interpreter into a bug error
+/// located at the statement being executed
+fn catch_panic<T, F>(action: F) -> FoxResult<T>
+where
+    F: FnOnce() -> FoxResult<T>,
+{
+    interpreter::reset_executing_location();
+    let payload = match catch_unwind(AssertUnwindSafe(action)) {
+        Ok(result) => return result,
+        Err(payload) => payload,
+    };
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|x| x.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown reason".to_string());
+    let kind = ErrorKind::Bug(format!(
+        "Interpreter panicked: {message} (fox-lang {})",
+        env!("CARGO_PKG_VERSION")
+    ));
+    let error = match executing_location() {
+        Some(location) => FoxError::code_location(kind, location),
+        None => FoxError::error(kind),
+    };
+    Err(error)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_panic_becomes_bug_error() {
+        let mut fox = Fox::with("print 1;\ninject_panic();".chars().collect());
+        let err = fox.run().unwrap_err();
+        let ErrorKind::Bug(message) = err.kind() else {
+            panic!("Bug error expected, found {err:?}");
+        };
+        assert!(message.contains("injected panic"), "{message}");
+        let ErrorInfo::Code(location) = err.info() else {
+            panic!("Error location expected");
+        };
+        assert_eq!(location.line_number(), 2);
+        assert!(fox.error_description(&err).contains("2 |inject_panic();"));
+    }
+
+    #[test]
+    fn test_panic_in_test_function() {
+        let code = "fun test_panic() {\ninject_panic();\n}\nfun test_ok() {}";
+        let mut fox = Fox::with(code.chars().collect());
+        let report = fox.run_tests().unwrap();
+        assert_eq!(report.passed_count(), 1);
+        let failure = report.outcomes()[0].failure.as_ref().unwrap();
+        assert!(failure.contains("injected panic"), "{failure}");
+    }
+
+    fn fixture(name: &str) -> (Vec<char>, FoxConfig) {
+        let path = format!("tests/fixtures/imports/{name}");
+        let code = std::fs::read_to_string(&path).unwrap();
+        let config = FoxConfig {
+            source_name: path,
+            ..Default::default()
+        };
+        (code.chars().collect(), config)
+    }
+
+    #[test]
+    fn test_import_error_description() {
+        let (code, config) = fixture("failing.fox");
+        let mut fox = Fox::with_config(code, config);
+        // the files are imported again by the second run
+        for _ in 0..2 {
+            let err = fox.run().unwrap_err();
+            assert_eq!(
+                fox.error_description(&err),
+                "tests/fixtures/imports/lib/broken.fox:\n2 |    return x / 2;\n                ▲\n                └─ Operands of '/' must be numbers, got Nil and Number"
+            );
+        }
+    }
+
+    #[test]
+    fn test_assert_description() {
+        let code = "var x = 1;\nassert x > 0;\n  assert x > 1, \"x is \" + \"small\";";
+        let mut fox = Fox::with(code.chars().collect());
+        let err = fox.run().unwrap_err();
+        assert_eq!(
+            fox.error_description(&err),
+            "3 |  assert x > 1, \"x is \" + \"small\";\n          ▲\n          └─ Assertion failed: x is small"
+        );
+
+        let code = "fun test_sum() {\n    assert 1 + 1 == 3;\n}";
+        let mut fox = Fox::with(code.chars().collect());
+        let report = fox.run_tests().unwrap();
+        let failure = report.outcomes()[0].failure.as_ref().unwrap();
+        assert!(failure.contains("2 |    assert 1 + 1 == 3;"), "{failure}");
+        assert!(failure.ends_with("└─ Assertion failed"), "{failure}");
+    }
+
+    #[test]
+    fn test_fatal_error_aborts_tests() {
+        let code = "fun test_recoverable() {\ninject_catchable();\n}\n\
+            fun test_host_failure() {\ninject_fatal();\n}\nfun test_ok() {}";
+        let mut fox = Fox::with(code.chars().collect());
+        let err = fox.run_tests().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Fatal(_)));
+        assert!(!err.kind().is_catchable());
+        assert_eq!(err.kind().to_string(), "[FATAL] injected host failure");
+        let ErrorInfo::Token(token) = err.info() else {
+            panic!("Call location expected");
+        };
+        assert_eq!(token.code_location.line_number(), 5);
+
+        let code = "fun test_recoverable() {\ninject_catchable();\n}\nfun test_ok() {}";
+        let mut fox = Fox::with(code.chars().collect());
+        let report = fox.run_tests().unwrap();
+        assert_eq!(report.passed_count(), 1);
+        let failure = report.outcomes()[0].failure.as_ref().unwrap();
+        assert!(failure.contains("injected failure"), "{failure}");
+    }
+
+    #[test]
+    fn test_history_in_error_description() {
+        let code = r#"
+class Point {}
+fun half(x) {
+    return x / 2;
+}
+var i = 0;
+while (i < 3) i = i + 1;
+var p = Point();
+print half(4);
+print half(nil);"#;
+        let config = FoxConfig {
+            history: 8,
+            ..FoxConfig::default()
+        };
+        let mut fox = Fox::with_config(code.chars().collect(), config);
+        let err = fox.run().unwrap_err();
+        let description = fox.error_description(&err);
+        let expected = "recent execution history:
+  line 8: var
+  line 8: call Point
+  line 9: print
+  line 9: call half
+  line 4: return
+  line 10: print
+  line 10: call half
+  line 4: return";
+        assert!(description.ends_with(expected), "{description}");
+        assert_eq!(fox.history().unwrap().events().count(), 8);
+    }
+
+    #[test]
+    fn test_history_of_typed_runtime_errors() {
+        let config = FoxConfig {
+            history: 4,
+            ..FoxConfig::default()
+        };
+        for (code, kind) in [
+            (
+                "var n = len(\"ab\");\nprint -nil;",
+                "operand-must-be-number",
+            ),
+            ("var n = len(\"ab\");\nprint missing;", "undefined-variable"),
+        ] {
+            let mut fox = Fox::with_config(code.chars().collect(), config.clone());
+            let err = fox.run().unwrap_err();
+            assert_eq!(err.kind().code(), kind);
+            let description = fox.error_description(&err);
+            let expected = "recent execution history:
+  line 1: var
+  line 1: call len
+  line 2: print";
+            assert!(description.ends_with(expected), "{description}");
+            assert_eq!(fox.error_diagnostic(&err).notes.len(), 3);
+            assert_eq!(fox.failure_state().unwrap().to_string(), "var n = 2;\n");
+        }
+    }
+
+    #[test]
+    fn test_failure_state_reproduces_error() {
+        let setup = r#"var count = 3;
+var name = "fox";
+var items = [1, "two", {"k": [count]}];
+fun bump() { count = count + 1; name = name + "!"; }
+bump();
+"#;
+        let snippet = r#"print items[2]["k"][0] - name;"#;
+        let config = FoxConfig {
+            history: 4,
+            ..FoxConfig::default()
+        };
+        let mut fox = Fox::with_config(format!("{setup}{snippet}").chars().collect(), config);
+        let err = fox.run().unwrap_err();
+        let state = fox.failure_state().unwrap().to_string();
+        assert_eq!(
+            state,
+            r#"// bump: function omitted
+var count = 4;
+var items = [1, "two", {"k": [3]}];
+var name = "fox!";
+"#
+        );
+
+        let mut replay = Fox::with(format!("{state}{snippet}").chars().collect());
+        let replayed = replay.run().unwrap_err();
+        assert_eq!(replayed.kind().to_string(), err.kind().to_string());
+        assert_eq!(
+            replayed.kind().to_string(),
+            "Operands of '-' must be numbers, got Number and String"
+        );
+
+        // nothing is kept without the history
+        let mut fox = Fox::with(format!("{setup}{snippet}").chars().collect());
+        assert!(fox.run().is_err());
+        assert!(fox.failure_state().is_none());
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let config = FoxConfig {
+            cancel_flag: Some(flag.clone()),
+            ..FoxConfig::default()
+        };
+        let handle = std::thread::spawn(move || {
+            let code = "var i = 0;\nwhile (true) {\n  i = i + 1;\n}";
+            let mut fox = Fox::with_config(code.chars().collect(), config);
+            fox.run().unwrap_err().kind().to_string()
+        });
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        flag.store(true, std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(handle.join().unwrap(), "Execution cancelled");
+    }
+
+    #[test]
+    fn test_cancelled_sleep() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let config = FoxConfig {
+            cancel_flag: Some(flag.clone()),
+            ..FoxConfig::default()
+        };
+        let handle = std::thread::spawn(move || {
+            let code = "while (true) {\n  sleep(1000);\n}";
+            let mut fox = Fox::with_config(code.chars().collect(), config);
+            let start = std::time::Instant::now();
+            let message = fox.run().unwrap_err().kind().to_string();
+            (message, start.elapsed())
+        });
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        flag.store(true, std::sync::atomic::Ordering::Relaxed);
+        let (message, duration) = handle.join().unwrap();
+        assert_eq!(message, "Execution cancelled");
+        assert!(duration.as_secs() < 10, "{duration:?}");
+    }
+
+    #[test]
+    fn test_runs_are_independent() {
+        // the second run would succeed if the global survived the first one
+        let code = "print once;\nvar once = 1;";
+        let mut fox = Fox::with(format!("var once = 0;\n{code}").chars().collect());
+        fox.run().unwrap();
+        let mut fox = Fox::with(code.chars().collect());
+        assert!(fox.run().is_err());
+    }
+
+    fn host_config() -> FoxConfig {
+        let mut config = FoxConfig {
+            strict_globals: true,
+            ..FoxConfig::default()
+        };
+        config.declare_global("config", None);
+        config.declare_global("emit", Some(2));
+        config.declare_global("log", Some(1));
+        config
+    }
+
+    #[test]
+    fn test_declared_globals() {
+        let code = r#"
+fun handle(event) {
+    log("handling " + event);
+    emit(event, config);
+    return later;
+}
+fun shadow(emit) {
+    emit();
+}
+var later = clock();"#;
+        let mut fox = Fox::with_config(code.chars().collect(), host_config());
+        assert!(fox.compile().is_ok());
+
+        // inherited constants are known at runtime only
+        let code = "class A { const LIMIT = 1; }\nclass B < A { m() { return LIMIT; } }";
+        let mut fox = Fox::with_config(code.chars().collect(), host_config());
+        assert!(fox.compile().is_ok());
+
+        // the script own globals hide the declarations
+        let code = "fun log(a, b) {}\nlog(1, 2);";
+        let mut fox = Fox::with_config(code.chars().collect(), host_config());
+        assert!(fox.compile().is_ok());
+    }
+
+    #[test]
+    fn test_declared_globals_errors() {
+        let cases = [
+            ("emit(\"start\");", "Expected 2 arguments but got 1", ")"),
+            (
+                "fun f() {\n  log();\n}",
+                "Expected 1 arguments but got 0",
+                ")",
+            ),
+            ("lgo(\"typo\");", "Undefined variable lgo", "lgo"),
+            (
+                "fun f() {\n  return cnofig;\n}",
+                "Undefined variable cnofig",
+                "cnofig",
+            ),
+        ];
+        for (code, message, lexeme) in cases {
+            let mut fox = Fox::with_config(code.chars().collect(), host_config());
+            let err = fox.compile().unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(token.lexeme, lexeme, "{code}");
+            assert_eq!(
+                token.code_location.line_number(),
+                code.lines().count().min(2)
+            );
+        }
+
+        // undeclared globals are checked only in the strict mode
+        let config = FoxConfig {
+            strict_globals: false,
+            ..host_config()
+        };
+        let mut fox = Fox::with_config("lgo(1);".chars().collect(), config);
+        assert!(fox.compile().is_ok());
+    }
+
+    #[test]
+    fn test_history_is_off_by_default() {
+        let mut fox = Fox::with("print nil / 2;".chars().collect());
+        let err = fox.run().unwrap_err();
+        assert!(fox.history().is_none());
+        assert!(!fox.error_description(&err).contains("history"));
+    }
 }
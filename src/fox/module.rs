@@ -0,0 +1,138 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::fox::{BOM, FoxError, FoxResult, Object, Parser, Scanner, ast::Statement};
+
+/// Imported file placed into the source after the script,
+/// so the token locations of different files never collide
+///
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub name: String,
+    path: PathBuf,
+    // range of the file text in the source
+    start: usize,
+    end: usize,
+}
+
+impl SourceFile {
+    pub fn contains(&self, position: usize) -> bool {
+        (self.start..self.end).contains(&position)
+    }
+}
+
+/// File being loaded, the chain of them detects circular imports
+///
+pub struct ImportingFile {
+    pub name: String,
+    // canonical, the script may have no file
+    pub path: Option<PathBuf>,
+    pub dir: PathBuf,
+}
+
+/// Loads the imported files once each, repeated imports stay empty
+///
+#[derive(Debug, Default)]
+pub struct Modules {
+    files: Vec<SourceFile>,
+    loaded: HashSet<PathBuf>,
+}
+
+impl Modules {
+    /// Imported file the source position belongs to
+    pub fn file_at(&self, position: usize) -> Option<&SourceFile> {
+        self.files.iter().find(|file| file.contains(position))
+    }
+
+    /// Forgets the files placed at or after the source length
+    pub fn truncate(&mut self, len: usize) {
+        let (kept, dropped) = std::mem::take(&mut self.files)
+            .into_iter()
+            .partition::<Vec<_>, _>(|file| file.start < len);
+        for file in dropped {
+            self.loaded.remove(&file.path);
+        }
+        self.files = kept;
+    }
+
+    /// Appends the files imported by the top-level statements to the source
+    /// and puts their statements into the imports, the nested imports are loaded too
+    pub fn load(
+        &mut self,
+        code: &mut Vec<char>,
+        statements: &mut [Statement],
+        importer: &ImportingFile,
+    ) -> FoxResult<()> {
+        self.load_imports(code, statements, &[importer])
+    }
+
+    fn load_imports(
+        &mut self,
+        code: &mut Vec<char>,
+        statements: &mut [Statement],
+        chain: &[&ImportingFile],
+    ) -> FoxResult<()> {
+        let Some(importer) = chain.last() else {
+            return Err(FoxError::bug("Import without importing file"));
+        };
+        let dir = importer.dir.clone();
+        for stmt in statements {
+            let Statement::Import(data) = stmt else {
+                continue;
+            };
+            let Object::Text(relative) = &data.path.literal else {
+                return Err(FoxError::bug("Module path must be a string"));
+            };
+            let path = dir.join(relative.as_ref());
+            let name = path.display().to_string();
+            let error = |message: &str| FoxError::resolver(Some(data.path.clone()), message);
+            let read = |path: &Path| {
+                let canonical = path.canonicalize()?;
+                std::fs::read_to_string(path).map(|text| (canonical, text))
+            };
+            let (canonical, text) =
+                read(&path).map_err(|err| error(&format!("Can't import '{name}': {err}")))?;
+            if let Some(index) = chain
+                .iter()
+                .position(|file| file.path.as_ref() == Some(&canonical))
+            {
+                let cycle = chain[index..]
+                    .iter()
+                    .chain([&chain[index]])
+                    .map(|file| file.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(error(&format!("Circular import: {cycle}")));
+            }
+            if !self.loaded.insert(canonical.clone()) {
+                continue;
+            }
+
+            // the line break keeps the file apart from the previous one in error messages
+            code.push('\n');
+            let start = code.len();
+            code.extend(text.strip_prefix(BOM).unwrap_or(&text).chars());
+            self.files.push(SourceFile {
+                name: name.clone(),
+                path: canonical.clone(),
+                start,
+                end: code.len(),
+            });
+            let tokens = Scanner::with_source_at(code, start, 1).scan_tokens()?;
+            let mut module = Parser::new(&tokens).parse()?;
+
+            let file = ImportingFile {
+                name,
+                dir: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+                path: Some(canonical),
+            };
+            let mut nested = chain.to_vec();
+            nested.push(&file);
+            self.load_imports(code, &mut module, &nested)?;
+            data.statements = module;
+        }
+        Ok(())
+    }
+}
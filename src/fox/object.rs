@@ -1,22 +1,97 @@
-use std::{cell::RefCell, fmt::Display, rc::Rc};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    cmp::Ordering,
+    collections::HashMap,
+    fmt::Display,
+    hash::{DefaultHasher, Hasher},
+    rc::Rc,
+};
 
-use crate::fox::{FoxError, FoxResult, utils::SharedPtr};
+use crate::fox::{
+    BinaryOp, FoxError, FoxResult,
+    utils::{SharedPtr, format_number, mutable_cell},
+};
 
 use super::{
     class::{ClassInstance, MetaClass},
     func::{BuiltinFunc, Func},
+    range::NumberRange,
 };
 
+/// Runtime value
+/// Cloning is cheap for every variant: strings are immutable & shared,
+/// arrays and instances are shared by reference, so assignment and argument
+/// passing alias the same container. Use `deep_copy` for value semantics.
+/// Classes & instances compare and hash by identity,
+/// frozen instances compare and hash by their fields instead.
+/// Numbers compare as IEEE floats: NaN isn't equal to anything, itself included,
+/// so it can't be found as a key, `is_nan` is the way to detect it.
+/// Ordering comparisons with NaN fail instead of giving false.
+/// Both zeros hash the same since they are equal
+///
 #[derive(Clone, Debug)]
 pub enum Object {
     Nil,
-    Double(f32),
-    Text(String),
+    Double(f64),
+    Text(Rc<str>),
     Bool(bool),
     BuiltinCallee(BuiltinFunc),
     Callee(Func),
     Class(Rc<MetaClass>),
     Instance(Rc<RefCell<ClassInstance>>),
+    Array(SharedPtr<Vec<Object>>),
+    Range(NumberRange),
+    Map(SharedPtr<HashMap<Object, Object>>),
+    Type(ValueType),
+}
+
+/// Runtime type of non-instance values returned by `type()`,
+/// instances have their classes as types
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    Nil,
+    Number,
+    String,
+    Bool,
+    Function,
+    Class,
+    Array,
+    Range,
+    Map,
+    Type,
+}
+
+impl ValueType {
+    pub const ALL: [ValueType; 10] = [
+        ValueType::Nil,
+        ValueType::Number,
+        ValueType::String,
+        ValueType::Bool,
+        ValueType::Function,
+        ValueType::Class,
+        ValueType::Array,
+        ValueType::Range,
+        ValueType::Map,
+        ValueType::Type,
+    ];
+
+    /// Name of the global holding the type marker
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValueType::Nil => "Nil",
+            ValueType::Number => "Number",
+            ValueType::String => "String",
+            ValueType::Bool => "Bool",
+            ValueType::Function => "Function",
+            ValueType::Class => "Class",
+            ValueType::Array => "Array",
+            ValueType::Range => "Range",
+            ValueType::Map => "Map",
+            ValueType::Type => "Type",
+        }
+    }
 }
 
 impl std::hash::Hash for Object {
@@ -24,9 +99,13 @@ impl std::hash::Hash for Object {
         use Object::*;
         match self {
             Nil => 0.hash(state),
+            // NaN isn't equal to itself, so it's never found in a map whatever
+            // its hash is. Resolver keys are variable expressions whose tokens
+            // carry no number literals, so they never hold NaN either
             Double(val) => {
                 1.hash(state);
-                val.to_bits().hash(state);
+                let canonical = if *val == 0.0 { 0.0 } else { *val };
+                canonical.to_bits().hash(state);
             }
             Text(val) => {
                 2.hash(state);
@@ -46,11 +125,44 @@ impl std::hash::Hash for Object {
             }
             Class(val) => {
                 6.hash(state);
-                val.hash(state);
+                Rc::as_ptr(val).hash(state);
             }
             Instance(val) => {
                 7.hash(state);
-                val.borrow().hash(state);
+                let instance = val.borrow();
+                if instance.is_frozen() {
+                    instance.hash(state);
+                } else {
+                    Rc::as_ptr(val).hash(state);
+                }
+            }
+            // containers may hold themselves, so their items are hashed shallow
+            Array(val) => {
+                8.hash(state);
+                let items = val.borrow();
+                items.len().hash(state);
+                for item in items.iter() {
+                    item.hash_shallow(state);
+                }
+            }
+            Range(val) => {
+                9.hash(state);
+                val.hash(state);
+            }
+            Map(val) => {
+                10.hash(state);
+                // entries order is random, so their hashes are combined commutatively
+                let sum = val.borrow().iter().fold(0u64, |sum, (key, value)| {
+                    let mut hasher = DefaultHasher::new();
+                    key.hash(&mut hasher);
+                    value.hash_shallow(&mut hasher);
+                    sum.wrapping_add(hasher.finish())
+                });
+                sum.hash(state);
+            }
+            Type(val) => {
+                11.hash(state);
+                val.hash(state);
             }
         }
     }
@@ -58,6 +170,23 @@ impl std::hash::Hash for Object {
 
 impl std::cmp::Eq for Object {}
 
+impl Object {
+    /// Hash that doesn't look into containers & instances,
+    /// equal values still hash the same
+    pub fn hash_shallow<H: std::hash::Hasher>(&self, state: &mut H) {
+        use std::hash::Hash;
+        match self {
+            Object::Array(_) => 8.hash(state),
+            Object::Map(_) => 10.hash(state),
+            Object::Instance(instance) => {
+                7.hash(state);
+                Rc::as_ptr(&instance.borrow().class()).hash(state);
+            }
+            _ => self.hash(state),
+        }
+    }
+}
+
 impl Object {
     pub fn is_true(&self) -> bool {
         match self {
@@ -67,6 +196,111 @@ impl Object {
         }
     }
 
+    /// Class for instances, type marker for the rest values
+    pub fn type_of(&self) -> Object {
+        let value_type = match self {
+            Object::Instance(instance) => return Object::Class(instance.borrow().class()),
+            Object::Nil => ValueType::Nil,
+            Object::Double(_) => ValueType::Number,
+            Object::Text(_) => ValueType::String,
+            Object::Bool(_) => ValueType::Bool,
+            Object::BuiltinCallee(_) | Object::Callee(_) => ValueType::Function,
+            Object::Class(_) => ValueType::Class,
+            Object::Array(_) => ValueType::Array,
+            Object::Range(_) => ValueType::Range,
+            Object::Map(_) => ValueType::Map,
+            Object::Type(_) => ValueType::Type,
+        };
+        Object::Type(value_type)
+    }
+
+    /// Fox expression evaluating to an equal value if there is one:
+    /// literals, standard constants, arrays & maps of them.
+    /// Shared containers are written out at every use, cycles have no source
+    pub fn to_source(&self) -> Option<String> {
+        self.to_source_visiting(&mut Vec::new())
+    }
+
+    fn to_source_visiting(&self, visiting: &mut Vec<*const ()>) -> Option<String> {
+        let source = match self {
+            Object::Nil => "nil".to_string(),
+            Object::Bool(value) => value.to_string(),
+            Object::Double(value) if value.is_nan() => "NAN".to_string(),
+            Object::Double(value) if value.is_infinite() && *value > 0.0 => "INFINITY".to_string(),
+            Object::Double(value) if value.is_infinite() => "-INFINITY".to_string(),
+            Object::Double(value) => value.to_string(),
+            // strings have no escape sequences & the scanner normalizes line endings
+            Object::Text(value) if !value.contains(['"', '\r']) => format!("\"{value}\""),
+            Object::Type(value) => value.name().to_string(),
+            Object::Array(items) => {
+                let ptr = Rc::as_ptr(items) as *const ();
+                if visiting.contains(&ptr) {
+                    return None;
+                }
+                visiting.push(ptr);
+                let items = items
+                    .borrow()
+                    .iter()
+                    .map(|item| item.to_source_visiting(visiting))
+                    .collect::<Option<Vec<_>>>();
+                visiting.pop();
+                format!("[{}]", items?.join(", "))
+            }
+            Object::Map(map) => {
+                let ptr = Rc::as_ptr(map) as *const ();
+                if visiting.contains(&ptr) {
+                    return None;
+                }
+                visiting.push(ptr);
+                let map = map.borrow();
+                let mut entries = map.iter().collect::<Vec<_>>();
+                entries.sort_by(|a, b| key_order(a.0, b.0));
+                let entries = entries
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let key = key.to_source_visiting(visiting)?;
+                        let value = value.to_source_visiting(visiting)?;
+                        Some(format!("{key}: {value}"))
+                    })
+                    .collect::<Option<Vec<_>>>();
+                visiting.pop();
+                format!("{{{}}}", entries?.join(", "))
+            }
+            _ => return None,
+        };
+        Some(source)
+    }
+
+    /// Mutable containers would change their hash after insertion.
+    /// Freezing is shallow, so frozen instances qualify only
+    /// if their fields are hashable too
+    pub fn is_hashable(&self) -> bool {
+        self.is_hashable_visiting(&mut Vec::new())
+    }
+
+    fn is_hashable_visiting(&self, visiting: &mut Vec<*const ()>) -> bool {
+        match self {
+            Object::Array(_) | Object::Map(_) => false,
+            Object::Instance(instance) => {
+                let ptr = Rc::as_ptr(instance) as *const ();
+                if visiting.contains(&ptr) {
+                    return true;
+                }
+                let instance = instance.borrow();
+                if !instance.is_frozen() {
+                    return false;
+                }
+                visiting.push(ptr);
+                let hashable = instance
+                    .field_values()
+                    .all(|value| value.is_hashable_visiting(visiting));
+                visiting.pop();
+                hashable
+            }
+            _ => true,
+        }
+    }
+
     pub fn as_meta_class(&self) -> FoxResult<Rc<MetaClass>> {
         match self {
             Object::Class(meta) => Ok(meta.clone()),
@@ -85,75 +319,139 @@ impl Object {
         }
     }
 
-    pub fn plus(&self, other: &Object) -> Result<Object, String> {
-        use Object::*;
-        match (self, other) {
-            (Double(l), Double(r)) => Ok(Object::Double(l + r)),
-            (Text(l), Text(r)) => Ok(Object::Text(l.to_owned() + r)),
-            _ => Err("Type mismatch for '+'".to_string()),
-        }
-    }
-
-    pub fn minus(&self, other: &Object) -> Result<Object, String> {
-        use Object::*;
-        match (self, other) {
-            (Double(l), Double(r)) => Ok(Object::Double(l - r)),
-            _ => Err("Type mismatch for '-'".to_string()),
-        }
-    }
-
-    pub fn multiply(&self, other: &Object) -> Result<Object, String> {
-        use Object::*;
-        match (self, other) {
-            (Double(l), Double(r)) => Ok(Object::Double(l * r)),
-            _ => Err("Type mismatch for '*'".to_string()),
+    /// Copy of the instance or the container itself,
+    /// the copy shares the field values & elements with the original
+    pub fn shallow_copy(&self) -> Object {
+        match self {
+            Object::Instance(instance) => Object::Instance(mutable_cell(instance.borrow().clone())),
+            Object::Array(array) => Object::Array(mutable_cell(array.borrow().clone())),
+            Object::Map(map) => Object::Map(mutable_cell(map.borrow().clone())),
+            _ => self.clone(),
         }
     }
 
-    pub fn divide(&self, other: &Object) -> Result<Object, String> {
-        use Object::*;
-        match (self, other) {
-            (Double(_), Double(0.0)) => Err("Division by zero".to_string()),
-            (Double(l), Double(r)) => Ok(Object::Double(l / r)),
-            _ => Err("Type mismatch for '/'".to_string()),
-        }
+    /// Copy with value semantics: instances are duplicated recursively,
+    /// references shared inside the value stay shared in the copy (cycles too)
+    pub fn deep_copy(&self) -> Object {
+        let mut copied = HashMap::new();
+        self.deep_copy_with(&mut copied)
     }
 
-    pub fn greater(&self, other: &Object) -> Result<Object, String> {
-        use Object::*;
-        match (self, other) {
-            (Double(l), Double(r)) => Ok(Object::Bool(l > r)),
-            _ => Err("Type mismatch for '>'".to_string()),
+    // only originals are borrowed during recursion, copies are reached via `copied`
+    fn deep_copy_with(&self, copied: &mut HashMap<*const (), Object>) -> Object {
+        match self {
+            Object::Instance(instance) => {
+                let key = Rc::as_ptr(instance) as *const ();
+                if let Some(copy) = copied.get(&key) {
+                    return copy.clone();
+                }
+                let copy = mutable_cell(instance.borrow().clone());
+                copied.insert(key, Object::Instance(copy.clone()));
+                copy.borrow_mut()
+                    .map_fields(|value| value.deep_copy_with(copied));
+                Object::Instance(copy)
+            }
+            Object::Array(array) => {
+                let key = Rc::as_ptr(array) as *const ();
+                if let Some(copy) = copied.get(&key) {
+                    return copy.clone();
+                }
+                let copy = mutable_cell(array.borrow().clone());
+                copied.insert(key, Object::Array(copy.clone()));
+                for value in copy.borrow_mut().iter_mut() {
+                    *value = value.deep_copy_with(copied);
+                }
+                Object::Array(copy)
+            }
+            Object::Map(map) => {
+                let key = Rc::as_ptr(map) as *const ();
+                if let Some(copy) = copied.get(&key) {
+                    return copy.clone();
+                }
+                let copy = mutable_cell(HashMap::new());
+                copied.insert(key, Object::Map(copy.clone()));
+                let entries = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect::<Vec<_>>();
+                for (key, value) in entries {
+                    let value = value.deep_copy_with(copied);
+                    copy.borrow_mut().insert(key, value);
+                }
+                Object::Map(copy)
+            }
+            _ => self.clone(),
         }
     }
 
-    pub fn greater_equal(&self, other: &Object) -> Result<Object, String> {
-        use Object::*;
-        match (self, other) {
-            (Double(l), Double(r)) => Ok(Object::Bool(l >= r)),
-            _ => Err("Type mismatch for '>='".to_string()),
+    /// Name of the value type, the class name for instances.
+    /// The class of an instance is behind its cell, so only that name is copied
+    pub fn type_name(&self) -> Cow<'static, str> {
+        match self.type_of() {
+            Object::Class(class) => Cow::Owned(class.name().to_string()),
+            Object::Type(value_type) => Cow::Borrowed(value_type.name()),
+            other => Cow::Owned(other.to_string()),
         }
     }
 
-    pub fn less(&self, other: &Object) -> Result<Object, String> {
-        use Object::*;
-        match (self, other) {
-            (Double(l), Double(r)) => Ok(Object::Bool(l < r)),
-            _ => Err("Type mismatch for '<'".to_string()),
-        }
+    pub fn apply_binary(&self, op: BinaryOp, other: &Object) -> Result<Object, BinaryError> {
+        use BinaryOp::*;
+        use Object::{Bool, Double, Text};
+        let result = match (op, self, other) {
+            (Equal, l, r) => Bool(l == r),
+            (NotEqual, l, r) => Bool(l != r),
+            // the other operand is joined as printed
+            (Add, Text(l), r) => Text(format!("{l}{r}").into()),
+            (Add, l, Text(r)) => Text(format!("{l}{r}").into()),
+            (Divide | Modulo, Double(_), Double(0.0)) => return Err(BinaryError::DivisionByZero),
+            (Greater | GreaterEqual | Less | LessEqual, Double(l), Double(r))
+                if l.is_nan() || r.is_nan() =>
+            {
+                return Err(BinaryError::NanComparison);
+            }
+            (Add, Double(l), Double(r)) => Double(l + r),
+            (Subtract, Double(l), Double(r)) => Double(l - r),
+            (Multiply, Double(l), Double(r)) => Double(l * r),
+            (Divide, Double(l), Double(r)) => Double(l / r),
+            (Modulo, Double(l), Double(r)) => Double(l % r),
+            (Greater, Double(l), Double(r)) => Bool(l > r),
+            (GreaterEqual, Double(l), Double(r)) => Bool(l >= r),
+            (Less, Double(l), Double(r)) => Bool(l < r),
+            (LessEqual, Double(l), Double(r)) => Bool(l <= r),
+            _ => {
+                return Err(BinaryError::TypeMismatch {
+                    left: self.type_name().to_string(),
+                    right: other.type_name().to_string(),
+                });
+            }
+        };
+        Ok(result)
     }
+}
 
-    pub fn less_equal(&self, other: &Object) -> Result<Object, String> {
-        use Object::*;
-        match (self, other) {
-            (Double(l), Double(r)) => Ok(Object::Bool(l <= r)),
-            _ => Err("Type mismatch for '<='".to_string()),
-        }
-    }
+/// Failure of a binary operation, the caller knows the operator
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinaryError {
+    TypeMismatch {
+        left: String,
+        right: String,
+    },
+    DivisionByZero,
+    /// NaN has no order, so it fails rather than gives false
+    NanComparison,
 }
 
 impl PartialEq for Object {
     fn eq(&self, other: &Self) -> bool {
+        self.eq_visiting(other, &mut Vec::new())
+    }
+}
+
+impl Object {
+    /// Containers may hold themselves, a pair met again while being
+    /// compared counts as equal
+    fn eq_visiting(&self, other: &Object, visiting: &mut Vec<(*const (), *const ())>) -> bool {
         use Object::*;
         match (self, other) {
             (Nil, Nil) => true,
@@ -161,6 +459,57 @@ impl PartialEq for Object {
             (Text(l), Text(r)) => l == r,
             (Bool(l), Bool(r)) => l == r,
             (Callee(l), Callee(r)) => l == r,
+            (Class(l), Class(r)) => Rc::ptr_eq(l, r),
+            (Instance(l), Instance(r)) => {
+                if Rc::ptr_eq(l, r) {
+                    return true;
+                }
+                let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ());
+                if visiting.contains(&pair) {
+                    return true;
+                }
+                visiting.push(pair);
+                let equal = l
+                    .borrow()
+                    .frozen_eq(&r.borrow(), |l, r| l.eq_visiting(r, visiting));
+                visiting.pop();
+                equal
+            }
+            (Array(l), Array(r)) => {
+                if Rc::ptr_eq(l, r) {
+                    return true;
+                }
+                let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ());
+                if visiting.contains(&pair) {
+                    return true;
+                }
+                visiting.push(pair);
+                let (l, r) = (l.borrow(), r.borrow());
+                let equal = l.len() == r.len()
+                    && l.iter()
+                        .zip(r.iter())
+                        .all(|(l, r)| l.eq_visiting(r, visiting));
+                visiting.pop();
+                equal
+            }
+            (Range(l), Range(r)) => l == r,
+            (Map(l), Map(r)) => {
+                if Rc::ptr_eq(l, r) {
+                    return true;
+                }
+                let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ());
+                if visiting.contains(&pair) {
+                    return true;
+                }
+                visiting.push(pair);
+                let (l, r) = (l.borrow(), r.borrow());
+                let equal = l.len() == r.len()
+                    && l.iter()
+                        .all(|(key, l)| r.get(key).is_some_and(|r| l.eq_visiting(r, visiting)));
+                visiting.pop();
+                equal
+            }
+            (Type(l), Type(r)) => l == r,
             _ => false,
         }
     }
@@ -168,15 +517,82 @@ impl PartialEq for Object {
 
 impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_visiting(f, &mut Vec::new())
+    }
+}
+
+impl Object {
+    /// Containers already being written are shown as `[...]` or `{...}`
+    fn fmt_visiting(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        visiting: &mut Vec<*const ()>,
+    ) -> std::fmt::Result {
         match self {
             Self::Nil => write!(f, "nil"),
-            Self::Double(value) => write!(f, "{value}"),
+            Self::Double(value) => write!(f, "{}", format_number(*value)),
             Self::Text(value) => write!(f, "{value}"),
             Self::Bool(value) => write!(f, "{value}"),
             Self::BuiltinCallee(value) => write!(f, "{value}"),
             Self::Callee(value) => write!(f, "{value}"),
             Self::Class(value) => write!(f, "class {value}"),
             Self::Instance(value) => write!(f, "instance of {}", value.borrow()),
+            Self::Array(value) => {
+                let ptr = Rc::as_ptr(value) as *const ();
+                if visiting.contains(&ptr) {
+                    return write!(f, "[...]");
+                }
+                visiting.push(ptr);
+                write!(f, "[")?;
+                for (i, item) in value.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.fmt_visiting(f, visiting)?;
+                }
+                visiting.pop();
+                write!(f, "]")
+            }
+            Self::Range(value) => write!(f, "{value}"),
+            Self::Map(value) => {
+                let ptr = Rc::as_ptr(value) as *const ();
+                if visiting.contains(&ptr) {
+                    return write!(f, "{{...}}");
+                }
+                visiting.push(ptr);
+                let map = value.borrow();
+                let mut entries = map.iter().collect::<Vec<_>>();
+                entries.sort_by(|a, b| key_order(a.0, b.0));
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.into_iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    // keys are hashable, so they can't hold containers
+                    write!(f, "{key}: ")?;
+                    value.fmt_visiting(f, visiting)?;
+                }
+                visiting.pop();
+                write!(f, "}}")
+            }
+            Self::Type(value) => write!(f, "type {}", value.name()),
         }
     }
 }
+
+/// Stable order of map keys for printing & listing:
+/// numbers first, then strings, then the rest by their text
+pub fn key_order(a: &Object, b: &Object) -> Ordering {
+    let rank = |object: &Object| match object {
+        Object::Double(_) => 0,
+        Object::Text(_) => 1,
+        _ => 2,
+    };
+    match (a, b) {
+        (Object::Double(l), Object::Double(r)) => l.total_cmp(r),
+        (Object::Text(l), Object::Text(r)) => l.cmp(r),
+        _ => rank(a)
+            .cmp(&rank(b))
+            .then_with(|| a.to_string().cmp(&b.to_string())),
+    }
+}
@@ -1,11 +1,12 @@
 use crate::fox::{
     FoxError, FoxResult, Object, TokenType,
-    ast::{Expression, Statement},
+    ast::{Argument, Expression, Pattern, Statement},
+    class::INITIALIZER_NAME,
 };
 
 use super::{ErrorKind, Token};
 
-const MAX_FUNCTION_ARGUMENT_COUNT: usize = 255;
+pub const MAX_FUNCTION_ARGUMENT_COUNT: usize = 255;
 
 pub struct Parser<'l> {
     tokens: &'l [Token],
@@ -25,13 +26,23 @@ impl<'l> Parser<'l> {
             //     Ok(statement) => statements.push(statement),
             //     Err(_) => self.synchronize(),
             // }
-            let statement = self.declaration()?;
-            statements.push(statement);
+            let declared = self.declaration()?;
+            statements.extend(declared);
         }
 
         Ok(statements)
     }
 
+    /// Single expression taking all the tokens, such as the `eval()` text
+    pub fn parse_expression(&mut self) -> FoxResult<Expression> {
+        let expr = self.expression()?;
+        if !self.is_at_end() {
+            let kind = ErrorKind::Parse("Expect end of expression".to_string());
+            return Err(FoxError::token(kind, self.peek()));
+        }
+        Ok(expr)
+    }
+
     fn is_at_end(&self) -> bool {
         // 1. We're expecting EOF is always last token in array according to design
         // but because of robust reasons we also need to check if we still in token's range
@@ -42,17 +53,21 @@ impl<'l> Parser<'l> {
         token.is_eof()
     }
 
-    fn declaration(&mut self) -> FoxResult<Statement> {
+    /// Declaration or statement, a variable list becomes a statement per name
+    fn declaration(&mut self) -> FoxResult<Vec<Statement>> {
         if self.match_multiple(&[TokenType::Class]) {
-            return self.class();
+            return Ok(vec![self.class()?]);
         }
         if self.match_multiple(&[TokenType::Fun]) {
-            return self.function("function");
+            return Ok(vec![self.function("function")?]);
         }
         if self.match_multiple(&[TokenType::Var]) {
-            return self.var_declaration();
+            return self.var_declaration(false);
         }
-        self.statement()
+        if self.match_multiple(&[TokenType::Const]) {
+            return self.var_declaration(true);
+        }
+        Ok(vec![self.statement()?])
     }
 
     fn class(&mut self) -> FoxResult<Statement> {
@@ -69,14 +84,81 @@ impl<'l> Parser<'l> {
         self.consume_token(TokenType::LeftBrace, "Expect '{' before class body")?;
 
         let mut methods = Vec::new();
+        let mut constants = Vec::new();
+        let mut members: Vec<Token> = Vec::new();
         while !self.check_type(&TokenType::RightBrace) && !self.is_at_end() {
-            let func = self.function("method")?;
+            if self.matches(TokenType::Const) {
+                let constant = self.class_constant()?;
+                let Statement::Var(data) = &constant else {
+                    return Err(FoxError::bug("Class constant must be a variable statement"));
+                };
+                Self::check_duplicate_member(&members, &name, &data.name, "Constant")?;
+                members.push(data.name.clone());
+                constants.push(constant);
+                continue;
+            }
+            self.check_method_start()?;
+            let func = if self.check_types_ahead(&[TokenType::Identifier, TokenType::LeftBrace]) {
+                self.getter()?
+            } else {
+                self.function("method")?
+            };
+            let method = &func.as_function()?.name;
+            Self::check_duplicate_member(&members, &name, method, "Method")?;
+            members.push(method.clone());
             methods.push(func);
         }
 
         self.consume_token(TokenType::RightBrace, "Expect '}' after class body")?;
 
-        Ok(Statement::class(name, superclass, methods))
+        Ok(Statement::class(name, superclass, methods, constants))
+    }
+
+    /// `const NAME = value;` after the consumed keyword
+    fn class_constant(&mut self) -> FoxResult<Statement> {
+        let name = self.consume_token(TokenType::Identifier, "Expect constant name")?;
+        self.consume_token(TokenType::Equal, "Expect '=' after constant name")?;
+        let initializer = Box::new(self.expression()?);
+        self.consume_semicolon()?;
+        Ok(Statement::var(name, Some(initializer), true))
+    }
+
+    /// A later member would silently replace the earlier one
+    fn check_duplicate_member(
+        members: &[Token],
+        class: &Token,
+        member: &Token,
+        kind: &str,
+    ) -> FoxResult<()> {
+        if !members.iter().any(|x| x.lexeme == member.lexeme) {
+            return Ok(());
+        }
+        let message = format!(
+            "{kind} '{}' is already declared in class '{}'",
+            member.lexeme, class.lexeme
+        );
+        Err(FoxError::token(
+            ErrorKind::Parse(message),
+            Some(member.clone()),
+        ))
+    }
+
+    fn check_method_start(&self) -> FoxResult<()> {
+        let Some(token) = self
+            .peek()
+            .filter(|token| token.token_type != TokenType::Identifier)
+        else {
+            return Ok(());
+        };
+        let message = if token.token_type == TokenType::Fun {
+            "Methods are declared without 'fun' keyword".to_string()
+        } else {
+            format!(
+                "Only methods and constants are allowed in a class body, found '{}'",
+                token.lexeme
+            )
+        };
+        Err(FoxError::token(ErrorKind::Parse(message), Some(token)))
     }
 
     fn function(&mut self, kind: &str) -> FoxResult<Statement> {
@@ -86,12 +168,22 @@ impl<'l> Parser<'l> {
             &format!("Expect '(' after {kind} name"),
         )?;
         let mut params = Vec::new();
+        let mut rest = None;
 
         let mut next_param = !self.check_type(&TokenType::RightParenthesis);
         while next_param {
             if params.len() >= MAX_FUNCTION_ARGUMENT_COUNT {
                 return Err(self.error(ErrorKind::TooManyFunctionArguments));
             }
+            if self.match_multiple(&[TokenType::DotDotDot]) {
+                rest =
+                    Some(self.consume_token(TokenType::Identifier, "Expect rest parameter name")?);
+                if self.check_type(&TokenType::Comma) {
+                    let kind = ErrorKind::Parse("Rest parameter must be the last one".to_string());
+                    return Err(self.error(kind));
+                }
+                break;
+            }
             let param = self.consume_token(TokenType::Identifier, "Expect parameter name")?;
             params.push(param);
             next_param = self.match_multiple(&[TokenType::Comma]);
@@ -105,46 +197,182 @@ impl<'l> Parser<'l> {
 
         let body = self.block()?;
 
-        Ok(Statement::function(name, params, body))
+        Ok(Statement::function(name, params, rest, body, false))
     }
 
-    fn var_declaration(&mut self) -> FoxResult<Statement> {
-        let name = self.consume_token(TokenType::Identifier, "Expect variable name")?;
+    /// `area { ... }` method without a parameter list
+    fn getter(&mut self) -> FoxResult<Statement> {
+        let name = self.consume_token(TokenType::Identifier, "Expect getter name")?;
+        if name.lexeme == INITIALIZER_NAME {
+            let kind = ErrorKind::Parse("Initializer can't be a getter".to_string());
+            return Err(FoxError::token(kind, Some(name)));
+        }
+        self.consume_token(TokenType::LeftBrace, "Expect '{' before getter body")?;
+        let body = self.block()?;
+        Ok(Statement::function(name, Vec::new(), None, body, true))
+    }
 
-        let initializer = if self.match_multiple(&[TokenType::Equal]) {
-            let expr = self.expression()?;
-            Some(Box::new(expr))
-        } else {
-            None
-        };
+    /// `var a = 1, b;` declares the names in order as separate statements
+    fn var_declaration(&mut self, constant: bool) -> FoxResult<Vec<Statement>> {
+        let mut declarations = Vec::new();
+        let mut names: Vec<Token> = Vec::new();
+        loop {
+            if self.matches(TokenType::LeftBracket) {
+                let bracket = self.force_previous_token()?;
+                let pattern = self.pattern()?;
+                for name in pattern.bound_names() {
+                    Self::check_duplicate(&names, name)?;
+                    names.push(name.clone());
+                }
+                self.consume_token(TokenType::Equal, "Expect '=' after destructuring pattern")?;
+                let initializer = Box::new(self.expression()?);
+                declarations.push(Statement::var_pattern(
+                    bracket,
+                    pattern,
+                    initializer,
+                    constant,
+                ));
+            } else {
+                let name = self.consume_token(TokenType::Identifier, "Expect variable name")?;
+                Self::check_duplicate(&names, &name)?;
+                names.push(name.clone());
+
+                let initializer = if self.match_multiple(&[TokenType::Equal]) {
+                    let expr = self.expression()?;
+                    Some(Box::new(expr))
+                } else if constant {
+                    let kind = ErrorKind::Parse("Expect '=' after constant name".to_string());
+                    return Err(self.error(kind));
+                } else {
+                    None
+                };
+                declarations.push(Statement::var(name, initializer, constant));
+            }
 
-        self.consume_token(
-            TokenType::Semicolon,
-            "Expected ';' after variable declaration",
-        )?;
+            if !self.match_multiple(&[TokenType::Comma]) {
+                break;
+            }
+        }
+
+        self.consume_semicolon()?;
+
+        Ok(declarations)
+    }
 
-        Ok(Statement::var(name, initializer))
+    /// The resolver can't tell a redeclared global from the name repeated in one declaration
+    fn check_duplicate(names: &[Token], name: &Token) -> FoxResult<()> {
+        if names.iter().any(|x| x.lexeme == name.lexeme) {
+            return Err(FoxError::resolver(
+                Some(name.clone()),
+                "Already a variable with this name in this scope",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Names of `[a, b, ...rest]` after the consumed opening bracket
+    fn pattern(&mut self) -> FoxResult<Pattern> {
+        let mut names = Vec::new();
+        let mut rest = None;
+        if !self.check_type(&TokenType::RightBracket) {
+            loop {
+                if self.matches(TokenType::DotDotDot) {
+                    rest =
+                        Some(self.consume_token(TokenType::Identifier, "Expect name after '...'")?);
+                    break;
+                }
+                names.push(self.consume_token(TokenType::Identifier, "Expect name in pattern")?);
+                if !self.matches(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume_token(TokenType::RightBracket, "Expect ']' after pattern")?;
+        Ok(Pattern { names, rest })
+    }
+
+    /// Checks if `[a, b, ...rest] =` follows, otherwise the bracket starts an array literal
+    fn check_pattern_ahead(&self) -> bool {
+        use TokenType::*;
+        let token_type = |offset: usize| {
+            self.tokens
+                .get(self.current + offset)
+                .map(|token| token.token_type)
+        };
+        if token_type(0) != Some(LeftBracket) {
+            return false;
+        }
+        let mut offset = 1;
+        if token_type(offset) != Some(RightBracket) {
+            loop {
+                match token_type(offset) {
+                    Some(Identifier) => offset += 1,
+                    Some(DotDotDot) if token_type(offset + 1) == Some(Identifier) => {
+                        offset += 2;
+                        break;
+                    }
+                    _ => return false,
+                }
+                if token_type(offset) != Some(Comma) {
+                    break;
+                }
+                offset += 1;
+            }
+        }
+        token_type(offset) == Some(RightBracket) && token_type(offset + 1) == Some(Equal)
     }
 
     fn statement(&mut self) -> FoxResult<Statement> {
+        if self.match_multiple(&[TokenType::Assert]) {
+            return self.assert_statement();
+        }
+        if self.match_multiple(&[TokenType::Break]) {
+            return self.break_statement();
+        }
+        if self.match_multiple(&[TokenType::Continue]) {
+            return self.continue_statement();
+        }
+        if self.match_multiple(&[TokenType::Defer]) {
+            let keyword = self.force_previous_token()?;
+            let body = self.statement()?;
+            return Ok(Statement::defer(keyword, Box::new(body)));
+        }
         if self.match_multiple(&[TokenType::For]) {
-            return self.for_statement();
+            return self.for_statement(None);
         }
         if self.match_multiple(&[TokenType::If]) {
             return self.if_statement();
         }
+        if self.match_multiple(&[TokenType::Import]) {
+            return self.import_statement();
+        }
+        if self.match_multiple(&[TokenType::Match]) {
+            return self.match_statement();
+        }
         if self.match_multiple(&[TokenType::Print]) {
             return self.print_statement();
         }
         if self.match_multiple(&[TokenType::Return]) {
             return self.return_statement();
         }
+        if self.match_multiple(&[TokenType::Throw]) {
+            return self.throw_statement();
+        }
+        if self.match_multiple(&[TokenType::Try]) {
+            return self.try_statement();
+        }
         if self.match_multiple(&[TokenType::While]) {
-            return self.while_statement();
+            return self.while_statement(None);
         }
         if self.match_multiple(&[TokenType::LeftBrace]) {
+            let brace = self.force_previous_token()?;
             let statements = self.block()?;
-            return Ok(Statement::block(statements));
+            return Ok(Statement::block(brace, statements, false));
+        }
+        if self.check_types_ahead(&[TokenType::Identifier, TokenType::Colon])
+            && self.match_multiple(&[TokenType::Identifier])
+        {
+            return self.labeled_statement();
         }
         self.expression_statement()
     }
@@ -152,22 +380,67 @@ impl<'l> Parser<'l> {
     fn return_statement(&mut self) -> FoxResult<Statement> {
         let keyword = self.force_previous_token()?;
         let mut value = None;
-        if !self.check_type(&TokenType::Semicolon) {
+        // a bare `return` closing the block is rather missing ';' than a value
+        if !self.check_type(&TokenType::Semicolon) && !self.check_type(&TokenType::RightBrace) {
             let expr = self.expression()?;
             value = Some(Box::new(expr));
         }
-        self.consume_token(TokenType::Semicolon, "Expect ';' after return value")?;
+        self.consume_semicolon()?;
         Ok(Statement::ret_fn(keyword, value))
     }
 
-    fn for_statement(&mut self) -> FoxResult<Statement> {
+    fn break_statement(&mut self) -> FoxResult<Statement> {
+        let keyword = self.force_previous_token()?;
+        let label = self.loop_label()?;
+        self.consume_semicolon()?;
+        Ok(Statement::break_stmt(keyword, label))
+    }
+
+    fn continue_statement(&mut self) -> FoxResult<Statement> {
+        let keyword = self.force_previous_token()?;
+        let label = self.loop_label()?;
+        self.consume_semicolon()?;
+        Ok(Statement::continue_stmt(keyword, label))
+    }
+
+    fn loop_label(&mut self) -> FoxResult<Option<Token>> {
+        if self.match_multiple(&[TokenType::Identifier]) {
+            return self.force_previous_token().map(Some);
+        }
+        Ok(None)
+    }
+
+    /// `label: while ...` or `label: for ...`, the label is consumed
+    fn labeled_statement(&mut self) -> FoxResult<Statement> {
+        let label = self.force_previous_token()?;
+        self.consume_token(TokenType::Colon, "Expect ':' after loop label")?;
+        if self.match_multiple(&[TokenType::While]) {
+            return self.while_statement(Some(label));
+        }
+        if self.match_multiple(&[TokenType::For]) {
+            return self.for_statement(Some(label));
+        }
+        let kind = ErrorKind::Parse("Expect loop after label".to_string());
+        Err(self.error(kind))
+    }
+
+    /// Desugars into `{ initializer; while (condition) body }`.
+    /// Synthetic nodes carry the most specific original token of their content:
+    /// clauses keep their own tokens, the loop takes the `for` keyword,
+    /// the scope block is marked synthetic and the omitted condition is a
+    /// literal that can't fail
+    fn for_statement(&mut self, label: Option<Token>) -> FoxResult<Statement> {
+        let keyword = self.force_previous_token()?;
         self.consume_token(TokenType::LeftParenthesis, "Expect '(' after 'for'")?;
+        if self.check_types_ahead(&[TokenType::Var, TokenType::Identifier, TokenType::In]) {
+            return self.for_in_statement(keyword, label);
+        }
         let initializer = if self.match_multiple(&[TokenType::Semicolon]) {
-            None
+            Vec::new()
         } else if self.match_multiple(&[TokenType::Var]) {
-            Some(self.var_declaration()?)
+            self.var_declaration(false)?
         } else {
-            Some(self.expression_statement()?)
+            vec![self.expression_statement()?]
         };
 
         let condition = if self.check_type(&TokenType::Semicolon) {
@@ -176,46 +449,90 @@ impl<'l> Parser<'l> {
             Some(self.expression()?)
         };
 
+        if self.match_multiple(&[TokenType::Comma]) {
+            let kind = ErrorKind::Parse(
+                "Comma-separated expressions are allowed only in the increment clause".to_string(),
+            );
+            return Err(self.error(kind));
+        }
         self.consume_token(TokenType::Semicolon, "Expected ';' after loop condition")?;
 
-        let increment = if self.check_type(&TokenType::RightParenthesis) {
-            None
-        } else {
-            Some(self.expression()?)
-        };
+        // a list, so the header can update several variables
+        let mut increments = Vec::new();
+        if !self.check_type(&TokenType::RightParenthesis) {
+            loop {
+                increments.push(self.expression()?);
+                if !self.match_multiple(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
 
         self.consume_token(
             TokenType::RightParenthesis,
             "Expected ')' after for clauses",
         )?;
 
-        let mut body = self.statement()?;
-
-        if let Some(increment) = increment {
-            body = Statement::block(vec![body, Statement::expression(Box::new(increment))]);
-        }
-
-        if let Some(condition) = condition {
-            body = Statement::while_stmt(Box::new(condition), Box::new(body));
-        }
+        let body = self.statement()?;
 
-        if let Some(initializer) = initializer {
-            body = Statement::block(vec![initializer, body])
+        // the loop without condition is infinite
+        let condition = condition.unwrap_or(Expression::literal(Object::Bool(true)));
+        let mut body = Statement::while_stmt(
+            keyword.clone(),
+            Box::new(condition),
+            Box::new(body),
+            increments,
+            label,
+        );
+
+        if !initializer.is_empty() {
+            let mut statements = initializer;
+            statements.push(body);
+            body = Statement::block(keyword, statements, true)
         }
 
         Ok(body)
     }
 
-    fn while_statement(&mut self) -> FoxResult<Statement> {
+    /// `for (var name in iterable) body`, the opening parenthesis is consumed
+    fn for_in_statement(&mut self, keyword: Token, label: Option<Token>) -> FoxResult<Statement> {
+        self.consume_token(TokenType::Var, "Expect 'var' before loop variable")?;
+        let name = self.consume_token(TokenType::Identifier, "Expect loop variable name")?;
+        let in_keyword = self.consume_token(TokenType::In, "Expect 'in' after loop variable")?;
+        let iterable = self.expression()?;
+        self.consume_token(
+            TokenType::RightParenthesis,
+            "Expected ')' after iterable expression",
+        )?;
+        let body = self.statement()?;
+        Ok(Statement::for_in(
+            keyword,
+            name,
+            in_keyword,
+            Box::new(iterable),
+            Box::new(body),
+            label,
+        ))
+    }
+
+    fn while_statement(&mut self, label: Option<Token>) -> FoxResult<Statement> {
+        let keyword = self.force_previous_token()?;
         self.consume_token(TokenType::LeftParenthesis, "Expected '(' after 'while'")?;
         let condition = self.expression()?;
         self.consume_token(TokenType::RightParenthesis, "Expected ')' after condition")?;
         let body = self.statement()?;
 
-        Ok(Statement::while_stmt(Box::new(condition), Box::new(body)))
+        Ok(Statement::while_stmt(
+            keyword,
+            Box::new(condition),
+            Box::new(body),
+            Vec::new(),
+            label,
+        ))
     }
 
     fn if_statement(&mut self) -> FoxResult<Statement> {
+        let keyword = self.force_previous_token()?;
         self.consume_token(TokenType::LeftParenthesis, "Expected '(' after 'if'")?;
         let condition = self.expression()?;
         self.consume_token(TokenType::RightParenthesis, "Expect ')' after if condition")?;
@@ -230,33 +547,146 @@ impl<'l> Parser<'l> {
         };
 
         Ok(Statement::if_stmt(
+            keyword,
             Box::new(condition),
             Box::new(then_branch),
             else_branch,
         ))
     }
 
+    /// `match (subject) { pattern => statement ... else => statement }`,
+    /// the default arm is optional & must be the last one
+    fn match_statement(&mut self) -> FoxResult<Statement> {
+        let keyword = self.force_previous_token()?;
+        self.consume_token(TokenType::LeftParenthesis, "Expected '(' after 'match'")?;
+        let subject = self.expression()?;
+        self.consume_token(
+            TokenType::RightParenthesis,
+            "Expect ')' after match subject",
+        )?;
+        self.consume_token(TokenType::LeftBrace, "Expect '{' before match arms")?;
+
+        let mut arms = Vec::new();
+        let mut default = None;
+        while !self.check_type(&TokenType::RightBrace) && !self.is_at_end() {
+            if self.match_multiple(&[TokenType::Else]) {
+                self.consume_token(TokenType::EqualGreater, "Expect '=>' after 'else'")?;
+                default = Some(Box::new(self.statement()?));
+                break;
+            }
+            let pattern = self.match_pattern()?;
+            self.consume_token(TokenType::EqualGreater, "Expect '=>' after match pattern")?;
+            arms.push((pattern, self.statement()?));
+        }
+        let message = if default.is_some() {
+            "Expect '}' after the default arm"
+        } else {
+            "Expect '}' after match arms"
+        };
+        self.consume_token(TokenType::RightBrace, message)?;
+        Ok(Statement::match_stmt(
+            keyword,
+            Box::new(subject),
+            arms,
+            default,
+        ))
+    }
+
+    /// Literal value, numbers may be negative
+    fn match_pattern(&mut self) -> FoxResult<Expression> {
+        use TokenType::*;
+        if self.matches(Minus) {
+            let number = self.consume_token(Number, "Expect number after '-' in match pattern")?;
+            let Object::Double(value) = number.literal else {
+                return Err(FoxError::bug("Number token without numeric literal"));
+            };
+            return Ok(Expression::literal(Object::Double(-value)));
+        }
+        if self.check_type(&False)
+            || self.check_type(&True)
+            || self.check_type(&Nil)
+            || self.check_type(&Number)
+            || self.check_type(&String)
+        {
+            return self.primary();
+        }
+        let kind = ErrorKind::Parse("Expect literal value as match pattern".to_string());
+        Err(self.error(kind))
+    }
+
     fn block(&mut self) -> FoxResult<Vec<Statement>> {
         let mut statements = Vec::new();
 
         while !self.check_type(&TokenType::RightBrace) && !self.is_at_end() {
-            let stmt = self.declaration()?;
-            statements.push(stmt);
+            let declared = self.declaration()?;
+            statements.extend(declared);
         }
 
         self.consume_token(TokenType::RightBrace, "Expected '}'")?;
         Ok(statements)
     }
 
+    fn import_statement(&mut self) -> FoxResult<Statement> {
+        let keyword = self.force_previous_token()?;
+        let path = self.consume_token(TokenType::String, "Expect module path after 'import'")?;
+        self.consume_semicolon()?;
+        Ok(Statement::import(keyword, path, Vec::new()))
+    }
+
+    fn throw_statement(&mut self) -> FoxResult<Statement> {
+        let keyword = self.force_previous_token()?;
+        let value = self.expression()?;
+        self.consume_semicolon()?;
+        Ok(Statement::throw_stmt(keyword, Box::new(value)))
+    }
+
+    fn try_statement(&mut self) -> FoxResult<Statement> {
+        let keyword = self.force_previous_token()?;
+        let brace = self.consume_token(TokenType::LeftBrace, "Expect '{' after 'try'")?;
+        let body = Statement::block(brace, self.block()?, false);
+        self.consume_token(TokenType::Catch, "Expect 'catch' after try block")?;
+        self.consume_token(TokenType::LeftParenthesis, "Expect '(' after 'catch'")?;
+        let name = self.consume_token(TokenType::Identifier, "Expect exception variable name")?;
+        self.consume_token(
+            TokenType::RightParenthesis,
+            "Expect ')' after exception variable name",
+        )?;
+        let brace = self.consume_token(TokenType::LeftBrace, "Expect '{' before catch body")?;
+        let handler = Statement::block(brace, self.block()?, false);
+        Ok(Statement::try_stmt(
+            keyword,
+            Box::new(body),
+            name,
+            Box::new(handler),
+        ))
+    }
+
+    fn assert_statement(&mut self) -> FoxResult<Statement> {
+        let keyword = self.force_previous_token()?;
+        let condition = self.expression()?;
+        let message = if self.match_multiple(&[TokenType::Comma]) {
+            Some(Box::new(self.expression()?))
+        } else {
+            None
+        };
+        self.consume_semicolon()?;
+        Ok(Statement::assert_stmt(
+            keyword,
+            Box::new(condition),
+            message,
+        ))
+    }
+
     fn print_statement(&mut self) -> FoxResult<Statement> {
+        let keyword = self.force_previous_token()?;
         let expr = self.expression()?;
-        self.consume_token(TokenType::Semicolon, "Expected ';' after value")?;
-        Ok(Statement::print(Box::new(expr)))
+        self.consume_semicolon()?;
+        Ok(Statement::print(keyword, Box::new(expr)))
     }
 
     fn expression_statement(&mut self) -> FoxResult<Statement> {
         let expr = self.expression()?;
-        self.consume_token(TokenType::Semicolon, "Expected ';' after expression")?;
+        self.consume_semicolon()?;
         Ok(Statement::expression(Box::new(expr)))
     }
 
@@ -265,6 +695,18 @@ impl<'l> Parser<'l> {
     }
 
     fn assignment(&mut self) -> FoxResult<Expression> {
+        if self.check_pattern_ahead() {
+            _ = self.advance();
+            let bracket = self.force_previous_token()?;
+            let pattern = self.pattern()?;
+            self.consume_token(TokenType::Equal, "Expect '=' after destructuring pattern")?;
+            let value = self.assignment()?;
+            return Ok(Expression::assign_pattern(
+                bracket,
+                pattern,
+                Box::new(value),
+            ));
+        }
         let expr = self.or()?;
         if !self.match_multiple(&[TokenType::Equal]) {
             return Ok(expr);
@@ -283,6 +725,12 @@ impl<'l> Parser<'l> {
                 get_expr.name,
                 Box::new(value),
             )),
+            Expression::Index(index_expr) => Ok(Expression::index_set(
+                index_expr.object,
+                index_expr.bracket,
+                index_expr.index,
+                Box::new(value),
+            )),
             _ => {
                 let err = FoxError::token(ErrorKind::InvalidAssignmentTarget, Some(equals));
                 Err(err)
@@ -340,7 +788,18 @@ impl<'l> Parser<'l> {
 
     fn comparison(&mut self) -> FoxResult<Expression> {
         use TokenType::*;
-        self.parse_binary(Self::term, &[Greater, GreaterEqual, Less, LessEqual])
+        self.parse_binary(Self::range, &[Greater, GreaterEqual, Less, LessEqual, Is])
+    }
+
+    /// `start..end` or `start..=end`, the bounds are terms so `0..n - 1` needs no parentheses
+    fn range(&mut self) -> FoxResult<Expression> {
+        let start = self.term()?;
+        if !self.match_multiple(&[TokenType::DotDot, TokenType::DotDotEqual]) {
+            return Ok(start);
+        }
+        let operator = self.force_previous_token()?;
+        let end = self.term()?;
+        Ok(Expression::range(Box::new(start), operator, Box::new(end)))
     }
 
     fn term(&mut self) -> FoxResult<Expression> {
@@ -350,7 +809,7 @@ impl<'l> Parser<'l> {
 
     fn factor(&mut self) -> FoxResult<Expression> {
         use TokenType::*;
-        self.parse_binary(Self::unary, &[Slash, Star])
+        self.parse_binary(Self::unary, &[Slash, Star, Percent])
     }
 
     fn unary(&mut self) -> FoxResult<Expression> {
@@ -360,27 +819,63 @@ impl<'l> Parser<'l> {
             let right = self.unary()?;
             return Ok(Expression::unary(Box::new(right), operator));
         }
+        if self.match_multiple(&[PlusPlus, MinusMinus]) {
+            let operator = self.force_previous_token()?;
+            let target = self.unary()?;
+            return self.prefix_update(operator, target);
+        }
 
         self.call()
     }
 
+    /// `++i` is an assignment of `i + 1`, the addition keeps the operator token
+    /// so the interpreter can tell it from a regular one
+    fn prefix_update(&self, operator: Token, target: Expression) -> FoxResult<Expression> {
+        let Expression::Variable(data) = target else {
+            let err = FoxError::token(ErrorKind::InvalidAssignmentTarget, Some(operator));
+            return Err(err);
+        };
+        let name = data.name.clone();
+        let value = Expression::binary(
+            Box::new(Expression::Variable(data)),
+            operator,
+            Box::new(Expression::literal(Object::Double(1.0))),
+        );
+        Ok(Expression::assign(name, Box::new(value)))
+    }
+
     fn call(&mut self) -> FoxResult<Expression> {
         let mut expr = self.primary()?;
+        let mut is_optional = false;
         loop {
             if self.match_multiple(&[TokenType::LeftParenthesis]) {
                 expr = self.finish_call(expr)?;
                 continue;
             }
 
-            if self.match_multiple(&[TokenType::Dot]) {
+            if self.match_multiple(&[TokenType::LeftBracket]) {
+                let index = self.expression()?;
+                let bracket =
+                    self.consume_token(TokenType::RightBracket, "Expected ']' after index")?;
+                expr = Expression::index(Box::new(expr), bracket, Box::new(index));
+                continue;
+            }
+
+            if self.match_multiple(&[TokenType::Dot, TokenType::QuestionDot]) {
+                let optional = self.force_previous_token()?.token_type == TokenType::QuestionDot;
                 let name =
                     self.consume_token(TokenType::Identifier, "Expect property name after '.'")?;
-                expr = Expression::get(Box::new(expr), name);
+                expr = Expression::get(Box::new(expr), name, optional);
+                is_optional |= optional;
                 continue;
             }
 
             break;
         }
+        // the whole chain is wrapped, so it can't be an assignment target
+        if is_optional {
+            expr = Expression::chain(Box::new(expr));
+        }
         Ok(expr)
     }
 
@@ -391,8 +886,13 @@ impl<'l> Parser<'l> {
                 if args.len() >= MAX_FUNCTION_ARGUMENT_COUNT {
                     return Err(self.error(ErrorKind::TooManyFunctionArguments));
                 }
-                let expr = self.expression()?;
-                args.push(expr);
+                let spread = if self.matches(TokenType::DotDotDot) {
+                    self.previous_token()
+                } else {
+                    None
+                };
+                let value = self.expression()?;
+                args.push(Argument { value, spread });
                 if !self.match_multiple(&[TokenType::Comma]) {
                     break;
                 }
@@ -440,6 +940,48 @@ impl<'l> Parser<'l> {
             return Ok(expr);
         }
 
+        if self.matches(Fun) {
+            let keyword = self.force_previous_token()?;
+            let kind = ErrorKind::Parse(
+                "anonymous functions are not supported; declare a named function and reference it"
+                    .to_string(),
+            );
+            return Err(FoxError::token(kind, Some(keyword)));
+        }
+
+        if self.matches(LeftBracket) {
+            let bracket = self.force_previous_token()?;
+            let mut elements = Vec::new();
+            if !self.check_type(&RightBracket) {
+                loop {
+                    elements.push(self.expression()?);
+                    if !self.matches(Comma) {
+                        break;
+                    }
+                }
+            }
+            self.consume_token(RightBracket, "Expected ']' after array elements")?;
+            return Ok(Expression::array(bracket, elements));
+        }
+
+        // blocks are statements, so the brace is a map here
+        if self.matches(LeftBrace) {
+            let brace = self.force_previous_token()?;
+            let mut entries = Vec::new();
+            if !self.check_type(&RightBrace) {
+                loop {
+                    let key = self.expression()?;
+                    self.consume_token(Colon, "Expected ':' after map key")?;
+                    entries.push((key, self.expression()?));
+                    if !self.matches(Comma) {
+                        break;
+                    }
+                }
+            }
+            self.consume_token(RightBrace, "Expected '}' after map entries")?;
+            return Ok(Expression::map(brace, entries));
+        }
+
         if self.matches(LeftParenthesis) {
             let expr = self.expression()?;
             self.consume_token(TokenType::RightParenthesis, "Expected ')'")?;
@@ -502,6 +1044,20 @@ impl<'l> Parser<'l> {
         Ok(token)
     }
 
+    /// Statement terminator is reported at the last token of the statement
+    /// because the next token is usually located on the following line
+    fn consume_semicolon(&mut self) -> FoxResult<Token> {
+        if self.check_type(&TokenType::Semicolon) {
+            return self.consume_token(TokenType::Semicolon, "Expect ';'");
+        }
+        let token = self.force_previous_token()?;
+        let message = format!(
+            "missing ';' at end of statement (line {})",
+            token.code_location.line_number()
+        );
+        Err(FoxError::token(ErrorKind::Parse(message), Some(token)))
+    }
+
     // fn synchronize(&mut self) {
     //     self.advance();
 
@@ -530,6 +1086,15 @@ impl<'l> Parser<'l> {
         FoxError::token(error_kind, self.previous_token())
     }
 
+    /// Checks the upcoming tokens without consuming them
+    fn check_types_ahead(&self, types: &[TokenType]) -> bool {
+        types.iter().enumerate().all(|(offset, tt)| {
+            self.tokens
+                .get(self.current + offset)
+                .is_some_and(|token| token.token_type == *tt)
+        })
+    }
+
     fn check_type(&self, tt: &TokenType) -> bool {
         let Some(value) = self.peek() else {
             return false;
@@ -540,5 +1105,570 @@ impl<'l> Parser<'l> {
 
 #[cfg(test)]
 mod test {
-    // use super::*;
+    use super::*;
+    use crate::fox::{ErrorInfo, Scanner};
+
+    fn parse(code: &str) -> FoxResult<Vec<Statement>> {
+        let source = code.chars().collect::<Vec<_>>();
+        let tokens = Scanner::with_source(&source).scan_tokens()?;
+        Parser::new(&tokens).parse()
+    }
+
+    fn error_line(err: &FoxError) -> usize {
+        let ErrorInfo::Token(token) = err.info() else {
+            panic!("Token expected for {err:?}");
+        };
+        token.code_location.line_number()
+    }
+
+    fn assert_missing_semicolon(code: &str, line: usize) {
+        let err = parse(code).unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            format!("missing ';' at end of statement (line {line})")
+        );
+        assert_eq!(error_line(&err), line);
+    }
+
+    #[test]
+    fn test_for_desugaring_origin() {
+        let statements = parse("for (var i = 0; i < 1; i = i + 1) {}").unwrap();
+        let [Statement::Block(block)] = statements.as_slice() else {
+            panic!("Block expected, found {statements:?}");
+        };
+        assert!(block.synthetic);
+        let [Statement::Var(_), Statement::While(data)] = block.statements.as_slice() else {
+            panic!("Initializer & loop expected, found {block:?}");
+        };
+        assert_eq!(data.keyword.lexeme, "for");
+        assert_eq!(data.increments.len(), 1);
+        let statements = parse("{}").unwrap();
+        assert!(matches!(&statements[0], Statement::Block(block) if !block.synthetic));
+    }
+
+    #[test]
+    fn test_for_comma_outside_increment() {
+        let err = parse("var j;\nfor (var i = 0; i < 1, j < 1; i = i + 1) {}").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Comma-separated expressions are allowed only in the increment clause"
+        );
+        assert_eq!(error_line(&err), 2);
+        // not a general comma operator
+        assert!(parse("var a = 1, 2;").is_err());
+        assert!(parse("var i, j;\nfor (i = 0, j = 0; i < 1; i = i + 1) {}").is_err());
+    }
+
+    #[test]
+    fn test_index_assignment_target() {
+        let statements = parse("xs[0][1] = 5;").unwrap();
+        let [Statement::Expression(stmt)] = statements.as_slice() else {
+            panic!("Expression expected, found {statements:?}");
+        };
+        let Expression::IndexSet(data) = stmt.expression.as_ref() else {
+            panic!("Index assignment expected, found {stmt:?}");
+        };
+        assert!(matches!(data.object.as_ref(), Expression::Index(_)));
+        for code in [
+            "[1, 2] = 3;",
+            "xs[0;",
+            "[1, 2;",
+            "[1,];",
+            "var m = {1 2};",
+            "var m = {1: 2",
+        ] {
+            assert!(parse(code).is_err(), "{code}");
+        }
+    }
+
+    #[test]
+    fn test_class_body_statements() {
+        for (code, message, lexeme) in [
+            (
+                "class A {\n  return 1;\n}",
+                "Only methods and constants are allowed in a class body, found 'return'",
+                "return",
+            ),
+            (
+                "class A {\n  var x = 1;\n}",
+                "Only methods and constants are allowed in a class body, found 'var'",
+                "var",
+            ),
+            (
+                "class A {\n  print this;\n}",
+                "Only methods and constants are allowed in a class body, found 'print'",
+                "print",
+            ),
+            (
+                "class A {\n  fun m() {}\n}",
+                "Methods are declared without 'fun' keyword",
+                "fun",
+            ),
+        ] {
+            let err = parse(code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+            assert_eq!(error_line(&err), 2, "{code}");
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Token expected for {err:?}");
+            };
+            assert_eq!(token.lexeme, lexeme);
+        }
+    }
+
+    #[test]
+    fn test_getters() {
+        let statements = parse("class C { area { return 1; } size() {} }").unwrap();
+        let Statement::Class(class) = &statements[0] else {
+            panic!("Class expected, got {:?}", statements[0]);
+        };
+        let getters = class
+            .methods
+            .iter()
+            .map(|method| method.as_function().unwrap().getter)
+            .collect::<Vec<_>>();
+        assert_eq!(getters, [true, false]);
+        let err = parse("class C { init { } }").unwrap_err();
+        assert_eq!(err.kind().to_string(), "Initializer can't be a getter");
+        assert!(parse("fun f { }").is_err());
+    }
+
+    #[test]
+    fn test_class_constants() {
+        let statements = parse("class C { const A = 1; m() {} const B = A + 1; }").unwrap();
+        let Statement::Class(class) = &statements[0] else {
+            panic!("Class expected, got {:?}", statements[0]);
+        };
+        assert_eq!(class.methods.len(), 1);
+        assert_eq!(class.constants.len(), 2);
+        for (code, message) in [
+            (
+                "class C { const A = 1; const A = 2; }",
+                "Constant 'A' is already declared in class 'C'",
+            ),
+            (
+                "class C { A() {} const A = 2; }",
+                "Constant 'A' is already declared in class 'C'",
+            ),
+            ("class C { const A; }", "Expect '=' after constant name"),
+        ] {
+            let err = parse(code).unwrap_err();
+            assert!(err.kind().to_string().contains(message), "{code}: {err:?}");
+        }
+    }
+
+    #[test]
+    fn test_duplicate_methods() {
+        let code = "class A {\n  m() {}\n  n() {}\n  m(a) {}\n}";
+        let err = parse(code).unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Method 'm' is already declared in class 'A'"
+        );
+        assert_eq!(error_line(&err), 4);
+        assert!(parse("class A { m() {} }\nclass B { m() {} }").is_ok());
+    }
+
+    #[test]
+    fn test_property_access() {
+        let statements = parse("instance.field = 1;\na.b.c();\nmake().m();").unwrap();
+        let expression = |index: usize| {
+            let Statement::Expression(data) = &statements[index] else {
+                panic!("Expression expected, got {:?}", statements[index]);
+            };
+            data.expression.as_ref()
+        };
+        let Expression::Set(set) = expression(0) else {
+            panic!("Set expected, got {:?}", expression(0));
+        };
+        assert_eq!(set.name.lexeme, "field");
+        assert!(matches!(set.object.as_ref(), Expression::Variable(_)));
+        let callee_object = |index: usize| {
+            let Expression::Call(call) = expression(index) else {
+                panic!("Call expected, got {:?}", expression(index));
+            };
+            let Expression::Get(get) = call.callee.as_ref() else {
+                panic!("Get expected, got {:?}", call.callee);
+            };
+            get.object.as_ref()
+        };
+        assert!(matches!(callee_object(1), Expression::Get(data) if data.name.lexeme == "b"));
+        assert!(matches!(callee_object(2), Expression::Call(_)));
+        assert!(parse("a.1 = 2;").is_err());
+    }
+
+    #[test]
+    fn test_super_without_method() {
+        for (code, message) in [
+            ("class B < A { m() { super; } }", "Expect '.' after 'super'"),
+            (
+                "class B < A { m() { super(); } }",
+                "Expect '.' after 'super'",
+            ),
+            (
+                "class B < A { m() { super.; } }",
+                "Expect superclass method name",
+            ),
+        ] {
+            let err = parse(code).unwrap_err();
+            assert!(err.kind().to_string().contains(message), "{code}: {err:?}");
+        }
+        let statements = parse("class B < A { m() { return super.m(this.x); } }").unwrap();
+        assert!(matches!(statements[0], Statement::Class(_)));
+    }
+
+    #[test]
+    fn test_prefix_update_desugaring() {
+        let statements = parse("--i;").unwrap();
+        let [Statement::Expression(stmt)] = statements.as_slice() else {
+            panic!("Expression expected, found {statements:?}");
+        };
+        let Expression::Assign(assign) = stmt.expression.as_ref() else {
+            panic!("Assignment expected, found {stmt:?}");
+        };
+        assert_eq!(assign.name.lexeme, "i");
+        let Expression::Binary(binary) = assign.value.as_ref() else {
+            panic!("Binary expected, found {assign:?}");
+        };
+        assert_eq!(binary.operator.token_type, TokenType::MinusMinus);
+        assert!(
+            matches!(binary.left.as_ref(), Expression::Variable(data) if data.name.lexeme == "i")
+        );
+    }
+
+    #[test]
+    fn test_prefix_update_invalid_target() {
+        for code in ["++(a + b);", "--1;", "++a.b;", "++ ++a;", "--f();"] {
+            let err = parse(code).unwrap_err();
+            assert!(
+                matches!(err.kind(), ErrorKind::InvalidAssignmentTarget),
+                "{code}: {err:?}"
+            );
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Token expected for {err:?}");
+            };
+            assert_eq!(token.code_location.absolute_position(), 1, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_optional_chain_assignment_target() {
+        for code in ["a?.b = 1;", "a?.b.c = 1;"] {
+            let err = parse(code).unwrap_err();
+            assert!(
+                matches!(err.kind(), ErrorKind::InvalidAssignmentTarget),
+                "{code}: {err:?}"
+            );
+        }
+        assert!(parse("a.b?.c;").is_ok());
+    }
+
+    #[test]
+    fn test_match_syntax_errors() {
+        for (code, message) in [
+            (
+                "match (x) { y => print 1; }",
+                "Expect literal value as match pattern",
+            ),
+            (
+                "match (x) { 1 print 1; }",
+                "Expect '=>' after match pattern",
+            ),
+            (
+                "match (x) { else => print 1; 1 => print 2; }",
+                "Expect '}' after the default arm",
+            ),
+            (
+                "match (x) { - \"a\" => {} }",
+                "Expect number after '-' in match pattern",
+            ),
+            ("match x { }", "Expected '(' after 'match'"),
+        ] {
+            let err = parse(code).unwrap_err();
+            assert!(err.kind().to_string().contains(message), "{code}: {err:?}");
+        }
+        assert!(parse("match (x) {}").is_ok());
+    }
+
+    #[test]
+    fn test_const_declaration() {
+        let statements = parse("const PI = 3.14; var x = 1;").unwrap();
+        let [Statement::Var(pi), Statement::Var(x)] = statements.as_slice() else {
+            panic!("Two declarations expected, got {statements:?}");
+        };
+        assert!(pi.constant && pi.initializer.is_some());
+        assert!(!x.constant);
+
+        let err = parse("const PI;").unwrap_err();
+        assert_eq!(err.kind().to_string(), "Expect '=' after constant name");
+        assert_eq!(error_line(&err), 1);
+    }
+
+    #[test]
+    fn test_var_list() {
+        let statements = parse("var a = 1, b, c = a;").unwrap();
+        let names = statements
+            .iter()
+            .map(|stmt| match stmt {
+                Statement::Var(data) => (data.name.lexeme.as_str(), data.initializer.is_some()),
+                _ => panic!("Declaration expected, got {stmt:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, [("a", true), ("b", false), ("c", true)]);
+
+        let statements = parse("for (var i = 0, j = 10; i < j; i = i + 1) {}").unwrap();
+        let [Statement::Block(block)] = statements.as_slice() else {
+            panic!("Desugared loop expected, got {statements:?}");
+        };
+        assert!(matches!(
+            block.statements.as_slice(),
+            [Statement::Var(_), Statement::Var(_), Statement::While(_)]
+        ));
+
+        let err = parse("var a = 1,\nb, a;").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Already a variable with this name in this scope"
+        );
+        assert_eq!(error_line(&err), 2);
+        for (code, message) in [
+            ("const a = 1, b;", "Expect '=' after constant name"),
+            ("var a, ;", "Expect variable name"),
+            ("var a b;", "missing ';'"),
+        ] {
+            let err = parse(code).unwrap_err();
+            assert!(err.kind().to_string().contains(message), "{code}: {err:?}");
+        }
+    }
+
+    #[test]
+    fn test_assert_statement() {
+        let statements = parse("assert a == 1;\nassert b, \"b is falsy\";").unwrap();
+        let messages = statements
+            .iter()
+            .map(|stmt| match stmt {
+                Statement::Assert(data) => data.message.is_some(),
+                _ => panic!("Assert expected, got {stmt:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(messages, [false, true]);
+        for (code, message) in [
+            ("assert;", "Expect expression"),
+            ("assert a,;", "Expect expression"),
+            ("assert a, b, c;", "missing ';'"),
+            ("assert a", "missing ';'"),
+        ] {
+            let err = parse(code).unwrap_err();
+            assert!(err.kind().to_string().contains(message), "{code}: {err:?}");
+        }
+    }
+
+    #[test]
+    fn test_rest_parameter() {
+        let statements = parse("fun f(a, ...b) {}\nfun g(...c) {}").unwrap();
+        let rest = statements
+            .iter()
+            .map(|stmt| {
+                let func = stmt.as_function().unwrap();
+                (
+                    func.params.len(),
+                    func.rest.as_ref().map(|x| x.lexeme.clone()),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rest,
+            [(1, Some("b".to_string())), (0, Some("c".to_string()))]
+        );
+        for (code, message) in [
+            ("fun f(...a, b) {}", "Rest parameter must be the last one"),
+            ("fun f(...) {}", "Expect rest parameter name"),
+            ("fun f(a...) {}", "Expect ')' after parameters"),
+        ] {
+            let err = parse(code).unwrap_err();
+            assert!(err.kind().to_string().contains(message), "{code}: {err:?}");
+        }
+    }
+
+    #[test]
+    fn test_spread_arguments() {
+        let statements = parse("f(1, ...a, ...[b]);").unwrap();
+        let Statement::Expression(data) = &statements[0] else {
+            panic!("Expression expected, got {:?}", statements[0]);
+        };
+        let Expression::Call(call) = data.expression.as_ref() else {
+            panic!("Call expected, got {:?}", data.expression);
+        };
+        let spreads = call
+            .arguments
+            .iter()
+            .map(|arg| arg.spread.is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(spreads, [false, true, true]);
+        for code in ["f(...);", "f(a...);"] {
+            assert!(parse(code).is_err(), "{code}");
+        }
+    }
+
+    #[test]
+    fn test_loop_labels() {
+        let statements = parse("outer: while (a) break outer;\nl: for (;;) continue;").unwrap();
+        let Statement::While(data) = &statements[0] else {
+            panic!("While expected, got {:?}", statements[0]);
+        };
+        assert_eq!(data.label.as_ref().unwrap().lexeme, "outer");
+        let Statement::Break(data) = data.body.as_ref() else {
+            panic!("Break expected, got {:?}", data.body);
+        };
+        assert_eq!(data.label.as_ref().unwrap().lexeme, "outer");
+        let Statement::While(data) = &statements[1] else {
+            panic!("While expected, got {:?}", statements[1]);
+        };
+        assert_eq!(data.label.as_ref().unwrap().lexeme, "l");
+        for (code, message) in [
+            ("a: print 1;", "Expect loop after label"),
+            ("a: { }", "Expect loop after label"),
+            ("while (a) break 1;", "missing ';'"),
+        ] {
+            let err = parse(code).unwrap_err();
+            assert!(err.kind().to_string().contains(message), "{code}: {err:?}");
+        }
+    }
+
+    #[test]
+    fn test_import_statement() {
+        let statements = parse("import \"lib/a.fox\";").unwrap();
+        let Statement::Import(data) = &statements[0] else {
+            panic!("Import expected, got {:?}", statements[0]);
+        };
+        assert_eq!(data.path.literal, Object::Text("lib/a.fox".into()));
+        assert!(data.statements.is_empty());
+        for (code, message) in [
+            ("import a;", "Expect module path after 'import'"),
+            ("import 1;", "Expect module path after 'import'"),
+            ("import \"a.fox\"", "missing ';'"),
+        ] {
+            let err = parse(code).unwrap_err();
+            assert!(err.kind().to_string().contains(message), "{code}: {err:?}");
+        }
+    }
+
+    #[test]
+    fn test_try_syntax_errors() {
+        let statements = parse("try { throw 1; } catch (e) { print e; }").unwrap();
+        let [Statement::Try(data)] = statements.as_slice() else {
+            panic!("Try statement expected, got {statements:?}");
+        };
+        assert_eq!(data.name.lexeme, "e");
+        assert!(matches!(data.body.as_ref(), Statement::Block(_)));
+        assert!(matches!(data.handler.as_ref(), Statement::Block(_)));
+
+        for (code, message) in [
+            ("try print 1; catch (e) {}", "Expect '{' after 'try'"),
+            ("try {}", "Expect 'catch' after try block"),
+            ("try {} catch e {}", "Expect '(' after 'catch'"),
+            ("try {} catch () {}", "Expect exception variable name"),
+            (
+                "try {} catch (e, f) {}",
+                "Expect ')' after exception variable name",
+            ),
+            ("try {} catch (e) print e;", "Expect '{' before catch body"),
+            ("throw;", "Expect expression"),
+            ("throw 1", "missing ';'"),
+        ] {
+            let err = parse(code).unwrap_err();
+            assert!(err.kind().to_string().contains(message), "{code}: {err:?}");
+        }
+    }
+
+    #[test]
+    fn test_destructuring_patterns() {
+        let statements = parse("var [a, b, ...rest] = list, c = 1;").unwrap();
+        let [Statement::VarPattern(data), Statement::Var(_)] = statements.as_slice() else {
+            panic!("Pattern declaration expected, got {statements:?}");
+        };
+        let names = data
+            .pattern
+            .bound_names()
+            .map(|name| name.lexeme.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "rest"]);
+        assert!(data.pattern.rest.is_some());
+
+        for code in ["[a, b] = [b, a];", "x = [a, ...b] = list;", "[] = list;"] {
+            let statements = parse(code).unwrap();
+            let [Statement::Expression(data)] = statements.as_slice() else {
+                panic!("Expression expected for {code}, got {statements:?}");
+            };
+            let mut expr = data.expression.as_ref();
+            if let Expression::Assign(data) = expr {
+                expr = &data.value;
+            }
+            assert!(matches!(expr, Expression::AssignPattern(_)), "{code}");
+        }
+        // array literals aren't patterns
+        for code in ["[a, b][0] = 1;", "print [a, b] == c;", "[a, 1];"] {
+            let statements = parse(code).unwrap();
+            assert!(
+                !format!("{statements:?}").contains("AssignPattern"),
+                "{code}"
+            );
+        }
+
+        for (code, message) in [
+            ("var [a, a] = list;", "Already a variable with this name"),
+            ("var a, [b, a] = list;", "Already a variable with this name"),
+            ("var [a, b];", "Expect '=' after destructuring pattern"),
+            ("var [a, ...b, c] = list;", "Expect ']' after pattern"),
+            ("var [a, 1] = list;", "Expect name in pattern"),
+            ("var [...] = list;", "Expect name after '...'"),
+            ("[a, 1] = list;", "Invalid assignment target"),
+        ] {
+            let err = parse(code).unwrap_err();
+            assert!(err.kind().to_string().contains(message), "{code}: {err:?}");
+        }
+    }
+
+    #[test]
+    fn test_anonymous_function_in_expression() {
+        for code in [
+            "var f = fun (x) { return x; };",
+            "print fun () {};",
+            "call(1, fun (x) { return x; });",
+        ] {
+            let err = parse(code).unwrap_err();
+            assert_eq!(
+                err.kind().to_string(),
+                "anonymous functions are not supported; declare a named function and reference it"
+            );
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Token expected for {err:?}");
+            };
+            assert_eq!(token.token_type, TokenType::Fun);
+            // token location points to the last character of the lexeme
+            let position = code.find("fun").unwrap() + "fun".len() - 1;
+            assert_eq!(token.code_location.absolute_position(), position);
+        }
+    }
+
+    #[test]
+    fn test_missing_semicolon_expression() {
+        assert_missing_semicolon("var a;\na = 1\nprint a;", 2);
+    }
+
+    #[test]
+    fn test_missing_semicolon_print() {
+        assert_missing_semicolon("print 1\nprint 2;", 1);
+    }
+
+    #[test]
+    fn test_missing_semicolon_var() {
+        assert_missing_semicolon("var a = 1\nvar b = 2;", 1);
+        assert_missing_semicolon("\nvar a\nvar b = 2;", 2);
+    }
+
+    #[test]
+    fn test_missing_semicolon_return() {
+        assert_missing_semicolon("fun f() {\n  return 1\n}", 2);
+        assert_missing_semicolon("fun f() {\n  return\n}", 2);
+    }
 }
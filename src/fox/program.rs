@@ -0,0 +1,1022 @@
+use crate::fox::{
+    CodeLocation, ErrorKind, FoxError, FoxResult, Object, Source, Token, TokenType, ast::*,
+};
+
+/// Header of the compiled program file
+const MAGIC: &[u8; 4] = b"FOXC";
+/// Layout version of the compiled program file, bump on any format change
+const FORMAT_VERSION: u8 = 27;
+/// Programs are compatible only with the interpreter that produced them
+const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Token types in serialization order, the index is the stored tag
+const TOKEN_TYPES: [TokenType; 62] = {
+    use TokenType::*;
+    [
+        LeftParenthesis,
+        RightParenthesis,
+        LeftBrace,
+        RightBrace,
+        LeftBracket,
+        RightBracket,
+        Colon,
+        Comma,
+        Dot,
+        DotDot,
+        DotDotEqual,
+        DotDotDot,
+        QuestionDot,
+        Semicolon,
+        Slash,
+        Star,
+        Percent,
+        Minus,
+        MinusMinus,
+        Plus,
+        PlusPlus,
+        Bang,
+        BangEqual,
+        Equal,
+        EqualEqual,
+        EqualGreater,
+        Greater,
+        GreaterEqual,
+        Less,
+        LessEqual,
+        Identifier,
+        String,
+        Number,
+        And,
+        Assert,
+        Break,
+        Catch,
+        Class,
+        Const,
+        Continue,
+        Defer,
+        Else,
+        False,
+        Fun,
+        For,
+        If,
+        Import,
+        In,
+        Is,
+        Match,
+        Nil,
+        Or,
+        Print,
+        Return,
+        Super,
+        This,
+        Throw,
+        True,
+        Try,
+        Var,
+        While,
+        Eof,
+    ]
+};
+
+/// Parsed & resolved script ready for execution,
+/// can be stored on disk to skip the front end on the next run
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    source_name: String,
+    source_hash: u64,
+    statements: Vec<Statement>,
+    locals: Vec<(Expression, usize)>,
+}
+
+impl Program {
+    pub(crate) fn new(
+        source_name: &str,
+        code: &Source,
+        statements: Vec<Statement>,
+        mut locals: Vec<(Expression, usize)>,
+    ) -> Self {
+        // stable order makes the output reproducible
+        locals.sort_by_key(|(expr, depth)| {
+            let position = expr.token().map(|x| x.code_location.absolute_position());
+            (position, *depth)
+        });
+        Self {
+            source_name: source_name.to_string(),
+            source_hash: source_hash(code),
+            statements,
+            locals,
+        }
+    }
+
+    pub fn source_name(&self) -> &str {
+        &self.source_name
+    }
+
+    pub fn source_hash(&self) -> u64 {
+        self.source_hash
+    }
+
+    pub(crate) fn statements(&self) -> &[Statement] {
+        &self.statements
+    }
+
+    /// Resolution table as (expression, scope depth) pairs
+    pub(crate) fn locals(&self) -> &[(Expression, usize)] {
+        &self.locals
+    }
+
+    /// Fails if the program wasn't compiled from the code
+    pub fn verify_source(&self, code: &Source) -> FoxResult<()> {
+        if source_hash(code) != self.source_hash {
+            return Err(cache_error(&format!(
+                "Compiled program is out of date with '{}', recompile it",
+                self.source_name
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> FoxResult<Vec<u8>> {
+        let mut writer = Writer::default();
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.u8(FORMAT_VERSION);
+        writer.str(CRATE_VERSION);
+        writer.str(&self.source_name);
+        writer.u64(self.source_hash);
+        writer.statements(&self.statements)?;
+        writer.usize(self.locals.len());
+        for (expr, depth) in &self.locals {
+            writer.expression(expr)?;
+            writer.usize(*depth);
+        }
+        Ok(writer.bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> FoxResult<Self> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(cache_error("Not a compiled fox program"));
+        }
+        let format = reader.u8()?;
+        let version = reader.str()?;
+        if format != FORMAT_VERSION || version != CRATE_VERSION {
+            return Err(cache_error(&format!(
+                "Program was compiled by fox-lang {version}, current version is {CRATE_VERSION}; recompile it"
+            )));
+        }
+        let source_name = reader.str()?;
+        let source_hash = reader.u64()?;
+        let statements = reader.statements()?;
+        let count = reader.usize()?;
+        let mut locals = Vec::new();
+        for _ in 0..count {
+            let expr = reader.expression()?;
+            let depth = reader.usize()?;
+            locals.push((expr, depth));
+        }
+        if reader.position != bytes.len() {
+            return Err(corrupted());
+        }
+        Ok(Self {
+            source_name,
+            source_hash,
+            statements,
+            locals,
+        })
+    }
+}
+
+/// FNV-1a hash, stays the same across platforms & toolchains
+fn source_hash(code: &Source) -> u64 {
+    code.iter()
+        .flat_map(|ch| (*ch as u32).to_le_bytes())
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+fn cache_error(message: &str) -> FoxError {
+    FoxError::error(ErrorKind::Cache(message.to_string()))
+}
+
+fn corrupted() -> FoxError {
+    cache_error("Compiled program is corrupted")
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    fn str(&mut self, value: &str) {
+        self.usize(value.len());
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn object(&mut self, value: &Object) -> FoxResult<()> {
+        match value {
+            Object::Nil => self.u8(0),
+            Object::Double(value) => {
+                self.u8(1);
+                self.bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            Object::Text(value) => {
+                self.u8(2);
+                self.str(value);
+            }
+            Object::Bool(value) => {
+                self.u8(3);
+                self.bool(*value);
+            }
+            value => {
+                return Err(FoxError::bug(&format!(
+                    "Literal {value:?} can't be stored in a compiled program"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn token(&mut self, token: &Token) -> FoxResult<()> {
+        self.u8(token.token_type as u8);
+        self.str(&token.lexeme);
+        self.object(&token.literal)?;
+        self.usize(token.code_location.line_number());
+        self.usize(token.code_location.absolute_position());
+        Ok(())
+    }
+
+    fn tokens(&mut self, tokens: &[Token]) -> FoxResult<()> {
+        self.usize(tokens.len());
+        tokens.iter().try_for_each(|token| self.token(token))
+    }
+
+    fn optional_token(&mut self, token: Option<&Token>) -> FoxResult<()> {
+        self.bool(token.is_some());
+        token.map_or(Ok(()), |token| self.token(token))
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) -> FoxResult<()> {
+        self.tokens(&pattern.names)?;
+        self.optional_token(pattern.rest.as_ref())
+    }
+
+    fn expression(&mut self, expr: &Expression) -> FoxResult<()> {
+        use Expression::*;
+        match expr {
+            Array(data) => {
+                self.u8(12);
+                self.token(&data.bracket)?;
+                self.expressions(&data.elements)
+            }
+            Assign(data) => {
+                self.u8(0);
+                self.token(&data.name)?;
+                self.expression(&data.value)
+            }
+            AssignPattern(data) => {
+                self.u8(17);
+                self.token(&data.bracket)?;
+                self.pattern(&data.pattern)?;
+                self.expression(&data.value)
+            }
+            Binary(data) => {
+                self.u8(1);
+                self.expression(&data.left)?;
+                self.token(&data.operator)?;
+                self.expression(&data.right)
+            }
+            Call(data) => {
+                self.u8(2);
+                self.expression(&data.callee)?;
+                self.token(&data.paren)?;
+                self.usize(data.arguments.len());
+                data.arguments.iter().try_for_each(|arg| {
+                    self.expression(&arg.value)?;
+                    self.optional_token(arg.spread.as_ref())
+                })
+            }
+            Get(data) => {
+                self.u8(3);
+                self.expression(&data.object)?;
+                self.token(&data.name)?;
+                self.bool(data.optional);
+                Ok(())
+            }
+            Chain(data) => {
+                self.u8(16);
+                self.expression(&data.expression)
+            }
+            Grouping(data) => {
+                self.u8(4);
+                self.expression(&data.expression)
+            }
+            Map(data) => {
+                self.u8(15);
+                self.token(&data.brace)?;
+                self.usize(data.entries.len());
+                data.entries.iter().try_for_each(|(key, value)| {
+                    self.expression(key)?;
+                    self.expression(value)
+                })
+            }
+            Index(data) => {
+                self.u8(13);
+                self.expression(&data.object)?;
+                self.token(&data.bracket)?;
+                self.expression(&data.index)
+            }
+            IndexSet(data) => {
+                self.u8(14);
+                self.expression(&data.object)?;
+                self.token(&data.bracket)?;
+                self.expression(&data.index)?;
+                self.expression(&data.value)
+            }
+            Literal(data) => {
+                self.u8(5);
+                self.object(&data.value)
+            }
+            Logical(data) => {
+                self.u8(6);
+                self.expression(&data.left)?;
+                self.token(&data.operator)?;
+                self.expression(&data.right)
+            }
+            Range(data) => {
+                self.u8(18);
+                self.expression(&data.start)?;
+                self.token(&data.operator)?;
+                self.expression(&data.end)
+            }
+            Set(data) => {
+                self.u8(7);
+                self.expression(&data.object)?;
+                self.token(&data.name)?;
+                self.expression(&data.value)
+            }
+            Super(data) => {
+                self.u8(8);
+                self.token(&data.keyword)?;
+                self.token(&data.method)
+            }
+            This(data) => {
+                self.u8(9);
+                self.token(&data.keyword)
+            }
+            Unary(data) => {
+                self.u8(10);
+                self.expression(&data.expression)?;
+                self.token(&data.operator)
+            }
+            Variable(data) => {
+                self.u8(11);
+                self.token(&data.name)
+            }
+        }
+    }
+
+    fn expressions(&mut self, exprs: &[Expression]) -> FoxResult<()> {
+        self.usize(exprs.len());
+        exprs.iter().try_for_each(|expr| self.expression(expr))
+    }
+
+    fn optional_expression(&mut self, expr: Option<&Expression>) -> FoxResult<()> {
+        self.bool(expr.is_some());
+        expr.map_or(Ok(()), |expr| self.expression(expr))
+    }
+
+    fn statement(&mut self, stmt: &Statement) -> FoxResult<()> {
+        use Statement::*;
+        match stmt {
+            Block(data) => {
+                self.u8(0);
+                self.token(&data.brace)?;
+                self.statements(&data.statements)?;
+                self.bool(data.synthetic);
+                Ok(())
+            }
+            Break(data) => {
+                self.u8(9);
+                self.token(&data.keyword)?;
+                self.optional_token(data.label.as_ref())
+            }
+            Class(data) => {
+                self.u8(1);
+                self.token(&data.name)?;
+                self.optional_expression(data.superclass.as_ref())?;
+                self.statements(&data.methods)?;
+                self.statements(&data.constants)
+            }
+            Expression(data) => {
+                self.u8(2);
+                self.expression(&data.expression)
+            }
+            Function(data) => {
+                self.u8(3);
+                self.token(&data.name)?;
+                self.tokens(&data.params)?;
+                self.optional_token(data.rest.as_ref())?;
+                self.statements(&data.body)?;
+                self.bool(data.getter);
+                Ok(())
+            }
+            If(data) => {
+                self.u8(4);
+                self.token(&data.keyword)?;
+                self.expression(&data.condition)?;
+                self.statement(&data.then_branch)?;
+                self.bool(data.else_branch.is_some());
+                data.else_branch
+                    .as_ref()
+                    .map_or(Ok(()), |stmt| self.statement(stmt))
+            }
+            Print(data) => {
+                self.u8(5);
+                self.token(&data.keyword)?;
+                self.expression(&data.expression)
+            }
+            Return(data) => {
+                self.u8(6);
+                self.token(&data.keyword)?;
+                self.optional_expression(data.value.as_deref())
+            }
+            Var(data) => {
+                self.u8(7);
+                self.token(&data.name)?;
+                self.optional_expression(data.initializer.as_deref())?;
+                self.bool(data.constant);
+                Ok(())
+            }
+            VarPattern(data) => {
+                self.u8(13);
+                self.token(&data.bracket)?;
+                self.pattern(&data.pattern)?;
+                self.expression(&data.initializer)?;
+                self.bool(data.constant);
+                Ok(())
+            }
+            While(data) => {
+                self.u8(8);
+                self.token(&data.keyword)?;
+                self.expression(&data.condition)?;
+                self.statement(&data.body)?;
+                self.expressions(&data.increments)?;
+                self.optional_token(data.label.as_ref())
+            }
+            Continue(data) => {
+                self.u8(10);
+                self.token(&data.keyword)?;
+                self.optional_token(data.label.as_ref())
+            }
+            ForIn(data) => {
+                self.u8(11);
+                self.token(&data.keyword)?;
+                self.token(&data.name)?;
+                self.token(&data.in_keyword)?;
+                self.expression(&data.iterable)?;
+                self.statement(&data.body)?;
+                self.optional_token(data.label.as_ref())
+            }
+            Match(data) => {
+                self.u8(12);
+                self.token(&data.keyword)?;
+                self.expression(&data.subject)?;
+                self.usize(data.arms.len());
+                for (pattern, stmt) in &data.arms {
+                    self.expression(pattern)?;
+                    self.statement(stmt)?;
+                }
+                self.bool(data.default.is_some());
+                data.default
+                    .as_ref()
+                    .map_or(Ok(()), |stmt| self.statement(stmt))
+            }
+            Throw(data) => {
+                self.u8(14);
+                self.token(&data.keyword)?;
+                self.expression(&data.value)
+            }
+            Try(data) => {
+                self.u8(15);
+                self.token(&data.keyword)?;
+                self.statement(&data.body)?;
+                self.token(&data.name)?;
+                self.statement(&data.handler)
+            }
+            Defer(data) => {
+                self.u8(16);
+                self.token(&data.keyword)?;
+                self.statement(&data.body)
+            }
+            Assert(data) => {
+                self.u8(17);
+                self.token(&data.keyword)?;
+                self.expression(&data.condition)?;
+                self.optional_expression(data.message.as_deref())
+            }
+            Import(data) => {
+                self.u8(18);
+                self.token(&data.keyword)?;
+                self.token(&data.path)?;
+                self.statements(&data.statements)
+            }
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement]) -> FoxResult<()> {
+        self.usize(statements.len());
+        statements.iter().try_for_each(|stmt| self.statement(stmt))
+    }
+}
+
+struct Reader<'l> {
+    bytes: &'l [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> FoxResult<&[u8]> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(corrupted)?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> FoxResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> FoxResult<u64> {
+        let bytes = self.take(8)?.try_into().map_err(|_| corrupted())?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn usize(&mut self) -> FoxResult<usize> {
+        usize::try_from(self.u64()?).map_err(|_| corrupted())
+    }
+
+    fn bool(&mut self) -> FoxResult<bool> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(corrupted()),
+        }
+    }
+
+    fn str(&mut self) -> FoxResult<String> {
+        let len = self.usize()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| corrupted())
+    }
+
+    fn object(&mut self) -> FoxResult<Object> {
+        let object = match self.u8()? {
+            0 => Object::Nil,
+            1 => {
+                let bytes = self.take(8)?.try_into().map_err(|_| corrupted())?;
+                Object::Double(f64::from_le_bytes(bytes))
+            }
+            2 => Object::Text(self.str()?.into()),
+            3 => Object::Bool(self.bool()?),
+            _ => return Err(corrupted()),
+        };
+        Ok(object)
+    }
+
+    fn token(&mut self) -> FoxResult<Token> {
+        let token_type = *TOKEN_TYPES.get(self.u8()? as usize).ok_or_else(corrupted)?;
+        let lexeme = self.str()?;
+        let literal = self.object()?;
+        let line = self.usize()?;
+        let position = self.usize()?;
+        Ok(Token {
+            token_type,
+            lexeme,
+            literal,
+            code_location: CodeLocation::new(line, position),
+        })
+    }
+
+    fn tokens(&mut self) -> FoxResult<Vec<Token>> {
+        let count = self.usize()?;
+        (0..count).map(|_| self.token()).collect()
+    }
+
+    fn optional_token(&mut self) -> FoxResult<Option<Token>> {
+        if self.bool()? {
+            Ok(Some(self.token()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn pattern(&mut self) -> FoxResult<Pattern> {
+        let names = self.tokens()?;
+        let rest = self.optional_token()?;
+        Ok(Pattern { names, rest })
+    }
+
+    fn boxed_expression(&mut self) -> FoxResult<Box<Expression>> {
+        self.expression().map(Box::new)
+    }
+
+    fn expression(&mut self) -> FoxResult<Expression> {
+        let expr = match self.u8()? {
+            0 => Expression::assign(self.token()?, self.boxed_expression()?),
+            1 => Expression::binary(
+                self.boxed_expression()?,
+                self.token()?,
+                self.boxed_expression()?,
+            ),
+            2 => Expression::call(self.boxed_expression()?, self.token()?, self.arguments()?),
+            3 => Expression::get(self.boxed_expression()?, self.token()?, self.bool()?),
+            4 => Expression::grouping(self.boxed_expression()?),
+            5 => Expression::literal(self.object()?),
+            6 => Expression::logical(
+                self.boxed_expression()?,
+                self.token()?,
+                self.boxed_expression()?,
+            ),
+            7 => Expression::set(
+                self.boxed_expression()?,
+                self.token()?,
+                self.boxed_expression()?,
+            ),
+            8 => Expression::super_expr(self.token()?, self.token()?),
+            9 => Expression::this(self.token()?),
+            10 => Expression::unary(self.boxed_expression()?, self.token()?),
+            11 => Expression::variable(self.token()?),
+            12 => Expression::array(self.token()?, self.expressions()?),
+            13 => Expression::index(
+                self.boxed_expression()?,
+                self.token()?,
+                self.boxed_expression()?,
+            ),
+            14 => Expression::index_set(
+                self.boxed_expression()?,
+                self.token()?,
+                self.boxed_expression()?,
+                self.boxed_expression()?,
+            ),
+            15 => {
+                let brace = self.token()?;
+                let count = self.usize()?;
+                let entries = (0..count)
+                    .map(|_| Ok((self.expression()?, self.expression()?)))
+                    .collect::<FoxResult<Vec<_>>>()?;
+                Expression::map(brace, entries)
+            }
+            16 => Expression::chain(self.boxed_expression()?),
+            17 => {
+                Expression::assign_pattern(self.token()?, self.pattern()?, self.boxed_expression()?)
+            }
+            18 => Expression::range(
+                self.boxed_expression()?,
+                self.token()?,
+                self.boxed_expression()?,
+            ),
+            _ => return Err(corrupted()),
+        };
+        Ok(expr)
+    }
+
+    fn expressions(&mut self) -> FoxResult<Vec<Expression>> {
+        let count = self.usize()?;
+        (0..count).map(|_| self.expression()).collect()
+    }
+
+    fn arguments(&mut self) -> FoxResult<Vec<Argument>> {
+        let count = self.usize()?;
+        (0..count)
+            .map(|_| {
+                let value = self.expression()?;
+                let spread = self.optional_token()?;
+                Ok(Argument { value, spread })
+            })
+            .collect()
+    }
+
+    fn optional_expression(&mut self) -> FoxResult<Option<Expression>> {
+        if self.bool()? {
+            Ok(Some(self.expression()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn statement(&mut self) -> FoxResult<Statement> {
+        let stmt = match self.u8()? {
+            0 => Statement::block(self.token()?, self.statements()?, self.bool()?),
+            1 => Statement::class(
+                self.token()?,
+                self.optional_expression()?,
+                self.statements()?,
+                self.statements()?,
+            ),
+            2 => Statement::expression(self.boxed_expression()?),
+            3 => Statement::function(
+                self.token()?,
+                self.tokens()?,
+                self.optional_token()?,
+                self.statements()?,
+                self.bool()?,
+            ),
+            4 => {
+                let keyword = self.token()?;
+                let condition = self.boxed_expression()?;
+                let then_branch = Box::new(self.statement()?);
+                let else_branch = if self.bool()? {
+                    Some(Box::new(self.statement()?))
+                } else {
+                    None
+                };
+                Statement::if_stmt(keyword, condition, then_branch, else_branch)
+            }
+            5 => Statement::print(self.token()?, self.boxed_expression()?),
+            6 => Statement::ret_fn(self.token()?, self.optional_expression()?.map(Box::new)),
+            7 => Statement::var(
+                self.token()?,
+                self.optional_expression()?.map(Box::new),
+                self.bool()?,
+            ),
+            8 => Statement::while_stmt(
+                self.token()?,
+                self.boxed_expression()?,
+                Box::new(self.statement()?),
+                self.expressions()?,
+                self.optional_token()?,
+            ),
+            9 => Statement::break_stmt(self.token()?, self.optional_token()?),
+            10 => Statement::continue_stmt(self.token()?, self.optional_token()?),
+            11 => Statement::for_in(
+                self.token()?,
+                self.token()?,
+                self.token()?,
+                self.boxed_expression()?,
+                Box::new(self.statement()?),
+                self.optional_token()?,
+            ),
+            12 => {
+                let keyword = self.token()?;
+                let subject = self.boxed_expression()?;
+                let count = self.usize()?;
+                let arms = (0..count)
+                    .map(|_| Ok((self.expression()?, self.statement()?)))
+                    .collect::<FoxResult<Vec<_>>>()?;
+                let default = if self.bool()? {
+                    Some(Box::new(self.statement()?))
+                } else {
+                    None
+                };
+                Statement::match_stmt(keyword, subject, arms, default)
+            }
+            13 => Statement::var_pattern(
+                self.token()?,
+                self.pattern()?,
+                self.boxed_expression()?,
+                self.bool()?,
+            ),
+            14 => Statement::throw_stmt(self.token()?, self.boxed_expression()?),
+            15 => Statement::try_stmt(
+                self.token()?,
+                Box::new(self.statement()?),
+                self.token()?,
+                Box::new(self.statement()?),
+            ),
+            16 => Statement::defer(self.token()?, Box::new(self.statement()?)),
+            17 => Statement::assert_stmt(
+                self.token()?,
+                self.boxed_expression()?,
+                self.optional_expression()?.map(Box::new),
+            ),
+            18 => Statement::import(self.token()?, self.token()?, self.statements()?),
+            _ => return Err(corrupted()),
+        };
+        Ok(stmt)
+    }
+
+    fn statements(&mut self) -> FoxResult<Vec<Statement>> {
+        let count = self.usize()?;
+        (0..count).map(|_| self.statement()).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::fox::{
+        Fox, FoxConfig, Parser, Scanner, interpreter::Interpreter, resolver::Resolver,
+    };
+
+    const CODE: &str = r#"class Base {
+    init(x) {
+        this.x = x;
+    }
+    get() {
+        return this.x;
+    }
+}
+class Derived < Base {
+    const STEP = 1;
+    get() {
+        return super.get() + 1;
+    }
+    doubled {
+        return this.x * 2;
+    }
+}
+var d = Derived(1);
+d.x = -2;
+var none = nil;
+const LIMIT = 3;
+print none?.x.y;
+fun sum(n) {
+    if (n <= 0 or false) return 0;
+    else {
+        return n + sum(n - 1);
+    }
+}
+var i = 0;
+top: while (i < LIMIT and true) {
+    i = i + 1;
+    if (i > 10) break top;
+    for (var j = 0; j < 2; j = j + 1, --i, ++i) {
+        if (j == 0) continue;
+    }
+}
+for (var ch in "ab") {
+    if (ch == "a") continue;
+    print ch;
+}
+var text = [1, [2]];
+text[1][0] = text[0];
+var [first, ...others] = text;
+[first, others] = [others, first];
+var m = {"k": text, 1: {}};
+assert m["k"] == text, "map entry";
+assert true;
+assert len(0..=2) == 3;
+fun count(first, ...items) {
+    return len(items);
+}
+assert count(1, 2, 3) == 2 and count(...[1, 2], 3) == 2;
+assert d is Base and !(1 is String) and d.doubled == -4 and Derived.STEP == 1;
+text = "a" + "b";
+print (sum(3) * 2.5 % 100);
+print d.get();
+print !nil;
+match (i) {
+    -1 => print "negative";
+    3 => print "three";
+    else => print "other";
+}
+{
+    var local = i;
+    defer print "bye";
+    fun show() {
+        print local;
+    }
+    show();
+}
+try {
+    throw "no " + text;
+} catch (err) {
+    print err;
+}
+print text;"#;
+
+    fn compile(code: &str) -> Program {
+        let source = code.chars().collect::<Vec<_>>();
+        let tokens = Scanner::with_source(&source).scan_tokens().unwrap();
+        let statements = Parser::new(&tokens).parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        Resolver::with(&mut interpreter)
+            .resolve_statements(&statements)
+            .unwrap();
+        Program::new(
+            "test.fox",
+            &source,
+            statements,
+            interpreter.resolved_locals(),
+        )
+    }
+
+    fn execute(program: &Program) -> String {
+        let mut interpreter = Interpreter::new();
+        for (expr, depth) in program.locals() {
+            interpreter.resolve(expr.clone(), *depth).unwrap();
+        }
+        interpreter.capture_output();
+        interpreter.interpret(program.statements()).unwrap();
+        interpreter.take_output().unwrap()
+    }
+
+    #[test]
+    fn test_token_types_table() {
+        for (index, token_type) in TOKEN_TYPES.iter().enumerate() {
+            assert_eq!(*token_type as usize, index);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let program = compile(CODE);
+        let bytes = program.to_bytes().unwrap();
+        let loaded = Program::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded, program);
+        assert_eq!(loaded.to_bytes().unwrap(), bytes);
+
+        // resolution is identical, not only the order of entries
+        let table = |program: &Program| program.locals().iter().cloned().collect::<HashMap<_, _>>();
+        assert_eq!(table(&loaded), table(&program));
+        assert!(!loaded.locals().is_empty());
+
+        let output = execute(&program);
+        assert_eq!(output, "nil\nb\n15\n-1\ntrue\nthree\n3\nbye\nno ab\nab\n");
+        assert_eq!(execute(&loaded), output);
+    }
+
+    #[test]
+    fn test_imports_embedded() {
+        let path = "tests/fixtures/imports/main.fox";
+        let config = FoxConfig {
+            source_name: path.to_string(),
+            ..Default::default()
+        };
+        let code = std::fs::read_to_string(path).unwrap();
+        let program = Fox::with_config(code.chars().collect(), config)
+            .compile()
+            .unwrap();
+        // the hash covers the script only
+        assert!(
+            program
+                .verify_source(&code.chars().collect::<Vec<_>>())
+                .is_ok()
+        );
+        let loaded = Program::from_bytes(&program.to_bytes().unwrap()).unwrap();
+        assert_eq!(loaded, program);
+        assert_eq!(execute(&loaded), "counter loaded\nhi fox\n42\n");
+    }
+
+    #[test]
+    fn test_source_verification() {
+        let program = compile(CODE);
+        let source = CODE.chars().collect::<Vec<_>>();
+        assert!(program.verify_source(&source).is_ok());
+        let changed = format!("{CODE}\n").chars().collect::<Vec<_>>();
+        let err = program.verify_source(&changed).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Cache(_)));
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        let mut bytes = compile("print 1;").to_bytes().unwrap();
+        // the version string follows the magic, the format byte & its length
+        let offset = MAGIC.len() + 1 + 8;
+        bytes[offset] = b'x';
+        let err = Program::from_bytes(&bytes).unwrap_err();
+        let ErrorKind::Cache(message) = err.kind() else {
+            panic!("Cache error expected, found {err:?}");
+        };
+        assert!(message.contains("recompile"), "{message}");
+
+        let mut bytes = compile("print 1;").to_bytes().unwrap();
+        bytes[MAGIC.len()] = FORMAT_VERSION + 1;
+        assert!(Program::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        let bytes = compile(CODE).to_bytes().unwrap();
+        for len in [0, 3, bytes.len() / 2, bytes.len() - 1] {
+            let err = Program::from_bytes(&bytes[..len]).unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::Cache(_)), "{len}");
+        }
+        assert!(Program::from_bytes(b"print 1;").is_err());
+    }
+}
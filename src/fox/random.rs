@@ -0,0 +1,59 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Pseudo-random numbers of the `random` builtins (SplitMix64),
+/// seeded by the time unless the script fixes the sequence
+///
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn with_seed(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::with_seed(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = self.state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        value ^ (value >> 31)
+    }
+
+    /// Number in [0, 1), the mantissa bits only so it can't round up to 1
+    pub fn next_unit(&mut self) -> f64 {
+        (self.next_u64() >> 40) as f64 / (1u64 << 24) as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut first = Random::with_seed(7);
+        let mut second = Random::with_seed(7);
+        let sequence = (0..5).map(|_| first.next_u64()).collect::<Vec<_>>();
+        assert_eq!(
+            sequence,
+            (0..5).map(|_| second.next_u64()).collect::<Vec<_>>()
+        );
+        assert_ne!(sequence[0], Random::with_seed(8).next_u64());
+    }
+
+    #[test]
+    fn test_unit_range() {
+        let mut random = Random::with_seed(1);
+        assert!((0..10_000).all(|_| (0.0..1.0).contains(&random.next_unit())));
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt::Display;
+
+/// Lazy arithmetic progression of numbers
+/// produced by `range` when the array would be too large
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumberRange {
+    start: f64,
+    end: f64,
+    step: f64,
+    len: usize,
+}
+
+impl NumberRange {
+    /// Empty when the step sign doesn't match the direction
+    pub fn new(start: f64, end: f64, step: f64) -> Self {
+        let count = ((end - start) / step).ceil();
+        Self::with_count(start, end, step, count)
+    }
+
+    /// Range reaching the end if the steps land on it, `start..=end`
+    pub fn inclusive(start: f64, end: f64, step: f64) -> Self {
+        let count = ((end - start) / step).floor() + 1.0;
+        let mut range = Self::with_count(start, end, step, count);
+        // the exclusive end keeps the description valid for `range`
+        range.end = start + range.len as f64 * step;
+        range
+    }
+
+    fn with_count(start: f64, end: f64, step: f64, count: f64) -> Self {
+        let len = if count.is_finite() && count > 0.0 {
+            count as usize
+        } else {
+            0
+        };
+        Self {
+            start,
+            end,
+            step,
+            len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<f64> {
+        // multiplication instead of accumulation keeps fractional steps precise
+        (index < self.len).then_some(self.start + index as f64 * self.step)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = f64> + '_ {
+        (0..self.len).filter_map(|index| self.get(index))
+    }
+}
+
+impl std::hash::Hash for NumberRange {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.start.to_bits().hash(state);
+        self.end.to_bits().hash(state);
+        self.step.to_bits().hash(state);
+    }
+}
+
+impl Display for NumberRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "range({}, {}, {})", self.start, self.end, self.step)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ascending() {
+        let range = NumberRange::new(0.0, 5.0, 2.0);
+        assert_eq!(range.iter().collect::<Vec<_>>(), vec![0.0, 2.0, 4.0]);
+    }
+
+    #[test]
+    fn test_descending() {
+        let range = NumberRange::new(3.0, 0.0, -1.0);
+        assert_eq!(range.iter().collect::<Vec<_>>(), vec![3.0, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn test_fractional_step() {
+        let range = NumberRange::new(0.0, 1.0, 0.25);
+        assert_eq!(range.iter().collect::<Vec<_>>(), vec![0.0, 0.25, 0.5, 0.75]);
+    }
+
+    #[test]
+    fn test_direction_mismatch_is_empty() {
+        assert!(NumberRange::new(0.0, 10.0, -1.0).is_empty());
+        assert!(NumberRange::new(10.0, 0.0, 1.0).is_empty());
+        assert!(NumberRange::new(1.0, 1.0, 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_inclusive() {
+        let range = NumberRange::inclusive(1.0, 3.0, 1.0);
+        assert_eq!(range.iter().collect::<Vec<_>>(), vec![1.0, 2.0, 3.0]);
+        assert_eq!(range, NumberRange::new(1.0, 4.0, 1.0));
+        let range = NumberRange::inclusive(0.5, 2.0, 1.0);
+        assert_eq!(range.iter().collect::<Vec<_>>(), vec![0.5, 1.5]);
+        assert_eq!(NumberRange::inclusive(2.0, 2.0, 1.0).len(), 1);
+        assert!(NumberRange::inclusive(10.0, 0.0, 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_get() {
+        let range = NumberRange::new(0.0, 10_000_000.0, 1.0);
+        assert_eq!(range.len(), 10_000_000);
+        assert_eq!(range.get(9_999_999), Some(9_999_999.0));
+        assert_eq!(range.get(10_000_000), None);
+    }
+}
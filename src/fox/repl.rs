@@ -0,0 +1,139 @@
+use crate::fox::{
+    Completion, Fox, FoxConfig, FoxResult, Parser, Scanner, catch_panic, completion,
+    interpreter::Interpreter, resolver::Resolver,
+};
+
+/// Interactive session executing the code line by line,
+/// the globals defined by a line are available to the next ones
+pub struct Repl {
+    // keeps the entered code for error messages
+    fox: Fox,
+    interpreter: Interpreter,
+    // length of the code scanned successfully with the files it imported,
+    // the lines are scanned in place so the token locations never repeat
+    accepted: usize,
+    // count of the accepted lines, the imported files aren't numbered with them
+    lines: usize,
+}
+
+impl Repl {
+    pub fn with_config(config: FoxConfig) -> Self {
+        let fox = Fox::with_config(Vec::new(), config);
+        let interpreter = fox.make_interpreter();
+        Self {
+            fox,
+            interpreter,
+            accepted: 0,
+            lines: 0,
+        }
+    }
+
+    /// Session state to describe the errors & warnings with
+    pub fn fox(&self) -> &Fox {
+        &self.fox
+    }
+
+    pub fn eval(&mut self, line: &str) -> FoxResult<()> {
+        // the rejected line is kept until now to describe its error
+        self.fox.code.truncate(self.accepted);
+        self.fox.modules.truncate(self.accepted);
+        let start = self.accepted;
+        self.fox.code.extend(line.chars());
+        if !line.ends_with('\n') {
+            self.fox.code.push('\n');
+        }
+        let tokens =
+            Scanner::with_source_at(&self.fox.code, start, self.lines + 1).scan_tokens()?;
+        self.accepted = self.fox.code.len();
+        self.lines += self.fox.code[start..]
+            .iter()
+            .filter(|ch| **ch == '\n')
+            .count();
+
+        let mut statements = Parser::new(&tokens).parse()?;
+        // imported relative to the working directory
+        self.fox.load_imports(&mut statements)?;
+        self.accepted = self.fox.code.len();
+        let mut resolver = Resolver::with(&mut self.interpreter);
+        for kind in self.fox.config.warn.iter().chain(&self.fox.config.deny) {
+            resolver.enable_warning(*kind);
+        }
+        if self.fox.config.strict_globals {
+            resolver.enable_strict_globals();
+        }
+        let result = resolver
+            .resolve_statements(&statements)
+            .and_then(|_| catch_panic(|| self.interpreter.interpret(&statements)));
+        self.fox.warnings = self.interpreter.take_warnings();
+        result
+    }
+
+    /// Candidates for the identifier ending the text as if it was the next line
+    pub fn completions(&self, text: &str) -> Vec<Completion> {
+        let mut code = self.fox.code[..self.accepted].to_vec();
+        code.extend(text.chars());
+        let globals = completion::global_completions(&self.interpreter);
+        completion::complete(&code, code.len(), &globals)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fox::{CompletionKind, ErrorKind};
+
+    #[test]
+    fn test_globals_persist() {
+        let mut repl = Repl::with_config(FoxConfig::default());
+        repl.eval("var total = 1;").unwrap();
+        repl.eval("fun add(x) { total = total + x; }").unwrap();
+        repl.eval("add(2);").unwrap();
+        repl.eval("var sum = total;").unwrap();
+        let candidates = repl.completions("print add(to");
+        assert_eq!(
+            candidates,
+            [Completion::new("total", CompletionKind::Variable, None)]
+        );
+        let candidates = repl.completions("ad");
+        assert_eq!(
+            candidates,
+            [Completion::new("add", CompletionKind::Function, Some(1))]
+        );
+    }
+
+    #[test]
+    fn test_import() {
+        let mut repl = Repl::with_config(FoxConfig::default());
+        repl.eval("var a = 1;").unwrap();
+        repl.eval("import \"tests/fixtures/imports/lib/broken.fox\";")
+            .unwrap();
+        repl.eval("import \"tests/fixtures/imports/lib/broken.fox\";")
+            .unwrap();
+        let err = repl.eval("print half(nil) + a;").unwrap_err();
+        let description = repl.fox().error_description(&err);
+        assert!(
+            description.contains("broken.fox:\n2 |    return x / 2;"),
+            "{description}"
+        );
+        // the lines are numbered without the imported file
+        let err = repl.eval("print b;").unwrap_err();
+        let description = repl.fox().error_description(&err);
+        assert!(description.starts_with("5 |print b;"), "{description}");
+        let err = repl.eval("import \"missing.fox\";").unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Resolver(_)));
+    }
+
+    #[test]
+    fn test_rejected_line() {
+        let mut repl = Repl::with_config(FoxConfig::default());
+        repl.eval("var a = 1;").unwrap();
+        let err = repl.eval("print \"open").unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::UnterminatedString));
+        assert!(repl.fox().error_description(&err).contains("print \"open"));
+        // the unfinished string doesn't break the next lines
+        repl.eval("var b = a + 1;").unwrap();
+        let err = repl.eval("print c;").unwrap_err();
+        let description = repl.fox().error_description(&err);
+        assert!(description.contains("print c;"), "{description}");
+    }
+}
@@ -1,11 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::fox::{
-    FoxError, FoxResult, KEYWORD_SUPER, KEYWORD_THIS, ast::*, class::INITIALIZER_NAME,
-    interpreter::Interpreter, token::Token,
+    ErrorKind, FoxError, FoxResult, KEYWORD_SUPER, KEYWORD_THIS, Object, Warning, WarningKind,
+    ast::*, class::INITIALIZER_NAME, interpreter::Interpreter, token::Token,
 };
 
-type Scope = HashMap<String, bool>;
+#[derive(Default)]
+struct Scope {
+    // declared names, marked true once defined
+    names: HashMap<String, bool>,
+    // names declared with `const`
+    constants: HashSet<String>,
+}
 
 #[derive(Clone, Copy)]
 enum FuncType {
@@ -27,6 +33,14 @@ pub struct Resolver<'l> {
     scopes: Vec<Scope>,
     current_function: FuncType,
     current_class: ClassType,
+    enabled_warnings: HashSet<WarningKind>,
+    // labels of the loops enclosing the current statement within the function
+    loops: Vec<Option<String>>,
+    strict_globals: bool,
+    // names declared at the top level of the script
+    script_globals: HashSet<String>,
+    // top level names declared with `const`
+    script_constants: HashSet<String>,
 }
 
 impl<'l> Resolver<'l> {
@@ -36,11 +50,75 @@ impl<'l> Resolver<'l> {
             scopes: Default::default(),
             current_function: FuncType::None,
             current_class: ClassType::None,
+            enabled_warnings: HashSet::new(),
+            loops: Vec::new(),
+            strict_globals: false,
+            script_globals: HashSet::new(),
+            script_constants: HashSet::new(),
+        }
+    }
+
+    /// Resolver continuing the scopes of the running code, outermost first,
+    /// for the code evaluated at runtime
+    pub fn with_scopes(interpreter: &'l mut Interpreter, scopes: Vec<Vec<String>>) -> Self {
+        let mut resolver = Self::with(interpreter);
+        for names in scopes {
+            if names.iter().any(|name| name == KEYWORD_SUPER) {
+                resolver.current_class = ClassType::Subclass;
+            } else if names.iter().any(|name| name == KEYWORD_THIS)
+                && matches!(resolver.current_class, ClassType::None)
+            {
+                resolver.current_class = ClassType::Class;
+            }
+            resolver.scopes.push(Scope {
+                names: names.into_iter().map(|name| (name, true)).collect(),
+                constants: HashSet::new(),
+            });
+        }
+        resolver
+    }
+
+    pub fn resolve_expression(&mut self, expr: &Expression) -> FoxResult<()> {
+        self.resolve_expr(expr)
+    }
+
+    /// Globals must be builtins, declared by the host or defined by the script
+    pub fn enable_strict_globals(&mut self) {
+        self.strict_globals = true;
+    }
+
+    /// Enables opt-in static analysis warnings
+    pub fn enable_warning(&mut self, kind: WarningKind) {
+        self.enabled_warnings.insert(kind);
+    }
+
+    fn warn(&mut self, kind: WarningKind, token: &Token, message: &str) -> FoxResult<()> {
+        if self.interpreter.is_denied(kind) {
+            return Err(FoxError::resolver(Some(token.clone()), message));
+        }
+        self.interpreter
+            .add_warning(Warning::with_token(kind, message, token));
+        Ok(())
+    }
+
+    fn check_missing_return(&mut self, func: &FunctionStmt, func_type: FuncType) -> FoxResult<()> {
+        if !self.enabled_warnings.contains(&WarningKind::MissingReturn)
+            || matches!(func_type, FuncType::Initializer)
+            || !contains_value_return(&func.body)
+        {
+            return Ok(());
         }
+        let fallback = func.name.code_location.line_number();
+        let Some(line) = fallthrough_line(&func.body, fallback) else {
+            return Ok(());
+        };
+        let message =
+            format!("not all paths return a value; the path ending at line {line} returns nil");
+        self.warn(WarningKind::MissingReturn, &func.name, &message)
     }
 
     fn begin_scope(&mut self) {
-        self.scopes.push(Scope::new());
+        self.scopes.push(Scope::default());
     }
 
     fn end_scope(&mut self) {
@@ -51,14 +129,14 @@ impl<'l> Resolver<'l> {
         let Some(scope) = self.scopes.last_mut() else {
             return Ok(());
         };
-        if scope.contains_key(&name.lexeme) {
+        if scope.names.contains_key(&name.lexeme) {
             let err = FoxError::resolver(
                 Some(name.clone()),
                 "Already a variable with this name in this scope",
             );
             return Err(err);
         }
-        scope.insert(name.lexeme.clone(), false);
+        scope.names.insert(name.lexeme.clone(), false);
         Ok(())
     }
 
@@ -70,16 +148,48 @@ impl<'l> Resolver<'l> {
         let Some(scope) = self.scopes.last_mut() else {
             return;
         };
-        scope.insert(lexeme.to_string(), true);
+        scope.names.insert(lexeme.to_string(), true);
     }
 
     pub fn resolve_statements(&mut self, statements: &[Statement]) -> FoxResult<()> {
+        if self.scopes.is_empty() {
+            // functions may refer globals declared below them
+            self.collect_script_globals(statements);
+        }
         for stmt in statements {
             self.resolve_stmt(stmt)?;
         }
         Ok(())
     }
 
+    fn collect_script_globals(&mut self, statements: &[Statement]) {
+        for stmt in statements {
+            let names = match stmt {
+                Statement::Class(data) => vec![&data.name],
+                Statement::Function(data) => vec![&data.name],
+                Statement::Var(data) => vec![&data.name],
+                Statement::VarPattern(data) => data.pattern.bound_names().collect(),
+                // the module globals are merged into the script ones
+                Statement::Import(data) => {
+                    self.collect_script_globals(&data.statements);
+                    continue;
+                }
+                _ => continue,
+            };
+            let constant = match stmt {
+                Statement::Var(data) => data.constant,
+                Statement::VarPattern(data) => data.constant,
+                _ => false,
+            };
+            for name in names {
+                self.script_globals.insert(name.lexeme.clone());
+                if constant {
+                    self.script_constants.insert(name.lexeme.clone());
+                }
+            }
+        }
+    }
+
     fn resolve_stmt(&mut self, stmt: &Statement) -> FoxResult<()> {
         stmt.accept(self)
     }
@@ -90,36 +200,242 @@ impl<'l> Resolver<'l> {
 
     fn resolve_local(&mut self, expr: Expression, name: &Token) -> FoxResult<()> {
         for (i, scope) in self.scopes.iter().enumerate().rev() {
-            if scope.contains_key(&name.lexeme) {
-                self.interpreter.resolve(expr, self.scopes.len() - i - 1)?;
-                break;
+            if scope.names.contains_key(&name.lexeme) {
+                return self.interpreter.resolve(expr, self.scopes.len() - i - 1);
             }
         }
+        self.check_global(name)
+    }
+
+    fn check_global(&self, name: &Token) -> FoxResult<()> {
+        let lexeme = &name.lexeme;
+        // inside a subclass the name may be an inherited constant, known at runtime only
+        if !self.strict_globals
+            || self.is_local(KEYWORD_SUPER)
+            || self.script_globals.contains(lexeme)
+            || self.declared_arity(lexeme).is_some()
+            || self
+                .interpreter
+                .globals()
+                .borrow()
+                .get_at(0, lexeme)
+                .is_ok()
+        {
+            return Ok(());
+        }
+        let kind = ErrorKind::UndefinedVariable(lexeme.clone());
+        Err(FoxError::token(kind, Some(name.clone())))
+    }
+
+    /// Arity of the host global unless the script defines its own global with the name
+    fn declared_arity(&self, name: &str) -> Option<Option<usize>> {
+        if self.script_globals.contains(name) {
+            return None;
+        }
+        self.interpreter
+            .declared_globals()
+            .iter()
+            .find(|declaration| declaration.name == name)
+            .map(|declaration| declaration.arity)
+    }
+
+    fn is_local(&self, name: &str) -> bool {
+        self.scopes
+            .iter()
+            .any(|scope| scope.names.contains_key(name))
+    }
+
+    /// Whether the innermost declaration visible by the name is a constant
+    fn is_constant(&self, name: &str) -> bool {
+        match self
+            .scopes
+            .iter()
+            .rev()
+            .find(|scope| scope.names.contains_key(name))
+        {
+            Some(scope) => scope.constants.contains(name),
+            None => self.script_constants.contains(name),
+        }
+    }
+
+    fn check_inside_loop(&self, keyword: &Token, label: Option<&Token>) -> FoxResult<()> {
+        if self.loops.is_empty() {
+            let message = format!("Can't use '{}' outside of a loop", keyword.lexeme);
+            return Err(FoxError::resolver(Some(keyword.clone()), &message));
+        }
+        if let Some(label) = label
+            && !self.loops.contains(&Some(label.lexeme.clone()))
+        {
+            let message = format!("No enclosing loop labeled '{}'", label.lexeme);
+            return Err(FoxError::resolver(Some(label.clone()), &message));
+        }
         Ok(())
     }
 
+    /// Resolves the loop body, nested loops can't reuse the label
+    fn resolve_loop_body(&mut self, body: &Statement, label: Option<&Token>) -> FoxResult<()> {
+        if let Some(label) = label
+            && self.loops.contains(&Some(label.lexeme.clone()))
+        {
+            let message = format!(
+                "Label '{}' is already used by an enclosing loop",
+                label.lexeme
+            );
+            return Err(FoxError::resolver(Some(label.clone()), &message));
+        }
+        self.loops.push(label.map(|label| label.lexeme.clone()));
+        let result = self.resolve_stmt(body);
+        _ = self.loops.pop();
+        result
+    }
+
     fn resolve_function(&mut self, func: &FunctionStmt, func_type: FuncType) -> FoxResult<()> {
+        self.check_missing_return(func, func_type)?;
         let enclosing_function = self.current_function;
         self.current_function = func_type;
+        // loops don't cross the function boundary
+        let enclosing_loops = std::mem::take(&mut self.loops);
         self.begin_scope();
-        for param in &func.params {
+        for param in func.params.iter().chain(&func.rest) {
             self.declare(param)?;
             self.define(param);
         }
         self.resolve_statements(&func.body)?;
         self.end_scope();
         self.current_function = enclosing_function;
+        self.loops = enclosing_loops;
         Ok(())
     }
 }
 
+/// Checks if there is `return <value>;` in the function body,
+/// nested declarations have their own returns
+fn contains_value_return(statements: &[Statement]) -> bool {
+    statements.iter().any(|stmt| match stmt {
+        Statement::Return(data) => data.value.is_some(),
+        Statement::Block(data) => contains_value_return(&data.statements),
+        Statement::If(data) => {
+            contains_value_return(std::slice::from_ref(&data.then_branch))
+                || data
+                    .else_branch
+                    .as_ref()
+                    .is_some_and(|stmt| contains_value_return(std::slice::from_ref(stmt)))
+        }
+        Statement::While(data) => contains_value_return(std::slice::from_ref(&data.body)),
+        Statement::ForIn(data) => contains_value_return(std::slice::from_ref(&data.body)),
+        Statement::Try(data) => {
+            contains_value_return(std::slice::from_ref(&data.body))
+                || contains_value_return(std::slice::from_ref(&data.handler))
+        }
+        Statement::Match(data) => {
+            data.arms
+                .iter()
+                .any(|(_, stmt)| contains_value_return(std::slice::from_ref(stmt)))
+                || data
+                    .default
+                    .as_ref()
+                    .is_some_and(|stmt| contains_value_return(std::slice::from_ref(stmt)))
+        }
+        _ => false,
+    })
+}
+
+fn always_returns_value(stmt: &Statement) -> bool {
+    match stmt {
+        Statement::Return(data) => data.value.is_some(),
+        // never falls through either
+        Statement::Throw(_) => true,
+        Statement::Try(data) => {
+            always_returns_value(&data.body) && always_returns_value(&data.handler)
+        }
+        Statement::Block(data) => data.statements.iter().any(always_returns_value),
+        Statement::If(data) => {
+            always_returns_value(&data.then_branch)
+                && data
+                    .else_branch
+                    .as_ref()
+                    .is_some_and(|stmt| always_returns_value(stmt))
+        }
+        Statement::Match(data) => {
+            data.default
+                .as_ref()
+                .is_some_and(|stmt| always_returns_value(stmt))
+                && data.arms.iter().all(|(_, stmt)| always_returns_value(stmt))
+        }
+        // the loop body may be skipped
+        _ => false,
+    }
+}
+
+/// Line of the last statement of some path that doesn't return a value
+fn fallthrough_line(statements: &[Statement], fallback: usize) -> Option<usize> {
+    for stmt in statements {
+        if always_returns_value(stmt) {
+            return None;
+        }
+        if let Statement::Return(data) = stmt {
+            return Some(data.keyword.code_location.line_number());
+        }
+    }
+    let Some(last) = statements.last() else {
+        return Some(fallback);
+    };
+    let line = last.line_number().unwrap_or(fallback);
+    match last {
+        Statement::Block(data) => fallthrough_line(&data.statements, fallback),
+        Statement::Try(data) => fallthrough_line(std::slice::from_ref(&data.body), line)
+            .or(fallthrough_line(std::slice::from_ref(&data.handler), line)),
+        Statement::If(data) => {
+            let then_line = fallthrough_line(std::slice::from_ref(&data.then_branch), line);
+            match &data.else_branch {
+                Some(stmt) => then_line.or(fallthrough_line(std::slice::from_ref(stmt), line)),
+                None => then_line.or(Some(line)),
+            }
+        }
+        Statement::Match(data) => {
+            let arm_line = data
+                .arms
+                .iter()
+                .map(|(_, stmt)| stmt)
+                .chain(data.default.as_deref())
+                .find_map(|stmt| fallthrough_line(std::slice::from_ref(stmt), line));
+            match &data.default {
+                Some(_) => arm_line,
+                // no arm may match
+                None => arm_line.or(Some(line)),
+            }
+        }
+        _ => Some(line),
+    }
+}
+
 impl<'l> ExpressionVisitor<()> for Resolver<'l> {
     fn visit_assign(&mut self, data: &AssignExpr) -> FoxResult<()> {
+        if self.is_constant(&data.name.lexeme) {
+            let message = format!("Can't assign to constant '{}'", data.name.lexeme);
+            return Err(FoxError::resolver(Some(data.name.clone()), &message));
+        }
         self.resolve_expr(&data.value)?;
         let expr = Expression::Assign(data.clone());
         self.resolve_local(expr, &data.name)
     }
 
+    fn visit_assign_pattern(&mut self, data: &AssignPatternExpr) -> FoxResult<()> {
+        if let Some(name) = data
+            .pattern
+            .bound_names()
+            .find(|name| self.is_constant(&name.lexeme))
+        {
+            let message = format!("Can't assign to constant '{}'", name.lexeme);
+            return Err(FoxError::resolver(Some(name.clone()), &message));
+        }
+        self.resolve_expr(&data.value)?;
+        for name in data.pattern.bound_names() {
+            self.resolve_local(Expression::variable(name.clone()), name)?;
+        }
+        Ok(())
+    }
+
     fn visit_binary(&mut self, data: &BinaryExpr) -> FoxResult<()> {
         self.resolve_expr(&data.left)?;
         self.resolve_expr(&data.right)
@@ -128,7 +444,20 @@ impl<'l> ExpressionVisitor<()> for Resolver<'l> {
     fn visit_call(&mut self, data: &CallExpr) -> FoxResult<()> {
         self.resolve_expr(&data.callee)?;
         for arg in &data.arguments {
-            self.resolve_expr(arg)?;
+            self.resolve_expr(&arg.value)?;
+        }
+        // the count of the spread arguments is known at runtime only
+        if let Expression::Variable(callee) = data.callee.as_ref()
+            && data.arguments.iter().all(|arg| arg.spread.is_none())
+            && !self.is_local(&callee.name.lexeme)
+            && let Some(Some(arity)) = self.declared_arity(&callee.name.lexeme)
+            && arity != data.arguments.len()
+        {
+            let message = format!(
+                "Expected {arity} arguments but got {}",
+                data.arguments.len()
+            );
+            return Err(FoxError::resolver(Some(data.paren.clone()), &message));
         }
         Ok(())
     }
@@ -146,6 +475,11 @@ impl<'l> ExpressionVisitor<()> for Resolver<'l> {
         self.resolve_expr(&data.right)
     }
 
+    fn visit_range(&mut self, data: &RangeExpr) -> FoxResult<()> {
+        self.resolve_expr(&data.start)?;
+        self.resolve_expr(&data.end)
+    }
+
     fn visit_unary(&mut self, data: &UnaryExpr) -> FoxResult<()> {
         self.resolve_expr(&data.expression)
     }
@@ -155,7 +489,7 @@ impl<'l> ExpressionVisitor<()> for Resolver<'l> {
             == self
                 .scopes
                 .last()
-                .and_then(|scope| scope.get(&data.name.lexeme))
+                .and_then(|scope| scope.names.get(&data.name.lexeme))
         {
             let err = FoxError::resolver(
                 Some(data.name.clone()),
@@ -167,6 +501,34 @@ impl<'l> ExpressionVisitor<()> for Resolver<'l> {
         self.resolve_local(expr, &data.name)
     }
 
+    fn visit_array(&mut self, data: &ArrayExpr) -> FoxResult<()> {
+        data.elements
+            .iter()
+            .try_for_each(|element| self.resolve_expr(element))
+    }
+
+    fn visit_map(&mut self, data: &MapExpr) -> FoxResult<()> {
+        data.entries.iter().try_for_each(|(key, value)| {
+            self.resolve_expr(key)?;
+            self.resolve_expr(value)
+        })
+    }
+
+    fn visit_index(&mut self, data: &IndexExpr) -> FoxResult<()> {
+        self.resolve_expr(&data.object)?;
+        self.resolve_expr(&data.index)
+    }
+
+    fn visit_index_set(&mut self, data: &IndexSetExpr) -> FoxResult<()> {
+        self.resolve_expr(&data.value)?;
+        self.resolve_expr(&data.object)?;
+        self.resolve_expr(&data.index)
+    }
+
+    fn visit_chain(&mut self, data: &ChainExpr) -> FoxResult<()> {
+        self.resolve_expr(&data.expression)
+    }
+
     fn visit_get(&mut self, data: &GetExpr) -> FoxResult<()> {
         self.resolve_expr(&data.object)
     }
@@ -178,7 +540,7 @@ impl<'l> ExpressionVisitor<()> for Resolver<'l> {
 
     fn visit_this(&mut self, data: &ThisExpr) -> FoxResult<()> {
         if matches!(self.current_class, ClassType::None) {
-            let err = FoxError::runtime(
+            let err = FoxError::resolver(
                 Some(data.keyword.clone()),
                 "Can't use 'this' outside of a class",
             );
@@ -238,10 +600,44 @@ impl<'l> StatementVisitor<()> for Resolver<'l> {
         Ok(())
     }
 
+    fn visit_match(&mut self, data: &MatchStmt) -> FoxResult<()> {
+        self.resolve_expr(&data.subject)?;
+        let mut patterns = Vec::<&Expression>::new();
+        for (pattern, stmt) in &data.arms {
+            if patterns.contains(&pattern) {
+                let token = stmt.token().unwrap_or(&data.keyword);
+                let value = match pattern {
+                    Expression::Literal(LiteralExpr {
+                        value: Object::Text(text),
+                    }) => format!("\"{text}\""),
+                    Expression::Literal(literal) => literal.value.to_string(),
+                    _ => "pattern".to_string(),
+                };
+                let message = format!("Duplicate match arm for {value}");
+                return Err(FoxError::resolver(Some(token.clone()), &message));
+            }
+            patterns.push(pattern);
+            self.resolve_expr(pattern)?;
+            self.resolve_stmt(stmt)?;
+        }
+        if let Some(default) = &data.default {
+            self.resolve_stmt(default)?;
+        }
+        Ok(())
+    }
+
     fn visit_print(&mut self, data: &PrintStmt) -> FoxResult<()> {
         self.resolve_expr(&data.expression)
     }
 
+    fn visit_assert(&mut self, data: &AssertStmt) -> FoxResult<()> {
+        self.resolve_expr(&data.condition)?;
+        if let Some(message) = &data.message {
+            self.resolve_expr(message)?;
+        }
+        Ok(())
+    }
+
     fn visit_return(&mut self, data: &ReturnStmt) -> FoxResult<()> {
         if matches!(self.current_function, FuncType::None) {
             let err = FoxError::resolver(
@@ -252,7 +648,7 @@ impl<'l> StatementVisitor<()> for Resolver<'l> {
         }
         if let Some(value) = &data.value {
             if matches!(self.current_function, FuncType::Initializer) {
-                return Err(FoxError::runtime(
+                return Err(FoxError::resolver(
                     Some(data.keyword.clone()),
                     "Can't return a value from an initializer",
                 ));
@@ -262,8 +658,28 @@ impl<'l> StatementVisitor<()> for Resolver<'l> {
         Ok(())
     }
 
+    fn visit_throw(&mut self, data: &ThrowStmt) -> FoxResult<()> {
+        self.resolve_expr(&data.value)
+    }
+
+    fn visit_try(&mut self, data: &TryStmt) -> FoxResult<()> {
+        self.resolve_stmt(&data.body)?;
+        // the caught value lives in its own scope enclosing the handler
+        self.begin_scope();
+        self.declare(&data.name)?;
+        self.define(&data.name);
+        let result = self.resolve_stmt(&data.handler);
+        self.end_scope();
+        result
+    }
+
     fn visit_var(&mut self, data: &VarStmt) -> FoxResult<()> {
         self.declare(&data.name)?;
+        if data.constant
+            && let Some(scope) = self.scopes.last_mut()
+        {
+            scope.constants.insert(data.name.lexeme.clone());
+        }
         if let Some(expr) = &data.initializer {
             self.resolve_expr(expr)?;
         }
@@ -271,14 +687,71 @@ impl<'l> StatementVisitor<()> for Resolver<'l> {
         Ok(())
     }
 
+    fn visit_var_pattern(&mut self, data: &VarPatternStmt) -> FoxResult<()> {
+        for name in data.pattern.bound_names() {
+            self.declare(name)?;
+            if data.constant
+                && let Some(scope) = self.scopes.last_mut()
+            {
+                scope.constants.insert(name.lexeme.clone());
+            }
+        }
+        self.resolve_expr(&data.initializer)?;
+        for name in data.pattern.bound_names() {
+            self.define(name);
+        }
+        Ok(())
+    }
+
     fn visit_while(&mut self, data: &WhileStmt) -> FoxResult<()> {
         self.resolve_expr(&data.condition)?;
+        self.resolve_loop_body(&data.body, data.label.as_ref())?;
+        data.increments
+            .iter()
+            .try_for_each(|increment| self.resolve_expr(increment))
+    }
+
+    fn visit_for_in(&mut self, data: &ForInStmt) -> FoxResult<()> {
+        self.resolve_expr(&data.iterable)?;
+        // the loop variable lives in its own scope enclosing the body
+        self.begin_scope();
+        self.declare(&data.name)?;
+        self.define(&data.name);
+        let result = self.resolve_loop_body(&data.body, data.label.as_ref());
+        self.end_scope();
+        result
+    }
+
+    fn visit_break(&mut self, data: &BreakStmt) -> FoxResult<()> {
+        self.check_inside_loop(&data.keyword, data.label.as_ref())
+    }
+
+    fn visit_defer(&mut self, data: &DeferStmt) -> FoxResult<()> {
+        if self.scopes.is_empty() {
+            let err =
+                FoxError::resolver(Some(data.keyword.clone()), "Can't defer in top-level code");
+            return Err(err);
+        }
         self.resolve_stmt(&data.body)
     }
 
+    fn visit_import(&mut self, data: &ImportStmt) -> FoxResult<()> {
+        if !self.scopes.is_empty() {
+            let err = FoxError::resolver(
+                Some(data.keyword.clone()),
+                "Can't import outside of top-level code",
+            );
+            return Err(err);
+        }
+        self.resolve_statements(&data.statements)
+    }
+
+    fn visit_continue(&mut self, data: &ContinueStmt) -> FoxResult<()> {
+        self.check_inside_loop(&data.keyword, data.label.as_ref())
+    }
+
     fn visit_class(&mut self, data: &ClassStmt) -> FoxResult<()> {
         let enclosing = self.current_class;
-        self.current_class = ClassType::Class;
         self.declare(&data.name)?;
         self.define(&data.name);
 
@@ -290,8 +763,20 @@ impl<'l> StatementVisitor<()> for Resolver<'l> {
                     "A class can't inherit from itself",
                 ));
             }
-            self.current_class = ClassType::Subclass;
             self.resolve_expr(superclass)?;
+        }
+
+        // the values are evaluated by the class statement, there is no `this` yet
+        let has_constants = !data.constants.is_empty();
+        if has_constants {
+            self.current_class = enclosing;
+            self.begin_scope();
+            self.resolve_statements(&data.constants)?;
+        }
+
+        self.current_class = ClassType::Class;
+        if data.superclass.is_some() {
+            self.current_class = ClassType::Subclass;
             self.begin_scope();
             self.define_lexeme_with_true(KEYWORD_SUPER);
         }
@@ -311,7 +796,572 @@ impl<'l> StatementVisitor<()> for Resolver<'l> {
         if data.superclass.is_some() {
             self.end_scope();
         }
+        if has_constants {
+            self.end_scope();
+        }
         self.current_class = enclosing;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fox::{ErrorInfo, ErrorKind, Parser, Scanner};
+
+    fn resolve(interpreter: &mut Interpreter, code: &str) -> FoxResult<()> {
+        let source = code.chars().collect::<Vec<_>>();
+        let tokens = Scanner::with_source(&source).scan_tokens()?;
+        let statements = Parser::new(&tokens).parse()?;
+        let mut resolver = Resolver::with(interpreter);
+        resolver.enable_warning(WarningKind::MissingReturn);
+        resolver.resolve_statements(&statements)
+    }
+
+    fn missing_return_warnings(code: &str) -> Vec<(usize, String)> {
+        let mut interpreter = Interpreter::new();
+        resolve(&mut interpreter, code).unwrap();
+        interpreter
+            .take_warnings()
+            .into_iter()
+            .filter(|warning| warning.kind() == WarningKind::MissingReturn)
+            .map(|warning| {
+                let ErrorInfo::Token(token) = warning.info() else {
+                    panic!("Warning location expected");
+                };
+                (
+                    token.code_location.line_number(),
+                    warning.message().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_if_without_else() {
+        let code = r"
+fun sign(x) {
+    if (x > 0) {
+        return 1;
+    }
+    print x;
+}";
+        assert_eq!(
+            missing_return_warnings(code),
+            vec![(
+                2,
+                "not all paths return a value; the path ending at line 6 returns nil".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_if_without_else_as_last_statement() {
+        let code = r"
+fun sign(x) {
+    if (x > 0)
+        return 1;
+}";
+        let warnings = missing_return_warnings(code);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].1.contains("line 3"), "{warnings:?}");
+    }
+
+    #[test]
+    fn test_while_only_body() {
+        let code = r"
+fun find(x) {
+    while (x > 0) {
+        return x;
+    }
+}";
+        let warnings = missing_return_warnings(code);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].1.contains("line 3"), "{warnings:?}");
+    }
+
+    #[test]
+    fn test_throw_and_try_paths() {
+        let code = r"
+fun check(x) {
+    if (x > 0) return x;
+    throw x;
+}
+fun parse(s) {
+    try {
+        return num(s);
+    } catch (e) {
+        return 0;
+    }
+}";
+        assert!(missing_return_warnings(code).is_empty());
+        let code = r"
+fun parse(s) {
+    try {
+        return num(s);
+    } catch (e) {
+        print e;
+    }
+}";
+        let warnings = missing_return_warnings(code);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].1.contains("line 6"), "{warnings:?}");
+    }
+
+    #[test]
+    fn test_defer_placement() {
+        let mut interpreter = Interpreter::new();
+        for code in [
+            "{ defer print 1; }",
+            "fun f() { defer { print 1; } }",
+            "while (true) { defer if (true) print 1; break; }",
+        ] {
+            assert!(resolve(&mut interpreter, code).is_ok(), "{code}");
+        }
+        for code in ["defer print 1;", "var a = 1;\nif (a) defer print a;"] {
+            let err = resolve(&mut interpreter, code).unwrap_err();
+            assert_eq!(
+                err.kind().to_string(),
+                "Can't defer in top-level code",
+                "{code}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_import_placement() {
+        let mut interpreter = Interpreter::new();
+        for code in ["{ import \"a.fox\"; }", "fun f() { import \"a.fox\"; }"] {
+            let err = resolve(&mut interpreter, code).unwrap_err();
+            assert_eq!(
+                err.kind().to_string(),
+                "Can't import outside of top-level code",
+                "{code}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_catch_scope() {
+        let mut interpreter = Interpreter::new();
+        let code = "{ var e = 1; try { var e = 2; } catch (e) { var e = 3; print e; } }";
+        assert!(resolve(&mut interpreter, code).is_ok());
+        let code = "fun f() { try {} catch (err) { return err; } }";
+        assert!(resolve(&mut interpreter, code).is_ok());
+        let err = resolve(
+            &mut interpreter,
+            "throw 1;
+return;",
+        )
+        .unwrap_err();
+        assert_eq!(err.kind().to_string(), "Can't return from top-level code");
+    }
+
+    #[test]
+    fn test_exhaustive_if_else() {
+        let code = r"
+fun sign(x) {
+    if (x > 0) {
+        return 1;
+    } else if (x < 0) {
+        return -1;
+    } else {
+        return 0;
+    }
+}
+fun early(x) {
+    return x;
+    print x;
+}";
+        assert!(missing_return_warnings(code).is_empty());
+    }
+
+    #[test]
+    fn test_match_paths() {
+        let code = r#"
+fun name(x) {
+    match (x) {
+        1 => return "one";
+        else => return "many";
+    }
+}
+fun partial(x) {
+    match (x) {
+        1 => return "one";
+        2 => print x;
+        else => return "many";
+    }
+}
+fun no_default(x) {
+    match (x) {
+        1 => return "one";
+    }
+}"#;
+        let warnings = missing_return_warnings(code);
+        let lines = warnings.iter().map(|(line, _)| *line).collect::<Vec<_>>();
+        assert_eq!(lines, [8, 15], "{warnings:?}");
+        assert!(warnings[0].1.contains("line 11"), "{warnings:?}");
+        assert!(warnings[1].1.contains("line 16"), "{warnings:?}");
+    }
+
+    #[test]
+    fn test_duplicate_match_arms() {
+        for (code, message) in [
+            (
+                "match (1) { 1 => print 1; 2 => print 2; 1 => print 3; }",
+                "Duplicate match arm for 1",
+            ),
+            (
+                "match (1) { \"a\" => print 1; \"a\" => print 2; }",
+                "Duplicate match arm for \"a\"",
+            ),
+            (
+                "match (1) { -1 => print 1; nil => {} -1 => print 2; }",
+                "Duplicate match arm for -1",
+            ),
+        ] {
+            let mut interpreter = Interpreter::new();
+            let err = resolve(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+        }
+        let mut interpreter = Interpreter::new();
+        let code = "match (1) { 1 => print 1; \"1\" => print 2; true => {} 2 => {} }";
+        assert!(resolve(&mut interpreter, code).is_ok());
+    }
+
+    #[test]
+    fn test_exemptions() {
+        let code = r"
+fun bare(x) {
+    if (x) return;
+    print x;
+}
+fun nothing() {
+    print 1;
+}
+class A {
+    init(x) {
+        if (x) return;
+        this.x = x;
+    }
+}
+fun outer() {
+    fun inner() {
+        return 1;
+    }
+    print inner;
+}";
+        assert!(missing_return_warnings(code).is_empty());
+    }
+
+    #[test]
+    fn test_bare_return_path() {
+        let code = r"
+fun f(x) {
+    if (x) return 1;
+    return;
+}";
+        let warnings = missing_return_warnings(code);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].1.contains("line 4"), "{warnings:?}");
+    }
+
+    #[test]
+    fn test_denied_missing_return() {
+        let mut interpreter = Interpreter::new();
+        interpreter.deny(WarningKind::MissingReturn);
+        let err = resolve(&mut interpreter, "fun f(x) { if (x) return 1; }").unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Resolver(_)));
+    }
+
+    #[test]
+    fn test_break_outside_loop() {
+        for (code, column) in [
+            ("break;", 4),
+            ("if (true) { break; }", 16),
+            ("while (true) { fun f() { break; } }", 29),
+        ] {
+            let mut interpreter = Interpreter::new();
+            let err = resolve(&mut interpreter, code).unwrap_err();
+            assert_eq!(
+                err.kind().to_string(),
+                "Can't use 'break' outside of a loop",
+                "{code}"
+            );
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected");
+            };
+            assert_eq!(token.code_location.absolute_position(), column, "{code}");
+        }
+        let mut interpreter = Interpreter::new();
+        assert!(resolve(&mut interpreter, "while (true) { { if (true) break; } }").is_ok());
+    }
+
+    #[test]
+    fn test_loop_labels() {
+        let mut interpreter = Interpreter::new();
+        let code = "a: while (true) { b: for (var x in []) { continue a; } break a; }";
+        assert!(resolve(&mut interpreter, code).is_ok());
+        for (code, message, column) in [
+            (
+                "a: while (true) { break b; }",
+                "No enclosing loop labeled 'b'",
+                24,
+            ),
+            (
+                "a: while (true) {}\nwhile (true) { continue a; }",
+                "No enclosing loop labeled 'a'",
+                43,
+            ),
+            (
+                "a: while (true) { fun f() { while (true) break a; } }",
+                "No enclosing loop labeled 'a'",
+                47,
+            ),
+            (
+                "a: while (true) { a: while (true) {} }",
+                "Label 'a' is already used by an enclosing loop",
+                18,
+            ),
+        ] {
+            let err = resolve(&mut interpreter, code).unwrap_err();
+            assert_eq!(err.kind().to_string(), message, "{code}");
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected");
+            };
+            assert_eq!(token.code_location.absolute_position(), column, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_const_assignment() {
+        for (code, position) in [
+            ("const PI = 3.14;\nPI = 3;", 18),
+            ("{ const x = 1; x = 2; }", 15),
+            ("{ const x = 1; { print x; x = 2; } }", 26),
+            ("const x = 1;\n++x;", 15),
+            // functions may refer the constant declared below them
+            ("fun f() { limit = 0; }\nconst limit = 10;", 14),
+            ("fun f() { const n = 1; fun g() { n = 2; } }", 33),
+        ] {
+            let mut interpreter = Interpreter::new();
+            let err = resolve(&mut interpreter, code).unwrap_err();
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected");
+            };
+            assert_eq!(
+                err.kind().to_string(),
+                format!("Can't assign to constant '{}'", token.lexeme),
+                "{code}"
+            );
+            assert_eq!(token.code_location.absolute_position(), position, "{code}");
+        }
+        for code in [
+            "const x = 1; { var x = 2; x = 3; }",
+            "const x = 1; fun f(x) { x = 2; }",
+            "{ const x = 1; { var x = 2; { x = 3; } } }",
+            "var y = 1; y = 2;",
+        ] {
+            let mut interpreter = Interpreter::new();
+            assert!(resolve(&mut interpreter, code).is_ok(), "{code}");
+        }
+        let mut interpreter = Interpreter::new();
+        let err = resolve(&mut interpreter, "{ const x = 1; var x = 2; }").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Already a variable with this name in this scope"
+        );
+    }
+
+    #[test]
+    fn test_var_list_scope() {
+        let mut interpreter = Interpreter::new();
+        assert!(resolve(&mut interpreter, "{ var a = 1, b = a + 1; print b; }").is_ok());
+        let err = resolve(&mut interpreter, "{ var a, b = b; }").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Can't read local variable in its own initializer"
+        );
+        let err = resolve(&mut interpreter, "{ var a; var b, a; }").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Already a variable with this name in this scope"
+        );
+    }
+
+    #[test]
+    fn test_pattern_scope() {
+        let mut interpreter = Interpreter::new();
+        let code = "{ var [a, ...b] = [1, 2]; [a, b] = [b, a]; print a; }";
+        assert!(resolve(&mut interpreter, code).is_ok());
+        let code = "{ var [a, b] = [1, b]; }";
+        let err = resolve(&mut interpreter, code).unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Can't read local variable in its own initializer"
+        );
+        let err = resolve(&mut interpreter, "{ var a; var [b, a] = []; }").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Already a variable with this name in this scope"
+        );
+        for code in [
+            "const [x, y] = [1, 2];\n[y] = [3];",
+            "{ var y; const [...x] = []; [y, ...x] = []; }",
+            "fun f() { [y] = [0]; }\nconst [y] = [1];",
+        ] {
+            let err = resolve(&mut interpreter, code).unwrap_err();
+            assert!(
+                err.kind()
+                    .to_string()
+                    .starts_with("Can't assign to constant"),
+                "{code}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_continue_outside_loop() {
+        let mut interpreter = Interpreter::new();
+        let err = resolve(&mut interpreter, "fun f() { continue; }").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "Can't use 'continue' outside of a loop"
+        );
+        let mut interpreter = Interpreter::new();
+        let code = "for (var i = 0; i < 1; i = i + 1) { if (true) continue; }";
+        assert!(resolve(&mut interpreter, code).is_ok());
+    }
+
+    #[test]
+    fn test_placement_violations() {
+        let cases = [
+            ("return 1;", "Can't return from top-level code", 1, "return"),
+            (
+                "{\n  return;\n}",
+                "Can't return from top-level code",
+                2,
+                "return",
+            ),
+            (
+                "if (true) return;",
+                "Can't return from top-level code",
+                1,
+                "return",
+            ),
+            (
+                "class A {\n  init() { return 1; }\n}",
+                "Can't return a value from an initializer",
+                2,
+                "return",
+            ),
+            (
+                "print this;",
+                "Can't use 'this' outside of a class",
+                1,
+                "this",
+            ),
+            (
+                "fun f() { return this; }",
+                "Can't use 'this' outside of a class",
+                1,
+                "this",
+            ),
+            (
+                "class A {}\nfun g() { this; }",
+                "Can't use 'this' outside of a class",
+                2,
+                "this",
+            ),
+            ("break;", "Can't use 'break' outside of a loop", 1, "break"),
+            (
+                "fun f() { continue; }",
+                "Can't use 'continue' outside of a loop",
+                1,
+                "continue",
+            ),
+            (
+                "while (true) {\n  fun f() { break; }\n}",
+                "Can't use 'break' outside of a loop",
+                2,
+                "break",
+            ),
+            (
+                "print super.x;",
+                "Can't use 'super' outside of a class",
+                1,
+                "super",
+            ),
+            (
+                "class A {\n  m() { super.m(); }\n}",
+                "Can't use 'super' in a class with no superclass",
+                2,
+                "super",
+            ),
+            (
+                "fun f() {\n  class B { m() { return super.m(); } }\n}",
+                "Can't use 'super' in a class with no superclass",
+                2,
+                "super",
+            ),
+            (
+                "class A < A {}",
+                "A class can't inherit from itself",
+                1,
+                "A",
+            ),
+            (
+                "{\n  var a = 1;\n  var a = 2;\n}",
+                "Already a variable with this name in this scope",
+                3,
+                "a",
+            ),
+            (
+                "{ var a = a; }",
+                "Can't read local variable in its own initializer",
+                1,
+                "a",
+            ),
+        ];
+        for (code, message, line, lexeme) in cases {
+            let mut interpreter = Interpreter::new();
+            let err = resolve(&mut interpreter, code).unwrap_err();
+            assert!(
+                matches!(err.kind(), ErrorKind::Resolver(_)),
+                "{code}: {err:?}"
+            );
+            assert_eq!(err.kind().to_string(), message, "{code}");
+            let ErrorInfo::Token(token) = err.info() else {
+                panic!("Error location expected for {code}");
+            };
+            assert_eq!(token.code_location.line_number(), line, "{code}");
+            assert_eq!(token.lexeme, lexeme, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_allowed_placements() {
+        for code in [
+            "class A { m() { fun f() { return this; } return f; } }",
+            "class A { m() {} }\nclass B < A { m() { fun f() { return super.m; } return f(); } }",
+            "class A { init() { if (true) return; } }",
+            "class A { init() { fun f() { return 1; } f(); } }",
+            "while (true) { { if (true) break; } }",
+        ] {
+            let mut interpreter = Interpreter::new();
+            assert!(resolve(&mut interpreter, code).is_ok(), "{code}");
+        }
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let source = "fun f(x) { if (x) return 1; }".chars().collect::<Vec<_>>();
+        let tokens = Scanner::with_source(&source).scan_tokens().unwrap();
+        let statements = Parser::new(&tokens).parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        Resolver::with(&mut interpreter)
+            .resolve_statements(&statements)
+            .unwrap();
+        assert!(interpreter.take_warnings().is_empty());
+    }
+}
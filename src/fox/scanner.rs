@@ -1,4 +1,4 @@
-use super::{CodeLocation, ErrorKind, FoxError, FoxResult, Object, Source, Token, TokenType};
+use super::{BOM, CodeLocation, ErrorKind, FoxError, FoxResult, Object, Source, Token, TokenType};
 
 pub struct Scanner<'l> {
     start: usize,
@@ -22,8 +22,22 @@ impl<'l> Scanner<'l> {
         }
     }
 
+    /// Scans the source from the position, the locations stay absolute
+    pub fn with_source_at(source: &'l Source, position: usize, line: usize) -> Self {
+        Self {
+            start: position,
+            current: position,
+            line,
+            source,
+        }
+    }
+
     pub fn scan_tokens(&mut self) -> FoxResult<Vec<Token>> {
         let mut tokens = Vec::<Token>::new();
+        // positions still refer to the source with BOM
+        if self.current == 0 && self.source.first() == Some(&BOM) {
+            self.current = 1;
+        }
         let mut is_eof = false;
         while !is_eof {
             self.start = self.current;
@@ -51,18 +65,44 @@ impl<'l> Scanner<'l> {
             ')' => self.scan_data_by_type(RightParenthesis),
             '{' => self.scan_data_by_type(LeftBrace),
             '}' => self.scan_data_by_type(RightBrace),
+            '[' => self.scan_data_by_type(LeftBracket),
+            ']' => self.scan_data_by_type(RightBracket),
+            ':' => self.scan_data_by_type(Colon),
             ',' => self.scan_data_by_type(Comma),
+            '.' if self.peek() == Some('.') && self.peek_next() == Some('.') => {
+                self.current += 2;
+                self.scan_data_by_type(DotDotDot)
+            }
+            '.' if self.peek() == Some('.') && self.peek_next() == Some('=') => {
+                self.current += 2;
+                self.scan_data_by_type(DotDotEqual)
+            }
+            '.' if self.matches('.') => self.scan_data_by_type(DotDot),
             '.' => self.scan_data_by_type(Dot),
-            '-' => self.scan_data_by_type(Minus),
-            '+' => self.scan_data_by_type(Plus),
+            '?' if self.matches('.') => self.scan_data_by_type(QuestionDot),
             ';' => self.scan_data_by_type(Semicolon),
             '*' => self.scan_data_by_type(Star),
+            '%' => self.scan_data_by_type(Percent),
+            '-' => {
+                let t_type = if self.matches('-') { MinusMinus } else { Minus };
+                self.scan_data_by_type(t_type)
+            }
+            '+' => {
+                let t_type = if self.matches('+') { PlusPlus } else { Plus };
+                self.scan_data_by_type(t_type)
+            }
             '!' => {
                 let t_type = if self.matches('=') { BangEqual } else { Bang };
                 self.scan_data_by_type(t_type)
             }
             '=' => {
-                let t_type = if self.matches('=') { EqualEqual } else { Equal };
+                let t_type = if self.matches('=') {
+                    EqualEqual
+                } else if self.matches('>') {
+                    EqualGreater
+                } else {
+                    Equal
+                };
                 self.scan_data_by_type(t_type)
             }
             '<' => {
@@ -81,18 +121,20 @@ impl<'l> Scanner<'l> {
                 if self.matches('/') {
                     self.advance_to_eol();
                     ScanData::Skip
+                } else if self.matches('*') {
+                    self.skip_block_comment()?
                 } else {
                     self.scan_data_by_type(Slash)
                 }
             }
-            ' ' | '\r' | '\t' => ScanData::Skip,
-            '\n' => {
-                self.line += 1;
+            ' ' | '\t' => ScanData::Skip,
+            '\n' | '\r' => {
+                self.count_line(ch);
                 ScanData::Skip
             }
             '\"' => self.scan_string()?,
             ch if ch.is_ascii_digit() => self.scan_number()?,
-            ch if ch.is_ascii_alphabetic() => self.scan_identifier()?,
+            ch if ch.is_ascii_alphabetic() || ch == '_' => self.scan_identifier()?,
             _ => {
                 return Err(self.error(ErrorKind::UnexpectedCharacter));
             }
@@ -124,53 +166,108 @@ impl<'l> Scanner<'l> {
         false
     }
 
+    /// `\n`, `\r\n` and `\r` are single line terminators
+    fn count_line(&mut self, ch: char) {
+        let is_terminator = match ch {
+            '\n' => true,
+            '\r' => self.peek() != Some('\n'),
+            _ => false,
+        };
+        if is_terminator {
+            self.line += 1;
+        }
+    }
+
     fn advance_to_eol(&mut self) {
         while let Some(ch) = self.peek() {
-            if ch == '\n' {
+            if ch == '\n' || ch == '\r' {
                 break;
             }
             _ = self.advance();
         }
     }
 
+    fn skip_block_comment(&mut self) -> FoxResult<ScanData> {
+        let opening = CodeLocation::new(self.line, self.start);
+        loop {
+            let Some(ch) = self.advance() else {
+                return Err(FoxError::code_location(
+                    ErrorKind::UnterminatedComment,
+                    opening,
+                ));
+            };
+            self.count_line(ch);
+            if ch == '*' && self.matches('/') {
+                break Ok(ScanData::Skip);
+            }
+        }
+    }
+
     fn scan_string(&mut self) -> FoxResult<ScanData> {
         loop {
             let Some(ch) = self.advance() else {
                 return Err(self.error(ErrorKind::UnterminatedString));
             };
-            if ch == '\n' {
-                self.line += 1;
-            }
+            self.count_line(ch);
 
             if ch == '\"' {
-                let value = self.substring(self.start + 1, self.current - 1);
-                let data = self.scan_data_by_type_literal(TokenType::String, Object::Text(value));
+                // literal value doesn't depend on the file's line endings
+                let value = self
+                    .substring(self.start + 1, self.current - 1)
+                    .replace("\r\n", "\n")
+                    .replace('\r', "\n");
+                let data =
+                    self.scan_data_by_type_literal(TokenType::String, Object::Text(value.into()));
                 break Ok(data);
             }
         }
     }
 
     fn scan_number(&mut self) -> FoxResult<ScanData> {
-        while is_digit(self.peek()) {
-            _ = self.advance();
-        }
+        self.scan_digits()?;
 
-        if self.peek() == Some('.') && is_digit(self.peek_next()) {
+        if self.peek() == Some('.') && self.peek_next() == Some(DIGIT_SEPARATOR) {
             _ = self.advance();
+            _ = self.advance();
+            return Err(self.error(ErrorKind::InvalidDigitSeparator));
         }
-
-        while is_digit(self.peek()) {
+        if self.peek() == Some('.') && is_digit(self.peek_next()) {
             _ = self.advance();
+            self.scan_digits()?;
         }
 
-        let value = self.substring(self.start, self.current);
+        // the lexeme keeps the separators
+        let value = self
+            .substring(self.start, self.current)
+            .replace(DIGIT_SEPARATOR, "");
         let double = value
-            .parse::<f32>()
+            .parse::<f64>()
             .map_err(|_| self.error(ErrorKind::UnexpectedCharacter))?;
         let data = self.scan_data_by_type_literal(TokenType::Number, Object::Double(double));
         Ok(data)
     }
 
+    /// Consumes the digits following a digit, single separators may be among them
+    fn scan_digits(&mut self) -> FoxResult<()> {
+        loop {
+            if is_digit(self.peek()) {
+                _ = self.advance();
+                continue;
+            }
+            if self.peek() != Some(DIGIT_SEPARATOR) {
+                return Ok(());
+            }
+            _ = self.advance();
+            if !is_digit(self.peek()) {
+                // the doubled separator is reported at the second one
+                if self.peek() == Some(DIGIT_SEPARATOR) {
+                    _ = self.advance();
+                }
+                return Err(self.error(ErrorKind::InvalidDigitSeparator));
+            }
+        }
+    }
+
     fn scan_identifier(&mut self) -> Result<ScanData, FoxError> {
         while is_alphanumeric(self.peek()) {
             _ = self.advance();
@@ -179,19 +276,31 @@ impl<'l> Scanner<'l> {
         use TokenType::*;
         let t_type = match value.as_str() {
             "and" => And,
+            "assert" => Assert,
+            "break" => Break,
+            "catch" => Catch,
             "class" => Class,
+            "const" => Const,
+            "continue" => Continue,
+            "defer" => Defer,
             "else" => Else,
             "false" => False,
             "for" => For,
             "fun" => Fun,
             "if" => If,
+            "import" => Import,
+            "in" => In,
+            "is" => Is,
+            "match" => Match,
             "nil" => Nil,
             "or" => Or,
             "print" => Print,
             "return" => Return,
             "super" => Super,
             "this" => This,
+            "throw" => Throw,
             "true" => True,
+            "try" => Try,
             "var" => Var,
             "while" => While,
             _ => Identifier,
@@ -236,6 +345,9 @@ impl<'l> Scanner<'l> {
     }
 }
 
+/// Visual separator allowed between digits of number literals
+const DIGIT_SEPARATOR: char = '_';
+
 fn is_digit(value: Option<char>) -> bool {
     is_matches_criteria(value, |ch| ch.is_ascii_digit())
 }
@@ -251,9 +363,35 @@ where
     value.map(criteria).unwrap_or(false)
 }
 
+/// Value of the text written as a number literal, the minus
+/// that is an operator in the code may lead it, surrounding whitespace is ignored
+pub fn parse_number_literal(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (sign, literal) = match text.strip_prefix('-') {
+        Some(literal) => (-1.0, literal),
+        None => (1.0, text),
+    };
+    let source = literal.chars().collect::<Vec<_>>();
+    let tokens = Scanner::with_source(&source).scan_tokens().ok()?;
+    match tokens.as_slice() {
+        [number, eof]
+            if number.token_type == TokenType::Number
+                && number.lexeme == literal
+                && eof.is_eof() =>
+        {
+            let Object::Double(value) = number.literal else {
+                return None;
+            };
+            Some(sign * value)
+        }
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::fox::ErrorInfo;
 
     #[test]
     fn test_comment_parse() {
@@ -271,6 +409,77 @@ mod test {
         assert!(is_token_types_matches(&result, &expected));
     }
 
+    #[test]
+    fn test_dots() {
+        let input = "a.b ...rest .. .... 0..=1.5".chars().collect::<Vec<_>>();
+        let result = Scanner::with_source(&input).scan_tokens().unwrap();
+        use TokenType::*;
+        let expected = [
+            Identifier,
+            Dot,
+            Identifier,
+            DotDotDot,
+            Identifier,
+            DotDot,
+            DotDotDot,
+            Dot,
+            Number,
+            DotDotEqual,
+            Number,
+            Eof,
+        ];
+        assert!(is_token_types_matches(&result, &expected));
+        assert_eq!(result[3].lexeme, "...");
+    }
+
+    #[test]
+    fn test_block_comment() {
+        let input = "a /* \" // \n * / \n */ b\nc".chars().collect::<Vec<_>>();
+        let mut scanner = Scanner::with_source(&input);
+        let result = scanner.scan_tokens().unwrap();
+        use TokenType::*;
+        let expected = [Identifier, Identifier, Identifier, Eof];
+        assert!(is_token_types_matches(&result, &expected));
+        let lines = result
+            .iter()
+            .map(|token| (token.lexeme.as_str(), token.code_location.line_number()))
+            .collect::<Vec<_>>();
+        assert_eq!(lines[..3], [("a", 1), ("b", 3), ("c", 4)]);
+    }
+
+    #[test]
+    fn test_line_terminators() {
+        for input in [
+            "a\nb // c\n\"d\ne\"\n/*\n*/f",
+            "a\r\nb // c\r\n\"d\r\ne\"\r\n/*\r\n*/f",
+            "a\rb // c\r\"d\re\"\r/*\r*/f",
+            "\u{feff}a\r\nb // c\r\n\"d\r\ne\"\r\n/*\r\n*/f",
+        ] {
+            let input = input.chars().collect::<Vec<_>>();
+            let result = Scanner::with_source(&input).scan_tokens().unwrap();
+            let lines = result
+                .iter()
+                .map(|token| token.code_location.line_number())
+                .collect::<Vec<_>>();
+            assert_eq!(lines, [1, 2, 4, 6, 6], "{input:?}");
+            assert!(matches!(result[0].token_type, TokenType::Identifier));
+            assert!(matches!(&result[2].literal, Object::Text(text) if &**text == "d\ne"));
+        }
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let input = "a\n  /* comment\n".chars().collect::<Vec<_>>();
+        let mut scanner = Scanner::with_source(&input);
+        let err = scanner.scan_tokens().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::UnterminatedComment));
+        let ErrorInfo::Code(location) = err.info() else {
+            panic!("Error location expected");
+        };
+        assert_eq!(location.line_number(), 2);
+        assert_eq!(location.absolute_position(), 4);
+    }
+
     #[test]
     fn test_string_parse() {
         let input = "\"ABCDEF\"".chars().collect::<Vec<_>>();
@@ -283,7 +492,7 @@ mod test {
         let Object::Text(value) = &token.literal else {
             panic!("Invalid literal type");
         };
-        assert_eq!(*value, "ABCDEF".to_string());
+        assert_eq!(value.as_ref(), "ABCDEF");
     }
 
     #[test]
@@ -326,9 +535,93 @@ mod test {
         assert_eq!(*value, 123.456);
     }
 
+    #[test]
+    fn test_digit_separators() {
+        for (code, expected) in [
+            ("1_000_000", 1_000_000.0),
+            ("2.718_5", 2.718_5),
+            ("1_0.2_5", 10.25),
+            ("0_1", 1.0),
+            ("16_777_217", 16_777_217.0),
+        ] {
+            let input = code.chars().collect::<Vec<_>>();
+            let tokens = Scanner::with_source(&input).scan_tokens().unwrap();
+            assert_eq!(tokens.len(), 2, "{code}");
+            assert_eq!(tokens[0].literal, Object::Double(expected), "{code}");
+            assert_eq!(tokens[0].lexeme, code);
+        }
+    }
+
+    #[test]
+    fn test_invalid_digit_separators() {
+        // positions point at the offending separator
+        for (code, position) in [
+            ("100_", 3),
+            ("100_;", 3),
+            ("1__0", 2),
+            ("1_000__000", 6),
+            ("1_.5", 1),
+            ("1._5", 2),
+            ("3.14_", 4),
+            ("3.1__4", 4),
+            ("1_a", 1),
+            ("var x =\n 2__0;", 11),
+        ] {
+            let input = code.chars().collect::<Vec<_>>();
+            let err = Scanner::with_source(&input).scan_tokens().unwrap_err();
+            assert!(
+                matches!(err.kind(), ErrorKind::InvalidDigitSeparator),
+                "{code}"
+            );
+            let ErrorInfo::Code(location) = err.info() else {
+                panic!("Code location expected");
+            };
+            assert_eq!(location.absolute_position(), position, "{code}");
+        }
+        let input = "var x =\n 2__0;".chars().collect::<Vec<_>>();
+        let err = Scanner::with_source(&input).scan_tokens().unwrap_err();
+        let ErrorInfo::Code(location) = err.info() else {
+            panic!("Code location expected");
+        };
+        assert_eq!(location.line_number(), 2);
+    }
+
+    #[test]
+    fn test_leading_separator_is_not_number() {
+        // it starts an identifier, private property names do
+        let input = "_100 x_100 x_".chars().collect::<Vec<_>>();
+        let tokens = Scanner::with_source(&input).scan_tokens().unwrap();
+        use TokenType::*;
+        assert!(is_token_types_matches(
+            &tokens,
+            &[Identifier, Identifier, Identifier, Eof]
+        ));
+        assert_eq!(tokens[0].lexeme, "_100");
+        assert_eq!(tokens[1].lexeme, "x_100");
+    }
+
+    #[test]
+    fn test_parse_number_literal() {
+        for (text, value) in [
+            ("12", 12.0),
+            (" -1_000.5\n", -1000.5),
+            ("0.25", 0.25),
+            ("-0", 0.0),
+            ("16777217", 16777217.0),
+            ("9007199254740993", 9007199254740992.0),
+        ] {
+            assert_eq!(parse_number_literal(text), Some(value), "{text}");
+        }
+        for text in [
+            "", " ", "12abc", "1.", ".5", "1__0", "1_", "- 1", "--1", "+1", "1e5", "1 2", "1//",
+        ] {
+            assert_eq!(parse_number_literal(text), None, "{text}");
+        }
+    }
+
     #[test]
     fn test_token_parse() {
-        let input = "(){},.+-;*!!===<<=>>=/".chars().collect::<Vec<_>>();
+        let input = "(){}[]:,.+-;*%!!===<<=>>=/".chars().collect::<Vec<_>>();
         let mut scanner = Scanner::with_source(&input);
         let result = scanner.scan_tokens().unwrap();
         use TokenType::*;
@@ -337,12 +630,16 @@ mod test {
             RightParenthesis,
             LeftBrace,
             RightBrace,
+            LeftBracket,
+            RightBracket,
+            Colon,
             Comma,
             Dot,
             Plus,
             Minus,
             Semicolon,
             Star,
+            Percent,
             Bang,
             BangEqual,
             EqualEqual,
@@ -356,18 +653,76 @@ mod test {
         assert!(is_token_types_matches(&result, &expected));
     }
 
+    #[test]
+    fn test_increment_decrement_parse() {
+        let input = "++a --b + +c - - d---e".chars().collect::<Vec<_>>();
+        let result = Scanner::with_source(&input).scan_tokens().unwrap();
+        use TokenType::*;
+        let expected = [
+            PlusPlus, Identifier, MinusMinus, Identifier, Plus, Plus, Identifier, Minus, Minus,
+            Identifier, MinusMinus, Minus, Identifier, Eof,
+        ];
+        assert!(is_token_types_matches(&result, &expected));
+    }
+
+    #[test]
+    fn test_optional_chain_parse() {
+        let input = "a?.b.c ?.d".chars().collect::<Vec<_>>();
+        let result = Scanner::with_source(&input).scan_tokens().unwrap();
+        use TokenType::*;
+        let expected = [
+            Identifier,
+            QuestionDot,
+            Identifier,
+            Dot,
+            Identifier,
+            QuestionDot,
+            Identifier,
+            Eof,
+        ];
+        assert!(is_token_types_matches(&result, &expected));
+    }
+
+    #[test]
+    fn test_match_parse() {
+        let input = "match (x) { 1 => a = b; } >= ==>"
+            .chars()
+            .collect::<Vec<_>>();
+        let result = Scanner::with_source(&input).scan_tokens().unwrap();
+        use TokenType::*;
+        let expected = [
+            Match,
+            LeftParenthesis,
+            Identifier,
+            RightParenthesis,
+            LeftBrace,
+            Number,
+            EqualGreater,
+            Identifier,
+            Equal,
+            Identifier,
+            Semicolon,
+            RightBrace,
+            GreaterEqual,
+            EqualEqual,
+            Greater,
+            Eof,
+        ];
+        assert!(is_token_types_matches(&result, &expected));
+    }
+
     #[test]
     fn test_identifier_parse() {
         let input =
-            "and class else false for fun if nil or print return super this true var while aa_aa bbb"
+            "and break class const continue else false for fun if in nil or print return super this true var while aa_aa bbb"
                 .chars()
                 .collect::<Vec<_>>();
         let mut scanner = Scanner::with_source(&input);
         let result = scanner.scan_tokens().unwrap();
         use TokenType::*;
         let expected = [
-            And, Class, Else, False, For, Fun, If, Nil, Or, Print, Return, Super, This, True, Var,
-            While, Identifier, Identifier, Eof,
+            And, Break, Class, Const, Continue, Else, False, For, Fun, If, In, Nil, Or, Print,
+            Return, Super, This, True, Var, While, Identifier, Identifier, Eof,
         ];
         assert!(is_token_types_matches(&result, &expected));
     }
@@ -0,0 +1,154 @@
+use std::fmt::Display;
+
+use crate::fox::{Object, func::Func};
+
+/// Prefix of global functions treated as script-defined tests
+pub const TEST_FUNCTION_PREFIX: &str = "test_";
+
+/// Picks test functions from the global bindings in declaration order
+///
+pub fn discover_tests(bindings: Vec<(String, Object)>) -> Vec<Func> {
+    let mut tests = bindings
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(TEST_FUNCTION_PREFIX))
+        .filter_map(|(_, object)| match object {
+            Object::Callee(func) => Some(func),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    tests.sort_by_key(|func| func.decl.name.code_location.absolute_position());
+    tests
+}
+
+/// Result of a single test function call
+///
+#[derive(Debug, Clone)]
+pub struct TestOutcome {
+    pub name: String,
+    /// Formatted error description if the test failed
+    pub failure: Option<String>,
+}
+
+impl TestOutcome {
+    pub fn is_passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// Summary of the script test run
+///
+#[derive(Debug, Clone, Default)]
+pub struct TestReport {
+    outcomes: Vec<TestOutcome>,
+}
+
+impl TestReport {
+    pub fn push(&mut self, outcome: TestOutcome) {
+        self.outcomes.push(outcome);
+    }
+
+    pub fn outcomes(&self) -> &[TestOutcome] {
+        &self.outcomes
+    }
+
+    pub fn passed_count(&self) -> usize {
+        self.outcomes.iter().filter(|x| x.is_passed()).count()
+    }
+
+    pub fn failed_count(&self) -> usize {
+        self.outcomes.len() - self.passed_count()
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.failed_count() == 0
+    }
+}
+
+impl Display for TestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for outcome in &self.outcomes {
+            let Some(failure) = &outcome.failure else {
+                writeln!(f, "test {} ... ok", outcome.name)?;
+                continue;
+            };
+            writeln!(f, "test {} ... FAILED", outcome.name)?;
+            writeln!(f, "{failure}")?;
+        }
+        let status = if self.is_success() { "ok" } else { "FAILED" };
+        writeln!(
+            f,
+            "test result: {status}. {} passed; {} failed",
+            self.passed_count(),
+            self.failed_count()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fox::Fox;
+
+    #[test]
+    fn test_run_script_tests() {
+        let code = r#"fun helper(x) {
+    return x + 1;
+}
+var test_value = 10;
+fun test_passing() {
+    var a = helper(1);
+}
+fun test_failing() {
+    var a = helper(nil);
+}
+fun test_with_args(a) {
+}"#;
+        let mut fox = Fox::with(code.chars().collect());
+        let report = fox.run_tests().unwrap();
+
+        let names = report
+            .outcomes()
+            .iter()
+            .map(|x| (x.name.as_str(), x.is_passed()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                ("test_passing", true),
+                ("test_failing", false),
+                ("test_with_args", false)
+            ]
+        );
+        assert!(!report.is_success());
+
+        let text = report.to_string();
+        assert!(text.contains("test test_passing ... ok"));
+        assert!(text.contains("test test_failing ... FAILED"));
+        // failure shows the failing line
+        assert!(text.contains("2 |    return x + 1;"));
+        assert!(text.ends_with("test result: FAILED. 1 passed; 2 failed\n"));
+    }
+
+    #[test]
+    fn test_all_passed() {
+        let code = "fun test_one() {} fun test_two() {}";
+        let mut fox = Fox::with(code.chars().collect());
+        let report = fox.run_tests().unwrap();
+        assert!(report.is_success());
+        assert_eq!(report.passed_count(), 2);
+    }
+
+    #[test]
+    fn test_exit_stops_tests() {
+        let code = "fun test_one() { exit(2); } fun test_two() {}";
+        let mut fox = Fox::with(code.chars().collect());
+        let err = fox.run_tests().unwrap_err();
+        assert_eq!(err.kind().exit_code(), Some(2));
+    }
+
+    #[test]
+    fn test_top_level_error() {
+        let code = "fun test_one() {} print undefined;";
+        let mut fox = Fox::with(code.chars().collect());
+        assert!(fox.run_tests().is_err());
+    }
+}
@@ -23,18 +23,29 @@ pub enum TokenType {
     RightParenthesis,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
-    Minus,
-    Plus,
+    DotDot,
+    DotDotEqual,
+    DotDotDot,
+    QuestionDot,
     Semicolon,
     Slash,
     Star,
+    Percent,
     // 1 or 2 chars tokens
+    Minus,
+    MinusMinus,
+    Plus,
+    PlusPlus,
     Bang,
     BangEqual,
     Equal,
     EqualEqual,
+    EqualGreater,
     Greater,
     GreaterEqual,
     Less,
@@ -45,21 +56,71 @@ pub enum TokenType {
     Number,
     // Keywords
     And,
+    Assert,
+    Break,
+    Catch,
     Class,
+    Const,
+    Continue,
+    Defer,
     Else,
     False,
     Fun,
     For,
     If,
+    Import,
+    In,
+    Is,
+    Match,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
     //
     Eof,
 }
+
+impl TokenType {
+    /// Operation applied to the evaluated operands of the binary expression
+    pub fn binary_op(&self) -> Option<BinaryOp> {
+        use TokenType::*;
+        let op = match self {
+            // desugared prefix operators
+            Plus | PlusPlus => BinaryOp::Add,
+            Minus | MinusMinus => BinaryOp::Subtract,
+            Star => BinaryOp::Multiply,
+            Slash => BinaryOp::Divide,
+            Percent => BinaryOp::Modulo,
+            Greater => BinaryOp::Greater,
+            GreaterEqual => BinaryOp::GreaterEqual,
+            Less => BinaryOp::Less,
+            LessEqual => BinaryOp::LessEqual,
+            EqualEqual => BinaryOp::Equal,
+            BangEqual => BinaryOp::NotEqual,
+            _ => return None,
+        };
+        Some(op)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Equal,
+    NotEqual,
+}
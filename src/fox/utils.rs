@@ -1,4 +1,4 @@
-use std::{cell::RefCell, collections::HashMap, hash::Hash, rc::Rc};
+use std::{cell::RefCell, fmt::Write, rc::Rc};
 /// Code location struct
 /// use to define token position inside input source code
 /// mostly used for formatting error messages
@@ -35,24 +35,181 @@ impl Default for CodeLocation {
 ///
 pub type SharedPtr<T> = Rc<RefCell<T>>;
 
+/// Byte order mark written by some editors at the file start
+pub const BOM: char = '\u{feff}';
+
 /// convention function to create mutable pointer
 ///
 pub fn mutable_cell<T>(value: T) -> SharedPtr<T> {
     Rc::new(RefCell::new(value))
 }
 
-/// Fill hash for map of <Hashable1: Hashable2>
+/// Levenshtein distance between strings, used for typo suggestions
 ///
-pub fn fill_hash<H, K, V>(map: &HashMap<K, V>, state: &mut H)
-where
-    H: std::hash::Hasher,
-    K: Hash + Ord,
-    V: Hash,
-{
-    let mut keys: Vec<_> = map.keys().collect();
-    keys.sort();
-    for key in keys {
-        key.hash(state);
-        map[key].hash(state);
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ch_a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, ch_b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ch_a != *ch_b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Text of a number as printed: whole numbers have no fraction,
+/// the rest have the shortest digits reading back as the same value.
+/// Magnitudes from 1e21 and below 1e-6 use the exponent notation
+///
+pub fn format_number(value: f64) -> String {
+    if value.is_nan() {
+        return "nan".to_string();
+    }
+    let magnitude = value.abs();
+    if magnitude.is_finite() && (magnitude >= 1e21 || (magnitude > 0.0 && magnitude < 1e-6)) {
+        return format!("{value:e}");
+    }
+    value.to_string()
+}
+
+/// Text escaped for a JSON string, the quotes aren't added.
+/// Besides the required escapes, DEL & C1 controls, `<`, `>`, `&` and
+/// the JS line separators are escaped, so the output is safe to embed in HTML
+///
+pub fn escape_json(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '<' | '>' | '&' | '\u{2028}' | '\u{2029}' => _ = write!(result, "\\u{:04x}", ch as u32),
+            ch if ch.is_control() => _ = write!(result, "\\u{:04x}", ch as u32),
+            ch => result.push(ch),
+        }
+    }
+    result
+}
+
+/// Text escaped for a quoted DOT id, the quotes aren't added.
+/// Line breaks become `\n` label escapes, the rest control chars
+/// have no representation and are replaced with U+FFFD
+///
+pub fn escape_dot(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => {
+                if chars.peek() == Some(&'\n') {
+                    _ = chars.next();
+                }
+                result.push_str("\\n");
+            }
+            ch if ch.is_control() => result.push(char::REPLACEMENT_CHARACTER),
+            ch => result.push(ch),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(4.0), "4");
+        assert_eq!(format_number(-12.0), "-12");
+        assert_eq!(format_number(0.0), "0");
+        assert_eq!(format_number(-0.0), "-0");
+        assert_eq!(format_number(2.5), "2.5");
+        assert_eq!(format_number(16777217.0), "16777217");
+        assert_eq!(format_number(9007199254740992.0), "9007199254740992");
+        assert_eq!(format_number(1e20), "100000000000000000000");
+        assert_eq!(format_number(1e21), "1e21");
+        assert_eq!(format_number(-3.5e30), "-3.5e30");
+        assert_eq!(format_number(f64::MAX), "1.7976931348623157e308");
+        assert_eq!(format_number(0.000001), "0.000001");
+        assert_eq!(format_number(1.5e-7), "1.5e-7");
+        assert_eq!(format_number(f64::INFINITY), "inf");
+        assert_eq!(format_number(f64::NEG_INFINITY), "-inf");
+        assert_eq!(format_number(f64::NAN), "nan");
+    }
+
+    #[test]
+    fn test_format_number_round_trip() {
+        // float noise is kept only when it's needed to read back the value
+        assert_eq!(format_number(0.3), "0.3");
+        let sum = 0.1 + 0.2;
+        assert_eq!(format_number(sum), "0.30000000000000004");
+        assert_eq!(format_number(0.1 + 0.7), "0.7999999999999999");
+        for value in [sum, 1.0 / 3.0, 123456.789, 1e-10, 7e25, -0.0, f64::MAX] {
+            let text = format_number(value);
+            assert_eq!(
+                text.parse::<f64>().unwrap().to_bits(),
+                value.to_bits(),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_escape_json_controls() {
+        for code in (0..0x20).chain(0x7f..=0x9f) {
+            let ch = char::from_u32(code).unwrap();
+            let expected = match ch {
+                '\n' => "\\n".to_string(),
+                '\r' => "\\r".to_string(),
+                '\t' => "\\t".to_string(),
+                _ => format!("\\u{code:04x}"),
+            };
+            assert_eq!(escape_json(&ch.to_string()), expected, "{code:#x}");
+        }
+    }
+
+    #[test]
+    fn test_escape_json() {
+        assert_eq!(escape_json("plain text"), "plain text");
+        assert_eq!(escape_json("\""), "\\\"");
+        assert_eq!(escape_json("\\"), "\\\\");
+        assert_eq!(escape_json("\\\""), "\\\\\\\"");
+        assert_eq!(escape_json("</script>&"), "\\u003c/script\\u003e\\u0026");
+        assert_eq!(escape_json("\u{2028}\u{2029}"), "\\u2028\\u2029");
+        // valid as is, including the astral plane
+        assert_eq!(escape_json("é▲🦊\u{10ffff}\u{a0}"), "é▲🦊\u{10ffff}\u{a0}");
+    }
+
+    #[test]
+    fn test_escape_dot() {
+        assert_eq!(escape_dot("node"), "node");
+        assert_eq!(escape_dot("\""), "\\\"");
+        assert_eq!(escape_dot("\\"), "\\\\");
+        assert_eq!(escape_dot("a\nb\r\nc\rd"), "a\\nb\\nc\\nd");
+        assert_eq!(escape_dot("</script>🦊"), "</script>🦊");
+        for code in (0..0x20).chain(0x7f..=0x9f) {
+            let ch = char::from_u32(code).unwrap();
+            if ch != '\n' && ch != '\r' {
+                assert_eq!(escape_dot(&ch.to_string()), "\u{fffd}", "{code:#x}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("--tset", "--test"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("same", "same"), 0);
     }
 }
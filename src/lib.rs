@@ -0,0 +1,3 @@
+mod fox;
+
+pub use fox::*;
@@ -1,36 +1,822 @@
-mod fox;
+mod repl;
+mod watch;
 
-use std::process::exit;
+use std::{process::exit, time::Duration};
 
-use crate::fox::Fox;
+use fox_lang::{
+    BuiltinGroup, Diagnostic, Fox, FoxConfig, FoxError, Program, Severity, Warning, WarningKind,
+    edit_distance, executing_location,
+};
+
+use crate::watch::WatchOptions;
 
 type ExitCode = i32;
 const EXIT_CODE_OK: ExitCode = 0;
 const EXIT_CODE_IO_ERROR: ExitCode = 1;
 const EXIT_CODE_PROCESSING_ERROR: ExitCode = 2;
+const EXIT_CODE_TESTS_FAILED: ExitCode = 3;
+const EXIT_CODE_USAGE_ERROR: ExitCode = 4;
+const EXIT_CODE_INTERNAL_ERROR: ExitCode = 5;
+
+const FLAG_HELP: &str = "--help";
+const FLAG_COVERAGE: &str = "--coverage";
+const FLAG_STATS: &str = "--stats";
+const FLAG_TEST: &str = "--test";
+const FLAG_WARN: &str = "--warn";
+const FLAG_DENY: &str = "--deny";
+const FLAG_COMPILE: &str = "--compile";
+const FLAG_OUTPUT: &str = "--output";
+const FLAG_DISABLE: &str = "--disable";
+const FLAG_HISTORY: &str = "--history";
+const FLAG_WATCH: &str = "--watch";
+const FLAG_NO_CLEAR: &str = "--no-clear";
+const FLAG_TIMEOUT: &str = "--timeout";
+const FLAG_ERROR_FORMAT: &str = "--error-format";
+const FLAG_REPL: &str = "--repl";
+
+/// Marks the end of flags, the rest arguments are the script file & its arguments
+const END_OF_FLAGS: &str = "--";
+
+/// Extension of the compiled programs
+const COMPILED_EXTENSION: &str = "foxc";
+
+/// Command line flag description used both for parsing & help output
+struct Flag {
+    name: &'static str,
+    alias: Option<&'static str>,
+    value: Option<&'static str>,
+    group: &'static str,
+    description: &'static str,
+}
+
+const FLAGS: &[Flag] = &[
+    Flag {
+        name: FLAG_HELP,
+        alias: Some("-h"),
+        value: None,
+        group: "General",
+        description: "Print this help",
+    },
+    Flag {
+        name: FLAG_REPL,
+        alias: None,
+        value: None,
+        group: "General",
+        description: "Start an interactive session, a line ending with a tab lists completions",
+    },
+    Flag {
+        name: FLAG_TEST,
+        alias: None,
+        value: None,
+        group: "Execution",
+        description: "Run the script, then every `test_*` function",
+    },
+    Flag {
+        name: FLAG_COVERAGE,
+        alias: None,
+        value: None,
+        group: "Execution",
+        description: "Print line & function coverage after the run",
+    },
+    Flag {
+        name: FLAG_STATS,
+        alias: None,
+        value: None,
+        group: "Execution",
+        description: "Print interpreter statistics to stderr after the run",
+    },
+    Flag {
+        name: FLAG_WATCH,
+        alias: None,
+        value: None,
+        group: "Watch",
+        description: "Re-run the script every time the file changes",
+    },
+    Flag {
+        name: FLAG_NO_CLEAR,
+        alias: None,
+        value: None,
+        group: "Watch",
+        description: "Keep the output of the previous runs on the screen",
+    },
+    Flag {
+        name: FLAG_TIMEOUT,
+        alias: None,
+        value: Some("<seconds>"),
+        group: "Watch",
+        description: "Cancel a run lasting longer, 10 seconds by default",
+    },
+    Flag {
+        name: FLAG_COMPILE,
+        alias: None,
+        value: None,
+        group: "Compilation",
+        description: "Store the parsed script as a program file instead of running it",
+    },
+    Flag {
+        name: FLAG_OUTPUT,
+        alias: Some("-o"),
+        value: Some("<path>"),
+        group: "Compilation",
+        description: "Compiled program path, the script path with .foxc extension by default",
+    },
+    Flag {
+        name: FLAG_WARN,
+        alias: None,
+        value: Some("<kind>"),
+        group: "Diagnostics",
+        description: "Report opt-in warnings of the kind",
+    },
+    Flag {
+        name: FLAG_DENY,
+        alias: None,
+        value: Some("<kind>"),
+        group: "Diagnostics",
+        description: "Report warnings of the kind as errors",
+    },
+    Flag {
+        name: FLAG_HISTORY,
+        alias: None,
+        value: Some("<count>"),
+        group: "Diagnostics",
+        description: "Print the last executed statements & calls and the globals with runtime errors",
+    },
+    Flag {
+        name: FLAG_ERROR_FORMAT,
+        alias: None,
+        value: Some("<format>"),
+        group: "Diagnostics",
+        description: "Print errors & warnings as 'human' text or 'json' lines on stderr",
+    },
+    Flag {
+        name: FLAG_DISABLE,
+        alias: None,
+        value: Some("<group>"),
+        group: "Sandbox",
+        description: "Make builtins of the group fail when called",
+    },
+];
+
+/// Max edit distance of an unknown flag to be suggested as a typo
+const MAX_SUGGESTION_DISTANCE: usize = 3;
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Help,
+    Repl,
+    Run {
+        path: String,
+        test: bool,
+        stats: bool,
+    },
+    Compile {
+        path: String,
+        output: String,
+    },
+    Watch {
+        path: String,
+        test: bool,
+        stats: bool,
+        options: WatchOptions,
+    },
+}
+
+/// How errors & warnings are printed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum ErrorFormat {
+    /// Source line with the location marked, errors go to stdout
+    #[default]
+    Human,
+    /// A JSON object per line on stderr
+    Json,
+}
+
+impl ErrorFormat {
+    const ALL: [ErrorFormat; 2] = [ErrorFormat::Human, ErrorFormat::Json];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ErrorFormat::Human => "human",
+            ErrorFormat::Json => "json",
+        }
+    }
+}
+
+struct Cli {
+    command: Command,
+    config: FoxConfig,
+    error_format: ErrorFormat,
+}
 
 fn main() {
-    let args = std::env::args().collect::<Vec<_>>();
-    match args.len() {
-        2 => run(&args[1]),
-        _ => show_usage(),
+    let cli = match parse_args(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(message) => {
+            eprintln!("error: {message}");
+            eprintln!("Run with '{FLAG_HELP}' to see available options");
+            exit(EXIT_CODE_USAGE_ERROR);
+        }
+    };
+    let mut config = cli.config;
+    let format = cli.error_format;
+    if let Command::Run { path, .. } | Command::Compile { path, .. } | Command::Watch { path, .. } =
+        &cli.command
+    {
+        install_panic_hook(path.clone());
+    }
+    match cli.command {
+        Command::Help => show_help(),
+        Command::Repl => repl::repl(config, format),
+        Command::Compile { path, output } => {
+            config.source_name = path;
+            compile_program(config, &output, format)
+        }
+        Command::Run { path, test, stats } if path.ends_with(&format!(".{COMPILED_EXTENSION}")) => {
+            run_compiled(config, &path, test, stats, format)
+        }
+        Command::Run { path, test, stats } => {
+            config.source_name = path;
+            let Ok(data) = std::fs::read_to_string(&config.source_name) else {
+                exit(EXIT_CODE_IO_ERROR);
+            };
+            let code = data.chars().collect::<Vec<_>>();
+            run(Fox::with_config(code, config), test, stats, format)
+        }
+        Command::Watch {
+            path,
+            test,
+            stats,
+            options,
+        } => {
+            config.source_name = path;
+            watch::watch(config, &options, move |fox| {
+                execute(fox, test, stats, format);
+            })
+        }
     }
     exit(EXIT_CODE_OK);
 }
 
-fn run<T: AsRef<str>>(path: T) {
-    let Ok(data) = std::fs::read_to_string(path.as_ref()) else {
+/// Replaces the raw backtrace with a bug report request
+fn install_panic_hook(path: String) {
+    std::panic::set_hook(Box::new(move |info| {
+        let reason = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|x| x.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown reason".to_string());
+        let location = match executing_location() {
+            Some(location) => format!("{path}:{}", location.line_number()),
+            None => path.clone(),
+        };
+        eprintln!(
+            "internal error: fox-lang {} crashed while running {location}: {reason}",
+            env!("CARGO_PKG_VERSION")
+        );
+        eprintln!("This is a bug in the interpreter, please report it with the script attached");
+        exit(EXIT_CODE_INTERNAL_ERROR);
+    }));
+}
+
+fn parse_args<I>(args: I) -> Result<Cli, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut config = FoxConfig::default();
+    let mut used = Vec::<&'static str>::new();
+    let mut output = None;
+    let mut watch_options = WatchOptions::default();
+    let mut error_format = ErrorFormat::default();
+    let mut paths = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == END_OF_FLAGS {
+            paths.extend(args.by_ref());
+            break;
+        }
+        if !arg.starts_with('-') || arg == "-" {
+            paths.push(arg);
+            continue;
+        }
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        let flag = find_flag(name)?;
+        let value = match (flag.value, value) {
+            (Some(placeholder), None) => {
+                let value = args.next().filter(|value| !value.starts_with('-'));
+                Some(value.ok_or_else(|| {
+                    format!("flag '{}' requires a value {placeholder}", flag.name)
+                })?)
+            }
+            (None, Some(_)) => return Err(format!("flag '{}' doesn't take a value", flag.name)),
+            (_, value) => value,
+        };
+        used.push(flag.name);
+        match flag.name {
+            FLAG_OUTPUT => output = value,
+            FLAG_WARN | FLAG_DENY => {
+                let name = value.unwrap_or_default();
+                let kind = WarningKind::with_name(&name).ok_or_else(|| {
+                    format!(
+                        "unknown warning kind '{name}' for '{}', expected one of: {}",
+                        flag.name,
+                        warning_kinds(", ")
+                    )
+                })?;
+                if flag.name == FLAG_WARN {
+                    config.warn.push(kind);
+                } else {
+                    config.deny.push(kind);
+                }
+            }
+            FLAG_DISABLE => {
+                let name = value.unwrap_or_default();
+                let group = BuiltinGroup::with_name(&name).ok_or_else(|| {
+                    format!(
+                        "unknown builtin group '{name}', expected one of: {}",
+                        builtin_groups(", ")
+                    )
+                })?;
+                config.disabled_builtins.push(group);
+            }
+            FLAG_HISTORY => {
+                let count = value.unwrap_or_default();
+                config.history = count.parse().map_err(|_| {
+                    format!("'{FLAG_HISTORY}' expects a count of entries, got '{count}'")
+                })?;
+            }
+            FLAG_TIMEOUT => {
+                let seconds = value.unwrap_or_default();
+                watch_options.timeout = seconds
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|x| Duration::try_from_secs_f64(x).ok())
+                    .filter(|x| !x.is_zero())
+                    .ok_or_else(|| {
+                        format!(
+                            "'{FLAG_TIMEOUT}' expects a positive number of seconds, got '{seconds}'"
+                        )
+                    })?;
+            }
+            FLAG_ERROR_FORMAT => {
+                let name = value.unwrap_or_default();
+                error_format = ErrorFormat::ALL
+                    .into_iter()
+                    .find(|format| format.name() == name)
+                    .ok_or_else(|| {
+                        format!("unknown error format '{name}', expected 'human' or 'json'")
+                    })?;
+            }
+            FLAG_NO_CLEAR => watch_options.clear = false,
+            FLAG_COVERAGE => config.coverage = true,
+            _ => {
+                // boolean flags are checked by presence
+            }
+        }
+    }
+
+    let is_used = |name: &str| used.contains(&name);
+    if is_used(FLAG_HELP) {
+        return Ok(Cli {
+            command: Command::Help,
+            config,
+            error_format,
+        });
+    }
+    for (first, second) in [
+        (FLAG_COMPILE, FLAG_TEST),
+        (FLAG_COMPILE, FLAG_COVERAGE),
+        (FLAG_COMPILE, FLAG_STATS),
+        (FLAG_COMPILE, FLAG_HISTORY),
+        (FLAG_COMPILE, FLAG_WATCH),
+        (FLAG_REPL, FLAG_COMPILE),
+        (FLAG_REPL, FLAG_WATCH),
+        (FLAG_REPL, FLAG_TEST),
+        (FLAG_REPL, FLAG_COVERAGE),
+    ] {
+        if is_used(first) && is_used(second) {
+            return Err(format!("'{first}' can't be combined with '{second}'"));
+        }
+    }
+    for (flag, required) in [
+        (FLAG_OUTPUT, FLAG_COMPILE),
+        (FLAG_NO_CLEAR, FLAG_WATCH),
+        (FLAG_TIMEOUT, FLAG_WATCH),
+    ] {
+        if is_used(flag) && !is_used(required) {
+            return Err(format!("'{flag}' requires '{required}'"));
+        }
+    }
+    if is_used(FLAG_REPL) {
+        if !paths.is_empty() {
+            return Err(format!("'{FLAG_REPL}' doesn't take a script file"));
+        }
+        return Ok(Cli {
+            command: Command::Repl,
+            config,
+            error_format,
+        });
+    }
+    if paths.is_empty() {
+        return Err("no script file given".to_string());
+    }
+    let path = paths.remove(0);
+    config.script_args = paths;
+    if is_used(FLAG_COMPILE) && !config.script_args.is_empty() {
+        return Err(format!("'{FLAG_COMPILE}' doesn't take script arguments"));
+    }
+    if is_used(FLAG_WATCH) && path.ends_with(&format!(".{COMPILED_EXTENSION}")) {
+        return Err(format!("'{FLAG_WATCH}' expects a script source"));
+    }
+    let command = if is_used(FLAG_COMPILE) {
+        let output = output.unwrap_or_else(|| compiled_path(&path));
+        Command::Compile { path, output }
+    } else if is_used(FLAG_WATCH) {
+        Command::Watch {
+            path,
+            test: is_used(FLAG_TEST),
+            stats: is_used(FLAG_STATS),
+            options: watch_options,
+        }
+    } else {
+        let test = is_used(FLAG_TEST);
+        let stats = is_used(FLAG_STATS);
+        Command::Run { path, test, stats }
+    };
+    Ok(Cli {
+        command,
+        config,
+        error_format,
+    })
+}
+
+fn find_flag(name: &str) -> Result<&'static Flag, String> {
+    if let Some(flag) = FLAGS
+        .iter()
+        .find(|flag| flag.name == name || flag.alias == Some(name))
+    {
+        return Ok(flag);
+    }
+    let suggestion = FLAGS
+        .iter()
+        .map(|flag| (edit_distance(name, flag.name), flag.name))
+        .min()
+        .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE);
+    let mut message = format!("unknown flag '{name}'");
+    if let Some((_, suggestion)) = suggestion {
+        message.push_str(&format!(", did you mean '{suggestion}'?"));
+    }
+    Err(message)
+}
+
+fn builtin_groups(separator: &str) -> String {
+    BuiltinGroup::ALL
+        .iter()
+        .map(|group| group.name())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn warning_kinds(separator: &str) -> String {
+    WarningKind::ALL
+        .iter()
+        .map(|kind| kind.name())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn compiled_path(source_path: &str) -> String {
+    std::path::Path::new(source_path)
+        .with_extension(COMPILED_EXTENSION)
+        .to_string_lossy()
+        .to_string()
+}
+
+fn print_error(fox: &Fox, err: &FoxError, format: ErrorFormat) {
+    match format {
+        ErrorFormat::Human => println!("{}", fox.error_description(err)),
+        ErrorFormat::Json => eprintln!("{}", fox.error_diagnostic(err).to_json()),
+    }
+}
+
+fn print_warning(fox: &Fox, warning: &Warning, format: ErrorFormat) {
+    match format {
+        ErrorFormat::Human => eprintln!("{}", fox.warning_description(warning)),
+        ErrorFormat::Json => eprintln!("{}", fox.warning_diagnostic(warning).to_json()),
+    }
+}
+
+fn compile_program(config: FoxConfig, output: &str, format: ErrorFormat) {
+    let Ok(data) = std::fs::read_to_string(&config.source_name) else {
+        exit(EXIT_CODE_IO_ERROR);
+    };
+    let mut fox = Fox::with_config(data.chars().collect(), config);
+    let result = fox.compile().and_then(|program| program.to_bytes());
+    for warning in fox.warnings() {
+        print_warning(&fox, warning, format);
+    }
+    match result {
+        Ok(bytes) => {
+            if std::fs::write(output, bytes).is_err() {
+                exit(EXIT_CODE_IO_ERROR);
+            }
+        }
+        Err(err) => {
+            print_error(&fox, &err, format);
+            exit(EXIT_CODE_PROCESSING_ERROR);
+        }
+    }
+}
+
+fn run_compiled(config: FoxConfig, path: &str, test: bool, stats: bool, format: ErrorFormat) {
+    let Ok(bytes) = std::fs::read(path) else {
         exit(EXIT_CODE_IO_ERROR);
     };
-    let code = data.chars().collect::<Vec<_>>();
-    let fox = Fox::with(code);
-    let result = fox.run();
+    let fox = Program::from_bytes(&bytes).and_then(|program| {
+        // the source is optional, it's verified & used for error messages if exists
+        let code = std::fs::read_to_string(program.source_name())
+            .ok()
+            .map(|data| data.chars().collect());
+        Fox::with_program(program, code, config)
+    });
+    match fox {
+        Ok(fox) => run(fox, test, stats, format),
+        Err(err) => {
+            match format {
+                ErrorFormat::Human => println!("{}", err.kind()),
+                ErrorFormat::Json => {
+                    let kind = err.kind();
+                    let message = kind.to_string();
+                    let diagnostic = Diagnostic::new(Severity::Error, kind.code(), &message, path);
+                    eprintln!("{}", diagnostic.to_json());
+                }
+            }
+            exit(EXIT_CODE_PROCESSING_ERROR);
+        }
+    }
+}
+
+fn run(fox: Fox, test: bool, stats: bool, format: ErrorFormat) {
+    let code = execute(fox, test, stats, format);
+    if code != EXIT_CODE_OK {
+        exit(code);
+    }
+}
+
+/// Runs the script reporting the results, returns the exit code
+fn execute(mut fox: Fox, test: bool, stats: bool, format: ErrorFormat) -> ExitCode {
+    let mut tests_failed = false;
+    let result = if test {
+        fox.run_tests().map(|report| {
+            print!("{report}");
+            tests_failed = !report.is_success();
+        })
+    } else {
+        fox.run()
+    };
+    for warning in fox.warnings() {
+        print_warning(&fox, warning, format);
+    }
+    if let Some(report) = fox.coverage_report() {
+        print!("{report}");
+    }
+    if stats {
+        eprint!("{}", fox.statistics());
+    }
     if let Err(err) = result {
-        println!("{}", fox.error_description(&err));
-        exit(EXIT_CODE_PROCESSING_ERROR);
+        if let Some(code) = err.kind().exit_code() {
+            return code;
+        }
+        print_error(&fox, &err, format);
+        if let (ErrorFormat::Human, Some(state)) = (format, fox.failure_state()) {
+            println!("// globals at the failure, replayable as source:");
+            print!("{state}");
+        }
+        return EXIT_CODE_PROCESSING_ERROR;
+    }
+    if tests_failed {
+        return EXIT_CODE_TESTS_FAILED;
+    }
+    EXIT_CODE_OK
+}
+
+fn show_help() {
+    println!("Usage: fox-lang [flags] <script.fox|script.{COMPILED_EXTENSION}> [script arguments]");
+    println!("Arguments starting with '-' follow '{END_OF_FLAGS}' to reach the script");
+    let title = |flag: &Flag| {
+        let mut title = flag.name.to_string();
+        if let Some(alias) = flag.alias {
+            title = format!("{alias}, {title}");
+        }
+        if let Some(value) = flag.value {
+            title = format!("{title} {value}");
+        }
+        title
+    };
+    let width = FLAGS
+        .iter()
+        .map(|flag| title(flag).len())
+        .max()
+        .unwrap_or(0);
+    let mut groups = Vec::<&str>::new();
+    for flag in FLAGS {
+        if !groups.contains(&flag.group) {
+            groups.push(flag.group);
+        }
+    }
+    for group in groups {
+        println!();
+        println!("{group}:");
+        for flag in FLAGS.iter().filter(|flag| flag.group == group) {
+            println!("  {:width$}  {}", title(flag), flag.description);
+        }
     }
+    println!();
+    println!("Warning kinds: {}", warning_kinds(", "));
+    println!("Builtin groups: {}", builtin_groups(", "));
 }
 
-fn show_usage() {
-    println!("Usage: fox-lang <script.fox>");
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn parse_error(args: &[&str]) -> String {
+        match parse(args) {
+            Ok(cli) => panic!("Error expected, got {:?}", cli.command),
+            Err(message) => message,
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        let cli = parse(&["--coverage", "script.fox"]).unwrap();
+        assert!(cli.config.coverage);
+        assert_eq!(
+            cli.command,
+            Command::Run {
+                path: "script.fox".to_string(),
+                test: false,
+                stats: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_values() {
+        let cli = parse(&[
+            "--warn=missing-return",
+            "--deny",
+            "deprecated",
+            "--test",
+            "--stats",
+            "a.fox",
+        ])
+        .unwrap();
+        assert_eq!(cli.config.warn, vec![WarningKind::MissingReturn]);
+        assert_eq!(cli.config.deny, vec![WarningKind::Deprecated]);
+        assert!(matches!(
+            cli.command,
+            Command::Run {
+                test: true,
+                stats: true,
+                ..
+            }
+        ));
+
+        let message = parse_error(&["--deny", "unknown", "a.fox"]);
+        assert!(message.contains("expected one of: deprecated"), "{message}");
+        let message = parse_error(&["a.fox", "--deny"]);
+        assert_eq!(message, "flag '--deny' requires a value <kind>");
+        let cli = parse(&["--history", "8", "a.fox"]).unwrap();
+        assert_eq!(cli.config.history, 8);
+        let message = parse_error(&["--history=many", "a.fox"]);
+        assert_eq!(
+            message,
+            "'--history' expects a count of entries, got 'many'"
+        );
+        let cli = parse(&["--error-format=json", "a.fox"]).unwrap();
+        assert_eq!(cli.error_format, ErrorFormat::Json);
+        let message = parse_error(&["--error-format", "xml", "a.fox"]);
+        assert_eq!(
+            message,
+            "unknown error format 'xml', expected 'human' or 'json'"
+        );
+        let cli = parse(&["--disable", "io", "a.fox"]).unwrap();
+        assert_eq!(cli.config.disabled_builtins, vec![BuiltinGroup::Io]);
+        let message = parse_error(&["--test=yes", "a.fox"]);
+        assert_eq!(message, "flag '--test' doesn't take a value");
+    }
+
+    #[test]
+    fn test_watch() {
+        let cli = parse(&["--watch", "--no-clear", "--timeout", "0.5", "a.fox"]).unwrap();
+        let options = WatchOptions {
+            clear: false,
+            timeout: Duration::from_millis(500),
+        };
+        assert_eq!(
+            cli.command,
+            Command::Watch {
+                path: "a.fox".to_string(),
+                test: false,
+                stats: false,
+                options,
+            }
+        );
+        let cli = parse(&["--watch", "--test", "a.fox"]).unwrap();
+        assert!(
+            matches!(cli.command, Command::Watch { test: true, options, .. } if options == WatchOptions::default())
+        );
+
+        assert_eq!(
+            parse_error(&["--timeout=0", "--watch", "a.fox"]),
+            "'--timeout' expects a positive number of seconds, got '0'"
+        );
+        assert_eq!(
+            parse_error(&["--no-clear", "a.fox"]),
+            "'--no-clear' requires '--watch'"
+        );
+        assert_eq!(
+            parse_error(&["--watch", "a.foxc"]),
+            "'--watch' expects a script source"
+        );
+        assert_eq!(
+            parse_error(&["--compile", "--watch", "a.fox"]),
+            "'--compile' can't be combined with '--watch'"
+        );
+    }
+
+    #[test]
+    fn test_compile() {
+        let cli = parse(&["--compile", "dir/a.fox"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Compile {
+                path: "dir/a.fox".to_string(),
+                output: "dir/a.foxc".to_string()
+            }
+        );
+        let cli = parse(&["--compile", "a.fox", "-o", "b.foxc"]).unwrap();
+        assert!(matches!(cli.command, Command::Compile { output, .. } if output == "b.foxc"));
+    }
+
+    #[test]
+    fn test_conflicts() {
+        assert_eq!(
+            parse_error(&["--compile", "--test", "a.fox"]),
+            "'--compile' can't be combined with '--test'"
+        );
+        assert_eq!(
+            parse_error(&["--output", "b.foxc", "a.fox"]),
+            "'--output' requires '--compile'"
+        );
+    }
+
+    #[test]
+    fn test_unknown_flag() {
+        assert_eq!(
+            parse_error(&["--tset", "a.fox"]),
+            "unknown flag '--tset', did you mean '--test'?"
+        );
+        assert_eq!(
+            parse_error(&["--xyzxyzxyz", "a.fox"]),
+            "unknown flag '--xyzxyzxyz'"
+        );
+    }
+
+    #[test]
+    fn test_positional() {
+        assert_eq!(parse_error(&[]), "no script file given");
+        let cli = parse(&["a.fox", "b.fox", "--test", "--", "-v", "--stats"]).unwrap();
+        assert_eq!(cli.config.script_args, ["b.fox", "-v", "--stats"]);
+        assert!(
+            matches!(cli.command, Command::Run { path, test: true, stats: false } if path == "a.fox")
+        );
+        assert_eq!(
+            parse_error(&["--compile", "a.fox", "b.fox"]),
+            "'--compile' doesn't take script arguments"
+        );
+        let cli = parse(&["--", "--test"]).unwrap();
+        assert!(matches!(cli.command, Command::Run { path, test: false, .. } if path == "--test"));
+        assert_eq!(parse(&["--repl"]).unwrap().command, Command::Repl);
+        assert_eq!(
+            parse_error(&["--repl", "a.fox"]),
+            "'--repl' doesn't take a script file"
+        );
+        assert_eq!(
+            parse_error(&["--repl", "--watch"]),
+            "'--repl' can't be combined with '--watch'"
+        );
+        // help wins over missing arguments
+        let cli = parse(&["-h"]).unwrap();
+        assert_eq!(cli.command, Command::Help);
+    }
 }
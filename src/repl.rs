@@ -0,0 +1,103 @@
+use std::io::{BufRead, Write};
+
+use fox_lang::{Completion, FoxConfig, Repl};
+
+use crate::{ErrorFormat, print_error, print_warning};
+
+const PROMPT: &str = "> ";
+
+/// The terminal passes a typed tab with the line once Enter is pressed,
+/// so a line ending with it asks for completions instead of running
+const COMPLETION_KEY: char = '\t';
+
+/// Max count of candidates listed for a completion request
+const MAX_CANDIDATES: usize = 32;
+
+/// Reads & runs lines from stdin until it's closed
+pub fn repl(config: FoxConfig, format: ErrorFormat) {
+    let mut repl = Repl::with_config(config);
+    let mut input = std::io::stdin().lock();
+    loop {
+        print!("{PROMPT}");
+        _ = std::io::stdout().flush();
+        let mut line = String::new();
+        match input.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("error: can't read the input: {err}");
+                break;
+            }
+        }
+        if let Some(text) = completion_request(&line) {
+            print!("{}", candidates_list(&repl.completions(text)));
+            continue;
+        }
+        let result = repl.eval(&line);
+        for warning in repl.fox().warnings() {
+            print_warning(repl.fox(), warning, format);
+        }
+        if let Err(err) = result {
+            if let Some(code) = err.kind().exit_code() {
+                std::process::exit(code);
+            }
+            print_error(repl.fox(), &err, format);
+            // the host state can't be trusted anymore
+            if err.kind().is_fatal() {
+                break;
+            }
+        }
+    }
+    println!();
+}
+
+/// Text to complete if the line asks for it
+fn completion_request(line: &str) -> Option<&str> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    line.strip_suffix(COMPLETION_KEY)
+}
+
+fn candidates_list(candidates: &[Completion]) -> String {
+    if candidates.is_empty() {
+        return "no completions\n".to_string();
+    }
+    let mut list = candidates
+        .iter()
+        .take(MAX_CANDIDATES)
+        .map(|candidate| format!("  {candidate}\n"))
+        .collect::<String>();
+    if candidates.len() > MAX_CANDIDATES {
+        list.push_str(&format!(
+            "  ...and {} more\n",
+            candidates.len() - MAX_CANDIDATES
+        ));
+    }
+    list
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use fox_lang::CompletionKind;
+
+    #[test]
+    fn test_completion_request() {
+        assert_eq!(completion_request("print cl\t\n"), Some("print cl"));
+        assert_eq!(completion_request("pr\t\r\n"), Some("pr"));
+        assert_eq!(completion_request("print 1;\n"), None);
+        assert_eq!(completion_request("print \"\t\";\n"), None);
+    }
+
+    #[test]
+    fn test_candidates_list() {
+        assert_eq!(candidates_list(&[]), "no completions\n");
+        let candidates = [
+            Completion::new("clock", CompletionKind::Function, Some(0)),
+            Completion::new("class", CompletionKind::Keyword, None),
+        ];
+        assert_eq!(
+            candidates_list(&candidates),
+            "  clock/0 (function)\n  class (keyword)\n"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use fox_lang::{Fox, FoxConfig};
+
+/// How often the script modification time is checked
+pub const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// A change is picked up once the file stays untouched that long,
+/// editors often save in several writes
+pub const DEBOUNCE: Duration = Duration::from_millis(200);
+/// Run time after which the script is cancelled by default
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time given to the cancelled script to stop before it's abandoned
+const CANCEL_GRACE: Duration = Duration::from_secs(1);
+
+/// ANSI sequence clearing the terminal & moving the cursor home
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchOptions {
+    /// Clear the terminal before every run
+    pub clear: bool,
+    /// Max run time of the script
+    pub timeout: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            clear: true,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+/// Decides when the script must be re-run by its modification times
+///
+#[derive(Debug, Default)]
+pub struct ChangeTracker {
+    // modification time of the script version that was run last
+    last_run: Option<SystemTime>,
+    // new modification time & the moment it was noticed
+    pending: Option<(SystemTime, Instant)>,
+}
+
+impl ChangeTracker {
+    pub fn with_last_run(modified: Option<SystemTime>) -> Self {
+        Self {
+            last_run: modified,
+            pending: None,
+        }
+    }
+
+    /// Returns true if the script must be run now.
+    /// A missing file is ignored since editors may replace the file on save
+    pub fn poll(&mut self, modified: Option<SystemTime>, now: Instant) -> bool {
+        let Some(modified) = modified else {
+            return false;
+        };
+        if self.last_run == Some(modified) {
+            self.pending = None;
+            return false;
+        }
+        match self.pending {
+            Some((pending, noticed)) if pending == modified => {
+                if now.duration_since(noticed) < DEBOUNCE {
+                    return false;
+                }
+                self.last_run = Some(modified);
+                self.pending = None;
+                true
+            }
+            _ => {
+                self.pending = Some((modified, now));
+                false
+            }
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|x| x.modified()).ok()
+}
+
+/// Runs the script on every change until the process is interrupted,
+/// Ctrl-C terminates the process with the default signal handling.
+/// Every run gets a fresh interpreter in its own thread,
+/// so neither state nor a hanging script affects the next run
+pub fn watch<F>(config: FoxConfig, options: &WatchOptions, runner: F) -> !
+where
+    F: Fn(Fox) + Clone + Send + 'static,
+{
+    let path = Path::new(&config.source_name).to_path_buf();
+    let mut tracker = ChangeTracker::with_last_run(modified_time(&path));
+    loop {
+        if options.clear {
+            print!("{CLEAR_SCREEN}");
+        }
+        match std::fs::read_to_string(&path) {
+            Ok(data) => run_once(data.chars().collect(), &config, options, runner.clone()),
+            Err(err) => eprintln!("error: can't read '{}': {err}", path.display()),
+        }
+        eprintln!(
+            "[watching '{}' for changes, press Ctrl-C to exit]",
+            path.display()
+        );
+        while !tracker.poll(modified_time(&path), Instant::now()) {
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+fn run_once<F>(code: Vec<char>, config: &FoxConfig, options: &WatchOptions, runner: F)
+where
+    F: Fn(Fox) + Send + 'static,
+{
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    let mut config = config.clone();
+    config.cancel_flag = Some(cancel_flag.clone());
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        runner(Fox::with_config(code, config));
+        _ = sender.send(());
+    });
+    if let Err(RecvTimeoutError::Timeout) = receiver.recv_timeout(options.timeout) {
+        cancel_flag.store(true, Ordering::Relaxed);
+        eprintln!(
+            "error: the script is cancelled after running for {:?}",
+            options.timeout
+        );
+        // a builtin may block, such a thread is left behind
+        if let Err(RecvTimeoutError::Timeout) = receiver.recv_timeout(CANCEL_GRACE) {
+            eprintln!("error: the script didn't stop, abandoned");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_change_tracker() {
+        let start = Instant::now();
+        let saved = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        let mut tracker = ChangeTracker::with_last_run(Some(saved));
+        assert!(!tracker.poll(Some(saved), start));
+        assert!(!tracker.poll(None, start));
+
+        // the change is reported once it's stable for the debounce time
+        let edited = saved + Duration::from_secs(1);
+        assert!(!tracker.poll(Some(edited), start));
+        assert!(!tracker.poll(Some(edited), start + DEBOUNCE / 2));
+        assert!(tracker.poll(Some(edited), start + DEBOUNCE));
+        assert!(!tracker.poll(Some(edited), start + DEBOUNCE * 2));
+
+        // a write during the debounce restarts it
+        let first = edited + Duration::from_secs(1);
+        let second = first + Duration::from_secs(1);
+        let now = start + DEBOUNCE * 3;
+        assert!(!tracker.poll(Some(first), now));
+        assert!(!tracker.poll(Some(second), now + DEBOUNCE));
+        assert!(!tracker.poll(Some(second), now + DEBOUNCE + DEBOUNCE / 2));
+        assert!(tracker.poll(Some(second), now + DEBOUNCE * 2));
+
+        // going back to the version run last is not a change
+        let reverted = second + Duration::from_secs(1);
+        assert!(!tracker.poll(Some(reverted), now + DEBOUNCE * 3));
+        assert!(!tracker.poll(Some(second), now + DEBOUNCE * 4));
+        assert!(!tracker.poll(Some(second), now + DEBOUNCE * 6));
+    }
+
+    #[test]
+    fn test_first_appearance_of_file() {
+        let start = Instant::now();
+        let created = SystemTime::UNIX_EPOCH;
+        let mut tracker = ChangeTracker::with_last_run(None);
+        assert!(!tracker.poll(Some(created), start));
+        assert!(tracker.poll(Some(created), start + DEBOUNCE));
+    }
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use common::{fixture_path, fox_command, output_text};
+
+/// Runs the script with the arguments and returns stdout & stderr
+fn run(script_args: &[&str]) -> (String, String) {
+    let output = fox_command()
+        .arg(fixture_path("arguments.fox"))
+        .args(script_args)
+        .env("FOX_TEST_GREETING", "hi fox")
+        .env_remove("FOX_TEST_UNSET_VARIABLE")
+        .output()
+        .expect("failed to launch interpreter");
+    output_text(&output)
+}
+
+#[test]
+fn test_script_arguments() {
+    let (stdout, stderr) = run(&["first", "second arg", "--", "-v"]);
+    assert_eq!(stderr, "");
+    assert_eq!(stdout, "3\nfirst\nsecond arg\n-v\nhi fox\nnil\n");
+}
+
+#[test]
+fn test_no_arguments() {
+    let (stdout, stderr) = run(&[]);
+    assert_eq!(stderr, "");
+    assert_eq!(stdout, "0\nhi fox\nnil\n");
+}
@@ -0,0 +1,22 @@
+//! Helpers shared by the integration tests,
+//! each test crate uses only some of them
+#![allow(dead_code)]
+
+use std::process::{Command, Output};
+
+pub fn fixture_path(name: &str) -> String {
+    format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))
+}
+
+/// The interpreter binary built for the tests
+pub fn fox_command() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_fox-lang"))
+}
+
+/// Stdout & stderr of the finished process as text
+pub fn output_text(output: &Output) -> (String, String) {
+    (
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    )
+}
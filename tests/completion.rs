@@ -0,0 +1,88 @@
+mod common;
+
+use common::fixture_path;
+use fox_lang::{Completion, Fox, FoxConfig};
+
+fn fixture() -> Fox {
+    let code = std::fs::read_to_string(fixture_path("completion.fox")).expect("missing fixture");
+    let mut config = FoxConfig::default();
+    config.declare_global("host_log", Some(1));
+    Fox::with_config(code.chars().collect(), config)
+}
+
+/// Candidates at the 1-based line & column as "name/arity (kind)"
+fn candidates(line: usize, column: usize) -> Vec<String> {
+    fixture()
+        .completions(line, column)
+        .iter()
+        .map(Completion::to_string)
+        .collect()
+}
+
+#[test]
+fn test_method_locals() {
+    assert_eq!(candidates(15, 19), ["result (variable)"]);
+    let all = candidates(14, 9);
+    for expected in ["factor (variable)", "origin (variable)", "this (keyword)"] {
+        assert!(all.contains(&expected.to_string()), "{expected}");
+    }
+    // parameters of other methods are out of scope
+    assert!(!all.contains(&"r (variable)".to_string()));
+}
+
+#[test]
+fn test_function_locals() {
+    assert_eq!(
+        candidates(21, 13),
+        [
+            "shapes (variable)",
+            "sleep/1 (function)",
+            "sort/1 (function)",
+            "split/2 (function)",
+            "sqrt/1 (function)",
+            "starts_with/2 (function)",
+            "str/1 (function)",
+            "str_len/1 (function)",
+            "sum (variable)",
+            "super (keyword)"
+        ]
+    );
+    // the loop variable is visible in the body without braces
+    assert_eq!(
+        candidates(20, 45),
+        ["shape (variable)", "shapes (variable)"]
+    );
+}
+
+#[test]
+fn test_globals() {
+    let all = candidates(23, 1);
+    for expected in [
+        "Circle/1 (class)",
+        "Shape/1 (class)",
+        "clock/0 (function)",
+        "count (variable)",
+        "host_log/1 (function)",
+        "total/1 (function)",
+        "while (keyword)",
+    ] {
+        assert!(all.contains(&expected.to_string()), "{expected}");
+    }
+    assert!(!all.iter().any(|x| x.starts_with("sum")));
+    assert_eq!(candidates(23, 4), ["print (keyword)"]);
+}
+
+#[test]
+fn test_members() {
+    assert_eq!(
+        candidates(11, 14),
+        [
+            "area/0 (method)",
+            "init/1 (method)",
+            "name (variable)",
+            "radius (variable)",
+            "scale/2 (method)"
+        ]
+    );
+    assert_eq!(candidates(24, 7), ["area/0 (method)", "init/1 (method)"]);
+}
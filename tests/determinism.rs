@@ -0,0 +1,50 @@
+mod common;
+
+use common::{fixture_path, fox_command};
+
+/// Each process seeds its hash maps randomly, so the runs
+/// differ in the iteration order of every HashMap involved
+const RUNS: usize = 4;
+
+/// Runs the interpreter with the flags and returns stdout & stderr
+fn run(flags: &[&str]) -> (Vec<u8>, Vec<u8>) {
+    let output = fox_command()
+        .args(flags)
+        .output()
+        .expect("failed to launch interpreter");
+    (output.stdout, output.stderr)
+}
+
+/// Asserts the report is byte-identical across several runs
+fn assert_deterministic<T, F>(report: F)
+where
+    T: PartialEq + std::fmt::Debug,
+    F: Fn() -> T,
+{
+    let expected = report();
+    for _ in 1..RUNS {
+        assert_eq!(report(), expected);
+    }
+}
+
+#[test]
+fn test_program_output() {
+    let path = fixture_path("determinism.fox");
+    assert_deterministic(|| run(&[&path]));
+    assert_deterministic(|| run(&["--coverage", &path]));
+    assert_deterministic(|| run(&["--history", "8", &path]));
+    assert_deterministic(|| run(&["--error-format", "json", "--history", "8", &path]));
+    assert_deterministic(|| run(&["--test", &path]));
+}
+
+#[test]
+fn test_compiled_program() {
+    let path = fixture_path("determinism.fox");
+    let output = std::env::temp_dir().join(format!("fox-determinism-{}.foxc", std::process::id()));
+    let output = output.to_string_lossy().to_string();
+    assert_deterministic(|| {
+        run(&["--compile", &path, "--output", &output]);
+        std::fs::read(&output).expect("compiled program expected")
+    });
+    _ = std::fs::remove_file(&output);
+}
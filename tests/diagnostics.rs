@@ -0,0 +1,58 @@
+mod common;
+
+use common::{fixture_path, fox_command};
+
+/// Diagnostics printed as JSON lines with the file field normalized
+fn json_diagnostics(name: &str, flags: &[&str]) -> Vec<String> {
+    let path = fixture_path(name);
+    let output = fox_command()
+        .args(["--error-format", "json"])
+        .args(flags)
+        .arg(&path)
+        .output()
+        .expect("failed to launch interpreter");
+    String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .map(|line| line.replace(&format!("\"file\":\"{path}\""), "\"file\":\"<file>\""))
+        .collect()
+}
+
+#[test]
+fn test_scanner_error() {
+    assert_eq!(
+        json_diagnostics("scanner_error.fox", &[]),
+        [
+            r#"{"severity":"error","code":"unexpected-character","message":"Unexpected character","file":"<file>","line":2,"column":9,"length":1}"#
+        ]
+    );
+}
+
+#[test]
+fn test_parse_error() {
+    assert_eq!(
+        json_diagnostics("parse_error.fox", &[]),
+        [
+            r#"{"severity":"error","code":"parse","message":"missing ';' at end of statement (line 1)","file":"<file>","line":1,"column":9,"length":1}"#
+        ]
+    );
+}
+
+#[test]
+fn test_warning() {
+    assert_eq!(
+        json_diagnostics("warning.fox", &["--warn", "missing-return"]),
+        [
+            r#"{"severity":"warning","code":"missing-return","message":"not all paths return a value; the path ending at line 2 returns nil","file":"<file>","line":1,"column":8,"length":4}"#
+        ]
+    );
+}
+
+#[test]
+fn test_runtime_error_notes() {
+    assert_eq!(
+        json_diagnostics("runtime_error.fox", &["--history", "2"]),
+        [
+            r#"{"severity":"error","code":"runtime","message":"Operands of '+' must be two numbers or include a string, got Number and Nil","file":"<file>","line":2,"column":9,"length":1,"notes":["line 1: var","line 2: print"]}"#
+        ]
+    );
+}
@@ -0,0 +1,282 @@
+mod common;
+
+use common::fox_command;
+
+/// Text no emitter may pass through unescaped
+const ADVERSARIAL: &str = "back\\slash\nline</script><!--\t\u{7f}\u{1}\u{9f}\u{2028}🦊";
+
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// Strict RFC 8259 parser, the whole input must be a single value
+fn parse_json(text: &str) -> Result<Json, String> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut parser = JsonParser {
+        chars: &chars,
+        position: 0,
+    };
+    let value = parser.value()?;
+    parser.whitespace();
+    if parser.position != chars.len() {
+        return Err(format!("trailing data at {}", parser.position));
+    }
+    Ok(value)
+}
+
+struct JsonParser<'l> {
+    chars: &'l [char],
+    position: usize,
+}
+
+impl JsonParser<'_> {
+    fn error<T>(&self, message: &str) -> Result<T, String> {
+        Err(format!("{message} at {}", self.position))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<char, String> {
+        let ch = self.peek().ok_or("unexpected end")?;
+        self.position += 1;
+        Ok(ch)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.next()? != expected {
+            return self.error(&format!("expected '{expected}'"));
+        }
+        Ok(())
+    }
+
+    fn whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for ch in word.chars() {
+            self.expect(ch)?;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.whitespace();
+        match self.peek() {
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some('-' | '0'..='9') => self.number(),
+            _ => self.error("unexpected character"),
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.position;
+        if self.peek() == Some('-') {
+            self.position += 1;
+        }
+        let digits = |parser: &mut Self| {
+            let start = parser.position;
+            while parser.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+                parser.position += 1;
+            }
+            parser.position - start
+        };
+        match self.peek() {
+            Some('0') => self.position += 1,
+            Some('1'..='9') => _ = digits(self),
+            _ => return self.error("expected digit"),
+        }
+        if self.peek() == Some('.') {
+            self.position += 1;
+            if digits(self) == 0 {
+                return self.error("expected fraction digits");
+            }
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.position += 1;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.position += 1;
+            }
+            if digits(self) == 0 {
+                return self.error("expected exponent digits");
+            }
+        }
+        let text = self.chars[start..self.position].iter().collect::<String>();
+        text.parse()
+            .map(Json::Number)
+            .map_err(|err| err.to_string())
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = self.next()?.to_digit(16).ok_or("invalid \\u escape")?;
+            value = value * 16 + digit;
+        }
+        Ok(value)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut result = String::new();
+        loop {
+            let ch = self.next()?;
+            match ch {
+                '"' => return Ok(result),
+                '\\' => {
+                    let escaped = match self.next()? {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => {
+                            let mut code = self.hex4()?;
+                            if (0xd800..0xdc00).contains(&code) {
+                                self.expect('\\')?;
+                                self.expect('u')?;
+                                let low = self.hex4()?;
+                                if !(0xdc00..0xe000).contains(&low) {
+                                    return self.error("invalid low surrogate");
+                                }
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                            }
+                            char::from_u32(code).ok_or("lone surrogate")?
+                        }
+                        _ => return self.error("invalid escape"),
+                    };
+                    result.push(escaped);
+                }
+                ch if (ch as u32) < 0x20 => return self.error("raw control character"),
+                ch => result.push(ch),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.whitespace();
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.whitespace();
+            match self.next()? {
+                ',' => continue,
+                ']' => return Ok(Json::Array(items)),
+                _ => return self.error("expected ',' or ']'"),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut entries = Vec::new();
+        self.whitespace();
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(Json::Object(entries));
+        }
+        loop {
+            self.whitespace();
+            let key = self.string()?;
+            self.whitespace();
+            self.expect(':')?;
+            entries.push((key, self.value()?));
+            self.whitespace();
+            match self.next()? {
+                ',' => continue,
+                '}' => return Ok(Json::Object(entries)),
+                _ => return self.error("expected ',' or '}'"),
+            }
+        }
+    }
+}
+
+fn field<'l>(json: &'l Json, name: &str) -> &'l Json {
+    let Json::Object(entries) = json else {
+        panic!("Object expected, got {json:?}");
+    };
+    entries
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value)
+        .unwrap_or_else(|| panic!("Missing field '{name}' in {json:?}"))
+}
+
+#[test]
+fn test_parser_is_strict() {
+    for invalid in [
+        "\"raw\ttab\"",
+        "\"\\x41\"",
+        "\"\\ud800\"",
+        "[1,]",
+        "{\"a\" 1}",
+        "01",
+        "\"a\" \"b\"",
+    ] {
+        assert!(parse_json(invalid).is_err(), "{invalid}");
+    }
+    assert_eq!(
+        parse_json(r#"{"a": [null, true, -1.5e3, "\u00e9\ud83e\udd8a\/"]}"#).unwrap(),
+        Json::Object(vec![(
+            "a".to_string(),
+            Json::Array(vec![
+                Json::Null,
+                Json::Bool(true),
+                Json::Number(-1500.0),
+                Json::String("é🦊/".to_string())
+            ])
+        )])
+    );
+}
+
+#[test]
+fn test_json_diagnostics() {
+    // the path is reported as is, so it's adversarial too
+    let dir = std::env::temp_dir().join(format!("fox-escaping-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("quote\" back\\slash <script>&.fox");
+    // the literal can't hold a quote, Fox strings have no escapes
+    std::fs::write(&path, format!("throw \"{ADVERSARIAL}\";\n")).unwrap();
+    let path = path.to_string_lossy().to_string();
+
+    let output = fox_command()
+        .args(["--error-format", "json", &path])
+        .output()
+        .expect("failed to launch interpreter");
+    _ = std::fs::remove_dir_all(&dir);
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let lines = stderr.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 1, "{stderr}");
+    // safe to embed in HTML
+    assert!(!lines[0].contains("</"), "{}", lines[0]);
+    let json = parse_json(lines[0]).unwrap_or_else(|err| panic!("{err}: {}", lines[0]));
+    assert_eq!(
+        field(&json, "message"),
+        &Json::String(format!("Uncaught exception: {ADVERSARIAL}"))
+    );
+    assert_eq!(field(&json, "file"), &Json::String(path));
+}
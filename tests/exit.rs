@@ -0,0 +1,20 @@
+mod common;
+
+use common::{fixture_path, fox_command};
+
+#[test]
+fn test_exit_code() {
+    for flags in [&[][..], &["--test"]] {
+        let output = fox_command()
+            .args(flags)
+            .arg(fixture_path("exit.fox"))
+            .output()
+            .expect("failed to launch interpreter");
+        assert_eq!(output.status.code(), Some(3), "{flags:?}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "1\nnegative value\n"
+        );
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    }
+}
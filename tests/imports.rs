@@ -0,0 +1,54 @@
+mod common;
+
+use common::{fox_command, output_text};
+
+fn fixture_path(name: &str) -> String {
+    common::fixture_path(&format!("imports/{name}"))
+}
+
+/// Runs the script and returns stdout & stderr
+fn run(flags: &[&str], name: &str) -> (String, String) {
+    let output = fox_command()
+        .args(flags)
+        .arg(fixture_path(name))
+        .output()
+        .expect("failed to launch interpreter");
+    output_text(&output)
+}
+
+#[test]
+fn test_imported_globals() {
+    let (stdout, stderr) = run(&[], "main.fox");
+    assert_eq!(stderr, "");
+    // the counter is imported three times, directly & by the helpers
+    assert_eq!(stdout, "counter loaded\nhi fox\n42\n");
+}
+
+#[test]
+fn test_circular_import() {
+    let (stdout, stderr) = run(&["--error-format", "json"], "cycle_a.fox");
+    assert_eq!(stdout, "");
+    let cycle = ["cycle_a.fox", "cycle_b.fox", "cycle_a.fox"].map(fixture_path);
+    assert_eq!(
+        stderr.trim_end(),
+        format!(
+            r#"{{"severity":"error","code":"resolver","message":"Circular import: {}","file":"{}","line":2,"column":20,"length":13}}"#,
+            // JSON escapes the arrows
+            cycle.join(" -\\u003e "),
+            fixture_path("cycle_b.fox")
+        )
+    );
+}
+
+#[test]
+fn test_error_in_module() {
+    let (stdout, stderr) = run(&["--error-format", "json"], "failing.fox");
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr.trim_end(),
+        format!(
+            r#"{{"severity":"error","code":"runtime","message":"Operands of '/' must be numbers, got Nil and Number","file":"{}","line":2,"column":14,"length":1}}"#,
+            fixture_path("lib/broken.fox")
+        )
+    );
+}
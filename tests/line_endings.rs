@@ -0,0 +1,61 @@
+mod common;
+
+use std::process::Output;
+
+use common::{fixture_path, fox_command};
+use fox_lang::{ErrorInfo, Fox};
+
+/// The same program saved with LF, CRLF and BOM + CRLF
+const FIXTURES: [&str; 3] = ["lf.fox", "crlf.fox", "bom_crlf.fox"];
+
+fn run_fixture(name: &str) -> Output {
+    fox_command()
+        .arg(fixture_path(name))
+        .output()
+        .expect("failed to launch interpreter")
+}
+
+#[test]
+fn test_fixtures_differ_only_in_line_endings() {
+    let read = |name: &str| std::fs::read(fixture_path(name)).unwrap();
+    let lf = read("lf.fox");
+    let crlf = read("crlf.fox");
+    let bom_crlf = read("bom_crlf.fox");
+    assert!(!lf.contains(&b'\r'));
+    assert_eq!(
+        String::from_utf8(crlf.clone()).unwrap(),
+        String::from_utf8(lf).unwrap().replace('\n', "\r\n")
+    );
+    assert_eq!(bom_crlf, [&[0xef, 0xbb, 0xbf], crlf.as_slice()].concat());
+}
+
+#[test]
+fn test_identical_output() {
+    let outputs = FIXTURES.map(run_fixture);
+    for (name, output) in FIXTURES.iter().zip(&outputs) {
+        assert_eq!(output.status.code(), Some(2), "{name}");
+        assert_eq!(output.stdout, outputs[0].stdout, "{name}");
+        assert_eq!(output.stderr, outputs[0].stderr, "{name}");
+    }
+    let stdout = String::from_utf8_lossy(&outputs[0].stdout);
+    assert!(stdout.starts_with("Hello, fox!\nfirst\nsecond\n0\n1\n2\n"));
+    assert!(stdout.contains("15 |print 1 + nil;\n"));
+}
+
+#[test]
+fn test_identical_diagnostic_location() {
+    for name in FIXTURES {
+        let code = std::fs::read_to_string(fixture_path(name)).unwrap();
+        let mut fox = Fox::with(code.chars().collect());
+        let err = fox.run().expect_err(name);
+        let ErrorInfo::Token(token) = err.info() else {
+            panic!("{name}: expected error at token, got {:?}", err.info());
+        };
+        assert_eq!(token.code_location.line_number(), 15, "{name}");
+        let description = fox.error_description(&err);
+        assert!(
+            description.contains("15 |print 1 + nil;\n            ▲"),
+            "{name}: {description}"
+        );
+    }
+}
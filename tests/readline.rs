@@ -0,0 +1,44 @@
+mod common;
+
+use std::{io::Write, process::Stdio};
+
+use common::{fixture_path, fox_command, output_text};
+
+/// Runs the script with the input piped to stdin and returns stdout & stderr
+fn run_with_input(name: &str, input: &str) -> (String, String) {
+    let mut child = fox_command()
+        .arg(fixture_path(name))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to launch interpreter");
+    let mut stdin = child.stdin.take().expect("stdin expected");
+    stdin.write_all(input.as_bytes()).unwrap();
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    output_text(&output)
+}
+
+#[test]
+fn test_guessing_game() {
+    let (stdout, stderr) = run_with_input("guess.fox", "50\nabc\n 25\r\n42\n");
+    assert_eq!(stderr, "");
+    assert_eq!(
+        stdout,
+        "Your guess: Too high\n\
+         Your guess: 'abc' isn't a number\n\
+         Your guess: Too low\n\
+         Your guess: Correct in 3 tries\n"
+    );
+}
+
+#[test]
+fn test_end_of_input() {
+    let (stdout, stderr) = run_with_input("guess.fox", "10");
+    assert_eq!(stderr, "");
+    assert_eq!(
+        stdout,
+        "Your guess: Too low\nYour guess: \nBye, the number was 42\n"
+    );
+}
@@ -0,0 +1,18 @@
+mod common;
+
+use common::fixture_path;
+use fox_lang::Fox;
+
+/// Scripts checking the builtins in `test_` functions, with the number of them
+const SCRIPTS: [(&str, usize); 3] = [("arrays.fox", 8), ("maps.fox", 5), ("strings.fox", 7)];
+
+#[test]
+fn test_builtin_scripts() {
+    for (name, count) in SCRIPTS {
+        let code = std::fs::read_to_string(fixture_path(name)).unwrap();
+        let mut fox = Fox::with(code.chars().collect());
+        let report = fox.run_tests().unwrap();
+        assert!(report.is_success(), "{name}: {report}");
+        assert_eq!(report.passed_count(), count, "{name}");
+    }
+}